@@ -0,0 +1,92 @@
+/**
+ * Compares the ways the WASM module hands results to JavaScript:
+ * a JSON string parsed with JSON.parse, native objects built by
+ * serde-wasm-bindgen, and the columnar layout.
+ *
+ * Each path runs in its own process so the peak memory (max RSS, which
+ * includes the WASM linear memory) is not shared between them.
+ *
+ * Usage: npm run bench [-- <rows>]
+ */
+
+const { execFileSync } = require('child_process');
+
+const PATHS = {
+  'string + JSON.parse': (m, csv, options) => JSON.parse(m.process_csv_bytes(csv, options)),
+  'object (serde-wasm-bindgen)': (m, csv, options) => m.process_csv_object(csv, options),
+  'columnar': (m, csv, options) => m.process_csv_columnar(csv, options),
+  'stream string + JSON.parse': (m, csv, options) => stream(m, csv, options, (p, chunk) => JSON.parse(p.push_chunk(chunk)), (p) => JSON.parse(p.finish())),
+  'stream object': (m, csv, options) => stream(m, csv, options, (p, chunk) => p.push_chunk_object(chunk), (p) => p.finish_object())
+};
+// Same chunk size as fs.createReadStream, used by processCsv
+const CHUNK_BYTES = 64 * 1024;
+const RUNS = 5;
+
+// Synthetic file with text, integer, decimal and date columns
+function buildCsv(rows) {
+  const lines = ['id,nome,cidade,valor,quantidade,data'];
+  for (let i = 1; i <= rows; i++) {
+    lines.push(`${i},Cliente ${i},Cidade ${i % 100},${(i * 1.37).toFixed(2)},${i % 17},2024-01-${String(i % 28 + 1).padStart(2, '0')}`);
+  }
+  return Buffer.from(lines.join('\n') + '\n');
+}
+
+// Streaming path as processCsv runs it: batches collected chunk by chunk
+function stream(m, csv, options, push, finish) {
+  const processor = m.CsvStreamProcessor.with_options(options);
+  const processed_rows = [];
+  try {
+    for (let start = 0; start < csv.length; start += CHUNK_BYTES) {
+      processed_rows.push(...push(processor, csv.subarray(start, start + CHUNK_BYTES)).processed_rows);
+    }
+    processed_rows.push(...finish(processor).processed_rows);
+  } finally {
+    processor.free();
+  }
+  return { processed_rows };
+}
+
+// Child process: runs one path and prints its median time and peak memory
+function runPath(name, rows, types) {
+  const m = require('../pkg/processor.js');
+  const csv = buildCsv(rows);
+  const options = new m.CsvOptions();
+  options.set_types(types);
+  const rssBefore = process.memoryUsage().rss;
+
+  const times = [];
+  let result;
+  for (let i = 0; i < RUNS; i++) {
+    result = null;
+    const start = process.hrtime.bigint();
+    result = PATHS[name](m, csv, options);
+    times.push(Number(process.hrtime.bigint() - start) / 1e6);
+  }
+  times.sort((a, b) => a - b);
+
+  const valid = result.processed_rows ? result.processed_rows.length : result.line.length;
+  if (valid !== rows) throw new Error(`${name}: expected ${rows} rows, got ${valid}`);
+  const peak = process.resourceUsage().maxRSS * 1024 - rssBefore;
+  console.log(JSON.stringify({ ms: times[RUNS >> 1], peakMb: peak / 1024 / 1024 }));
+}
+
+function main() {
+  const rows = Number(process.argv[2]) || 200000;
+  console.log(`${rows} rows, ${(buildCsv(rows).length / 1024 / 1024).toFixed(1)} MB, median of ${RUNS} runs\n`);
+
+  for (const types of ['off', 'infer']) {
+    console.log(`types: ${types}`);
+    for (const name of Object.keys(PATHS)) {
+      const output = execFileSync(process.execPath, [__filename, '--child', name, String(rows), types]);
+      const { ms, peakMb } = JSON.parse(output);
+      console.log(`  ${name.padEnd(28)} ${ms.toFixed(1).padStart(8)} ms   peak memory +${peakMb.toFixed(0)} MB`);
+    }
+    console.log();
+  }
+}
+
+if (process.argv[2] === '--child') {
+  runPath(process.argv[3], Number(process.argv[4]), process.argv[5]);
+} else {
+  main();
+}
@@ -1,27 +1,509 @@
+import type { Readable } from 'stream';
+
+/**
+ * A cell value. Dates are ISO-8601 strings.
+ */
+export type CellValue = string | number | boolean | null;
+
 /**
  * Represents a successfully processed CSV row.
  */
 export interface ProcessedRow {
   /** The line number in the original CSV file (starting from 2, accounting for header) */
   line: number;
-  /** The parsed data as a key-value object */
-  data: Record<string, string>;
-  /** SHA-256 hash of the row data */
+  /** The parsed data as a key-value object. Values are strings unless `types` is enabled. */
+  data: Record<string, CellValue>;
+  /** Hex hash of the row data (SHA-256 of the values joined with "," by default) */
   hash: string;
+  /** Record type, for fixed-width layouts with several of them */
+  record?: string;
 }
 
+/**
+ * Stable identifier of each kind of error.
+ * - empty_fields: a row without schema has an empty field
+ * - field_count_mismatch: the row has a different number of fields than the header
+ * - invalid_utf8: the row is not valid UTF-8
+ * - required, min_length, max_length: schema column constraints
+ * - invalid_value: the value does not match the column type (int, date, cpf, email...)
+ * - not_allowed: the value is not one of the "enum" values
+ * - pattern_mismatch: the value does not match the "regex" pattern
+ * - duplicate: the row repeats another one (see `dedup`)
+ * - row_rule: a cross-column rule from the schema `rules` failed
+ * - invalid_json: a JSON or NDJSON record is not a valid JSON object, or has a repeated or colliding flattened key
+ * - unknown_record: a fixed-width record matches no record type of the layout
+ * - record_length: a fixed-width record is not `record_length` characters long
+ * - duplicate_header: a warning for a repeated header name that was renamed to `name_2`, `name_3`...
+ */
+export type ErrorCode =
+  | 'empty_fields'
+  | 'field_count_mismatch'
+  | 'invalid_utf8'
+  | 'required'
+  | 'min_length'
+  | 'max_length'
+  | 'invalid_value'
+  | 'not_allowed'
+  | 'pattern_mismatch'
+  | 'duplicate'
+  | 'row_rule'
+  | 'invalid_json'
+  | 'unknown_record'
+  | 'record_length'
+  | 'duplicate_header';
+
 /**
  * Represents a validation error for a CSV row.
  */
 export interface ValidationError {
   /** The line number where the error occurred */
   line: number;
-  /** Description of the validation error */
+  /** Stable error code, safe to match on */
+  code: ErrorCode;
+  /** "error" rejects the row; "warning" is reported while the row stays in processed_rows */
+  severity: 'error' | 'warning';
+  /** Human-readable description of the error, for display only */
+  error: string;
+  /** The parsed row that failed validation, or null if the row could not be parsed */
+  data: Record<string, string> | null;
+  /** The column that failed, when the error comes from a schema rule */
+  column?: string;
+  /** The schema rule that failed (e.g. "required", "int", "email"), the name of a row rule, or "duplicate" */
+  rule?: string;
+  /** For "empty_fields", the headers of the empty fields */
+  empty_columns?: string[];
+  /** Byte offset where the record starts in the file as read (UTF-16 and Windows-1252 files included) */
+  byte_offset?: number;
+  /** What the rule expected, e.g. "int", "date %d/%m/%Y", "min_length 3", "4 fields" */
+  expected?: string;
+  /** The value found, e.g. the cell value or "3 fields" */
+  actual?: string;
+  /** For duplicates, the line of the occurrence that was kept */
+  duplicate_of?: number;
+  /** Record type, for fixed-width layouts with several of them */
+  record?: string;
+}
+
+/**
+ * Validation rules for a single column.
+ */
+export interface ColumnSchema {
+  /** Expected value type. Defaults to "string". */
+  type?: 'string' | 'int' | 'decimal' | 'date' | 'bool' | 'email' | 'enum' | 'regex' | 'cpf' | 'cnpj' | 'cep' | 'uf';
+  /** Whether the column may be empty (optional column). Defaults to false. */
+  nullable?: boolean;
+  /** Value used when the field is empty. Must itself pass the column rules. */
+  default?: string;
+  /** Minimum length in characters */
+  min_length?: number;
+  /** Maximum length in characters */
+  max_length?: number;
+  /** Allowed values for type "enum" */
+  values?: string[];
+  /** Regular expression for type "regex" */
+  pattern?: string;
+  /** strftime format for type "date". Defaults to "%Y-%m-%d". */
+  format?: string;
+  /** "warning" reports failures without rejecting the row. Defaults to "error". */
+  severity?: 'error' | 'warning';
+}
+
+/**
+ * Rules checked once against the header row. A header that breaks them
+ * throws a `bad_header` error before any row is processed.
+ */
+export interface HeaderSchema {
+  /** Header name in the file -> canonical name used in `columns`, `data` and the output */
+  aliases?: Record<string, string>;
+  /** Canonical names that must be present */
+  required?: string[];
+  /** Names that must not be present */
+  forbidden?: string[];
+  /** "error" rejects columns missing from `columns` and `required`. Defaults to "allow". */
+  unknown?: 'allow' | 'error';
+  /**
+   * Repeated header names become `name_2`, `name_3`... With "error" (the default),
+   * repeating a column used by the schema is a bad_header error and the other renamed
+   * columns are reported as duplicate_header warnings; "rename" renames without a warning.
+   */
+  duplicates?: 'error' | 'rename';
+}
+
+/**
+ * Column schema used to validate each row. Columns not listed must not be empty.
+ */
+/**
+ * Rule across the columns of a row, written in a small expression language:
+ * column names, string/number literals, `+ - * /`, `== != < <= > >=`,
+ * `and or not`, `in [...]` and the functions `empty`, `len`, `upper`,
+ * `lower`, `trim`, `abs`, `round`, `matches(column, 'regex')` and
+ * `lookup('table', column)`.
+ */
+export interface RowRule {
+  /** Reported as `rule` in the error */
+  name: string;
+  /** Expression that must be true, e.g. "data_fim >= data_inicio" */
+  check: string;
+  /** Only rows where this expression is true are checked */
+  when?: string;
+  /** Shown instead of the expression when the rule fails */
+  message?: string;
+  /** "warning" reports failures without rejecting the row. Defaults to "error". */
+  severity?: 'error' | 'warning';
+}
+
+/**
+ * Checks over the whole file. Failures are reported in `file_errors` and do
+ * not reject rows. `max_rows` and the trailer count cover every data row;
+ * `unique`, `monotonic` and the trailer sums only see accepted rows, with
+ * the schema defaults applied.
+ */
+export interface FileSchema {
+  /** Column sets whose values must not repeat, e.g. [["cpf"], ["id", "data"]]. Empty keys are ignored. */
+  unique?: string[][];
+  /** Columns whose values must increase row after row (as numbers when both are numbers) */
+  monotonic?: { column: string; allow_equal?: boolean }[];
+  /** Reports an error once the file goes over this many data rows */
+  max_rows?: number;
+  /** Trailer record with the file totals. It is not validated as a data row. */
+  trailer?: {
+    /** Expression that identifies the trailer, e.g. "id == 'TOTAL'" */
+    when: string;
+    /** Trailer column holding the number of data rows */
+    count?: string;
+    /** Summed column -> trailer column holding the expected sum */
+    sums?: Record<string, string>;
+  };
+}
+
+/**
+ * Stable code of a file-level error.
+ */
+export type FileErrorCode =
+  | 'unique'
+  | 'monotonic'
+  | 'max_rows'
+  | 'trailer_missing'
+  | 'trailer_repeated'
+  | 'trailer_count'
+  | 'trailer_sum'
+  | 'record_order'
+  | 'header_mismatch';
+
+/**
+ * A failed file-level check.
+ */
+export interface FileError {
+  code: FileErrorCode;
+  /** Human-readable description, for display only */
   error: string;
-  /** The raw CSV data that failed validation */
-  raw_data: string;
+  /** Line where the problem showed up, if there is one */
+  line?: number;
+  /** Columns involved in the check */
+  columns?: string[];
+  expected?: string;
+  actual?: string;
+  /** For `unique`, the line that first had the same key */
+  duplicate_of?: number;
+  /** Record type checked, for fixed-width layouts with several of them */
+  record?: string;
+}
+
+export interface Schema {
+  columns?: Record<string, ColumnSchema>;
+  headers?: HeaderSchema;
+  /** Cross-column rules, checked on rows whose columns passed */
+  rules?: RowRule[];
+  /** Tables for `lookup()` in rules: table name -> key -> value */
+  lookups?: Record<string, Record<string, string>>;
+  /** Checks over the whole file */
+  file?: FileSchema;
+}
+
+/**
+ * A schema compiled by the WebAssembly module, reusable across calls.
+ */
+export interface CsvSchema {
+  free(): void;
+}
+
+/**
+ * A field of a fixed-width record. Besides its position, it takes the rules
+ * of a schema column (`type`, `nullable`, `format`...), used to validate it.
+ */
+export interface FixedWidthField extends ColumnSchema {
+  name: string;
+  /** Position of the first character, starting at 1 */
+  start: number;
+  /** Number of characters */
+  length: number;
+  /** "space", "zero" or a single character. Defaults to "zero" for int and decimal, "space" otherwise. */
+  padding?: string;
+  /** Side the value sits on; the padding is stripped from the other side. Defaults to "right" for int and decimal, "left" otherwise. */
+  align?: 'left' | 'right';
+  /** Implied decimal places of a "decimal" field: "000000012345" with 2 becomes "123.45". Padding and a leading sign are stripped first; any other non-digit rejects the row with invalid_value. */
+  decimals?: number;
+}
+
+/**
+ * A record type of a fixed-width layout, such as a header, detail or trailer.
+ */
+export interface FixedWidthRecord {
+  /** Reported as `record` in rows and errors */
+  name: string;
+  /** Values at fixed positions that identify the type, e.g. `[{ start: 8, value: '3' }]`. Without it, matches any record. */
+  match?: { start: number; value: string | string[] }[];
+  fields: FixedWidthField[];
+  /** Cross-column rules, as in a schema */
+  rules?: RowRule[];
+  /** Checks over the records of this type, as in a schema */
+  file?: FileSchema;
+}
+
+/**
+ * Layout of a fixed-width file: either `fields`, for a single record type,
+ * or `records`. Record types are tried in order and the first match wins.
+ */
+export interface FixedWidthLayoutDefinition {
+  /** Length of every record; shorter or longer ones are rejected. Files without line breaks are cut every `record_length` characters. */
+  record_length?: number;
+  fields?: FixedWidthField[];
+  rules?: RowRule[];
+  file?: FileSchema;
+  records?: FixedWidthRecord[];
+  /** Tables for `lookup()` in rules */
+  lookups?: Record<string, Record<string, string>>;
 }
 
+/**
+ * A fixed-width layout compiled by the WebAssembly module, reusable across calls.
+ */
+export interface FixedWidthLayout {
+  free(): void;
+}
+
+/**
+ * How the hash of each row is computed.
+ */
+export interface HashOptions {
+  /** Hash algorithm. xxh3 is fast but not cryptographic. Defaults to "sha256". */
+  algorithm?: 'sha256' | 'sha512' | 'blake3' | 'xxh3';
+  /**
+   * How the row is serialized before hashing. Defaults to "joined".
+   * - joined: values joined with "," (ambiguous when values contain commas)
+   * - canonical: `name=value` lines sorted by column name, independent of column order
+   * - length_prefixed: `<bytes>:<name><bytes>:<value>` for each column
+   * - json: a compact JSON object with the hashed columns, keys in column order
+   */
+  encoding?: 'joined' | 'canonical' | 'length_prefixed' | 'json';
+  /** Columns included in the hash, in this order. Defaults to all columns. A column missing from the header is a bad_header error. */
+  columns?: string[];
+  /** Secret key; turns the hash into an HMAC (keyed BLAKE3 for "blake3"). */
+  key?: string | Uint8Array;
+}
+
+/**
+ * How repeated rows are detected and handled.
+ */
+export interface DedupOptions {
+  /**
+   * What to do with repeated rows. Defaults to "off".
+   * - flag: report them as errors with rule "duplicate" and `duplicate_of`
+   * - drop: remove them silently
+   */
+  mode?: 'off' | 'flag' | 'drop';
+  /**
+   * Which occurrence stays in `processed_rows`. Defaults to "first".
+   * With "last", valid rows are only delivered at the end of the file.
+   */
+  keep?: 'first' | 'last';
+  /** Columns identifying a duplicate (e.g. ["cpf"]). Defaults to all the columns of the row. A column missing from the header is a bad_header error. */
+  key?: string[];
+}
+
+/**
+ * How cell values are typed in `processed_rows`.
+ */
+export interface TypeOptions {
+  /**
+   * Defaults to "off" (every value is a string).
+   * - schema: int, decimal, date and bool schema columns become numbers, ISO dates and booleans;
+   *   empty nullable columns become null
+   * - infer: also infers numbers, booleans and dates for columns outside the schema
+   */
+  mode?: 'off' | 'schema' | 'infer';
+  /**
+   * Decimal separator of numbers. When omitted it is decided per value: with both
+   * separators the last one is the decimal one, so "1.234,56" and "1,234.56" both work.
+   */
+  decimal_separator?: '.' | ',';
+}
+
+/**
+ * Size limits. Going over one stops processing with a `limit_exceeded` error.
+ */
+export interface LimitOptions {
+  /** Maximum number of data rows */
+  max_rows?: number;
+  /** Maximum size of a single record, in bytes; guards against an unclosed quote swallowing the file */
+  max_record_bytes?: number;
+}
+
+/**
+ * Column profiling settings.
+ */
+export interface StatsOptions {
+  /** Number of most frequent values listed per column. Defaults to 10. */
+  top_k?: number;
+}
+
+/**
+ * How JSON and NDJSON records become columns.
+ */
+export interface JsonOptions {
+  /** Joins nested keys into column names: `{ endereco: { cidade } }` becomes "endereco.cidade". Defaults to ".". */
+  separator?: string;
+  /** Levels of nested objects flattened; deeper values are kept as JSON text. Unlimited by default; 0 flattens none. */
+  max_depth?: number;
+  /** "json" (default) keeps an array as JSON text in one field; "index" makes a column per item ("tags.0", "tags.1"). */
+  arrays?: 'json' | 'index';
+}
+
+/**
+ * Type inferred for a column from all its non-empty values, by the rules of
+ * `types: 'infer'`. Integers mixed with decimals make a decimal column; any
+ * other mix makes a string column. "empty" means every value was empty.
+ */
+export type ColumnType = 'int' | 'decimal' | 'date' | 'bool' | 'string' | 'empty';
+
+/**
+ * Profile of one column, computed over every data row (valid or not) with the
+ * values as read from the file.
+ */
+export interface ColumnStats {
+  name: string;
+  type: ColumnType;
+  /** Number of non-empty values */
+  count: number;
+  /** Number of empty values */
+  nulls: number;
+  /** Fraction of empty values, from 0 to 1 */
+  null_rate: number;
+  /** Estimated number of distinct values (HyperLogLog, typically within 1.6%) */
+  distinct: number;
+  /** Smallest and largest value: numbers for numeric columns, ISO dates for date columns, text otherwise */
+  min?: number | string;
+  max?: number | string;
+  /** Most frequent values, most frequent first */
+  top: { value: string; count: number }[];
+  /** False when the column had too many distinct values to count them all; `top` counts are then lower bounds */
+  top_exact: boolean;
+  /** Length of the non-empty values, in characters */
+  lengths: {
+    min: number;
+    max: number;
+    mean: number;
+    /** Power-of-two buckets (1, 2-3, 4-7, ...) that hold at least one value */
+    histogram: { min: number; max: number; count: number }[];
+  };
+}
+
+/**
+ * The `stats` block: the totals of the file and a profile of each column.
+ */
+export interface Stats extends Omit<StreamSummary, 'dialect' | 'stats'> {
+  columns: ColumnStats[];
+}
+
+/**
+ * Kind of a fatal error, which stops the whole file instead of rejecting a row.
+ */
+export type FatalErrorCode =
+  | 'bad_header'
+  | 'invalid_utf8'
+  | 'limit_exceeded'
+  | 'bad_spreadsheet'
+  | 'invalid_json'
+  | 'invalid_cnab';
+
+/**
+ * Error thrown when a file cannot be processed at all: a header that is not
+ * valid UTF-8 or repeats a column name, a limit exceeded, or a spreadsheet
+ * that cannot be read or lacks the requested sheet, a JSON document that
+ * is malformed or not an array of objects, or a file that is not CNAB. Rows rejected by
+ * validation never throw; they are reported in `errors`.
+ */
+export interface CsvFatalError extends Error {
+  code: FatalErrorCode;
+  /** Line of the record that caused the error; 0 when it does not come from a record */
+  line: number;
+  /** Byte offset of that record in the file as read; 0 when it does not come from a record */
+  byte: number;
+}
+
+/**
+ * Options accepted by the processing functions.
+ */
+export interface ProcessOptions {
+  /** Column schema, either plain or compiled with `compileSchema` */
+  schema?: Schema | CsvSchema;
+  /** CSV dialect. Omitted settings keep their defaults; "auto" detects it from the file. */
+  dialect?: Partial<Dialect> | 'auto';
+  /** Row hashing settings */
+  hash?: HashOptions;
+  /** Duplicate detection settings */
+  dedup?: DedupOptions;
+  /** Typed values, or just the mode */
+  types?: TypeOptions | TypeOptions['mode'];
+  /**
+   * Rows with more or fewer fields than the header. "strict" (default)
+   * rejects them with "Expected N fields, got M." and `data` null; "report"
+   * also keeps the columns present in `data` and the extra values in
+   * `data._extra`; "pad" fills short rows with empty
+   * fields; "extra" also pads and keeps the extra values of long rows in
+   * `data._extra`. `dialect.flexible` takes precedence.
+   */
+  field_count?: 'strict' | 'report' | 'pad' | 'extra';
+  /** Size limits */
+  limits?: LimitOptions;
+  /**
+   * Add a `stats` block profiling each column, computed in the same pass as
+   * the validation. Off by default.
+   */
+  stats?: boolean | StatsOptions;
+  /**
+   * Spreadsheets only: the sheet to read, by name or by position starting
+   * at 0. Defaults to the first sheet.
+   */
+  sheet?: string | number;
+  /**
+   * Spreadsheets only: the cells to read, such as "B3:F200", "A:D" or "B3"
+   * (from B3 to the last filled row and column). The first row of the range
+   * is the header. Defaults to the whole sheet.
+   */
+  range?: string;
+  /** JSON and NDJSON only: how nested objects and arrays become columns */
+  json?: JsonOptions;
+}
+
+/**
+ * Compiles a schema once so it can be reused across many files.
+ *
+ * @param schema - The schema definition
+ * @returns The compiled schema
+ * @throws {Error} If the schema is invalid
+ */
+export function compileSchema(schema: Schema): CsvSchema;
+
+/**
+ * Compiles a fixed-width layout once so it can be reused across many files.
+ *
+ * @param layout - The layout definition
+ * @returns The compiled layout
+ * @throws {Error} If the layout is invalid, such as overlapping fields
+ */
+export function compileLayout(layout: FixedWidthLayoutDefinition): FixedWidthLayout;
+
 /**
  * Result object returned from CSV processing.
  */
@@ -30,14 +512,258 @@ export interface ProcessingResult {
   processed_rows: ProcessedRow[];
   /** Array of validation errors encountered during processing */
   errors: ValidationError[];
+  /** Failed file-level checks from the schema `file` section */
+  file_errors: FileError[];
+  /** Column profile, with the `stats` option */
+  stats?: Stats;
+  /** The CSV dialect used to read the file */
+  dialect?: Dialect;
+}
+
+/**
+ * How a CSV file is read.
+ */
+export interface Dialect {
+  /** Field separator. Defaults to ",". */
+  delimiter: string;
+  /** Quote character. Defaults to '"'. */
+  quote: string;
+  /** Escape character inside quoted fields, or null. Defaults to null. */
+  escape: string | null;
+  /** Whether "" inside a quoted field is a literal quote. Defaults to true. */
+  double_quote: boolean;
+  /** Lines starting with this character are skipped, or null. Defaults to null. */
+  comment: string | null;
+  /** Whether the first record is a header. Without one, columns are named column_1, column_2, ... Defaults to true. */
+  has_headers: boolean;
+  /** Which fields have surrounding whitespace removed. Defaults to "none". */
+  trim: 'none' | 'headers' | 'fields' | 'all';
+  /** Whether records may have a different number of fields than the header. Defaults to false. */
+  flexible: boolean;
+  /** Text encoding of the file. Detected automatically unless set. */
+  encoding: Encoding;
+}
+
+/**
+ * Text encodings understood by the processor.
+ */
+export type Encoding = 'utf-8' | 'utf-16le' | 'utf-16be' | 'windows-1252';
+
+/**
+ * Dialect detected from a sample of the file.
+ */
+export interface SniffResult {
+  /** Detected text encoding */
+  encoding: Encoding;
+  /** Whether the file starts with a byte order mark */
+  bom: boolean;
+  /** Detected field separator */
+  delimiter: string;
+  /** Detected quote character */
+  quote: string;
+  /** Whether any quoted field was found */
+  has_quotes: boolean;
+  /** Whether the first record looks like a header */
+  has_headers: boolean;
+}
+
+/**
+ * Detects the encoding, delimiter, quote character and header presence of a
+ * CSV file from a sample of its first bytes.
+ *
+ * @param filePath - The path to the CSV file
+ * @returns The detected dialect
+ */
+export function sniffCsv(filePath: string): SniffResult;
+
+/**
+ * A batch of rows completed by one chunk of the input.
+ */
+export interface ProcessingBatch {
+  /** Rows from this batch that passed validation */
+  processed_rows: ProcessedRow[];
+  /** Validation errors from this batch */
+  errors: ValidationError[];
+  /** File-level errors found so far; trailer checks arrive with the last batch */
+  file_errors: FileError[];
+}
+
+/**
+ * Totals returned once a streamed file has been fully processed.
+ */
+export interface StreamSummary {
+  /** Number of data rows read (header excluded) */
+  total_rows: number;
+  /** Number of rows that passed validation */
+  valid_rows: number;
+  /** Number of rows rejected */
+  invalid_rows: number;
+  /** Number of repeated rows found, whether flagged or dropped */
+  duplicate_rows: number;
+  /** Number of bytes read from the file */
+  bytes_processed: number;
+  /** Column profile, with the `stats` option */
+  stats?: Stats;
+  /** The CSV dialect used to read the file */
+  dialect: Dialect;
+}
+
+/**
+ * Processes a CSV file in chunks without loading it entirely into memory.
+ *
+ * @param filePath - The path to the CSV file to process
+ * @param onBatch - Called with the rows and errors completed by each chunk
+ * @param options - Processing options
+ * @returns A promise that resolves to the totals for the whole file
+ * @throws {CsvFatalError} If the file cannot be processed (bad header, limit exceeded)
+ * @throws {Error} If the file cannot be read
+ *
+ * @example
+ * ```typescript
+ * import { processCsvStream } from 'gbr-csv';
+ *
+ * const summary = await processCsvStream('./big.csv', async (batch) => {
+ *   await db.insert(batch.processed_rows);
+ * });
+ * console.log(`${summary.valid_rows} of ${summary.total_rows} rows loaded`);
+ * ```
+ */
+export function processCsvStream(
+  filePath: string,
+  onBatch: (batch: ProcessingBatch) => void | Promise<void>,
+  options?: ProcessOptions
+): Promise<StreamSummary>;
+
+/**
+ * Valid rows laid out by column, returned by `processCsvColumnar`.
+ */
+export interface ColumnarResult extends Omit<StreamSummary, 'dialect'> {
+  /** Column names, in file order */
+  headers: string[];
+  /** Line number of each valid row */
+  line: Float64Array;
+  /** Hash of each valid row */
+  hash: string[];
+  /**
+   * Values of each column, one entry per valid row. Columns holding only
+   * numbers (and empty cells) are Float64Arrays with NaN for the empty cells;
+   * integers above 2^53 lose precision.
+   */
+  columns: Record<string, Float64Array | CellValue[]>;
+  /** Validation errors, one per rejected row or failed rule */
+  errors: ValidationError[];
+  /** Failed file-level checks */
+  file_errors: FileError[];
+  /** The CSV dialect used to read the file */
+  dialect: Dialect;
+}
+
+/**
+ * Processes a CSV file and returns the valid rows by column instead of one
+ * object per row. Numeric columns come as Float64Arrays, so enable `types`
+ * to get them.
+ *
+ * @param filePath - The path to the CSV file to process
+ * @param options - Processing options
+ * @returns A promise that resolves to the columns, the errors and the totals
+ * @throws {CsvFatalError} If the file cannot be processed (bad header, limit exceeded)
+ * @throws {Error} If the file cannot be read
+ *
+ * @example
+ * ```typescript
+ * import { processCsvColumnar } from 'gbr-csv';
+ *
+ * const { columns } = await processCsvColumnar('./vendas.csv', { types: 'infer' });
+ * const total = (columns.valor as Float64Array).reduce((a, b) => a + b, 0);
+ * ```
+ */
+export function processCsvColumnar(filePath: string, options?: ProcessOptions): Promise<ColumnarResult>;
+
+/**
+ * One line of the NDJSON output, tagged by `type`.
+ */
+export type NdjsonRecord =
+  | ({ type: 'row' } & ProcessedRow)
+  | ({ type: 'error' } & ValidationError)
+  | ({ type: 'file_error' } & FileError)
+  | ({ type: 'stats' } & Stats)
+  | ({ type: 'summary' } & Omit<StreamSummary, 'dialect' | 'stats'>);
+
+/**
+ * Processes a CSV file in chunks and returns a readable stream of NDJSON
+ * (JSON Lines) text. Each line is a `NdjsonRecord`: the rows and errors in
+ * file order, then a `summary` line with the totals.
+ *
+ * @param filePath - The path to the CSV file to process
+ * @param options - Processing options
+ * @returns A readable stream of NDJSON text
+ *
+ * @example
+ * ```typescript
+ * import { processCsvNdjson } from 'gbr-csv';
+ *
+ * processCsvNdjson('./big.csv').pipe(fs.createWriteStream('./big.ndjson'));
+ * ```
+ */
+export function processCsvNdjson(filePath: string, options?: ProcessOptions): Readable;
+
+/**
+ * Output files for `splitCsv`. Either may be omitted to discard those rows.
+ */
+export interface SplitOutputs {
+  /** Path of the CSV file that receives the valid rows */
+  valid?: string;
+  /** Path of the CSV file that receives the rejected rows, with `error_code` and `error_message` columns, plus `_extra` (surplus fields as a JSON array) when some rejected row has them */
+  rejected?: string;
+}
+
+export interface SplitOptions extends ProcessOptions {
+  /** Append a `line` column with the original line number to both files */
+  include_line?: boolean;
+  /** Append a `hash` column to the valid rows */
+  include_hash?: boolean;
 }
 
+/**
+ * Totals returned by `splitCsv`.
+ */
+export type SplitSummary = Omit<StreamSummary, 'dialect'> & {
+  /** Failed file-level checks, which have no place in the CSV outputs */
+  file_errors: FileError[];
+};
+
+/**
+ * Writes the valid and the rejected rows of a CSV file to two CSV files, in
+ * the original header order, names and dialect. A rejected row keeps its original
+ * fields; when it fails several rules, the codes are joined with `;` and the
+ * messages with `; `.
+ *
+ * @param filePath - The path to the CSV file to process
+ * @param outputs - Paths of the valid and rejected output files
+ * @param options - Processing options
+ * @returns A promise that resolves to the totals for the whole file
+ * @throws {Error} If a file cannot be read or written
+ *
+ * @example
+ * ```typescript
+ * import { splitCsv } from 'gbr-csv';
+ *
+ * await splitCsv('./clientes.csv', { valid: './ok.csv', rejected: './rejeitados.csv' }, {
+ *   schema: { columns: { cpf: { type: 'cpf' } } },
+ *   include_line: true,
+ * });
+ * ```
+ */
+export function splitCsv(filePath: string, outputs: SplitOutputs, options?: SplitOptions): Promise<SplitSummary>;
+
 /**
  * Processes a CSV file using the high-performance WebAssembly module.
  * 
  * @param filePath - The path to the CSV file to process
+ * @param options - Processing options
  * @returns A promise that resolves to an object containing processed rows and validation errors
- * @throws {Error} If the file cannot be read or processed
+ * @throws {CsvFatalError} If the file cannot be processed (bad header, limit exceeded)
+ * @throws {Error} If the file cannot be read
  * 
  * @example
  * ```typescript
@@ -48,15 +774,246 @@ export interface ProcessingResult {
  * console.log(`Found ${result.errors.length} errors`);
  * ```
  */
-export function processCsv(filePath: string): Promise<ProcessingResult>;
+export function processCsv(filePath: string, options?: ProcessOptions): Promise<ProcessingResult>;
+
+/**
+ * Profiles the columns of a CSV file without keeping its rows. The rows are
+ * still validated with the given options, so `valid_rows` and `invalid_rows`
+ * match `processCsv`. Rows are not hashed and `dedup` is ignored, so
+ * `duplicate_rows` is always 0.
+ *
+ * @param filePath - The path to the CSV file to profile
+ * @param options - Processing options; `stats` is always on
+ * @returns A promise that resolves to the totals and the profile of each column
+ * @throws {CsvFatalError} If the file cannot be processed (bad header, limit exceeded)
+ * @throws {Error} If the file cannot be read
+ *
+ * @example
+ * ```typescript
+ * import { profileCsv } from 'gbr-csv';
+ *
+ * const { columns } = await profileCsv('./clientes.csv');
+ * for (const c of columns) console.log(c.name, c.type, c.null_rate, c.distinct);
+ * ```
+ */
+export function profileCsv(filePath: string, options?: ProcessOptions): Promise<Stats>;
+
+/**
+ * Result of processing a spreadsheet: the same as a CSV file, with the sheet
+ * that was read instead of the dialect.
+ */
+export interface SpreadsheetResult extends Omit<ProcessingResult, 'dialect'> {
+  /** Name of the sheet that was read */
+  sheet: string;
+  /** Column names, in sheet order */
+  headers: string[];
+}
+
+/**
+ * Processes a spreadsheet (.xlsx, .xlsm, .xls or .ods) with the same
+ * validation, hashing and deduplication as a CSV file. Each sheet row is a
+ * record, the first one being the header, and `line` is the row number in
+ * the sheet; blank rows are skipped. Numbers come as Excel shows them
+ * ("1500.5", "0.3") and dates as ISO-8601 ("2024-01-15",
+ * "2024-01-15T10:30:00"). The format is detected from the contents.
+ *
+ * @param filePath - The path to the spreadsheet, or its contents already read
+ * @param options - Processing options; `sheet` and `range` choose what is read
+ * @returns A promise that resolves to the processed rows and validation errors of the sheet
+ * @throws {CsvFatalError} If the file is not a readable spreadsheet or the sheet does not exist (`bad_spreadsheet`)
+ * @throws {Error} If the file cannot be read or `range` is invalid
+ *
+ * @example
+ * ```typescript
+ * import { processSpreadsheet } from 'gbr-csv';
+ *
+ * const result = await processSpreadsheet('./clientes.xlsx', { sheet: 'Ativos', range: 'A3:F' });
+ * console.log(`${result.sheet}: ${result.processed_rows.length} rows`);
+ * ```
+ */
+export function processSpreadsheet(filePath: string | Buffer, options?: ProcessOptions): Promise<SpreadsheetResult>;
+
+/**
+ * Lists the sheets of a spreadsheet (.xlsx, .xlsm, .xls or .ods) in tab
+ * order. Chart sheets are left out.
+ *
+ * @param filePath - The path to the spreadsheet, or its contents already read
+ * @returns A promise that resolves to the sheet names
+ * @throws {CsvFatalError} If the file is not a readable spreadsheet (`bad_spreadsheet`)
+ */
+export function listSheets(filePath: string | Buffer): Promise<string[]>;
+
+/**
+ * Result of processing a JSON or NDJSON file: the same as a CSV file,
+ * without the dialect.
+ */
+export interface JsonResult extends Omit<ProcessingResult, 'dialect'> {
+  /** Column names: every key seen, in the order they first appear */
+  headers: string[];
+}
+
+/**
+ * Processes a JSON file holding an array of objects (or a single object)
+ * with the same validation, hashing and deduplication as a CSV file. Each
+ * object is a record and nested objects are flattened into columns such as
+ * "endereco.cidade"; a key missing from an object is an empty field. Numbers
+ * and booleans come as text and `null` as an empty field. `line` is the
+ * position in the array, starting at 0, and an element that is not an
+ * object is rejected with code "invalid_json".
+ *
+ * @param filePath - The path to the JSON file
+ * @param options - Processing options; `json` controls the flattening
+ * @returns A promise that resolves to the processed rows and validation errors
+ * @throws {CsvFatalError} If the file is not valid JSON or not an array of objects (`invalid_json`)
+ *
+ * @example
+ * ```typescript
+ * import { processJson } from 'gbr-csv';
+ *
+ * const result = await processJson('./clientes.json', {
+ *   schema: { columns: { 'endereco.uf': { type: 'uf', required: true } } }
+ * });
+ * ```
+ */
+export function processJson(filePath: string, options?: ProcessOptions): Promise<JsonResult>;
+
+/**
+ * Processes an NDJSON (JSON Lines) file, one object per line, like
+ * `processJson`. `line` is the line in the file and blank lines are skipped;
+ * a line that is not a valid JSON object is rejected with code
+ * "invalid_json" instead of stopping the file.
+ *
+ * @param filePath - The path to the NDJSON file
+ * @param options - Processing options; `json` controls the flattening
+ * @returns A promise that resolves to the processed rows and validation errors
+ */
+export function processNdjson(filePath: string, options?: ProcessOptions): Promise<JsonResult>;
+
+/**
+ * Result of processing a fixed-width file: the same as a CSV file, without
+ * the dialect.
+ */
+export interface FixedWidthResult extends Omit<ProcessingResult, 'dialect'> {
+  /** Field names of a single-record layout; empty with several record types */
+  headers: string[];
+}
+
+/**
+ * Processes a fixed-width file, such as a CNAB bank file or a mainframe
+ * export, with the same validation, hashing and deduplication as a CSV file.
+ * Each line is a record cut into fields by the layout, without the padding;
+ * `line` is the line in the file. With several record types, each type has
+ * its own columns and checks, and rows and errors tell their type in
+ * `record`. The encoding is detected as for CSV files (Windows-1252 when not
+ * UTF-8) unless `dialect.encoding` is given.
+ *
+ * @param filePath - The path to the file
+ * @param layout - The layout, plain or compiled with `compileLayout`
+ * @param options - Processing options
+ * @returns A promise that resolves to the processed rows and validation errors
+ * @throws {Error} If the layout is invalid
+ *
+ * @example
+ * ```typescript
+ * import { processFixedWidth } from 'gbr-csv';
+ *
+ * const result = await processFixedWidth('./clientes.txt', {
+ *   fields: [
+ *     { name: 'id', start: 1, length: 6, type: 'int' },
+ *     { name: 'nome', start: 7, length: 30 },
+ *     { name: 'saldo', start: 37, length: 13, type: 'decimal', decimals: 2 }
+ *   ]
+ * });
+ * ```
+ */
+export function processFixedWidth(
+  filePath: string,
+  layout: FixedWidthLayoutDefinition | FixedWidthLayout,
+  options?: ProcessOptions
+): Promise<FixedWidthResult>;
+
+/**
+ * A title (boleto) of a CNAB file: the fields of all its segments together,
+ * without the control fields (bank, batch, record type, sequential number).
+ * Numbers and amounts are numbers, dates are ISO-8601 strings and blank
+ * fields are null; codes such as `agencia` and `conta` keep their leading
+ * zeros as strings.
+ */
+export interface CnabEntry {
+  /** Line of the first segment */
+  line: number;
+  /** Batch number, in CNAB 240 */
+  batch?: number;
+  /** Segments of the title, such as ['P', 'Q', 'R'] or ['T', 'U']; absent in CNAB 400 */
+  segments?: string[];
+  data: Record<string, string | number | null>;
+}
+
+/**
+ * Summary of a CNAB file.
+ */
+export interface CnabFile {
+  format: 'cnab240' | 'cnab400';
+  /** From the file header: remessa (company to bank) or retorno (bank to company) */
+  direction?: 'remessa' | 'retorno';
+  /** Bank code from the file header */
+  bank?: string;
+  /** Number of batches, in CNAB 240 */
+  batches?: number;
+  /** Titles whose segments are all valid, in file order */
+  entries: CnabEntry[];
+}
+
+/**
+ * Result of processing a CNAB file.
+ */
+export interface CnabResult extends Omit<ProcessingResult, 'dialect'> {
+  /** Always empty: each record type has its own fields */
+  headers: string[];
+  cnab: CnabFile;
+}
+
+/**
+ * Processes a CNAB 240 or CNAB 400 collection file (boleto remittance or
+ * return), detected from the file. CNAB 240 uses the Febraban layout, with
+ * segments P, Q and R in remittances and T and U in returns; CNAB 400 has
+ * no Febraban standard and uses the Bradesco layout. Each record is
+ * validated like a fixed-width file and tagged in `record` ('header_arquivo',
+ * 'header_lote', 'segmento_p', 'trailer_lote', 'trailer_arquivo', 'detalhe').
+ *
+ * The file structure is checked into `file_errors`: header and trailer order
+ * (`record_order`, `trailer_missing`, `trailer_repeated`), batch and
+ * sequential numbers (`record_order`), bank and batch numbers that differ
+ * from their header (`header_mismatch`), and the record counts, title counts
+ * and totals informed in the trailers (`trailer_count`, `trailer_sum`).
+ * Trailer totals left as zeros are not checked.
+ *
+ * @param filePath - The path to the CNAB file
+ * @param options - Processing options; `schema` is not used
+ * @returns A promise that resolves to the records, errors and titles
+ * @throws {CsvFatalError} With code `invalid_cnab` if the file is neither CNAB 240 nor CNAB 400
+ *
+ * @example
+ * ```typescript
+ * import { processCnab } from 'gbr-csv';
+ *
+ * const { cnab, file_errors } = await processCnab('./retorno.ret');
+ * for (const { data } of cnab.entries) {
+ *   console.log(data.nosso_numero, data.valor_pago, data.data_credito);
+ * }
+ * ```
+ */
+export function processCnab(filePath: string, options?: ProcessOptions): Promise<CnabResult>;
 
 /**
  * Synchronous version of processCsv for backwards compatibility.
  * @deprecated Use processCsv instead for better performance.
  * 
  * @param filePath - The path to the CSV file to process
+ * @param options - Processing options
  * @returns An object containing processed rows and validation errors
- * @throws {Error} If the file cannot be read or processed
+ * @throws {CsvFatalError} If the file cannot be processed (bad header, limit exceeded)
+ * @throws {Error} If the file cannot be read
  * 
  * @example
  * ```typescript
@@ -66,4 +1023,4 @@ export function processCsv(filePath: string): Promise<ProcessingResult>;
  * console.log(`Processed ${result.processed_rows.length} rows`);
  * ```
  */
-export function processCsvSync(filePath: string): ProcessingResult;
\ No newline at end of file
+export function processCsvSync(filePath: string, options?: ProcessOptions): ProcessingResult;
\ No newline at end of file
@@ -1,27 +1,490 @@
-const fs = require('fs').promises;
-const { process_csv_data } = require('./pkg/processor.js');
+const fs = require('fs');
+const { Readable } = require('stream');
+const {
+  process_csv_bytes,
+  process_csv_columnar,
+  process_spreadsheet_bytes,
+  process_json_data,
+  process_ndjson_data,
+  process_fixed_width_bytes,
+  process_cnab_bytes,
+  list_sheets,
+  sniff_csv,
+  CsvOptions,
+  CsvSchema,
+  FixedWidthLayout,
+  CsvStreamProcessor,
+  CsvSplitter,
+  CsvProfiler
+} = require('./pkg/processor.js');
+
+/**
+ * Compiles a column schema once so it can be reused across many files.
+ *
+ * @param {object} schema The schema definition, e.g. `{ columns: { idade: { type: 'int' } } }`.
+ * @returns {CsvSchema} The compiled schema.
+ */
+function compileSchema(schema) {
+  return new CsvSchema(JSON.stringify(schema));
+}
+
+// Accepts either a compiled CsvSchema or a plain schema object
+function resolveSchema(schema) {
+  if (!schema) return null;
+  return schema instanceof CsvSchema ? schema : compileSchema(schema);
+}
+
+/**
+ * Compiles a fixed-width layout once so it can be reused across many files.
+ *
+ * @param {object} layout The layout definition, e.g. `{ fields: [{ name: 'id', start: 1, length: 5, type: 'int' }] }`.
+ * @returns {FixedWidthLayout} The compiled layout.
+ */
+function compileLayout(layout) {
+  return new FixedWidthLayout(JSON.stringify(layout));
+}
+
+// Number of bytes from the start of the file used to detect the dialect
+const SNIFF_SAMPLE_BYTES = 64 * 1024;
+
+/**
+ * Detects the encoding, delimiter, quote character and header presence of a
+ * CSV file from a sample of its first bytes.
+ *
+ * @param {string} filePath The path to the CSV file.
+ * @returns {object} `{ encoding, bom, delimiter, quote, has_quotes, has_headers }`.
+ */
+function sniffCsv(filePath) {
+  const fd = fs.openSync(filePath, 'r');
+  try {
+    const buffer = Buffer.alloc(SNIFF_SAMPLE_BYTES);
+    const bytesRead = fs.readSync(fd, buffer, 0, buffer.length, 0);
+    return JSON.parse(sniff_csv(buffer.subarray(0, bytesRead)));
+  } finally {
+    fs.closeSync(fd);
+  }
+}
+
+// Builds the wasm CsvOptions from the plain options object. With
+// `dialect: 'auto'` the dialect is detected from the file itself.
+function buildOptions(options, filePath) {
+  const csvOptions = new CsvOptions();
+  let dialect = options.dialect || {};
+  if (dialect === 'auto') {
+    const { encoding, delimiter, quote, has_headers } = sniffCsv(filePath);
+    dialect = { encoding, delimiter, quote, has_headers };
+  }
+
+  if (dialect.delimiter !== undefined) csvOptions.set_delimiter(dialect.delimiter);
+  if (dialect.quote !== undefined) csvOptions.set_quote(dialect.quote);
+  if (dialect.escape !== undefined) csvOptions.set_escape(dialect.escape || undefined);
+  if (dialect.double_quote !== undefined) csvOptions.set_double_quote(dialect.double_quote);
+  if (dialect.comment !== undefined) csvOptions.set_comment(dialect.comment || undefined);
+  if (dialect.has_headers !== undefined) csvOptions.set_has_headers(dialect.has_headers);
+  if (dialect.trim !== undefined) csvOptions.set_trim(dialect.trim);
+  if (dialect.flexible !== undefined) csvOptions.set_flexible(dialect.flexible);
+  if (dialect.encoding !== undefined) csvOptions.set_encoding(dialect.encoding);
+
+  const hash = options.hash || {};
+  if (hash.algorithm !== undefined) csvOptions.set_hash_algorithm(hash.algorithm);
+  if (hash.encoding !== undefined) csvOptions.set_hash_encoding(hash.encoding);
+  if (hash.columns !== undefined) csvOptions.set_hash_columns(hash.columns);
+  if (hash.key !== undefined) csvOptions.set_hash_key(Buffer.from(hash.key));
+
+  const dedup = options.dedup || {};
+  if (dedup.mode !== undefined) csvOptions.set_dedup(dedup.mode);
+  if (dedup.keep !== undefined) csvOptions.set_dedup_keep(dedup.keep);
+  if (dedup.key !== undefined) csvOptions.set_dedup_key(dedup.key);
+
+  const types = typeof options.types === 'string' ? { mode: options.types } : options.types || {};
+  if (types.mode !== undefined) csvOptions.set_types(types.mode);
+  if (types.decimal_separator !== undefined) csvOptions.set_decimal_separator(types.decimal_separator || undefined);
+
+  if (options.field_count !== undefined) csvOptions.set_field_count(options.field_count);
+
+  if (typeof options.sheet === 'number') csvOptions.set_sheet_index(options.sheet);
+  else if (options.sheet !== undefined) csvOptions.set_sheet(options.sheet);
+  if (options.range !== undefined) csvOptions.set_range(options.range);
+
+  const json = options.json || {};
+  if (json.separator !== undefined) csvOptions.set_json_separator(json.separator);
+  if (json.max_depth !== undefined) csvOptions.set_json_max_depth(json.max_depth);
+  if (json.arrays !== undefined) csvOptions.set_json_arrays(json.arrays);
+
+  const stats = typeof options.stats === 'boolean' ? { enabled: options.stats } : { enabled: true, ...options.stats };
+  if (options.stats !== undefined) csvOptions.set_stats(stats.enabled);
+  if (stats.top_k !== undefined) csvOptions.set_stats_top_k(stats.top_k);
+
+  const limits = options.limits || {};
+  if (limits.max_rows !== undefined) csvOptions.set_max_rows(limits.max_rows);
+  if (limits.max_record_bytes !== undefined) csvOptions.set_max_record_bytes(limits.max_record_bytes);
+
+  const schema = resolveSchema(options.schema);
+  if (schema) csvOptions.set_schema(schema);
+
+  return csvOptions;
+}
+
+// Fatal errors thrown by the WASM module start with their kind
+const FATAL_KINDS = ['bad_header', 'invalid_utf8', 'limit_exceeded', 'bad_spreadsheet', 'invalid_json', 'invalid_cnab'];
+
+// Wraps an error from the WASM module, exposing the fatal error kind as `code`
+function wasmError(prefix, e) {
+  const error = new Error(`${prefix}: ${e.message}`);
+  if (FATAL_KINDS.includes(e.kind)) {
+    error.code = e.kind;
+    error.line = e.line;
+    error.byte = e.byte;
+  }
+  return error;
+}
+
+/**
+ * Processes a CSV file in chunks, calling `onBatch` with the rows and errors
+ * completed by each chunk. The file is never held entirely in memory.
+ *
+ * @param {string} filePath The path to the CSV file.
+ * @param {function(object): (void|Promise<void>)} onBatch Called with `{ processed_rows, errors, file_errors }` for every batch.
+ * @param {object} [options] Processing options.
+ * @param {object|CsvSchema} [options.schema] Column schema used to validate each row.
+ * @param {object|string} [options.dialect] CSV dialect: delimiter, quote, escape, double_quote, comment, has_headers, trim, flexible, encoding. Use 'auto' to detect it from the file.
+ * @param {object} [options.hash] Row hashing: algorithm, encoding, columns and an optional HMAC key.
+ * @param {object} [options.dedup] Duplicate detection: mode ('off', 'flag', 'drop'), keep ('first', 'last') and key columns.
+ * @param {object|string} [options.types] Typed values: mode ('off', 'schema', 'infer') and decimal_separator ('.' or ',').
+ * @param {string} [options.field_count] Rows with more or fewer fields than the header: 'strict' (default), 'report', 'pad' or 'extra'.
+ * @param {object} [options.limits] Size limits: max_rows and max_record_bytes. Going over one throws an error with `code: 'limit_exceeded'`.
+ * @param {boolean|object} [options.stats] Add a `stats` block with a profile of each column to the summary; `{ top_k }` sets how many frequent values are listed (default 10).
+ * @param {string|number} [options.sheet] Spreadsheets only (see `processSpreadsheet`): sheet name, or its position starting at 0.
+ * @param {string} [options.range] Spreadsheets only: cells to read, such as 'B3:F200', 'A:D' or 'B3'.
+ * @param {object} [options.json] JSON input only (see `processJson`): separator joining nested keys (default '.'), max_depth of nested objects flattened and arrays ('json' or 'index').
+ * @returns {Promise<object>} A promise that resolves to the summary returned by `finish()`.
+ */
+async function processCsvStream(filePath, onBatch, options = {}) {
+  const csvOptions = buildOptions(options, filePath);
+  const processor = CsvStreamProcessor.with_options(csvOptions);
+  csvOptions.free();
+  try {
+    for await (const chunk of fs.createReadStream(filePath)) {
+      // JSON.parse beats push_chunk_object here too (see bench/RESULTS.md)
+      const batch = JSON.parse(processor.push_chunk(chunk));
+      if (batch.processed_rows.length > 0 || batch.errors.length > 0 || batch.file_errors.length > 0) {
+        await onBatch(batch);
+      }
+    }
+
+    const summary = JSON.parse(processor.finish());
+    const { processed_rows, errors, file_errors, ...totals } = summary;
+    if (processed_rows.length > 0 || errors.length > 0 || file_errors.length > 0) {
+      await onBatch({ processed_rows, errors, file_errors });
+    }
+    return totals;
+  } catch (e) {
+    // Handle file reading errors or other unexpected issues
+    throw wasmError('Failed to process CSV with Wasm module', e);
+  } finally {
+    processor.free();
+  }
+}
+
+/**
+ * Processes a CSV file in chunks and returns a readable stream of NDJSON text:
+ * one JSON object per line, with `type` 'row', 'error', 'file_error' or 'stats', and a final line
+ * with `type` 'summary' holding the totals. Pipe it into anything that reads
+ * JSON Lines.
+ *
+ * @param {string} filePath The path to the CSV file.
+ * @param {object} [options] Processing options, see `processCsvStream`.
+ * @returns {Readable} A stream of NDJSON text.
+ */
+function processCsvNdjson(filePath, options = {}) {
+  async function* lines() {
+    const csvOptions = buildOptions(options, filePath);
+    const processor = CsvStreamProcessor.with_options(csvOptions);
+    csvOptions.free();
+    try {
+      for await (const chunk of fs.createReadStream(filePath)) {
+        const text = processor.push_chunk_ndjson(chunk);
+        if (text) yield text;
+      }
+      yield processor.finish_ndjson();
+    } catch (e) {
+      throw wasmError('Failed to process CSV with Wasm module', e);
+    } finally {
+      processor.free();
+    }
+  }
+  return Readable.from(lines(), { objectMode: false });
+}
 
 /**
  * Processes a CSV file using the high-performance WebAssembly module.
  *
  * @param {string} filePath The path to the CSV file.
+ * @param {object} [options] Processing options, see `processCsvStream`.
  * @returns {Promise<object>} A promise that resolves to an object containing processedRows and errors.
  */
-async function processCsv(filePath) {
+async function processCsv(filePath, options = {}) {
+  const result = { processed_rows: [], errors: [], file_errors: [] };
+
+  // Stream the file through the WASM module and collect every batch
+  const summary = await processCsvStream(filePath, (batch) => {
+    result.processed_rows.push(...batch.processed_rows);
+    result.errors.push(...batch.errors);
+    result.file_errors.push(...batch.file_errors);
+  }, options);
+
+  if (summary.stats) result.stats = summary.stats;
+  result.dialect = summary.dialect;
+  return result;
+}
+
+/**
+ * Profiles the columns of a CSV file without keeping its rows: null rate,
+ * estimated distinct values, min/max, inferred type, most frequent values and
+ * a histogram of value lengths. The rows are still validated, so the totals
+ * count valid and invalid rows as `processCsv` would.
+ *
+ * @param {string} filePath The path to the CSV file.
+ * @param {object} [options] Processing options, see `processCsvStream`.
+ * @returns {Promise<object>} A promise that resolves to `{ ...totals, columns }`.
+ */
+async function profileCsv(filePath, options = {}) {
+  const csvOptions = buildOptions(options, filePath);
+  const profiler = CsvProfiler.with_options(csvOptions);
+  csvOptions.free();
+  try {
+    for await (const chunk of fs.createReadStream(filePath)) {
+      profiler.push_chunk(chunk);
+    }
+    return JSON.parse(profiler.finish());
+  } catch (e) {
+    throw wasmError('Failed to profile CSV with Wasm module', e);
+  } finally {
+    profiler.free();
+  }
+}
+
+/**
+ * Writes the valid rows and the rejected rows of a CSV file to two CSV files,
+ * keeping the original header order and dialect. Rejected rows keep their
+ * original fields and get `error_code` and `error_message` columns.
+ *
+ * @param {string} filePath The path to the CSV file.
+ * @param {object} outputs Where to write: `{ valid, rejected }` file paths; either may be omitted.
+ * @param {object} [options] Processing options, see `processCsvStream`, plus:
+ * @param {boolean} [options.include_line] Append a `line` column to both files.
+ * @param {boolean} [options.include_hash] Append a `hash` column to the valid rows.
+ * @returns {Promise<object>} A promise that resolves to the totals of the file.
+ */
+async function splitCsv(filePath, outputs, options = {}) {
+  const csvOptions = buildOptions(options, filePath);
+  const splitter = CsvSplitter.with_options(csvOptions, !!options.include_line, !!options.include_hash);
+  csvOptions.free();
+  const valid = outputs.valid ? fs.createWriteStream(outputs.valid) : null;
+  const rejected = outputs.rejected ? fs.createWriteStream(outputs.rejected) : null;
+  const write = async (stream, text) => {
+    if (stream && text && !stream.write(text)) {
+      await new Promise((resolve) => stream.once('drain', resolve));
+    }
+  };
+  const close = (stream) => stream && new Promise((resolve, reject) => {
+    stream.once('error', reject);
+    stream.end(resolve);
+  });
+
+  // File-level errors have no place in the CSV outputs and are returned
+  const fileErrors = [];
+
   try {
-    // Read the entire file content asynchronously
-    const csvContent = await fs.readFile(filePath, 'utf8');
+    for await (const chunk of fs.createReadStream(filePath)) {
+      const batch = JSON.parse(splitter.push_chunk(chunk));
+      await write(valid, batch.valid);
+      await write(rejected, batch.rejected);
+      if (batch.file_errors) fileErrors.push(...batch.file_errors);
+    }
 
-    // Call the WASM function with the CSV content string
-    const resultJson = process_csv_data(csvContent);
+    const { valid: lastValid, rejected: lastRejected, file_errors, ...totals } = JSON.parse(splitter.finish());
+    await write(valid, lastValid);
+    await write(rejected, lastRejected);
+    if (file_errors) fileErrors.push(...file_errors);
+    await Promise.all([close(valid), close(rejected)]);
+    return { ...totals, file_errors: fileErrors };
+  } catch (e) {
+    if (valid) valid.destroy();
+    if (rejected) rejected.destroy();
+    throw wasmError('Failed to split CSV with Wasm module', e);
+  } finally {
+    splitter.free();
+  }
+}
 
-    // Parse the JSON result string to a JavaScript object
-    const result = JSON.parse(resultJson);
+/**
+ * Processes a CSV file and returns the valid rows by column instead of by
+ * row: `columns[name]` holds every value of that column, as a Float64Array
+ * when the column is numeric (see the `types` option). Uses far less memory
+ * than one object per row for wide or long files.
+ *
+ * @param {string} filePath The path to the CSV file.
+ * @param {object} [options] Processing options, see `processCsvStream`.
+ * @returns {Promise<object>} A promise that resolves to `{ headers, line, hash, columns, errors, dialect, ...totals }`.
+ */
+async function processCsvColumnar(filePath, options = {}) {
+  let csvOptions;
+  try {
+    csvOptions = buildOptions(options, filePath);
+    const csvBytes = await fs.promises.readFile(filePath);
+    return process_csv_columnar(csvBytes, csvOptions);
+  } catch (e) {
+    throw wasmError('Failed to process CSV with Wasm module', e);
+  } finally {
+    if (csvOptions) csvOptions.free();
+  }
+}
 
-    return result;
+/**
+ * Processes a spreadsheet (.xlsx, .xlsm, .xls or .ods) with the same
+ * validation, hashing and deduplication as a CSV file. Each sheet row is a
+ * record and the first one is the header; `line` is the row number in the
+ * sheet. Numbers come as text as Excel shows them and dates as ISO-8601
+ * ('2024-01-15', '2024-01-15T10:30:00'). The format is detected from the
+ * file contents.
+ *
+ * @param {string|Buffer} filePath The path to the spreadsheet, or its contents already read.
+ * @param {object} [options] Processing options, see `processCsvStream`; `sheet` and `range` choose what is read (default: the first sheet, all of it).
+ * @returns {Promise<object>} A promise that resolves to `{ processed_rows, errors, file_errors, sheet }`. An unreadable file or a missing sheet throws an error with `code: 'bad_spreadsheet'`.
+ */
+async function processSpreadsheet(filePath, options = {}) {
+  let csvOptions;
+  try {
+    csvOptions = buildOptions(options, filePath);
+    const bytes = await readSpreadsheet(filePath);
+    return JSON.parse(process_spreadsheet_bytes(bytes, csvOptions));
   } catch (e) {
-    // Handle file reading errors or other unexpected issues
-    throw new Error(`Failed to process CSV with Wasm module: ${e.message}`);
+    throw wasmError('Failed to process spreadsheet with Wasm module', e);
+  } finally {
+    if (csvOptions) csvOptions.free();
+  }
+}
+
+/**
+ * Lists the sheets of a spreadsheet (.xlsx, .xlsm, .xls or .ods) in tab
+ * order. Chart sheets are left out.
+ *
+ * @param {string|Buffer} filePath The path to the spreadsheet, or its contents already read.
+ * @returns {Promise<string[]>} A promise that resolves to the sheet names.
+ */
+async function listSheets(filePath) {
+  try {
+    const bytes = await readSpreadsheet(filePath);
+    return JSON.parse(list_sheets(bytes));
+  } catch (e) {
+    throw wasmError('Failed to read spreadsheet with Wasm module', e);
+  }
+}
+
+// A spreadsheet given as a Buffer is used as is, so callers reading several
+// sheets open the file only once
+function readSpreadsheet(filePath) {
+  return Buffer.isBuffer(filePath) ? filePath : fs.promises.readFile(filePath);
+}
+
+// Reads a JSON or NDJSON file as text and processes it with `fn`
+async function processJsonWith(fn, filePath, options) {
+  let csvOptions;
+  try {
+    csvOptions = buildOptions(options, filePath);
+    const text = await fs.promises.readFile(filePath, 'utf8');
+    return JSON.parse(fn(text, csvOptions));
+  } catch (e) {
+    throw wasmError('Failed to process JSON with Wasm module', e);
+  } finally {
+    if (csvOptions) csvOptions.free();
+  }
+}
+
+/**
+ * Processes a JSON file holding an array of objects (or a single object)
+ * with the same validation, hashing and deduplication as a CSV file. Each
+ * object is a record and nested objects are flattened into columns such as
+ * 'endereco.cidade'; the columns are every key seen, in order, and a missing
+ * key is an empty field. `line` is the position in the array, starting at 0.
+ * An element that is not an object is rejected with code 'invalid_json'.
+ *
+ * @param {string} filePath The path to the JSON file.
+ * @param {object} [options] Processing options, see `processCsvStream`; `json` controls the flattening.
+ * @returns {Promise<object>} A promise that resolves to `{ processed_rows, errors, file_errors, headers }`. Malformed JSON throws an error with `code: 'invalid_json'`.
+ */
+async function processJson(filePath, options = {}) {
+  return processJsonWith(process_json_data, filePath, options);
+}
+
+/**
+ * Processes an NDJSON (JSON Lines) file, one object per line, like
+ * `processJson`. `line` is the line in the file and blank lines are skipped;
+ * a line that is not a valid JSON object is rejected with code
+ * 'invalid_json' instead of stopping the file.
+ *
+ * @param {string} filePath The path to the NDJSON file.
+ * @param {object} [options] Processing options, see `processJson`.
+ * @returns {Promise<object>} A promise that resolves to `{ processed_rows, errors, file_errors, headers }`.
+ */
+async function processNdjson(filePath, options = {}) {
+  return processJsonWith(process_ndjson_data, filePath, options);
+}
+
+/**
+ * Processes a fixed-width file, such as a CNAB bank file or a mainframe
+ * export, with the same validation, hashing and deduplication as a CSV file.
+ * Each line is a record cut into fields by the layout; when the layout has
+ * several record types (header, detail, trailer), each row and error tells
+ * its type in `record`. `line` is the line in the file.
+ *
+ * @param {string} filePath The path to the file.
+ * @param {object|FixedWidthLayout} layout The layout: `record_length` and either `fields` or `records` (`name`, `match`, `fields`), each field with `name`, `start` (from 1), `length`, schema column rules such as `type`, and optionally `padding`, `align` and `decimals`.
+ * @param {object} [options] Processing options, see `processCsvStream`; `dialect.encoding` sets the encoding (detected by default).
+ * @returns {Promise<object>} A promise that resolves to `{ processed_rows, errors, file_errors, headers }`; `headers` is empty with several record types.
+ */
+async function processFixedWidth(filePath, layout, options = {}) {
+  let csvOptions;
+  let compiled;
+  try {
+    compiled = layout instanceof FixedWidthLayout ? layout : compileLayout(layout);
+    csvOptions = buildOptions(options, filePath);
+    const bytes = await fs.promises.readFile(filePath);
+    return JSON.parse(process_fixed_width_bytes(bytes, compiled, csvOptions));
+  } catch (e) {
+    throw wasmError('Failed to process fixed-width file with Wasm module', e);
+  } finally {
+    if (csvOptions) csvOptions.free();
+    if (compiled && compiled !== layout) compiled.free();
+  }
+}
+
+/**
+ * Processes a CNAB 240 or CNAB 400 collection file (boleto remittance or
+ * return). The format is detected from the file: CNAB 240 uses the Febraban
+ * layout (segments P, Q and R in remittances, T and U in returns) and CNAB
+ * 400 the Bradesco one. Each record is validated like a fixed-width file and
+ * tagged in `record` ('header_arquivo', 'segmento_p', 'trailer_lote'...);
+ * the file structure (headers and trailers, batch numbers, sequential
+ * numbers, record counts and totals) is checked into `file_errors`. Titles
+ * whose segments are all valid are in `cnab.entries`, with numbers, amounts
+ * and dates already typed.
+ *
+ * @param {string} filePath The path to the CNAB file.
+ * @param {object} [options] Processing options, see `processCsvStream`; `schema` is not used.
+ * @returns {Promise<object>} A promise that resolves to `{ processed_rows, errors, file_errors, cnab }`, with `cnab` holding `format`, `direction`, `bank`, `batches` and `entries`. A file that is not CNAB throws an error with `code: 'invalid_cnab'`.
+ */
+async function processCnab(filePath, options = {}) {
+  let csvOptions;
+  try {
+    csvOptions = buildOptions(options, filePath);
+    const bytes = await fs.promises.readFile(filePath);
+    return JSON.parse(process_cnab_bytes(bytes, csvOptions));
+  } catch (e) {
+    throw wasmError('Failed to process CNAB file with Wasm module', e);
+  } finally {
+    if (csvOptions) csvOptions.free();
   }
 }
 
@@ -30,25 +493,31 @@ async function processCsv(filePath) {
  * @deprecated Use processCsv instead for better performance.
  *
  * @param {string} filePath The path to the CSV file.
+ * @param {object} [options] Processing options, see `processCsvStream`.
  * @returns {object} An object containing processedRows and errors.
  */
-function processCsvSync(filePath) {
-  const fsSync = require('fs');
+function processCsvSync(filePath, options = {}) {
+  let csvOptions;
   try {
-    // Read the entire file content synchronously
-    const csvContent = fsSync.readFileSync(filePath, 'utf8');
+    csvOptions = buildOptions(options, filePath);
+
+    // Read the raw file bytes synchronously; the WASM module handles the encoding
+    const csvBytes = fs.readFileSync(filePath);
 
-    // Call the WASM function with the CSV content string
-    const resultJson = process_csv_data(csvContent);
+    // Call the WASM function with the CSV bytes
+    const resultJson = process_csv_bytes(csvBytes, csvOptions);
 
-    // Parse the JSON result string to a JavaScript object
+    // Parse the JSON result string to a JavaScript object. In V8 this is
+    // faster than building the objects from WASM (see bench/RESULTS.md).
     const result = JSON.parse(resultJson);
 
     return result;
   } catch (e) {
     // Handle file reading errors or other unexpected issues
-    throw new Error(`Failed to process CSV with Wasm module: ${e.message}`);
+    throw wasmError('Failed to process CSV with Wasm module', e);
+  } finally {
+    if (csvOptions) csvOptions.free();
   }
 }
 
-module.exports = { processCsv, processCsvSync };
\ No newline at end of file
+module.exports = { processCsv, processCsvSync, processCsvStream, processCsvNdjson, processCsvColumnar, splitCsv, profileCsv, processSpreadsheet, listSheets, processJson, processNdjson, processFixedWidth, processCnab, compileSchema, compileLayout, sniffCsv };
\ No newline at end of file
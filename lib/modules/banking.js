@@ -4,6 +4,12 @@
 
 const { createModule } = require('../module-system');
 
+// A leitura do CNAB fica no módulo WASM; carregado só quando usado, como no
+// módulo Excel
+function core() {
+  return require('../../index');
+}
+
 // Códigos de bancos brasileiros (Febraban)
 const BRAZILIAN_BANKS = {
   '001': 'Banco do Brasil S.A.',
@@ -293,6 +299,35 @@ const bankStatementProcessor = {
   }
 };
 
+// Processador de arquivos CNAB 240 e 400 (remessa e retorno de cobrança)
+const cnabProcessor = {
+  extensions: ['.rem', '.ret'],
+  description: 'CNAB 240/400 collection file processor (Febraban)',
+
+  async process(filePath, options = {}) {
+    console.log(`🧾 Processing CNAB file: ${filePath}`);
+
+    try {
+      const result = await core().processCnab(filePath, options);
+      const { format, direction, bank, entries } = result.cnab;
+      console.log(`  📊 ${format} ${direction || ''}: ${entries.length} titles, ${result.file_errors.length} file errors`);
+
+      return {
+        ...result,
+        format,
+        account_info: {
+          bank_code: bank,
+          bank_name: BRAZILIAN_BANKS[bank]
+        }
+      };
+    } catch (error) {
+      const wrapped = new Error(`CNAB processing failed: ${error.message}`);
+      if (error.code) wrapped.code = error.code;
+      throw wrapped;
+    }
+  }
+};
+
 // Hook para validação de dados bancários
 async function validateBankingData(data) {
   if (!data || !data.processed_rows) return data;
@@ -503,7 +538,7 @@ function validateCNPJ(cnpj) {
 // Criar e exportar o módulo
 const bankingModule = createModule('banking')
   .version('1.0.0')
-  .description('Brazilian banking system with account validation, PIX, fraud detection, and BACEN compliance')
+  .description('Brazilian banking system with account validation, CNAB files, PIX, fraud detection, and BACEN compliance')
   
   // Hooks
   .hook('after:validate', validateBankingData)
@@ -511,6 +546,8 @@ const bankingModule = createModule('banking')
   // Processadores
   .processor('ofx', bankStatementProcessor)
   .processor('qif', bankStatementProcessor)
+  .processor('rem', cnabProcessor)
+  .processor('ret', cnabProcessor)
   
   // Validadores
   .validator('bank_code', validateBankCode)
@@ -1,221 +1,64 @@
 /**
- * Excel Module - Processador de arquivos Excel/XLSX
+ * Excel Module - Processador de arquivos Excel/XLSX e OpenOffice Calc
  */
 
+const fs = require('fs');
 const { createModule } = require('../module-system');
 
-// Simulação de processamento Excel (na prática usaria uma biblioteca como 'xlsx')
+// A leitura das planilhas fica no módulo WASM, com a mesma validação, hash e
+// deduplicação do CSV. Carregado só quando usado, para o módulo poder ser
+// registrado antes do build do WASM.
+function core() {
+  return require('../../index');
+}
+
 const excelProcessor = {
-  extensions: ['.xlsx', '.xls', '.xlsm'],
+  extensions: ['.xlsx', '.xls', '.xlsm', '.ods'],
   description: 'Excel and OpenOffice Calc processor',
-  
+
   async process(filePath, options = {}) {
     console.log(`📊 Processing Excel file: ${filePath}`);
-    
+
     try {
-      // Na implementação real, usaria:
-      // const XLSX = require('xlsx');
-      // const workbook = XLSX.readFile(filePath);
-      
-      // Simulação de processamento
-      const workbook = await this.readExcelFile(filePath);
+      const { processSpreadsheet, listSheets } = core();
+      // O arquivo é lido uma vez e os mesmos bytes servem a todas as planilhas
+      const bytes = await fs.promises.readFile(filePath);
+      const sheets = await listSheets(bytes);
+
+      // Opção para processar apenas planilhas específicas; sem ela, a
+      // primeira, como no Rust e na linha de comando
+      const selected = options.sheets || [options.sheet !== undefined ? options.sheet : 0];
       const processed_rows = [];
       const errors = [];
-      
-      // Processa cada planilha
-      for (const sheetName of Object.keys(workbook.sheets)) {
-        const sheet = workbook.sheets[sheetName];
-        
-        // Opção para processar apenas planilhas específicas
-        if (options.sheets && !options.sheets.includes(sheetName)) {
-          continue;
-        }
-        
-        console.log(`  📄 Processing sheet: ${sheetName}`);
-        
-        const sheetData = this.sheetToJSON(sheet, options);
-        
-        for (let i = 0; i < sheetData.length; i++) {
-          const row = sheetData[i];
-          
-          // Validação básica
-          const validation = await this.validateExcelRow(row, i + 2, sheetName);
-          
-          if (validation.valid) {
-            processed_rows.push({
-              line: i + 2,
-              sheet: sheetName,
-              data: row,
-              hash: this.generateHash(JSON.stringify(row))
-            });
-          } else {
-            errors.push({
-              line: i + 2,
-              sheet: sheetName,
-              error: validation.error,
-              raw_data: row
-            });
-          }
-        }
+      const file_errors = [];
+
+      for (const sheet of selected) {
+        const result = await processSpreadsheet(bytes, { ...options, sheet });
+        const name = result.sheet;
+        console.log(`  📄 Processed sheet: ${name}`);
+        processed_rows.push(...result.processed_rows.map((row) => ({ ...row, sheet: name })));
+        errors.push(...result.errors.map((error) => ({ ...error, sheet: name })));
+        file_errors.push(...result.file_errors.map((error) => ({ ...error, sheet: name })));
       }
-      
+
       return {
         processed_rows,
         errors,
+        file_errors,
         format: 'excel',
-        sheets: Object.keys(workbook.sheets)
+        sheets
       };
-      
+
     } catch (error) {
-      throw new Error(`Excel processing failed: ${error.message}`);
-    }
-  },
-  
-  async readExcelFile(filePath) {
-    // Simulação - na prática usaria XLSX.readFile(filePath)
-    return {
-      sheets: {
-        'Sheet1': {
-          '!ref': 'A1:D10',
-          'A1': { v: 'Nome' },
-          'B1': { v: 'Email' },
-          'C1': { v: 'Idade' },
-          'D1': { v: 'Cidade' },
-          'A2': { v: 'João Silva' },
-          'B2': { v: 'joao@email.com' },
-          'C2': { v: 30 },
-          'D2': { v: 'São Paulo' }
-          // ... mais dados simulados
-        }
-      }
-    };
-  },
-  
-  sheetToJSON(sheet, options = {}) {
-    // Simulação de conversão sheet para JSON
-    // Na prática usaria XLSX.utils.sheet_to_json(sheet, options)
-    return [
-      {
-        'Nome': 'João Silva',
-        'Email': 'joao@email.com',
-        'Idade': 30,
-        'Cidade': 'São Paulo'
-      },
-      {
-        'Nome': 'Maria Santos',
-        'Email': 'maria@email.com',
-        'Idade': 25,
-        'Cidade': 'Rio de Janeiro'
-      }
-      // ... mais dados simulados
-    ];
-  },
-  
-  async validateExcelRow(row, lineNumber, sheetName) {
-    // Validação específica para Excel
-    if (!row || typeof row !== 'object') {
-      return {
-        valid: false,
-        error: 'Invalid row data'
-      };
+      const wrapped = new Error(`Excel processing failed: ${error.message}`);
+      if (error.code) wrapped.code = error.code;
+      throw wrapped;
     }
-    
-    // Verifica se há campos vazios
-    const emptyFields = Object.entries(row)
-      .filter(([key, value]) => !value || value.toString().trim() === '')
-      .map(([key]) => key);
-    
-    if (emptyFields.length > 0) {
-      return {
-        valid: false,
-        error: `Empty fields: ${emptyFields.join(', ')}`
-      };
-    }
-    
-    return { valid: true };
-  },
-  
-  generateHash(content) {
-    // Simulação de hash - na prática usaria crypto
-    const hash = Buffer.from(content).toString('base64').substring(0, 16);
-    return `excel_${hash}`;
   }
 };
 
-// Hook para pré-processamento de dados Excel
-async function preprocessExcelData(data) {
-  if (!data || !data.format || data.format !== 'excel') {
-    return data;
-  }
-  
-  console.log(`📊 Excel preprocessing: ${data.processed_rows.length} rows`);
-  
-  // Limpa espaços em branco extras
-  data.processed_rows.forEach(row => {
-    if (row.data && typeof row.data === 'object') {
-      Object.keys(row.data).forEach(key => {
-        if (typeof row.data[key] === 'string') {
-          row.data[key] = row.data[key].trim();
-        }
-      });
-    }
-  });
-  
-  return data;
-}
-
-// Hook para converter tipos de dados Excel
-async function convertExcelTypes(data) {
-  if (!data || !data.format || data.format !== 'excel') {
-    return data;
-  }
-  
-  console.log('🔄 Converting Excel data types...');
-  
-  data.processed_rows.forEach(row => {
-    if (row.data && typeof row.data === 'object') {
-      Object.keys(row.data).forEach(key => {
-        const value = row.data[key];
-        
-        // Converte datas do Excel
-        if (this.isExcelDate(value)) {
-          row.data[key] = this.excelDateToJS(value);
-        }
-        
-        // Converte números
-        if (typeof value === 'string' && /^\d+\.?\d*$/.test(value)) {
-          row.data[key] = parseFloat(value);
-        }
-        
-        // Converte booleanos
-        if (typeof value === 'string') {
-          const lower = value.toLowerCase();
-          if (lower === 'true' || lower === 'verdadeiro' || lower === 'sim') {
-            row.data[key] = true;
-          } else if (lower === 'false' || lower === 'falso' || lower === 'não') {
-            row.data[key] = false;
-          }
-        }
-      });
-    }
-  });
-  
-  return data;
-}
-
 // Utilitários para Excel
 const excelUtils = {
-  isExcelDate(value) {
-    // Detecta se valor é uma data do Excel
-    return typeof value === 'number' && value > 25569 && value < 2958465;
-  },
-  
-  excelDateToJS(excelDate) {
-    // Converte data do Excel para JavaScript
-    const date = new Date((excelDate - 25569) * 86400 * 1000);
-    return date.toISOString().split('T')[0];
-  },
-  
   getColumnName(columnIndex) {
     // Converte índice para nome da coluna (A, B, C, ..., AA, AB)
     let result = '';
@@ -225,11 +68,11 @@ const excelUtils = {
     }
     return result;
   },
-  
-  parseRange(range) {
-    // Parse range like "A1:D10"
-    const [start, end] = range.split(':');
-    return { start, end };
+
+  // Campo CSV com aspas quando necessário
+  csvField(value) {
+    const text = value === null || value === undefined ? '' : String(value);
+    return /[",\r\n]/.test(text) ? `"${text.replace(/"/g, '""')}"` : text;
   }
 };
 
@@ -243,64 +86,71 @@ const excelCommands = {
         console.error('❌ File path required');
         return;
       }
-      
+
       try {
-        const workbook = await excelProcessor.readExcelFile(file);
-        const sheets = Object.keys(workbook.sheets);
-        
+        const sheets = await core().listSheets(file);
+
         console.log(`📊 Sheets in ${file}:`);
         sheets.forEach((sheet, index) => {
           console.log(`  ${index + 1}. ${sheet}`);
         });
-        
+
         return sheets;
       } catch (error) {
         console.error('❌ Error reading Excel file:', error.message);
       }
     }
   },
-  
+
   'excel:convert': {
     description: 'Convert Excel to CSV',
     handler: async (args) => {
-      const { input, output, sheet } = args;
+      const { input, output, sheet, range } = args;
       if (!input || !output) {
         console.error('❌ Input and output paths required');
         return;
       }
-      
+
       console.log(`🔄 Converting ${input} to ${output}`);
       if (sheet) {
         console.log(`📄 Processing sheet: ${sheet}`);
       }
-      
-      // Implementação da conversão seria aqui
-      console.log('✅ Conversion completed');
+
+      try {
+        // Só as linhas válidas, na ordem das colunas da planilha
+        const result = await core().processSpreadsheet(input, { sheet, range, field_count: 'pad' });
+        const { headers } = result;
+        const lines = [headers, ...result.processed_rows.map((row) => headers.map((h) => row.data[h]))]
+          .map((fields) => fields.map(excelUtils.csvField).join(','));
+        await fs.promises.writeFile(output, lines.join('\n') + '\n');
+
+        console.log(`✅ Conversion completed: ${result.processed_rows.length} rows, ${result.errors.length} errors`);
+        return result;
+      } catch (error) {
+        console.error('❌ Error converting Excel file:', error.message);
+      }
     }
   }
 };
 
 // Criar e exportar o módulo
 const excelModule = createModule('excel')
-  .version('1.0.0')
-  .description('Excel/XLSX processor with multi-sheet support and data type conversion')
-  
-  // Hooks
-  .hook('after:process', preprocessExcelData)
-  .hook('after:process', convertExcelTypes)
-  
+  .version('1.1.0')
+  .description('Excel/XLSX/ODS processor with multi-sheet support, powered by the WASM core')
+
   // Processador
   .processor('xlsx', excelProcessor)
   .processor('xls', excelProcessor)
   .processor('xlsm', excelProcessor)
-  
+  .processor('ods', excelProcessor)
+
   // Comandos CLI
   .command('excel:sheets', excelCommands['excel:sheets'])
   .command('excel:convert', excelCommands['excel:convert'])
-  
+
   .build();
 
 // Adicionar utilitários ao módulo
 excelModule.utils = excelUtils;
 
-module.exports = excelModule;
\ No newline at end of file
+module.exports = excelModule;
@@ -0,0 +1,704 @@
+// Linha de comando do processador: lê arquivos CSV (ou a entrada padrão) e
+// planilhas, valida cada linha e escreve o resultado em JSON, NDJSON ou CSV.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use processor::{
+    process_cnab, process_fixed_width, process_json, process_ndjson, process_reader, process_spreadsheet, write_ndjson,
+    write_ndjson_summary, CsvOptions, CsvSchema, FatalError, FixedWidthLayout, ProcessingResult, ProcessingTotals,
+    SplitWriter,
+};
+use serde::Serialize;
+
+const USAGE: &str = "\
+Usage: gbr-process [OPTIONS] [FILE]...
+
+Validates CSV files and writes the processed rows and errors. Reads stdin
+when no FILE (or \"-\") is given. Files ending in .xlsx, .xlsm, .xls or .ods
+are read as spreadsheets: each sheet row is a record, the first one the header.
+Files ending in .json (an array of objects) and .ndjson or .jsonl (one object
+per line) are read as JSON: each object is a record and its keys the columns.
+With --layout, files are read as fixed-width records (mainframe exports, bank
+layouts). Files ending in .rem or .ret are read as CNAB 240 or 400 (Febraban).
+
+Output:
+  -f, --format <FORMAT>          json (default), ndjson or csv (valid rows only)
+  -o, --output <FILE>            Write to FILE instead of stdout
+      --rejected <FILE>          With csv, write rejected rows to FILE with
+                                 error_code and error_message columns
+      --with-line                With csv, append the line number column
+      --with-hash                With csv, append the hash column to valid rows
+      --stats                    With json or ndjson, add column statistics (null rate,
+                                 distinct values, min/max, type, top values, lengths)
+      --stats-top-k <N>          Most frequent values listed per column (default 10)
+  -q, --quiet                    Do not print the summary to stderr
+
+Validation:
+  -s, --schema <FILE>            JSON schema used to validate each column
+      --types <MODE>             off (default), schema or infer
+      --decimal-separator <C>    '.' or ','; decided per value by default
+      --field-count <MODE>       Rows with too many or too few fields: strict (default),
+                                 report, pad or extra (pad and keep the rest in _extra)
+  -j, --threads <N>              Validate and hash on N threads (default: all cores)
+      --max-rows <N>             Stop with an error after N data rows
+      --max-record-bytes <N>     Stop with an error on a record larger than N bytes
+
+Input:
+      --input-format <FORMAT>    csv, json, ndjson, spreadsheet, fixed-width or cnab
+                                 (default: fixed-width with --layout, else from the
+                                 file extension; csv for stdin)
+      --layout <FILE>            JSON layout of a fixed-width file: the start, length,
+                                 type and padding of each field, per record type
+
+Spreadsheets:
+      --sheet <NAME>             Sheet to read (default: the first one)
+      --sheet-index <N>          Sheet to read by position, starting at 0
+      --range <RANGE>            Cells to read, such as B3:F200, A:D or B3
+
+JSON:
+      --json-separator <SEP>     Joins nested keys into column names (default '.')
+      --json-max-depth <N>       Nested objects flattened; deeper ones are kept as JSON text
+      --json-arrays <MODE>       json (default, the array as JSON text) or index
+                                 (one column per item: tags.0, tags.1, ...)
+
+Dialect:
+  -d, --delimiter <C>            Field delimiter (default ',')
+      --quote <C>                Quote character (default '\"')
+      --escape <C>               Escape character inside quoted fields
+      --no-double-quote          Do not treat \"\" as an escaped quote
+      --comment <C>              Skip lines starting with this character
+      --no-headers               The first line is data; columns are column_1, column_2, ...
+      --trim <MODE>              none (default), headers, fields or all
+      --flexible                 Accept rows with a different number of fields
+      --encoding <ENC>           auto (default), utf-8, utf-16le, utf-16be or windows-1252
+
+Hashing:
+      --hash-algorithm <ALG>     sha256 (default), sha512, blake3 or xxh3
+      --hash-encoding <ENC>      joined (default), canonical, length_prefixed or json
+      --hash-columns <A,B,...>   Columns included in the hash
+      --hash-key <KEY>           Secret key; turns the hash into an HMAC
+
+Duplicates:
+      --dedup <MODE>             off (default), flag or drop
+      --dedup-keep <WHICH>       first (default) or last
+      --dedup-key <A,B,...>      Columns identifying a duplicate (default: all columns)
+
+  -h, --help                     Print this help
+  -V, --version                  Print the version
+
+Exit status:
+  0  every row is valid
+  1  at least one row was rejected or a file check failed
+  2  invalid arguments or schema
+  3  a file could not be read or written
+  4  a file could not be processed (bad header, limit exceeded, unreadable spreadsheet,
+     invalid JSON, not a CNAB file)
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+// Formato de um arquivo de entrada
+#[derive(Clone, Copy, PartialEq)]
+enum InputFormat {
+    Csv,
+    Json,
+    Ndjson,
+    Spreadsheet,
+    FixedWidth,
+    Cnab,
+}
+
+struct Args {
+    format: Format,
+    output: Option<String>,
+    stats: bool,
+    quiet: bool,
+    rejected: Option<String>,
+    with_line: bool,
+    with_hash: bool,
+    inputs: Vec<String>,
+    // Formato de todas as entradas; sem ele, vem da extensão de cada arquivo
+    input_format: Option<InputFormat>,
+    layout: Option<FixedWidthLayout>,
+    options: CsvOptions,
+}
+
+// Falha de leitura ou escrita, com o arquivo envolvido
+struct Failure {
+    path: String,
+    error: io::Error,
+}
+
+impl Failure {
+    fn io(path: impl Into<String>) -> impl FnOnce(io::Error) -> Failure {
+        let path = path.into();
+        move |error| Failure { path, error }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => return ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("gbr-process: {}", message);
+            eprintln!("Try 'gbr-process --help' for more information.");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(Failure { path, error }) => {
+            eprintln!("gbr-process: {}: {}", path, error);
+            // Erros fatais do processamento chegam como InvalidData
+            let fatal = error.get_ref().is_some_and(|inner| inner.is::<FatalError>());
+            ExitCode::from(if fatal { 4 } else { 3 })
+        }
+    }
+}
+
+fn parse_args(raw: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut args = Args {
+        format: Format::Json,
+        output: None,
+        stats: false,
+        quiet: false,
+        rejected: None,
+        with_line: false,
+        with_hash: false,
+        inputs: Vec::new(),
+        input_format: None,
+        layout: None,
+        options: CsvOptions::new(),
+    };
+    let options = &mut args.options;
+    let mut raw = raw.peekable();
+    let mut only_files = false;
+
+    while let Some(arg) = raw.next() {
+        if only_files || arg == "-" || !arg.starts_with('-') {
+            args.inputs.push(arg);
+            continue;
+        }
+        // Aceita tanto "--opcao valor" quanto "--opcao=valor"
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || -> Result<String, String> {
+            match inline.clone() {
+                Some(value) => Ok(value),
+                None => raw.next().ok_or_else(|| format!("option '{}' requires a value", name)),
+            }
+        };
+
+        match name.as_str() {
+            "--" => only_files = true,
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return Ok(None);
+            }
+            "-V" | "--version" => {
+                println!("gbr-process {}", env!("CARGO_PKG_VERSION"));
+                return Ok(None);
+            }
+            "-f" | "--format" => {
+                args.format = match value()?.as_str() {
+                    "json" => Format::Json,
+                    "ndjson" => Format::Ndjson,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("unknown format '{}'", other)),
+                }
+            }
+            "-o" | "--output" => args.output = Some(value()?),
+            "--stats" => {
+                args.stats = true;
+                options.set_stats(true);
+            }
+            "--stats-top-k" => options.set_stats_top_k(number_arg(&name, &value()?)?),
+            "-q" | "--quiet" => args.quiet = true,
+            "--rejected" => args.rejected = Some(value()?),
+            "--with-line" => args.with_line = true,
+            "--with-hash" => args.with_hash = true,
+            "-s" | "--schema" => {
+                let path = value()?;
+                let json = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                let schema = CsvSchema::new(&json).map_err(|e| format!("{}: {}", path, e))?;
+                options.set_schema(&schema);
+            }
+            "--types" => options.set_types(&value()?).map_err(|e| e.to_string())?,
+            "--decimal-separator" => {
+                options.set_decimal_separator(Some(char_arg(&name, &value()?)?)).map_err(|e| e.to_string())?
+            }
+            "--field-count" => options.set_field_count(&value()?).map_err(|e| e.to_string())?,
+            "-j" | "--threads" => match number_arg(&name, &value()?)? {
+                0 => return Err(format!("option '{}' expects at least 1 thread", name)),
+                1 => options.set_parallel(false),
+                threads => rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads as usize)
+                    .build_global()
+                    .map_err(|e| e.to_string())?,
+            },
+            "--max-rows" => options.set_max_rows(Some(number_arg(&name, &value()?)?)),
+            "--max-record-bytes" => options.set_max_record_bytes(Some(number_arg(&name, &value()?)?)),
+            "--input-format" => {
+                args.input_format = Some(match value()?.as_str() {
+                    "csv" => InputFormat::Csv,
+                    "json" => InputFormat::Json,
+                    "ndjson" => InputFormat::Ndjson,
+                    "spreadsheet" => InputFormat::Spreadsheet,
+                    "fixed-width" => InputFormat::FixedWidth,
+                    "cnab" => InputFormat::Cnab,
+                    other => return Err(format!("unknown input format '{}'", other)),
+                })
+            }
+            "--layout" => {
+                let path = value()?;
+                let json = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                args.layout = Some(FixedWidthLayout::new(&json).map_err(|e| format!("{}: {}", path, e))?);
+            }
+            "--sheet" => options.set_sheet(&value()?),
+            "--sheet-index" => options.set_sheet_index(number_arg(&name, &value()?)?),
+            "--range" => options.set_range(&value()?).map_err(|e| e.to_string())?,
+            "--json-separator" => options.set_json_separator(&value()?).map_err(|e| e.to_string())?,
+            "--json-max-depth" => options.set_json_max_depth(Some(number_arg(&name, &value()?)?)),
+            "--json-arrays" => options.set_json_arrays(&value()?).map_err(|e| e.to_string())?,
+            "-d" | "--delimiter" => options.set_delimiter(char_arg(&name, &value()?)?).map_err(|e| e.to_string())?,
+            "--quote" => options.set_quote(char_arg(&name, &value()?)?).map_err(|e| e.to_string())?,
+            "--escape" => options.set_escape(Some(char_arg(&name, &value()?)?)).map_err(|e| e.to_string())?,
+            "--no-double-quote" => options.set_double_quote(false),
+            "--comment" => options.set_comment(Some(char_arg(&name, &value()?)?)).map_err(|e| e.to_string())?,
+            "--no-headers" => options.set_has_headers(false),
+            "--trim" => options.set_trim(&value()?).map_err(|e| e.to_string())?,
+            "--flexible" => options.set_flexible(true),
+            "--encoding" => options.set_encoding(&value()?).map_err(|e| e.to_string())?,
+            "--hash-algorithm" => options.set_hash_algorithm(&value()?).map_err(|e| e.to_string())?,
+            "--hash-encoding" => options.set_hash_encoding(&value()?).map_err(|e| e.to_string())?,
+            "--hash-columns" => options.set_hash_columns(list_arg(&value()?)),
+            "--hash-key" => options.set_hash_key(value()?.as_bytes()).map_err(|e| e.to_string())?,
+            "--dedup" => options.set_dedup(&value()?).map_err(|e| e.to_string())?,
+            "--dedup-keep" => options.set_dedup_keep(&value()?).map_err(|e| e.to_string())?,
+            "--dedup-key" => options.set_dedup_key(list_arg(&value()?)),
+            _ => return Err(format!("unknown option '{}'", name)),
+        }
+    }
+
+    if args.inputs.is_empty() {
+        args.inputs.push("-".to_string());
+    }
+    if args.format != Format::Csv && (args.rejected.is_some() || args.with_line || args.with_hash) {
+        return Err("--rejected, --with-line and --with-hash require --format csv".to_string());
+    }
+    match &args.layout {
+        None if args.input_format == Some(InputFormat::FixedWidth) => {
+            return Err("--input-format fixed-width requires --layout".to_string());
+        }
+        Some(_) if args.input_format.is_some_and(|f| f != InputFormat::FixedWidth) => {
+            return Err("--layout only applies to --input-format fixed-width".to_string());
+        }
+        Some(layout) if args.format == Format::Csv && !layout.record_types().is_empty() => {
+            return Err("--format csv requires a layout with a single record type".to_string());
+        }
+        _ => {}
+    }
+    // Os registros do CNAB têm colunas diferentes por tipo
+    let cnab = |input: &String| args.input_format.unwrap_or_else(|| input_format(input)) == InputFormat::Cnab;
+    if args.format == Format::Csv && args.layout.is_none() && args.inputs.iter().any(cnab) {
+        return Err("--format csv does not apply to CNAB files".to_string());
+    }
+    if args.format == Format::Csv && args.stats {
+        return Err("--stats requires --format json or ndjson".to_string());
+    }
+    Ok(Some(args))
+}
+
+fn char_arg(name: &str, value: &str) -> Result<char, String> {
+    // "\t" na linha de comando representa a tabulação
+    let value = if value == "\\t" { "\t" } else { value };
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("option '{}' expects a single character, got '{}'", name, value)),
+    }
+}
+
+fn number_arg(name: &str, value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("option '{}' expects a number, got '{}'", name, value))
+}
+
+fn list_arg(value: &str) -> Vec<String> {
+    value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect()
+}
+
+// Processa todas as entradas. Devolve false se alguma linha foi rejeitada.
+fn run(args: &Args) -> Result<bool, Failure> {
+    let output_name = args.output.clone().unwrap_or_else(|| "<stdout>".to_string());
+    let sink: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path).map_err(Failure::io(path))?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let rejected_name = args.rejected.clone().unwrap_or_default();
+    let rejected: Box<dyn Write> = match &args.rejected {
+        Some(path) => Box::new(File::create(path).map_err(Failure::io(path))?),
+        None => Box::new(io::sink()),
+    };
+    let mut out = Output::new(args, sink, rejected);
+    let mut all_valid = true;
+
+    for (index, input) in args.inputs.iter().enumerate() {
+        let name = if input == "-" { "<stdin>" } else { input.as_str() };
+
+        out.begin_file(index).map_err(Failure::io(&output_name))?;
+        let mut write_error = None;
+        let mut rejected = false;
+        // Na saída csv os erros do arquivo não têm onde ir e vão para o stderr
+        let mut file_errors = Vec::new();
+        let on_batch = |batch: ProcessingResult| {
+            rejected |= batch.has_errors() || !batch.file_errors().is_empty();
+            file_errors.extend(batch.file_errors().iter().map(|e| e.message().to_string()));
+            if write_error.is_none() {
+                write_error = out.batch(batch).err();
+            }
+        };
+        let totals = match read_input(args, input, name, on_batch) {
+            Ok(totals) => totals,
+            Err(failure) => {
+                // A saída é fechada antes de sair, para continuar válida; o
+                // erro de escrita, se houver, fica atrás do erro da leitura
+                let _ = out.fail_file(name, &failure.error);
+                return Err(failure);
+            }
+        };
+        if let Some(e) = write_error {
+            return Err(Failure { path: out.failed_sink(&output_name, &rejected_name), error: e });
+        }
+        out.end_file(name, totals).map_err(Failure::io(&output_name))?;
+
+        all_valid &= !rejected;
+        if !args.quiet {
+            if args.format == Format::Csv {
+                for error in &file_errors {
+                    eprintln!("{}: {}", name, error);
+                }
+            }
+            let file_summary = match file_errors.len() {
+                0 => String::new(),
+                n => format!(", {} file errors", n),
+            };
+            eprintln!(
+                "{}: {} rows, {} valid, {} invalid, {} duplicates{}",
+                name, totals.total_rows, totals.valid_rows, totals.invalid_rows, totals.duplicate_rows, file_summary
+            );
+        }
+    }
+
+    if let Err(e) = out.finish() {
+        return Err(Failure { path: out.failed_sink(&output_name, &rejected_name), error: e });
+    }
+    Ok(all_valid)
+}
+
+// Lê e processa uma entrada, entregando os lotes a `on_batch`
+fn read_input<F>(args: &Args, input: &str, name: &str, mut on_batch: F) -> Result<ProcessingTotals, Failure>
+where
+    F: FnMut(ProcessingResult),
+{
+    let reader = || -> Result<Box<dyn Read>, Failure> {
+        Ok(if input == "-" {
+            Box::new(io::stdin().lock())
+        } else {
+            Box::new(File::open(input).map_err(Failure::io(input))?)
+        })
+    };
+    let format = match (&args.input_format, &args.layout) {
+        (Some(format), _) => *format,
+        (None, Some(_)) => InputFormat::FixedWidth,
+        (None, None) => input_format(input),
+    };
+    if format == InputFormat::Csv {
+        return process_reader(reader()?, &args.options, on_batch).map_err(Failure::io(name));
+    }
+
+    // Os demais formatos são lidos inteiros: o índice do ZIP fica no fim do
+    // arquivo, e as colunas do JSON só se conhecem no fim
+    let mut data = Vec::new();
+    reader()?.read_to_end(&mut data).map_err(Failure::io(name))?;
+    let processed = match (format, &args.layout) {
+        (InputFormat::Spreadsheet, _) => process_spreadsheet(&data, &args.options),
+        (InputFormat::FixedWidth, Some(layout)) => process_fixed_width(&data, layout, &args.options),
+        (InputFormat::Cnab, _) => process_cnab(&data, &args.options),
+        _ => {
+            let text = std::str::from_utf8(&data)
+                .map_err(|e| Failure::io(name)(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+            match format {
+                InputFormat::Json => process_json(text, &args.options),
+                _ => process_ndjson(text, &args.options),
+            }
+        }
+    };
+    let (result, totals) = processed.map_err(|e| Failure::io(name)(e.into()))?;
+    on_batch(result);
+    Ok(totals)
+}
+
+// Planilhas, JSON e CNAB são reconhecidos pela extensão; o resto é lido como CSV
+fn input_format(path: &str) -> InputFormat {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("xlsx" | "xlsm" | "xls" | "ods") => InputFormat::Spreadsheet,
+        Some("json") => InputFormat::Json,
+        Some("ndjson" | "jsonl") => InputFormat::Ndjson,
+        Some("rem" | "ret") => InputFormat::Cnab,
+        _ => InputFormat::Csv,
+    }
+}
+
+// Arquivo interrompido por um erro, no lugar do resultado no array json
+#[derive(Serialize)]
+struct FileFailure<'a> {
+    file: &'a str,
+    // Tipo do erro fatal, como `code` no JavaScript
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
+    error: String,
+}
+
+// Resultado de um arquivo no formato json
+#[derive(Serialize)]
+struct FileReport<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(flatten)]
+    result: &'a ProcessingResult,
+    #[serde(flatten)]
+    totals: ProcessingTotals,
+}
+
+type Sink = BufWriter<Box<dyn Write>>;
+
+// Escrita do resultado, com o estado próprio de cada formato
+enum Output {
+    // Acumula o resultado do arquivo atual; vários arquivos viram um array
+    Json { writer: Sink, multiple: bool, result: ProcessingResult },
+    Ndjson { writer: Sink },
+    // Linhas válidas na saída e rejeitadas em --rejected, na ordem das
+    // colunas e no dialeto do arquivo lido
+    Csv { writer: Box<SplitWriter>, valid: Sink, rejected: Sink, failed_rejected: bool },
+}
+
+impl Output {
+    fn new(args: &Args, sink: Box<dyn Write>, rejected: Box<dyn Write>) -> Self {
+        let writer = BufWriter::new(sink);
+        match args.format {
+            Format::Json => Output::Json {
+                writer,
+                multiple: args.inputs.len() > 1,
+                result: ProcessingResult::default(),
+            },
+            Format::Ndjson => Output::Ndjson { writer },
+            Format::Csv => {
+                let mut split = SplitWriter::new(&args.options);
+                split.set_include_line(args.with_line);
+                split.set_include_hash(args.with_hash);
+                Output::Csv {
+                    writer: Box::new(split),
+                    valid: writer,
+                    rejected: BufWriter::new(rejected),
+                    failed_rejected: false,
+                }
+            }
+        }
+    }
+
+    fn begin_file(&mut self, index: usize) -> io::Result<()> {
+        match self {
+            Output::Json { writer, multiple, result } => {
+                *result = ProcessingResult::default();
+                if *multiple {
+                    writer.write_all(if index == 0 { b"[" } else { b"," })?;
+                }
+            }
+            Output::Ndjson { .. } | Output::Csv { .. } => {}
+        }
+        Ok(())
+    }
+
+    fn batch(&mut self, batch: ProcessingResult) -> io::Result<()> {
+        match self {
+            Output::Json { result, .. } => result.append(batch),
+            Output::Ndjson { writer } => write_ndjson(writer, &batch)?,
+            Output::Csv { writer, valid, rejected, failed_rejected } => {
+                let mut rejected = Tracked { inner: rejected, failed: failed_rejected };
+                writer.write_batch(&batch, valid, &mut rejected)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end_file(&mut self, name: &str, totals: ProcessingTotals) -> io::Result<()> {
+        match self {
+            Output::Json { writer, multiple, result } => {
+                let report = FileReport {
+                    file: multiple.then_some(name),
+                    result,
+                    totals,
+                };
+                serde_json::to_writer(writer, &report)?;
+            }
+            Output::Ndjson { writer } => write_ndjson_summary(writer, Some(name), totals)?,
+            Output::Csv { .. } => {}
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Output::Json { writer, multiple, .. } => {
+                if *multiple {
+                    writer.write_all(b"]")?;
+                }
+                writer.write_all(b"\n")?;
+                writer.flush()
+            }
+            Output::Ndjson { writer } => writer.flush(),
+            Output::Csv { writer, valid, rejected, failed_rejected } => {
+                let mut rejected = Tracked { inner: rejected, failed: failed_rejected };
+                writer.finish(valid, &mut rejected)?;
+                valid.flush()?;
+                rejected.flush()
+            }
+        }
+    }
+
+    // Fecha a saída depois de um erro na leitura de `name`. No json com
+    // vários arquivos o erro ocupa o lugar do resultado e o array é fechado;
+    // no csv as rejeitadas retidas são escritas.
+    fn fail_file(&mut self, name: &str, error: &io::Error) -> io::Result<()> {
+        match self {
+            Output::Json { writer, multiple: true, .. } => {
+                let fatal = error.get_ref().and_then(|inner| inner.downcast_ref::<FatalError>());
+                let failure = FileFailure {
+                    file: name,
+                    code: fatal.map(|f| f.kind().as_str()),
+                    error: error.to_string(),
+                };
+                serde_json::to_writer(&mut *writer, &failure)?;
+            }
+            Output::Json { .. } | Output::Ndjson { .. } => return Ok(()),
+            Output::Csv { .. } => {}
+        }
+        self.finish()
+    }
+
+    // Arquivo em que a escrita falhou
+    fn failed_sink(&self, output: &str, rejected: &str) -> String {
+        match self {
+            Output::Csv { failed_rejected: true, .. } => rejected.to_string(),
+            _ => output.to_string(),
+        }
+    }
+}
+
+// Escrita no arquivo de rejeitadas que registra se falhou, para a mensagem
+// de erro apontar o arquivo certo
+struct Tracked<'a> {
+    inner: &'a mut Sink,
+    failed: &'a mut bool,
+}
+
+impl Write for Tracked<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf).inspect_err(|_| *self.failed = true)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().inspect_err(|_| *self.failed = true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Arquivo temporário com `content`, removido no fim do teste
+    struct Temp(std::path::PathBuf);
+
+    impl Temp {
+        fn new(name: &str, content: &str) -> Temp {
+            let path = std::env::temp_dir().join(format!("gbr-process-{}-{}", std::process::id(), name));
+            std::fs::write(&path, content).unwrap();
+            Temp(path)
+        }
+
+        fn path(&self) -> String {
+            self.0.to_string_lossy().into_owned()
+        }
+
+        fn read(&self) -> String {
+            std::fs::read_to_string(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for Temp {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn run_with(args: &[String]) -> Result<bool, Failure> {
+        let args = parse_args(args.iter().cloned()).unwrap().unwrap();
+        run(&args)
+    }
+
+    #[test]
+    fn json_array_is_closed_after_a_fatal_error() {
+        let first = Temp::new("primeiro.csv", "id\n1\n");
+        let second = Temp::new("segundo.csv", "id\n1\n2\n3\n");
+        let output = Temp::new("saida.json", "");
+        let args = ["--max-rows", "2", "-q", "-o"].map(String::from);
+        let inputs = [output.path(), first.path(), second.path()];
+
+        let failure = run_with(&[&args[..], &inputs[..]].concat()).err().unwrap();
+        assert_eq!(failure.path, second.path());
+        let report: serde_json::Value = serde_json::from_str(&output.read()).unwrap();
+        let files = report.as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0]["total_rows"], 1);
+        assert_eq!(files[1]["file"], second.path().as_str());
+        assert_eq!(files[1]["code"], "limit_exceeded");
+
+        // Um arquivo que não existe também fecha o array
+        let missing = Temp::new("ausente.csv", "");
+        std::fs::remove_file(&missing.0).unwrap();
+        let inputs = [output.path(), first.path(), missing.path()];
+        assert!(run_with(&[&args[..], &inputs[..]].concat()).is_err());
+        let report: serde_json::Value = serde_json::from_str(&output.read()).unwrap();
+        assert_eq!(report[1]["file"], missing.path().as_str());
+        assert!(report[1].get("code").is_none());
+    }
+
+    #[test]
+    fn csv_rejected_rows_are_written_after_a_fatal_error() {
+        // As rejeitadas do primeiro arquivo ficam retidas até o fim; o erro
+        // no segundo não pode perdê-las
+        let first = Temp::new("entrada.csv", "id,nome\n1,\n2,Bia\n");
+        let second = Temp::new("longo.csv", "id,nome\n3,Caio\n4,Duda\n5,Eva\n");
+        let (valid, rejected) = (Temp::new("validas.csv", ""), Temp::new("rejeitadas.csv", ""));
+        let args = ["-f", "csv", "--max-rows", "2", "-q", "-o", &valid.path(), "--rejected", &rejected.path()]
+            .map(String::from);
+
+        assert!(run_with(&[&args[..], &[first.path(), second.path()]].concat()).is_err());
+        assert_eq!(valid.read(), "id,nome\n2,Bia\n");
+        let expected = "id,nome,error_code,error_message\n1,,empty_fields,Row contains empty fields: nome.\n";
+        assert_eq!(rejected.read(), expected);
+    }
+}
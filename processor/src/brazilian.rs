@@ -0,0 +1,95 @@
+// Validadores de documentos brasileiros, portados de lib/modules/brazilian.js
+// para rodar na mesma passada da validação do CSV
+
+const UFS: [&str; 27] = [
+    "AC", "AL", "AP", "AM", "BA", "CE", "DF", "ES", "GO", "MA", "MT", "MS", "MG", "PA",
+    "PB", "PR", "PE", "PI", "RJ", "RN", "RS", "RO", "RR", "SC", "SP", "SE", "TO",
+];
+
+// Mantém apenas os dígitos, ignorando a formatação (pontos, traços, barras)
+fn digits(value: &str) -> Vec<u32> {
+    value.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+fn all_same(digits: &[u32]) -> bool {
+    digits.windows(2).all(|w| w[0] == w[1])
+}
+
+// Dígito verificador módulo 11 a partir dos pesos de cada posição
+fn check_digit(digits: &[u32], weights: impl Iterator<Item = u32>) -> u32 {
+    let sum: u32 = digits.iter().zip(weights).map(|(d, w)| d * w).sum();
+    match 11 - (sum % 11) {
+        d if d > 9 => 0,
+        d => d,
+    }
+}
+
+pub(crate) fn validate_cpf(value: &str) -> bool {
+    let cpf = digits(value);
+    if cpf.len() != 11 || all_same(&cpf) {
+        return false;
+    }
+
+    let digit1 = check_digit(&cpf[..9], (2..=10).rev());
+    let digit2 = check_digit(&cpf[..10], (2..=11).rev());
+    cpf[9] == digit1 && cpf[10] == digit2
+}
+
+pub(crate) fn validate_cnpj(value: &str) -> bool {
+    let cnpj = digits(value);
+    if cnpj.len() != 14 || all_same(&cnpj) {
+        return false;
+    }
+
+    // Pesos 5..2 seguidos de 9..2 para o primeiro dígito e 6..2, 9..2 para o segundo
+    let digit1 = check_digit(&cnpj[..12], (2..=5).rev().chain((2..=9).rev()));
+    let digit2 = check_digit(&cnpj[..13], (2..=6).rev().chain((2..=9).rev()));
+    cnpj[12] == digit1 && cnpj[13] == digit2
+}
+
+pub(crate) fn validate_cep(value: &str) -> bool {
+    digits(value).len() == 8
+}
+
+pub(crate) fn validate_uf(value: &str) -> bool {
+    UFS.iter().any(|uf| uf.eq_ignore_ascii_case(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpf_check_digits() {
+        assert!(validate_cpf("529.982.247-25"));
+        assert!(validate_cpf("52998224725"));
+        // Resto menor que 2 dá dígito 0
+        assert!(validate_cpf("123.456.789-09"));
+        assert!(!validate_cpf("529.982.247-24"));
+        assert!(!validate_cpf("529.982.247-35"));
+        assert!(!validate_cpf("111.111.111-11"));
+        assert!(!validate_cpf("5299822472"));
+        assert!(!validate_cpf("529982247250"));
+        assert!(!validate_cpf(""));
+    }
+
+    #[test]
+    fn cnpj_check_digits() {
+        assert!(validate_cnpj("11.222.333/0001-81"));
+        assert!(validate_cnpj("11222333000181"));
+        assert!(validate_cnpj("11.444.777/0001-61"));
+        assert!(validate_cnpj("00.000.000/0001-91"));
+        assert!(!validate_cnpj("11.222.333/0001-80"));
+        assert!(!validate_cnpj("11.222.333/0001-91"));
+        assert!(!validate_cnpj("00.000.000/0000-00"));
+        assert!(!validate_cnpj("1122233300018"));
+    }
+
+    #[test]
+    fn cep_and_uf() {
+        assert!(validate_cep("01310-100") && validate_cep("01310100"));
+        assert!(!validate_cep("1310-100"));
+        assert!(validate_uf("SP") && validate_uf("rj"));
+        assert!(!validate_uf("XX") && !validate_uf("S"));
+    }
+}
@@ -0,0 +1,1247 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use serde_json::{json, Map, Number, Value};
+
+use crate::errors::{FatalError, FatalErrorKind, FileErrorCode};
+use crate::fixed_width::{self, CompiledLayout};
+use crate::values::parse_date;
+use crate::{CsvOptions, FileError, ProcessingResult, ProcessingTotals};
+
+// Arquivos CNAB da Febraban, de remessa e retorno de cobrança. Os registros
+// são lidos como um arquivo posicional, com um layout embutido para cada
+// formato; depois a estrutura do arquivo é conferida (headers, trailers,
+// lotes, quantidades e totais) e os segmentos de cada boleto são juntados em
+// um lançamento com os valores já tipados.
+//
+// CNAB 240: o tipo de registro fica na posição 8 (0 header do arquivo, 1
+// header do lote, 3 detalhe, 5 trailer do lote, 9 trailer do arquivo) e o
+// segmento do detalhe na 14. P, Q e R formam um título da remessa; T e U, um
+// título do retorno.
+//
+// CNAB 400: não tem padrão da Febraban, cada banco publica o seu. O layout
+// embutido é o do Bradesco, que boa parte dos bancos segue nos campos
+// principais: header (0), um detalhe por título (1) e trailer (9).
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub enum CnabFormat {
+    #[serde(rename = "cnab240")]
+    Cnab240,
+    #[serde(rename = "cnab400")]
+    Cnab400,
+}
+
+// Remessa (empresa para o banco) ou retorno (banco para a empresa), pelo
+// código do header do arquivo
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CnabDirection {
+    Remessa,
+    Retorno,
+}
+
+// Um título (boleto) do arquivo: os campos dos seus segmentos juntos, com
+// números, valores e datas já convertidos
+#[derive(Serialize, Debug)]
+pub struct CnabEntry {
+    // Linha do primeiro segmento
+    line: u64,
+    // Número do lote, no CNAB 240
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch: Option<u64>,
+    // Segmentos do título, como ["P", "Q", "R"]; vazio no CNAB 400
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    segments: Vec<String>,
+    data: Map<String, Value>,
+}
+
+impl CnabEntry {
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    pub fn batch(&self) -> Option<u64> {
+        self.batch
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    pub fn data(&self) -> &Map<String, Value> {
+        &self.data
+    }
+}
+
+// Resumo do arquivo CNAB, em `cnab` no resultado
+#[derive(Serialize, Debug)]
+pub struct CnabFile {
+    format: CnabFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<CnabDirection>,
+    // Código do banco (compensação), do header do arquivo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bank: Option<String>,
+    // Lotes do arquivo, no CNAB 240
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batches: Option<u64>,
+    // Títulos cujos segmentos são todos válidos, na ordem do arquivo
+    entries: Vec<CnabEntry>,
+}
+
+impl CnabFile {
+    pub fn format(&self) -> CnabFormat {
+        self.format
+    }
+
+    pub fn direction(&self) -> Option<CnabDirection> {
+        self.direction
+    }
+
+    pub fn bank(&self) -> Option<&str> {
+        self.bank.as_deref()
+    }
+
+    pub fn batches(&self) -> Option<u64> {
+        self.batches
+    }
+
+    pub fn entries(&self) -> &[CnabEntry] {
+        &self.entries
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    // Alfanumérico, sem os espaços à direita
+    Text,
+    // Código numérico que mantém os zeros à esquerda (banco, agência, conta)
+    Code,
+    Int,
+    // Valor com duas casas decimais implícitas
+    Money,
+    // ddmmaaaa no CNAB 240, ddmmaa no CNAB 400; só zeros é data não informada
+    Date,
+}
+
+struct Field {
+    name: &'static str,
+    // Posição a partir de 1 e tamanho, como nos manuais
+    start: usize,
+    length: usize,
+    kind: Kind,
+    required: bool,
+}
+
+const fn opt(name: &'static str, start: usize, length: usize, kind: Kind) -> Field {
+    Field { name, start, length, kind, required: false }
+}
+
+const fn req(name: &'static str, start: usize, length: usize, kind: Kind) -> Field {
+    Field { name, start, length, kind, required: true }
+}
+
+struct RecordType {
+    name: &'static str,
+    // Valores em posições fixas que reconhecem o registro
+    conditions: &'static [(usize, &'static str)],
+    fields: &'static [Field],
+}
+
+impl Field {
+    fn date_format(&self) -> &'static str {
+        if self.length == 6 {
+            "%d%m%y"
+        } else {
+            "%d%m%Y"
+        }
+    }
+
+    // Campo no formato do layout posicional
+    fn definition(&self) -> Value {
+        let mut field = json!({ "name": self.name, "start": self.start, "length": self.length });
+        match self.kind {
+            Kind::Text => {}
+            Kind::Code => {
+                field["type"] = json!("regex");
+                field["pattern"] = json!("^[0-9]+$");
+            }
+            Kind::Int => field["type"] = json!("int"),
+            Kind::Money => {
+                field["type"] = json!("decimal");
+                field["decimals"] = json!(2);
+            }
+            Kind::Date => {
+                field["type"] = json!("date");
+                field["format"] = json!(self.date_format());
+            }
+        }
+        if !self.required {
+            field["nullable"] = json!(true);
+        }
+        field
+    }
+
+    // Valor do lançamento: números e valores como número, datas em ISO-8601
+    fn typed(&self, value: &str) -> Value {
+        if value.is_empty() {
+            return Value::Null;
+        }
+        let typed = match self.kind {
+            Kind::Int => value.parse::<i64>().ok().map(Value::from),
+            Kind::Money => value.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number),
+            Kind::Date => parse_date(value, Some(self.date_format())).map(Value::String),
+            Kind::Text | Kind::Code => None,
+        };
+        typed.unwrap_or_else(|| Value::String(value.to_string()))
+    }
+}
+
+use Kind::{Code, Date, Int, Money, Text};
+
+// CNAB 240, layout padrão Febraban de cobrança (versão 10)
+
+const HEADER_ARQUIVO_240: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    opt("tipo_inscricao", 18, 1, Code),
+    opt("inscricao", 19, 14, Code),
+    opt("convenio", 33, 20, Text),
+    opt("agencia", 53, 5, Code),
+    opt("agencia_dv", 58, 1, Text),
+    opt("conta", 59, 12, Code),
+    opt("conta_dv", 71, 1, Text),
+    opt("agencia_conta_dv", 72, 1, Text),
+    opt("nome_empresa", 73, 30, Text),
+    opt("nome_banco", 103, 30, Text),
+    req("codigo_remessa_retorno", 143, 1, Code),
+    req("data_geracao", 144, 8, Date),
+    opt("hora_geracao", 152, 6, Code),
+    opt("sequencial_arquivo", 158, 6, Int),
+    opt("versao_layout", 164, 3, Code),
+    opt("densidade", 167, 5, Code),
+    opt("reservado_banco", 172, 20, Text),
+    opt("reservado_empresa", 192, 20, Text),
+];
+
+const HEADER_LOTE_240: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("tipo_operacao", 9, 1, Text),
+    req("tipo_servico", 10, 2, Code),
+    opt("versao_layout", 14, 3, Code),
+    opt("tipo_inscricao", 18, 1, Code),
+    opt("inscricao", 19, 15, Code),
+    opt("convenio", 34, 20, Text),
+    opt("agencia", 54, 5, Code),
+    opt("agencia_dv", 59, 1, Text),
+    opt("conta", 60, 12, Code),
+    opt("conta_dv", 72, 1, Text),
+    opt("agencia_conta_dv", 73, 1, Text),
+    opt("nome_empresa", 74, 30, Text),
+    opt("mensagem_1", 104, 40, Text),
+    opt("mensagem_2", 144, 40, Text),
+    opt("numero_remessa_retorno", 184, 8, Int),
+    opt("data_gravacao", 192, 8, Date),
+    opt("data_credito", 200, 8, Date),
+];
+
+const SEGMENTO_P: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("sequencial", 9, 5, Int),
+    req("segmento", 14, 1, Text),
+    req("codigo_movimento", 16, 2, Code),
+    opt("agencia", 18, 5, Code),
+    opt("agencia_dv", 23, 1, Text),
+    opt("conta", 24, 12, Code),
+    opt("conta_dv", 36, 1, Text),
+    opt("agencia_conta_dv", 37, 1, Text),
+    opt("nosso_numero", 38, 20, Text),
+    opt("carteira", 58, 1, Code),
+    opt("forma_cadastramento", 59, 1, Code),
+    opt("tipo_documento", 60, 1, Text),
+    opt("emissao_boleto", 61, 1, Code),
+    opt("distribuicao_boleto", 62, 1, Text),
+    opt("numero_documento", 63, 15, Text),
+    req("vencimento", 78, 8, Date),
+    req("valor_titulo", 86, 15, Money),
+    opt("agencia_cobradora", 101, 5, Code),
+    opt("agencia_cobradora_dv", 106, 1, Text),
+    opt("especie_titulo", 107, 2, Code),
+    opt("aceite", 109, 1, Text),
+    opt("data_emissao", 110, 8, Date),
+    opt("codigo_juros", 118, 1, Code),
+    opt("data_juros", 119, 8, Date),
+    opt("juros", 127, 15, Money),
+    opt("codigo_desconto_1", 142, 1, Code),
+    opt("data_desconto_1", 143, 8, Date),
+    opt("desconto_1", 151, 15, Money),
+    opt("valor_iof", 166, 15, Money),
+    opt("valor_abatimento", 181, 15, Money),
+    opt("uso_empresa", 196, 25, Text),
+    opt("codigo_protesto", 221, 1, Code),
+    opt("prazo_protesto", 222, 2, Int),
+    opt("codigo_baixa", 224, 1, Code),
+    opt("prazo_baixa", 225, 3, Int),
+    opt("codigo_moeda", 228, 2, Code),
+    opt("numero_contrato", 230, 10, Code),
+];
+
+const SEGMENTO_Q: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("sequencial", 9, 5, Int),
+    req("segmento", 14, 1, Text),
+    req("codigo_movimento", 16, 2, Code),
+    opt("pagador_tipo_inscricao", 18, 1, Code),
+    opt("pagador_inscricao", 19, 15, Code),
+    req("pagador_nome", 34, 40, Text),
+    opt("pagador_endereco", 74, 40, Text),
+    opt("pagador_bairro", 114, 15, Text),
+    opt("pagador_cep", 129, 8, Code),
+    opt("pagador_cidade", 137, 15, Text),
+    opt("pagador_uf", 152, 2, Text),
+    opt("sacador_tipo_inscricao", 154, 1, Code),
+    opt("sacador_inscricao", 155, 15, Code),
+    opt("sacador_nome", 170, 40, Text),
+    opt("banco_correspondente", 210, 3, Code),
+    opt("nosso_numero_correspondente", 213, 20, Text),
+];
+
+const SEGMENTO_R: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("sequencial", 9, 5, Int),
+    req("segmento", 14, 1, Text),
+    req("codigo_movimento", 16, 2, Code),
+    opt("codigo_desconto_2", 18, 1, Code),
+    opt("data_desconto_2", 19, 8, Date),
+    opt("desconto_2", 27, 15, Money),
+    opt("codigo_desconto_3", 42, 1, Code),
+    opt("data_desconto_3", 43, 8, Date),
+    opt("desconto_3", 51, 15, Money),
+    opt("codigo_multa", 66, 1, Code),
+    opt("data_multa", 67, 8, Date),
+    opt("multa", 75, 15, Money),
+    opt("informacao_pagador", 90, 10, Text),
+    opt("mensagem_3", 100, 40, Text),
+    opt("mensagem_4", 140, 40, Text),
+    opt("codigo_ocorrencia_pagador", 200, 8, Code),
+    opt("debito_banco", 208, 3, Code),
+    opt("debito_agencia", 211, 5, Code),
+    opt("debito_agencia_dv", 216, 1, Text),
+    opt("debito_conta", 217, 12, Code),
+    opt("debito_conta_dv", 229, 1, Text),
+    opt("debito_agencia_conta_dv", 230, 1, Text),
+    opt("aviso_debito", 231, 1, Code),
+];
+
+const SEGMENTO_T: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("sequencial", 9, 5, Int),
+    req("segmento", 14, 1, Text),
+    req("codigo_movimento", 16, 2, Code),
+    opt("agencia", 18, 5, Code),
+    opt("agencia_dv", 23, 1, Text),
+    opt("conta", 24, 12, Code),
+    opt("conta_dv", 36, 1, Text),
+    opt("agencia_conta_dv", 37, 1, Text),
+    opt("nosso_numero", 38, 20, Text),
+    opt("carteira", 58, 1, Code),
+    opt("numero_documento", 59, 15, Text),
+    opt("vencimento", 74, 8, Date),
+    req("valor_titulo", 82, 15, Money),
+    opt("banco_cobrador", 97, 3, Code),
+    opt("agencia_cobradora", 100, 5, Code),
+    opt("agencia_cobradora_dv", 105, 1, Text),
+    opt("uso_empresa", 106, 25, Text),
+    opt("codigo_moeda", 131, 2, Code),
+    opt("pagador_tipo_inscricao", 133, 1, Code),
+    opt("pagador_inscricao", 134, 15, Code),
+    opt("pagador_nome", 149, 40, Text),
+    opt("numero_contrato", 189, 10, Code),
+    opt("valor_tarifa", 199, 15, Money),
+    opt("motivo_ocorrencia", 214, 10, Text),
+];
+
+const SEGMENTO_U: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("sequencial", 9, 5, Int),
+    req("segmento", 14, 1, Text),
+    req("codigo_movimento", 16, 2, Code),
+    opt("juros_multa", 18, 15, Money),
+    opt("desconto", 33, 15, Money),
+    opt("valor_abatimento", 48, 15, Money),
+    opt("valor_iof", 63, 15, Money),
+    opt("valor_pago", 78, 15, Money),
+    opt("valor_liquido", 93, 15, Money),
+    opt("outras_despesas", 108, 15, Money),
+    opt("outros_creditos", 123, 15, Money),
+    opt("data_ocorrencia", 138, 8, Date),
+    opt("data_credito", 146, 8, Date),
+    opt("codigo_ocorrencia_pagador", 154, 4, Code),
+    opt("data_ocorrencia_pagador", 158, 8, Date),
+    opt("valor_ocorrencia_pagador", 166, 15, Money),
+    opt("complemento_ocorrencia", 181, 30, Text),
+    opt("banco_correspondente", 211, 3, Code),
+    opt("nosso_numero_correspondente", 214, 20, Text),
+];
+
+const TRAILER_LOTE_240: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("quantidade_registros", 18, 6, Int),
+    opt("quantidade_simples", 24, 6, Int),
+    opt("valor_simples", 30, 17, Money),
+    opt("quantidade_vinculada", 47, 6, Int),
+    opt("valor_vinculada", 53, 17, Money),
+    opt("quantidade_caucionada", 70, 6, Int),
+    opt("valor_caucionada", 76, 17, Money),
+    opt("quantidade_descontada", 93, 6, Int),
+    opt("valor_descontada", 99, 17, Money),
+    opt("numero_aviso", 116, 8, Text),
+];
+
+const TRAILER_ARQUIVO_240: &[Field] = &[
+    req("banco", 1, 3, Code),
+    req("lote", 4, 4, Int),
+    req("tipo_registro", 8, 1, Code),
+    req("quantidade_lotes", 18, 6, Int),
+    req("quantidade_registros", 24, 6, Int),
+    opt("quantidade_contas", 30, 6, Int),
+];
+
+const CNAB_240: &[RecordType] = &[
+    RecordType { name: "header_arquivo", conditions: &[(8, "0")], fields: HEADER_ARQUIVO_240 },
+    RecordType { name: "header_lote", conditions: &[(8, "1")], fields: HEADER_LOTE_240 },
+    RecordType { name: "segmento_p", conditions: &[(8, "3"), (14, "P")], fields: SEGMENTO_P },
+    RecordType { name: "segmento_q", conditions: &[(8, "3"), (14, "Q")], fields: SEGMENTO_Q },
+    RecordType { name: "segmento_r", conditions: &[(8, "3"), (14, "R")], fields: SEGMENTO_R },
+    RecordType { name: "segmento_t", conditions: &[(8, "3"), (14, "T")], fields: SEGMENTO_T },
+    RecordType { name: "segmento_u", conditions: &[(8, "3"), (14, "U")], fields: SEGMENTO_U },
+    RecordType { name: "trailer_lote", conditions: &[(8, "5")], fields: TRAILER_LOTE_240 },
+    RecordType { name: "trailer_arquivo", conditions: &[(8, "9")], fields: TRAILER_ARQUIVO_240 },
+];
+
+// CNAB 400 (layout do Bradesco)
+
+const HEADER_ARQUIVO_400: &[Field] = &[
+    req("tipo_registro", 1, 1, Code),
+    req("codigo_remessa_retorno", 2, 1, Code),
+    opt("literal_remessa_retorno", 3, 7, Text),
+    opt("codigo_servico", 10, 2, Code),
+    opt("literal_servico", 12, 15, Text),
+    opt("codigo_empresa", 27, 20, Text),
+    opt("nome_empresa", 47, 30, Text),
+    req("banco", 77, 3, Code),
+    opt("nome_banco", 80, 15, Text),
+    req("data_gravacao", 95, 6, Date),
+    req("sequencial", 395, 6, Int),
+];
+
+const DETALHE_REMESSA_400: &[Field] = &[
+    req("tipo_registro", 1, 1, Code),
+    opt("identificacao_empresa", 21, 17, Text),
+    opt("controle_participante", 38, 25, Text),
+    opt("banco_debito", 63, 3, Code),
+    opt("codigo_multa", 66, 1, Code),
+    opt("percentual_multa", 67, 4, Money),
+    opt("nosso_numero", 71, 12, Text),
+    opt("desconto_dia", 83, 10, Money),
+    opt("emissao_boleto", 93, 1, Code),
+    req("codigo_ocorrencia", 109, 2, Code),
+    opt("numero_documento", 111, 10, Text),
+    req("vencimento", 121, 6, Date),
+    req("valor_titulo", 127, 13, Money),
+    opt("banco_cobrador", 140, 3, Code),
+    opt("agencia_depositaria", 143, 5, Code),
+    opt("especie_titulo", 148, 2, Code),
+    opt("aceite", 150, 1, Text),
+    opt("data_emissao", 151, 6, Date),
+    opt("instrucao_1", 157, 2, Code),
+    opt("instrucao_2", 159, 2, Code),
+    opt("juros_dia", 161, 13, Money),
+    opt("data_desconto", 174, 6, Date),
+    opt("valor_desconto", 180, 13, Money),
+    opt("valor_iof", 193, 13, Money),
+    opt("valor_abatimento", 206, 13, Money),
+    opt("pagador_tipo_inscricao", 219, 2, Code),
+    opt("pagador_inscricao", 221, 14, Code),
+    req("pagador_nome", 235, 40, Text),
+    opt("pagador_endereco", 275, 40, Text),
+    opt("mensagem_1", 315, 12, Text),
+    opt("pagador_cep", 327, 8, Code),
+    opt("sacador_mensagem_2", 335, 60, Text),
+    req("sequencial", 395, 6, Int),
+];
+
+const DETALHE_RETORNO_400: &[Field] = &[
+    req("tipo_registro", 1, 1, Code),
+    opt("empresa_tipo_inscricao", 2, 2, Code),
+    opt("empresa_inscricao", 4, 14, Code),
+    opt("identificacao_empresa", 21, 17, Text),
+    opt("controle_participante", 38, 25, Text),
+    opt("nosso_numero", 71, 12, Text),
+    opt("carteira", 108, 1, Code),
+    req("codigo_ocorrencia", 109, 2, Code),
+    opt("data_ocorrencia", 111, 6, Date),
+    opt("numero_documento", 117, 10, Text),
+    opt("vencimento", 147, 6, Date),
+    req("valor_titulo", 153, 13, Money),
+    opt("banco_cobrador", 166, 3, Code),
+    opt("agencia_cobradora", 169, 5, Code),
+    opt("especie_titulo", 174, 2, Text),
+    opt("valor_tarifa", 176, 13, Money),
+    opt("outras_despesas", 189, 13, Money),
+    opt("juros_atraso", 202, 13, Money),
+    opt("valor_iof", 215, 13, Money),
+    opt("valor_abatimento", 228, 13, Money),
+    opt("desconto", 241, 13, Money),
+    opt("valor_pago", 254, 13, Money),
+    opt("juros_mora", 267, 13, Money),
+    opt("outros_creditos", 280, 13, Money),
+    opt("data_credito", 296, 6, Date),
+    opt("motivos_rejeicao", 319, 10, Text),
+    req("sequencial", 395, 6, Int),
+];
+
+const TRAILER_REMESSA_400: &[Field] = &[req("tipo_registro", 1, 1, Code), req("sequencial", 395, 6, Int)];
+
+const TRAILER_RETORNO_400: &[Field] = &[
+    req("tipo_registro", 1, 1, Code),
+    opt("codigo_retorno", 2, 1, Code),
+    opt("codigo_servico", 3, 2, Code),
+    opt("banco", 5, 3, Code),
+    opt("quantidade_titulos", 18, 8, Int),
+    opt("valor_titulos", 26, 14, Money),
+    opt("numero_aviso", 40, 8, Text),
+    req("sequencial", 395, 6, Int),
+];
+
+const CNAB_400_REMESSA: &[RecordType] = &[
+    RecordType { name: "header_arquivo", conditions: &[(1, "0")], fields: HEADER_ARQUIVO_400 },
+    RecordType { name: "detalhe", conditions: &[(1, "1")], fields: DETALHE_REMESSA_400 },
+    RecordType { name: "trailer_arquivo", conditions: &[(1, "9")], fields: TRAILER_REMESSA_400 },
+];
+
+const CNAB_400_RETORNO: &[RecordType] = &[
+    RecordType { name: "header_arquivo", conditions: &[(1, "0")], fields: HEADER_ARQUIVO_400 },
+    RecordType { name: "detalhe", conditions: &[(1, "1")], fields: DETALHE_RETORNO_400 },
+    RecordType { name: "trailer_arquivo", conditions: &[(1, "9")], fields: TRAILER_RETORNO_400 },
+];
+
+// Campos de controle, que ficam de fora dos lançamentos
+const CONTROL_FIELDS: &[&str] = &["banco", "lote", "tipo_registro", "sequencial", "segmento"];
+
+// Carteiras totalizadas nos trailers: `quantidade_<carteira>` e
+// `valor_<carteira>`. O CNAB 400 informa um total só ("titulos").
+const PORTFOLIOS: &[&str] = &["simples", "vinculada", "caucionada", "descontada", "titulos"];
+
+static LAYOUT_240: OnceLock<CompiledLayout> = OnceLock::new();
+static LAYOUT_400_REMESSA: OnceLock<CompiledLayout> = OnceLock::new();
+static LAYOUT_400_RETORNO: OnceLock<CompiledLayout> = OnceLock::new();
+
+// Layouts embutidos, compilados uma vez como qualquer layout posicional
+fn layout(cell: &'static OnceLock<CompiledLayout>, types: &'static [RecordType], length: usize) -> &'static CompiledLayout {
+    cell.get_or_init(|| {
+        let records: Vec<Value> = types
+            .iter()
+            .map(|record| {
+                json!({
+                    "name": record.name,
+                    "match": record.conditions.iter().map(|(start, value)| json!({ "start": start, "value": value })).collect::<Vec<_>>(),
+                    "fields": record.fields.iter().map(Field::definition).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        let definition = json!({ "record_length": length, "records": records });
+        CompiledLayout::from_json(&definition.to_string()).expect("built-in CNAB layouts are valid")
+    })
+}
+
+// Formato pelo tamanho do primeiro registro; sem quebras de linha (ou com
+// linhas de outro tamanho), pelo header do arquivo: "00000" nas posições 4 a
+// 8 no CNAB 240, "01" ou "02" no início no CNAB 400
+fn detect(text: &str) -> Option<CnabFormat> {
+    let text = text.trim_start_matches('\u{feff}');
+    let first = text.lines().map(|line| line.trim_end_matches('\r')).find(|line| !line.trim().is_empty())?;
+    if text.contains('\n') {
+        match first.chars().count() {
+            240 => return Some(CnabFormat::Cnab240),
+            400 => return Some(CnabFormat::Cnab400),
+            _ => {}
+        }
+    }
+    if first.get(3..8) == Some("00000") {
+        Some(CnabFormat::Cnab240)
+    } else if first.starts_with("01") || first.starts_with("02") {
+        Some(CnabFormat::Cnab400)
+    } else {
+        None
+    }
+}
+
+// Um registro lido, com os valores dos campos do seu tipo
+struct Record {
+    line: u64,
+    kind: Option<&'static RecordType>,
+    values: Vec<String>,
+}
+
+impl Record {
+    // Valor de um campo; None quando o tipo de registro não tem o campo
+    fn get(&self, name: &str) -> Option<&str> {
+        let index = self.kind?.fields.iter().position(|field| field.name == name)?;
+        self.values.get(index).map(String::as_str)
+    }
+
+    fn number(&self, name: &str) -> Option<u64> {
+        self.get(name)?.parse().ok()
+    }
+
+    // Valor em centavos, para somar sem arredondamento
+    fn cents(&self, name: &str) -> Option<u64> {
+        self.get(name)?.replace('.', "").parse().ok()
+    }
+}
+
+// Título em montagem: os segmentos lidos até o próximo P ou T
+struct OpenEntry {
+    entry: CnabEntry,
+    lines: Vec<u64>,
+}
+
+// Lote aberto do CNAB 240
+struct Batch {
+    line: u64,
+    number: u64,
+    records: u64,
+    titles: u64,
+    total: u64,
+    next_sequence: u64,
+}
+
+// Confere a estrutura do arquivo e monta os lançamentos
+struct Checker<'a> {
+    valid: &'a HashSet<u64>,
+    errors: Vec<FileError>,
+    entries: Vec<CnabEntry>,
+    open: Option<OpenEntry>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, code: FileErrorCode, message: String, line: Option<u64>, record: &str) -> &mut FileError {
+        let mut error = FileError::new(code, message, line);
+        error.record = Some(record.to_string());
+        self.errors.push(error);
+        self.errors.last_mut().unwrap()
+    }
+
+    // Campos do registro que não batem com o esperado
+    fn mismatch(&mut self, code: FileErrorCode, message: String, record: &Record, columns: &[&str], expected: String, actual: String) {
+        let name = record.kind.map_or("", |kind| kind.name);
+        let error = self.error(code, message, Some(record.line), name);
+        error.columns = Some(columns.iter().map(|c| c.to_string()).collect());
+        error.expected = Some(expected);
+        error.actual = Some(actual);
+    }
+
+    fn open_entry(&mut self, record: &Record, batch: Option<u64>, segment: Option<&str>) {
+        self.close_entry();
+        let entry = CnabEntry {
+            line: record.line,
+            batch,
+            segments: Vec::new(),
+            data: Map::new(),
+        };
+        self.open = Some(OpenEntry { entry, lines: Vec::new() });
+        self.add_segment(record, segment);
+    }
+
+    fn add_segment(&mut self, record: &Record, segment: Option<&str>) {
+        let (Some(open), Some(kind)) = (&mut self.open, record.kind) else { return };
+        open.lines.push(record.line);
+        if let Some(segment) = segment {
+            open.entry.segments.push(segment.to_string());
+        }
+        for (field, value) in kind.fields.iter().zip(&record.values) {
+            if !CONTROL_FIELDS.contains(&field.name) && !open.entry.data.contains_key(field.name) {
+                open.entry.data.insert(field.name.to_string(), field.typed(value));
+            }
+        }
+    }
+
+    // Só entram os títulos com todos os segmentos válidos; os rejeitados já
+    // estão em `errors`
+    fn close_entry(&mut self) {
+        if let Some(open) = self.open.take() {
+            if open.lines.iter().all(|line| self.valid.contains(line)) {
+                self.entries.push(open.entry);
+            }
+        }
+    }
+
+    // Quantidade e valor de títulos informados no trailer, somando as
+    // carteiras. Trailers zerados (bancos que não preenchem os totais) não
+    // são conferidos.
+    fn check_totals(&mut self, trailer: &Record, what: &str, titles: u64, total: u64) {
+        let informed: u64 = PORTFOLIOS.iter().filter_map(|p| trailer.number(&format!("quantidade_{}", p))).sum();
+        if informed > 0 && informed != titles {
+            self.mismatch(
+                FileErrorCode::TrailerCount,
+                format!("{} trailer informs {} titles, found {}", what, informed, titles),
+                trailer,
+                &present(trailer, "quantidade_"),
+                informed.to_string(),
+                titles.to_string(),
+            );
+        }
+        let amount: u64 = PORTFOLIOS.iter().filter_map(|p| trailer.cents(&format!("valor_{}", p))).sum();
+        if amount > 0 && amount != total {
+            self.mismatch(
+                FileErrorCode::TrailerSum,
+                format!("{} trailer informs a total of {}, titles sum {}", what, money(amount), money(total)),
+                trailer,
+                &present(trailer, "valor_"),
+                money(amount),
+                money(total),
+            );
+        }
+    }
+}
+
+// Campos do trailer com o prefixo, entre os totais das carteiras
+fn present(trailer: &Record, prefix: &str) -> Vec<&'static str> {
+    let names = trailer.kind.map_or(&[][..], |kind| kind.fields);
+    names
+        .iter()
+        .map(|field| field.name)
+        .filter(|name| name.strip_prefix(prefix).is_some_and(|p| PORTFOLIOS.contains(&p)))
+        .collect()
+}
+
+fn money(cents: u64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+fn check(format: CnabFormat, records: &[Record], valid: &HashSet<u64>) -> (Vec<FileError>, CnabFile) {
+    let mut checker = Checker { valid, errors: Vec::new(), entries: Vec::new(), open: None };
+    let mut header: Option<&Record> = None;
+    let mut header_missing = false;
+    let mut trailer = false;
+    let mut batch: Option<Batch> = None;
+    let mut batches = 0;
+    let (mut titles, mut total) = (0, 0);
+
+    for (index, record) in records.iter().enumerate() {
+        let position = index as u64 + 1;
+        let Some(kind) = record.kind else {
+            // Registros desconhecidos já estão em `errors`, mas contam nas
+            // quantidades do lote e do arquivo
+            if let Some(batch) = &mut batch {
+                batch.records += 1;
+            }
+            continue;
+        };
+        let name = kind.name;
+
+        if trailer {
+            checker.error(FileErrorCode::RecordOrder, "Record after the file trailer".to_string(), Some(record.line), name);
+        }
+        if name == "header_arquivo" {
+            if header.is_some() || index > 0 {
+                checker.error(
+                    FileErrorCode::RecordOrder,
+                    "The file header must be the first record and appear only once".to_string(),
+                    Some(record.line),
+                    name,
+                );
+            }
+            header = header.or(Some(record));
+        } else if header.is_none() && !header_missing {
+            header_missing = true;
+            checker.error(
+                FileErrorCode::RecordOrder,
+                "The file does not start with a file header".to_string(),
+                Some(record.line),
+                name,
+            );
+        }
+        if let (Some(expected), Some(actual)) = (header.and_then(|h| h.get("banco")), record.get("banco").filter(|b| !b.is_empty())) {
+            if expected != actual {
+                checker.mismatch(
+                    FileErrorCode::HeaderMismatch,
+                    format!("Bank code {} differs from the file header ({})", actual, expected),
+                    record,
+                    &["banco"],
+                    expected.to_string(),
+                    actual.to_string(),
+                );
+            }
+        }
+        // No CNAB 400 todo registro traz a sua posição no arquivo
+        if format == CnabFormat::Cnab400 {
+            if let Some(sequence) = record.number("sequencial").filter(|&s| s != position) {
+                checker.mismatch(
+                    FileErrorCode::RecordOrder,
+                    format!("Sequential number {}, expected {}", sequence, position),
+                    record,
+                    &["sequencial"],
+                    position.to_string(),
+                    sequence.to_string(),
+                );
+            }
+        }
+
+        match name {
+            "header_arquivo" => {}
+            "header_lote" => {
+                if let Some(open) = batch.take() {
+                    checker.close_entry();
+                    checker.error(
+                        FileErrorCode::TrailerMissing,
+                        format!("Batch {} has no batch trailer", open.number),
+                        Some(open.line),
+                        "trailer_lote",
+                    );
+                }
+                batches += 1;
+                let number = record.number("lote").unwrap_or(batches);
+                if number != batches {
+                    checker.mismatch(
+                        FileErrorCode::RecordOrder,
+                        format!("Batch number {}, expected {}", number, batches),
+                        record,
+                        &["lote"],
+                        batches.to_string(),
+                        number.to_string(),
+                    );
+                }
+                batch = Some(Batch { line: record.line, number, records: 1, titles: 0, total: 0, next_sequence: 1 });
+            }
+            "trailer_lote" => {
+                checker.close_entry();
+                let Some(mut open) = batch.take() else {
+                    checker.error(
+                        FileErrorCode::RecordOrder,
+                        "Batch trailer without a batch header".to_string(),
+                        Some(record.line),
+                        name,
+                    );
+                    continue;
+                };
+                open.records += 1;
+                let what = format!("Batch {}", open.number);
+                if let Some(number) = record.number("lote").filter(|&n| n != open.number) {
+                    checker.mismatch(
+                        FileErrorCode::HeaderMismatch,
+                        format!("{} trailer has batch number {}", what, number),
+                        record,
+                        &["lote"],
+                        open.number.to_string(),
+                        number.to_string(),
+                    );
+                }
+                if let Some(informed) = record.number("quantidade_registros").filter(|&n| n != open.records) {
+                    checker.mismatch(
+                        FileErrorCode::TrailerCount,
+                        format!("{} trailer informs {} records, found {}", what, informed, open.records),
+                        record,
+                        &["quantidade_registros"],
+                        informed.to_string(),
+                        open.records.to_string(),
+                    );
+                }
+                checker.check_totals(record, &what, open.titles, open.total);
+            }
+            "trailer_arquivo" => {
+                checker.close_entry();
+                if let Some(open) = batch.take() {
+                    checker.error(
+                        FileErrorCode::TrailerMissing,
+                        format!("Batch {} has no batch trailer", open.number),
+                        Some(open.line),
+                        "trailer_lote",
+                    );
+                }
+                if trailer {
+                    checker.error(FileErrorCode::TrailerRepeated, "More than one file trailer".to_string(), Some(record.line), name);
+                    continue;
+                }
+                trailer = true;
+                if let Some(informed) = record.number("quantidade_lotes").filter(|&n| n != batches) {
+                    checker.mismatch(
+                        FileErrorCode::TrailerCount,
+                        format!("File trailer informs {} batches, found {}", informed, batches),
+                        record,
+                        &["quantidade_lotes"],
+                        informed.to_string(),
+                        batches.to_string(),
+                    );
+                }
+                if let Some(informed) = record.number("quantidade_registros").filter(|&n| n != position) {
+                    checker.mismatch(
+                        FileErrorCode::TrailerCount,
+                        format!("File trailer informs {} records, found {}", informed, position),
+                        record,
+                        &["quantidade_registros"],
+                        informed.to_string(),
+                        position.to_string(),
+                    );
+                }
+                checker.check_totals(record, "File", titles, total);
+            }
+            "detalhe" => {
+                titles += 1;
+                total += record.cents("valor_titulo").unwrap_or(0);
+                checker.open_entry(record, None, None);
+                checker.close_entry();
+            }
+            _ => {
+                // Segmentos do CNAB 240
+                let segment = record.get("segmento").unwrap_or_default();
+                let Some(open) = &mut batch else {
+                    checker.error(
+                        FileErrorCode::RecordOrder,
+                        format!("Segment {} outside of a batch", segment),
+                        Some(record.line),
+                        name,
+                    );
+                    continue;
+                };
+                open.records += 1;
+                if let Some(number) = record.number("lote").filter(|&n| n != open.number) {
+                    let message = format!("Segment {} has batch number {}, inside batch {}", segment, number, open.number);
+                    let expected = open.number.to_string();
+                    checker.mismatch(FileErrorCode::HeaderMismatch, message, record, &["lote"], expected, number.to_string());
+                }
+                let expected = open.next_sequence;
+                if let Some(sequence) = record.number("sequencial") {
+                    open.next_sequence = sequence + 1;
+                    if sequence != expected {
+                        checker.mismatch(
+                            FileErrorCode::RecordOrder,
+                            format!("Sequential number {} in batch {}, expected {}", sequence, open.number, expected),
+                            record,
+                            &["sequencial"],
+                            expected.to_string(),
+                            sequence.to_string(),
+                        );
+                    }
+                }
+                let number = open.number;
+                match segment {
+                    "P" | "T" => {
+                        open.titles += 1;
+                        open.total += record.cents("valor_titulo").unwrap_or(0);
+                        checker.open_entry(record, Some(number), Some(segment));
+                    }
+                    _ => {
+                        // Q e R completam o P; U completa o T
+                        let first = if segment == "U" { "T" } else { "P" };
+                        let segments = checker.open.as_ref().map_or(&[][..], |o| o.entry.segments.as_slice());
+                        let message = if segments.first().map(String::as_str) != Some(first) {
+                            format!("Segment {} without a preceding segment {}", segment, first)
+                        } else if segments.iter().any(|s| s == segment) {
+                            format!("Segment {} repeated in the same title", segment)
+                        } else {
+                            checker.add_segment(record, Some(segment));
+                            continue;
+                        };
+                        checker.close_entry();
+                        checker.error(FileErrorCode::RecordOrder, message, Some(record.line), name);
+                    }
+                }
+            }
+        }
+    }
+
+    checker.close_entry();
+    if let Some(open) = batch {
+        checker.error(
+            FileErrorCode::TrailerMissing,
+            format!("Batch {} has no batch trailer", open.number),
+            Some(open.line),
+            "trailer_lote",
+        );
+    }
+    if !trailer {
+        checker.error(FileErrorCode::TrailerMissing, "The file has no file trailer".to_string(), None, "trailer_arquivo");
+    }
+
+    let direction = header.and_then(|h| h.get("codigo_remessa_retorno")).and_then(|code| match code {
+        "1" => Some(CnabDirection::Remessa),
+        "2" => Some(CnabDirection::Retorno),
+        _ => None,
+    });
+    let file = CnabFile {
+        format,
+        direction,
+        bank: header.and_then(|h| h.get("banco")).filter(|b| !b.is_empty()).map(String::from),
+        batches: (format == CnabFormat::Cnab240).then_some(batches),
+        entries: checker.entries,
+    };
+    (checker.errors, file)
+}
+
+pub(crate) fn process(input: &[u8], options: &CsvOptions, out: &mut ProcessingResult) -> Result<ProcessingTotals, FatalError> {
+    let (text, encoding) = fixed_width::decode(input, options);
+    let format = detect(&text).ok_or_else(|| {
+        FatalError::new(FatalErrorKind::InvalidCnab, "file is neither a CNAB 240 nor a CNAB 400 file", 0, 0)
+    })?;
+    let (types, layout) = match format {
+        CnabFormat::Cnab240 => (CNAB_240, layout(&LAYOUT_240, CNAB_240, 240)),
+        // O detalhe e o trailer do CNAB 400 mudam entre remessa e retorno
+        CnabFormat::Cnab400 if text.trim_start_matches('\u{feff}').starts_with("02") => {
+            (CNAB_400_RETORNO, layout(&LAYOUT_400_RETORNO, CNAB_400_RETORNO, 400))
+        }
+        CnabFormat::Cnab400 => (CNAB_400_REMESSA, layout(&LAYOUT_400_REMESSA, CNAB_400_REMESSA, 400)),
+    };
+
+    let records = fixed_width::records(&text, layout.record_length(), encoding);
+    let mut totals = fixed_width::process_records(&records, layout, options, out)?;
+    totals.bytes_processed = input.len() as u64;
+
+    let parsed: Vec<Record> = records
+        .iter()
+        .map(|&(position, text)| {
+            let index = layout.record_for(text);
+            // Um campo inválido (já em `errors`) fica vazio; os demais, como
+            // os de controle, continuam valendo para conferir a estrutura
+            let values = index.map_or_else(Vec::new, |i| {
+                layout.records[i].fields.iter().map(|field| field.value(text).unwrap_or_default()).collect()
+            });
+            Record { line: position.line, kind: index.map(|i| &types[i]), values }
+        })
+        .collect();
+    let valid: HashSet<u64> = out.processed_rows.iter().map(|row| row.line).collect();
+    let (errors, file) = check(format, &parsed, &valid);
+    out.file_errors.extend(errors);
+    out.cnab = Some(Box::new(file));
+    Ok(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Registro de `length` caracteres com os valores nas posições dos manuais
+    fn record(length: usize, fields: &[(usize, &str)]) -> String {
+        let mut chars = vec![' '; length];
+        for (start, value) in fields {
+            for (i, c) in value.chars().enumerate() {
+                chars[start - 1 + i] = c;
+            }
+        }
+        chars.into_iter().collect()
+    }
+
+    fn run(records: &[String]) -> ProcessingResult {
+        let mut out = ProcessingResult::default();
+        process(records.join("\r\n").as_bytes(), &CsvOptions::new(), &mut out).unwrap();
+        out
+    }
+
+    fn file_errors(out: &ProcessingResult) -> Vec<(FileErrorCode, Option<u64>, &str)> {
+        out.file_errors.iter().map(|e| (e.code, e.line, e.error.as_str())).collect()
+    }
+
+    fn header_240() -> String {
+        record(240, &[(1, "341"), (4, "0000"), (8, "0"), (143, "1"), (144, "15012024")])
+    }
+
+    fn batch_header(batch: &str) -> String {
+        record(240, &[(1, "341"), (4, batch), (8, "1"), (9, "R"), (10, "01")])
+    }
+
+    fn segment_p(batch: &str, sequence: &str, value: &str) -> String {
+        let fields = [(1, "341"), (4, batch), (8, "3"), (9, sequence), (14, "P"), (16, "01"), (78, "10022024"), (86, value)];
+        record(240, &fields)
+    }
+
+    fn segment_q(batch: &str, sequence: &str) -> String {
+        record(240, &[(1, "341"), (4, batch), (8, "3"), (9, sequence), (14, "Q"), (16, "01"), (34, "Ana")])
+    }
+
+    fn batch_trailer(batch: &str, records: &str, titles: &str, total: &str) -> String {
+        record(240, &[(1, "341"), (4, batch), (8, "5"), (18, records), (24, titles), (30, total)])
+    }
+
+    fn file_trailer_240(batches: &str, records: &str) -> String {
+        record(240, &[(1, "341"), (4, "9999"), (8, "9"), (18, batches), (24, records)])
+    }
+
+    fn valid_240() -> Vec<String> {
+        vec![
+            header_240(),
+            batch_header("0001"),
+            segment_p("0001", "00001", "000000000012345"),
+            segment_q("0001", "00002"),
+            batch_trailer("0001", "000004", "000001", "00000000000012345"),
+            file_trailer_240("000001", "000006"),
+        ]
+    }
+
+    #[test]
+    fn detects_the_format() {
+        assert_eq!(detect(&valid_240().join("\n")), Some(CnabFormat::Cnab240));
+        assert_eq!(detect(&valid_240().concat()), Some(CnabFormat::Cnab240));
+        assert_eq!(detect(&format!("\u{feff}{}", record(400, &[(1, "01")]))), Some(CnabFormat::Cnab400));
+        assert_eq!(detect("nome,valor\nAna,1\n"), None);
+
+        let mut out = ProcessingResult::default();
+        let error = process(b"nome,valor\nAna,1\n", &CsvOptions::new(), &mut out).unwrap_err();
+        assert_eq!(error.kind(), FatalErrorKind::InvalidCnab);
+    }
+
+    #[test]
+    fn cnab_240_valid_file() {
+        let out = run(&valid_240());
+        assert!(out.errors.is_empty());
+        assert_eq!(file_errors(&out), []);
+        let cnab = out.cnab.unwrap();
+        assert_eq!(
+            (cnab.format(), cnab.direction(), cnab.bank(), cnab.batches()),
+            (CnabFormat::Cnab240, Some(CnabDirection::Remessa), Some("341"), Some(1))
+        );
+        let entry = &cnab.entries()[0];
+        assert_eq!((entry.line(), entry.batch(), entry.segments()), (3, Some(1), &["P".to_string(), "Q".to_string()][..]));
+        assert_eq!(entry.data()["valor_titulo"], json!(123.45));
+        assert_eq!(entry.data()["vencimento"], json!("2024-02-10"));
+        assert_eq!(entry.data()["pagador_nome"], json!("Ana"));
+        assert!(!entry.data().contains_key("sequencial"));
+    }
+
+    #[test]
+    fn cnab_240_counts_and_totals() {
+        let mut records = valid_240();
+        records[4] = batch_trailer("0001", "000005", "000002", "00000000000010000");
+        records[5] = file_trailer_240("000002", "000007");
+        assert_eq!(
+            file_errors(&run(&records)),
+            [
+                (FileErrorCode::TrailerCount, Some(5), "Batch 1 trailer informs 5 records, found 4"),
+                (FileErrorCode::TrailerCount, Some(5), "Batch 1 trailer informs 2 titles, found 1"),
+                (FileErrorCode::TrailerSum, Some(5), "Batch 1 trailer informs a total of 100.00, titles sum 123.45"),
+                (FileErrorCode::TrailerCount, Some(6), "File trailer informs 2 batches, found 1"),
+                (FileErrorCode::TrailerCount, Some(6), "File trailer informs 7 records, found 6"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cnab_240_record_order() {
+        let records = vec![
+            batch_header("0001"),
+            segment_q("0001", "00001"),
+            segment_p("0001", "00003", "000000000000100"),
+            segment_q("0002", "00004"),
+            segment_q("0001", "00005"),
+            record(240, &[(1, "237"), (4, "0001"), (8, "3"), (9, "00006"), (14, "P"), (16, "01"), (78, "10022024"), (86, "1")]),
+            batch_header("0003"),
+        ];
+        let out = run(&records);
+        assert_eq!(
+            file_errors(&out),
+            [
+                (FileErrorCode::RecordOrder, Some(1), "The file does not start with a file header"),
+                (FileErrorCode::RecordOrder, Some(2), "Segment Q without a preceding segment P"),
+                (FileErrorCode::RecordOrder, Some(3), "Sequential number 3 in batch 1, expected 2"),
+                (FileErrorCode::HeaderMismatch, Some(4), "Segment Q has batch number 2, inside batch 1"),
+                (FileErrorCode::RecordOrder, Some(5), "Segment Q repeated in the same title"),
+                (FileErrorCode::TrailerMissing, Some(1), "Batch 1 has no batch trailer"),
+                (FileErrorCode::RecordOrder, Some(7), "Batch number 3, expected 2"),
+                (FileErrorCode::TrailerMissing, Some(7), "Batch 3 has no batch trailer"),
+                (FileErrorCode::TrailerMissing, None, "The file has no file trailer"),
+            ]
+        );
+        // O Q sem P não vira lançamento
+        let cnab = out.cnab.unwrap();
+        assert_eq!(cnab.entries().iter().map(|e| e.line()).collect::<Vec<_>>(), [3, 6]);
+    }
+
+    #[test]
+    fn cnab_240_bank_and_repeated_records() {
+        let mut records = valid_240();
+        records.insert(1, header_240());
+        records[3] = segment_p("0001", "00001", "00000000001234X");
+        records[4] = record(240, &[(1, "001"), (4, "0001"), (8, "3"), (9, "00002"), (14, "Q"), (16, "01"), (34, "Ana")]);
+        records.push(file_trailer_240("000001", "000008"));
+        let out = run(&records);
+        assert_eq!(
+            file_errors(&out),
+            [
+                (FileErrorCode::RecordOrder, Some(2), "The file header must be the first record and appear only once"),
+                (FileErrorCode::HeaderMismatch, Some(5), "Bank code 001 differs from the file header (341)"),
+                (FileErrorCode::TrailerSum, Some(6), "Batch 1 trailer informs a total of 123.45, titles sum 0.00"),
+                (FileErrorCode::TrailerCount, Some(7), "File trailer informs 6 records, found 7"),
+                (FileErrorCode::RecordOrder, Some(8), "Record after the file trailer"),
+                (FileErrorCode::TrailerRepeated, Some(8), "More than one file trailer"),
+            ]
+        );
+        // O P com valor inválido é rejeitado e o título fica de fora, mas o
+        // segmento continua contando na estrutura do lote
+        assert_eq!(out.errors.len(), 1);
+        assert!(out.cnab.unwrap().entries().is_empty());
+    }
+
+    fn remessa_400() -> Vec<String> {
+        vec![
+            record(400, &[(1, "0"), (2, "1"), (77, "237"), (95, "150124"), (395, "000001")]),
+            record(400, &[(1, "1"), (109, "01"), (121, "100224"), (127, "0000000012345"), (235, "Ana"), (395, "000002")]),
+            record(400, &[(1, "9"), (395, "000003")]),
+        ]
+    }
+
+    #[test]
+    fn cnab_400_remessa() {
+        let out = run(&remessa_400());
+        assert_eq!(file_errors(&out), []);
+        let cnab = out.cnab.unwrap();
+        assert_eq!(
+            (cnab.format(), cnab.direction(), cnab.bank(), cnab.batches()),
+            (CnabFormat::Cnab400, Some(CnabDirection::Remessa), Some("237"), None)
+        );
+        assert_eq!(cnab.entries()[0].data()["valor_titulo"], json!(123.45));
+        assert_eq!(cnab.entries()[0].data()["vencimento"], json!("2024-02-10"));
+
+        let mut records = remessa_400();
+        records[2] = record(400, &[(1, "9"), (395, "000004")]);
+        records.push(record(400, &[(1, "1"), (109, "01"), (121, "100224"), (127, "1"), (235, "Bia"), (395, "000004")]));
+        assert_eq!(
+            file_errors(&run(&records)),
+            [
+                (FileErrorCode::RecordOrder, Some(3), "Sequential number 4, expected 3"),
+                (FileErrorCode::RecordOrder, Some(4), "Record after the file trailer"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cnab_400_retorno_totals() {
+        let records = vec![
+            record(400, &[(1, "0"), (2, "2"), (77, "237"), (95, "150124"), (395, "000001")]),
+            record(400, &[(1, "1"), (109, "06"), (153, "0000000012345"), (254, "0000000012345"), (395, "000002")]),
+            record(400, &[(1, "1"), (109, "06"), (153, "0000000000100"), (395, "000003")]),
+            record(400, &[(1, "9"), (18, "00000003"), (26, "00000000012445"), (395, "000004")]),
+        ];
+        let out = run(&records);
+        assert_eq!(file_errors(&out), [(FileErrorCode::TrailerCount, Some(4), "File trailer informs 3 titles, found 2")]);
+        let cnab = out.cnab.unwrap();
+        assert_eq!(cnab.direction(), Some(CnabDirection::Retorno));
+        assert_eq!(cnab.entries().len(), 2);
+        assert_eq!(cnab.entries()[0].data()["valor_pago"], json!(123.45));
+    }
+}
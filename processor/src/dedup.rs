@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, HashMap};
+
+use xxhash_rust::xxh3::xxh3_128;
+
+use crate::ProcessedRow;
+
+// O que acontece com as linhas repetidas
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum DedupMode {
+    #[default]
+    Off,
+    // Repetições viram erros de validação apontando para a outra ocorrência
+    Flag,
+    // Repetições são descartadas sem gerar erro
+    Drop,
+}
+
+// Qual ocorrência continua em `processed_rows`
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum Keep {
+    #[default]
+    First,
+    // Exige guardar as linhas válidas até o fim do arquivo
+    Last,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct DedupConfig {
+    pub(crate) mode: DedupMode,
+    pub(crate) keep: Keep,
+    // Colunas que formam a chave; None compara todos os valores da linha
+    pub(crate) key: Option<Vec<String>>,
+}
+
+// Resultado da verificação de uma linha
+pub(crate) enum Occurrence {
+    Unique,
+    // A linha repete a da linha informada, que continua valendo
+    DuplicateOf(u64),
+    // A linha substitui a ocorrência anterior, que deixa de valer, com os
+    // campos guardados junto com ela
+    Replaces(ProcessedRow, Option<Vec<String>>),
+}
+
+pub(crate) struct Deduplicator {
+    config: DedupConfig,
+    key_indices: Option<Vec<usize>>,
+    // Só a impressão digital de cada chave é guardada, não o valor
+    seen: HashMap<u128, u64>,
+    // Linhas retidas no modo "last", por número de linha, com os campos
+    // originais para o caso de serem marcadas como repetidas depois
+    held: BTreeMap<u64, (ProcessedRow, Option<Vec<String>>)>,
+}
+
+impl Deduplicator {
+    // Colunas da chave que não existem no cabeçalho são um erro: sem elas,
+    // todas as linhas teriam a mesma chave
+    pub(crate) fn new(config: &DedupConfig, headers: &[String]) -> Result<Option<Self>, String> {
+        if config.mode == DedupMode::Off {
+            return Ok(None);
+        }
+        let key_indices = match &config.key {
+            Some(columns) => Some(
+                columns
+                    .iter()
+                    .map(|c| {
+                        headers
+                            .iter()
+                            .position(|h| h == c)
+                            .ok_or_else(|| format!("column '{}' used by dedup.key is not in the header", c))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+        Ok(Some(Deduplicator {
+            config: config.clone(),
+            key_indices,
+            seen: HashMap::new(),
+            held: BTreeMap::new(),
+        }))
+    }
+
+    pub(crate) fn mode(&self) -> DedupMode {
+        self.config.mode
+    }
+
+    pub(crate) fn holds_rows(&self) -> bool {
+        self.config.keep == Keep::Last
+    }
+
+    // Impressão digital da chave da linha válida: os valores das colunas da
+    // chave ou, sem chave, todos os valores da linha. O hash configurado não
+    // serve, já que pode cobrir só algumas colunas. Não altera o estado, então
+    // pode ser calculada em paralelo.
+    pub(crate) fn fingerprint(&self, values: &[&str]) -> u128 {
+        // Valores com o tamanho na frente, para que ("a,b", "c") e
+        // ("a", "b,c") não formem a mesma chave
+        let mut key = Vec::new();
+        let mut push = |value: &str| {
+            key.extend_from_slice(value.len().to_string().as_bytes());
+            key.push(b':');
+            key.extend_from_slice(value.as_bytes());
+        };
+        match &self.key_indices {
+            // Linhas curtas (modo flexível) não têm todas as colunas
+            Some(indices) => indices.iter().for_each(|&index| push(values.get(index).copied().unwrap_or(""))),
+            None => values.iter().for_each(|value| push(value)),
+        }
+        xxh3_128(&key)
+    }
+
+    // Verifica se a chave da linha válida já apareceu
+    pub(crate) fn check(&mut self, row: &ProcessedRow, fingerprint: u128) -> Occurrence {
+        match (self.seen.insert(fingerprint, row.line), self.config.keep) {
+            (None, _) => Occurrence::Unique,
+            (Some(first), Keep::First) => {
+                // Mantém a primeira ocorrência registrada
+                self.seen.insert(fingerprint, first);
+                Occurrence::DuplicateOf(first)
+            }
+            (Some(previous), Keep::Last) => match self.held.remove(&previous) {
+                Some((row, fields)) => Occurrence::Replaces(row, fields),
+                None => Occurrence::Unique,
+            },
+        }
+    }
+
+    pub(crate) fn hold(&mut self, row: ProcessedRow, fields: Option<Vec<String>>) {
+        self.held.insert(row.line, (row, fields));
+    }
+
+    // Devolve, em ordem de linha, as ocorrências que ficaram por último
+    pub(crate) fn release(&mut self) -> impl Iterator<Item = ProcessedRow> {
+        std::mem::take(&mut self.held).into_values().map(|(row, _)| row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{process_reader, CsvOptions, ProcessingResult, ProcessingTotals};
+
+    fn run(input: &str, setup: impl Fn(&mut CsvOptions)) -> (ProcessingResult, ProcessingTotals) {
+        let mut options = CsvOptions::default();
+        setup(&mut options);
+        let mut result = ProcessingResult::default();
+        let totals = process_reader(input.as_bytes(), &options, |batch| result.append(batch)).unwrap();
+        (result, totals)
+    }
+
+    fn lines(result: &ProcessingResult) -> Vec<u64> {
+        result.processed_rows().iter().map(|row| row.line()).collect()
+    }
+
+    // Linha, linha mantida, mensagem e campos guardados para a saída CSV
+    type Duplicate<'a> = (u64, Option<u64>, &'a str, Option<Vec<String>>);
+
+    fn duplicates(result: &ProcessingResult) -> Vec<Duplicate<'_>> {
+        result.errors().iter().map(|e| (e.line(), e.duplicate_of(), e.message(), e.fields.clone())).collect()
+    }
+
+    fn fields(values: &[&str]) -> Option<Vec<String>> {
+        Some(values.iter().map(|v| v.to_string()).collect())
+    }
+
+    #[test]
+    fn rows_are_compared_by_every_value() {
+        // O hash cobre só `id`, mas as linhas só se repetem com todos os valores iguais
+        let input = "id,nome,obs\n1,Ana,x\n1,Bia,x\n1,Ana,x\n2,\"a,b\",c\n2,a,\"b,c\"\n";
+        for algorithm in ["sha256", "xxh3"] {
+            let (result, totals) = run(input, |options| {
+                options.set_dedup("flag").unwrap();
+                options.set_hash_algorithm(algorithm).unwrap();
+                options.set_hash_columns(vec!["id".to_string()]);
+            });
+            assert_eq!(lines(&result), [2, 3, 5, 6]);
+            assert_eq!(
+                duplicates(&result),
+                [(4, Some(2), "Duplicate row: same values as line 2", fields(&["1", "Ana", "x"]))]
+            );
+            assert_eq!((totals.duplicate_rows, totals.invalid_rows), (1, 1));
+        }
+    }
+
+    #[test]
+    fn keep_first() {
+        let input = "id,nome\n1,Ana\n2,Bia\n1,Ana\n1,Ana\n";
+        let (result, totals) = run(input, |options| options.set_dedup("flag").unwrap());
+        assert_eq!(lines(&result), [2, 3]);
+        assert_eq!(
+            duplicates(&result),
+            [
+                (4, Some(2), "Duplicate row: same values as line 2", fields(&["1", "Ana"])),
+                (5, Some(2), "Duplicate row: same values as line 2", fields(&["1", "Ana"])),
+            ]
+        );
+        assert_eq!((totals.valid_rows, totals.invalid_rows, totals.duplicate_rows), (2, 2, 2));
+
+        // Descartadas, as repetições não viram erro nem linha inválida
+        let (result, totals) = run(input, |options| options.set_dedup("drop").unwrap());
+        assert_eq!(lines(&result), [2, 3]);
+        assert!(result.errors().is_empty());
+        assert_eq!((totals.valid_rows, totals.invalid_rows, totals.duplicate_rows), (2, 0, 2));
+    }
+
+    #[test]
+    fn keep_last_replaces_the_held_row() {
+        let input = "id,nome\n1,Ana\n2,Bia\n1,Carla\n1,Davi\n";
+        let keep_last = |mode: &'static str| {
+            move |options: &mut CsvOptions| {
+                options.set_dedup(mode).unwrap();
+                options.set_dedup_keep("last").unwrap();
+                options.set_dedup_key(vec!["id".to_string()]);
+            }
+        };
+        let (result, totals) = run(input, keep_last("flag"));
+        assert_eq!(lines(&result), [3, 5]);
+        assert_eq!(result.processed_rows()[1].data()["nome"], "Davi");
+        // A ocorrência substituída guarda os próprios campos, usados no rejected.csv
+        assert_eq!(
+            duplicates(&result),
+            [
+                (2, Some(4), "Duplicate key (id): same values as line 4", fields(&["1", "Ana"])),
+                (4, Some(5), "Duplicate key (id): same values as line 5", fields(&["1", "Carla"])),
+            ]
+        );
+        assert_eq!((totals.valid_rows, totals.invalid_rows, totals.duplicate_rows), (2, 2, 2));
+
+        let (result, totals) = run(input, keep_last("drop"));
+        assert_eq!(lines(&result), [3, 5]);
+        assert!(result.errors().is_empty());
+        assert_eq!((totals.valid_rows, totals.invalid_rows, totals.duplicate_rows), (2, 0, 2));
+    }
+
+    #[test]
+    fn key_columns_must_exist() {
+        let mut options = CsvOptions::default();
+        options.set_dedup("flag").unwrap();
+        options.set_dedup_key(vec!["cpf".to_string()]);
+        let error = crate::process(b"id,nome\n1,Ana\n", &options).err().unwrap();
+        assert_eq!(error.message(), "column 'cpf' used by dedup.key is not in the header");
+    }
+}
@@ -0,0 +1,347 @@
+use serde::Serialize;
+
+// Codificações de texto reconhecidas na entrada
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub(crate) enum Encoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "windows-1252")]
+    Windows1252,
+}
+
+impl Encoding {
+    pub(crate) fn from_label(label: &str) -> Option<Encoding> {
+        match label.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-16le" | "utf-16" => Some(Encoding::Utf16Le),
+            "utf-16be" => Some(Encoding::Utf16Be),
+            // Latin-1 é tratado como Windows-1252, como fazem os navegadores
+            "windows-1252" | "cp1252" | "latin1" | "iso-8859-1" => Some(Encoding::Windows1252),
+            _ => None,
+        }
+    }
+
+    // Tamanho na entrada original de um trecho do texto já convertido para
+    // UTF-8, para que as posições informadas sejam as do arquivo lido
+    pub(crate) fn original_len(self, utf8: &[u8]) -> u64 {
+        let chars = || utf8.iter().filter(|&&b| b & 0xc0 != 0x80).count() as u64;
+        match self {
+            Encoding::Utf8 => utf8.len() as u64,
+            // Cada caractere veio de um byte
+            Encoding::Windows1252 => chars(),
+            // Duas unidades de 16 bits fora do BMP, que em UTF-8 têm 4 bytes
+            Encoding::Utf16Le | Encoding::Utf16Be => 2 * (chars() + utf8.iter().filter(|&&b| b >= 0xf0).count() as u64),
+        }
+    }
+
+    fn codec(self) -> &'static encoding_rs::Encoding {
+        match self {
+            Encoding::Utf8 => encoding_rs::UTF_8,
+            Encoding::Utf16Le => encoding_rs::UTF_16LE,
+            Encoding::Utf16Be => encoding_rs::UTF_16BE,
+            Encoding::Windows1252 => encoding_rs::WINDOWS_1252,
+        }
+    }
+}
+
+pub(crate) struct Detected {
+    pub(crate) encoding: Encoding,
+    pub(crate) bom: bool,
+}
+
+// Detecta a codificação pelo BOM ou pelo conteúdo, com a mesma regra do
+// `Transcoder`: a primeira sequência não ASCII decide. Devolve None quando a
+// amostra só tem ASCII, que é lido igual em todas as codificações de 8 bits.
+pub(crate) fn detect(sample: &[u8]) -> Option<Detected> {
+    if let Some(detected) = detect_unicode(sample) {
+        return Some(detected);
+    }
+    let first = sample.iter().position(|b| !b.is_ascii())?;
+    // A amostra é só o começo do arquivo: sequência cortada no fim dela ainda
+    // conta como UTF-8
+    let encoding = detect_sequence(&sample[first..], false).unwrap_or(Encoding::Utf8);
+    Some(Detected { encoding, bom: false })
+}
+
+// BOM ou UTF-16 sem BOM, que só podem ser reconhecidos no começo do arquivo
+fn detect_unicode(sample: &[u8]) -> Option<Detected> {
+    let boms: [(&[u8], Encoding); 3] = [
+        (b"\xef\xbb\xbf", Encoding::Utf8),
+        (b"\xff\xfe", Encoding::Utf16Le),
+        (b"\xfe\xff", Encoding::Utf16Be),
+    ];
+    if let Some(&(_, encoding)) = boms.iter().find(|(bom, _)| sample.starts_with(bom)) {
+        return Some(Detected { encoding, bom: true });
+    }
+    detect_utf16(sample).map(|encoding| Detected { encoding, bom: false })
+}
+
+// Decide entre UTF-8 e Windows-1252 pela primeira sequência não ASCII.
+// Devolve None se a sequência ainda está incompleta e mais bytes virão.
+fn detect_sequence(bytes: &[u8], last: bool) -> Option<Encoding> {
+    let sequence = &bytes[..bytes.len().min(4)];
+    match std::str::from_utf8(sequence) {
+        Ok(_) => Some(Encoding::Utf8),
+        Err(e) if e.valid_up_to() > 0 => Some(Encoding::Utf8),
+        Err(e) if e.error_len().is_none() && !last => None,
+        Err(_) => Some(Encoding::Windows1252),
+    }
+}
+
+// UTF-16 sem BOM: texto majoritariamente ASCII deixa um byte zero em toda
+// posição par (big endian) ou ímpar (little endian)
+fn detect_utf16(sample: &[u8]) -> Option<Encoding> {
+    let sample = &sample[..sample.len().min(4096) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zeros_at = |offset: usize| sample.iter().skip(offset).step_by(2).filter(|&&b| b == 0).count();
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+
+    if odd * 10 >= pairs * 7 && even * 10 < pairs {
+        Some(Encoding::Utf16Le)
+    } else if even * 10 >= pairs * 7 && odd * 10 < pairs {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+// Converte a entrada para UTF-8 em streaming. Em modo automático, a decisão
+// é adiada enquanto só chega ASCII, que é igual em todas as codificações.
+// Cada trecho convertido é entregue com a codificação de onde veio; o BOM é
+// mantido como U+FEFF, para que as posições contem os seus bytes.
+pub(crate) struct Transcoder {
+    state: State,
+    // Bytes retidos enquanto a codificação automática não foi decidida
+    pending: Vec<u8>,
+    started: bool,
+    buffer: String,
+}
+
+enum State {
+    Pending,
+    Utf8,
+    Decoding(Encoding, encoding_rs::Decoder),
+}
+
+impl Transcoder {
+    pub(crate) fn new(encoding: Option<Encoding>) -> Self {
+        let state = match encoding {
+            None => State::Pending,
+            Some(encoding) => State::for_encoding(encoding),
+        };
+        Transcoder { state, pending: Vec::new(), started: false, buffer: String::new() }
+    }
+
+    // Codificação em uso; None enquanto a entrada automática só teve ASCII
+    pub(crate) fn encoding(&self) -> Option<Encoding> {
+        match &self.state {
+            State::Pending => None,
+            State::Utf8 => Some(Encoding::Utf8),
+            State::Decoding(encoding, _) => Some(*encoding),
+        }
+    }
+
+    // Converte um pedaço e entrega os bytes UTF-8 resultantes para `out`
+    pub(crate) fn feed<F>(&mut self, chunk: &[u8], mut out: F)
+    where
+        F: FnMut(&[u8], Encoding),
+    {
+        if chunk.is_empty() {
+            return;
+        }
+        if let State::Pending = self.state {
+            self.pending.extend_from_slice(chunk);
+            self.resolve(false, &mut out);
+        } else {
+            self.convert(chunk, false, &mut out);
+        }
+    }
+
+    // Entrega o que ainda estiver retido, inclusive no decodificador
+    pub(crate) fn finish<F>(&mut self, mut out: F)
+    where
+        F: FnMut(&[u8], Encoding),
+    {
+        if let State::Pending = self.state {
+            self.resolve(true, &mut out);
+        }
+        self.convert(&[], true, &mut out);
+    }
+
+    fn resolve<F>(&mut self, last: bool, out: &mut F)
+    where
+        F: FnMut(&[u8], Encoding),
+    {
+        if !self.started {
+            // Espera bytes suficientes para reconhecer BOM ou UTF-16
+            if self.pending.len() < 4 && !last {
+                return;
+            }
+            self.started = true;
+            if let Some(detected) = detect_unicode(&self.pending) {
+                return self.decide(detected.encoding, out);
+            }
+        }
+
+        match self.pending.iter().position(|b| !b.is_ascii()) {
+            // ASCII tem um byte por caractere em qualquer codificação de 8 bits
+            None => {
+                out(&self.pending, Encoding::Utf8);
+                self.pending.clear();
+            }
+            Some(i) => match detect_sequence(&self.pending[i..], last) {
+                Some(encoding) => self.decide(encoding, out),
+                None => {
+                    out(&self.pending[..i], Encoding::Utf8);
+                    self.pending.drain(..i);
+                }
+            },
+        }
+    }
+
+    fn decide<F>(&mut self, encoding: Encoding, out: &mut F)
+    where
+        F: FnMut(&[u8], Encoding),
+    {
+        self.state = State::for_encoding(encoding);
+        let pending = std::mem::take(&mut self.pending);
+        self.convert(&pending, false, out);
+    }
+
+    fn convert<F>(&mut self, input: &[u8], last: bool, out: &mut F)
+    where
+        F: FnMut(&[u8], Encoding),
+    {
+        match &mut self.state {
+            State::Pending => {}
+            State::Utf8 => {
+                if !input.is_empty() {
+                    out(input, Encoding::Utf8);
+                }
+            }
+            State::Decoding(encoding, decoder) => {
+                decode(decoder, input, last, &mut self.buffer);
+                out(self.buffer.as_bytes(), *encoding);
+            }
+        }
+    }
+}
+
+impl State {
+    fn for_encoding(encoding: Encoding) -> State {
+        match encoding {
+            Encoding::Utf8 => State::Utf8,
+            other => State::Decoding(other, other.codec().new_decoder_without_bom_handling()),
+        }
+    }
+}
+
+fn decode(decoder: &mut encoding_rs::Decoder, input: &[u8], last: bool, buffer: &mut String) {
+    buffer.clear();
+    let needed = decoder.max_utf8_buffer_length(input.len()).unwrap_or(input.len() * 3 + 16);
+    buffer.reserve(needed);
+    let _ = decoder.decode_to_string(input, buffer, last);
+}
+
+// Decodifica um trecho inteiro de uma vez, usado pela detecção de dialeto
+pub(crate) fn decode_sample(sample: &[u8], encoding: Encoding) -> String {
+    let (text, _, _) = encoding.codec().decode(sample);
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Passa os pedaços pelo Transcoder e junta a saída, com a codificação
+    // decidida no fim
+    fn transcode(chunks: &[&[u8]], encoding: Option<Encoding>) -> (String, Option<Encoding>) {
+        let mut transcoder = Transcoder::new(encoding);
+        let mut output = Vec::new();
+        for chunk in chunks {
+            transcoder.feed(chunk, |bytes, _| output.extend_from_slice(bytes));
+        }
+        transcoder.finish(|bytes, _| output.extend_from_slice(bytes));
+        (String::from_utf8_lossy(&output).into_owned(), transcoder.encoding())
+    }
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        let units = text.encode_utf16();
+        if little_endian {
+            units.flat_map(u16::to_le_bytes).collect()
+        } else {
+            units.flat_map(u16::to_be_bytes).collect()
+        }
+    }
+
+    #[test]
+    fn multibyte_character_split_across_chunks() {
+        let input = "nome\nJosé\nSão Paulo\n".as_bytes();
+        // Corta em cada posição, inclusive no meio de "é" e de "ã"
+        for at in 1..input.len() {
+            let (text, encoding) = transcode(&[&input[..at], &input[at..]], None);
+            assert_eq!(text, "nome\nJosé\nSão Paulo\n", "corte em {}", at);
+            assert_eq!(encoding, Some(Encoding::Utf8));
+        }
+
+        // Byte a byte, em Windows-1252
+        let input = b"nome\nJos\xe9\n";
+        let chunks: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(transcode(&chunks, None), ("nome\nJosé\n".to_string(), Some(Encoding::Windows1252)));
+    }
+
+    #[test]
+    fn utf16_without_bom() {
+        let text = "id,nome\n1,José\n";
+        for (little_endian, expected) in [(true, Encoding::Utf16Le), (false, Encoding::Utf16Be)] {
+            let input = utf16(text, little_endian);
+            assert_eq!(transcode(&[&input], None), (text.to_string(), Some(expected)));
+            // Em pedaços ímpares, que cortam as unidades de 16 bits
+            let chunks: Vec<&[u8]> = input.chunks(3).collect();
+            assert_eq!(transcode(&chunks, None), (text.to_string(), Some(expected)));
+            assert_eq!(detect(&input).map(|d| (d.encoding, d.bom)), Some((expected, false)));
+        }
+    }
+
+    #[test]
+    fn cp1252_after_valid_utf8_stays_utf8() {
+        // O primeiro caractere não ASCII decide: o "ã" em Windows-1252 depois
+        // de um "é" em UTF-8 segue como está e vira erro de UTF-8 na linha
+        let input = b"nome\nJos\xc3\xa9\nJo\xe3o\n";
+        let mut output = Vec::new();
+        let mut transcoder = Transcoder::new(None);
+        transcoder.feed(input, |bytes, encoding| {
+            assert_eq!(encoding, Encoding::Utf8);
+            output.extend_from_slice(bytes);
+        });
+        transcoder.finish(|bytes, _| output.extend_from_slice(bytes));
+        assert_eq!(output, input);
+        assert_eq!(transcoder.encoding(), Some(Encoding::Utf8));
+    }
+
+    #[test]
+    fn detect_agrees_with_the_transcoder() {
+        let samples: [&[u8]; 6] = [
+            b"nome\nJos\xc3\xa9\nJo\xe3o\n",
+            b"nome\nJo\xe3o\nJos\xc3\xa9\n",
+            b"nome\nJos\xe9\n",
+            b"\xef\xbb\xbfnome\nJo\xc3\xa3o\n",
+            b"\xff\xfen\x00o\x00",
+            b"nome\nJos\xc3\xa9",
+        ];
+        for sample in samples {
+            let detected = detect(sample).map(|d| d.encoding);
+            assert_eq!(detected, transcode(&[sample], None).1, "{:?}", sample);
+        }
+        // Sequência cortada no fim da amostra: o arquivo continua depois dela
+        assert_eq!(detect(b"nome\nJos\xc3").map(|d| d.encoding), Some(Encoding::Utf8));
+        assert!(detect(b"id,nome\n1,Ana\n").is_none());
+    }
+}
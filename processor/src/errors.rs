@@ -0,0 +1,244 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+// Código estável de cada tipo de erro, serializado como `code`. Ao contrário
+// da mensagem, não muda entre versões e pode ser comparado diretamente.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // Linha sem schema com algum campo vazio
+    EmptyFields,
+    // Quantidade de campos diferente da do cabeçalho
+    FieldCountMismatch,
+    InvalidUtf8,
+    // Coluna obrigatória vazia
+    Required,
+    MinLength,
+    MaxLength,
+    // Valor que não corresponde ao tipo da coluna (int, date, cpf, email...)
+    InvalidValue,
+    // Valor fora da lista do tipo "enum"
+    NotAllowed,
+    // Valor que não casa com o padrão do tipo "regex"
+    PatternMismatch,
+    Duplicate,
+    // Regra entre colunas da linha (`rules` do schema)
+    RowRule,
+    // Registro de uma entrada JSON que não é um objeto JSON válido
+    InvalidJson,
+    // Registro posicional que não casa com nenhum tipo de registro do layout
+    UnknownRecord,
+    // Registro posicional com tamanho diferente de `record_length`
+    RecordLength,
+    // Nome repetido no cabeçalho, renomeado para "nome_2"; só um aviso
+    DuplicateHeader,
+}
+
+// Código das verificações do arquivo inteiro, em `file_errors`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileErrorCode {
+    // Valor repetido em uma chave única
+    Unique,
+    // Coluna que deveria crescer e voltou ou repetiu
+    Monotonic,
+    // Mais linhas que `max_rows` do schema
+    MaxRows,
+    // Nenhum registro reconheceu a condição do trailer
+    TrailerMissing,
+    // Mais de um registro trailer
+    TrailerRepeated,
+    // Quantidade de linhas diferente da informada no trailer
+    TrailerCount,
+    // Soma de uma coluna diferente da informada no trailer
+    TrailerSum,
+    // Registro fora de lugar ou fora de sequência, como um detalhe fora de um
+    // lote do CNAB ou um número sequencial pulado
+    RecordOrder,
+    // Registro que não bate com o seu header, como outro código de banco ou
+    // outro número de lote
+    HeaderMismatch,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    // A linha é rejeitada
+    #[default]
+    Error,
+    // Só informativo: a linha continua em `processed_rows`
+    Warning,
+}
+
+// Tipo de um erro fatal, que interrompe o processamento do arquivo inteiro
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FatalErrorKind {
+    // Cabeçalho que não pode ser usado, como colunas com o mesmo nome
+    BadHeader,
+    // Cabeçalho que não é UTF-8 válido na codificação usada
+    InvalidUtf8,
+    // Arquivo acima de um dos limites das opções
+    LimitExceeded,
+    // Planilha que não pode ser lida: formato desconhecido, arquivo
+    // corrompido ou planilha escolhida inexistente
+    BadSpreadsheet,
+    // Documento JSON malformado, ou que não é um array de objetos
+    InvalidJson,
+    // Arquivo que não é um CNAB 240 nem um CNAB 400
+    InvalidCnab,
+}
+
+impl FatalErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FatalErrorKind::BadHeader => "bad_header",
+            FatalErrorKind::InvalidUtf8 => "invalid_utf8",
+            FatalErrorKind::LimitExceeded => "limit_exceeded",
+            FatalErrorKind::BadSpreadsheet => "bad_spreadsheet",
+            FatalErrorKind::InvalidJson => "invalid_json",
+            FatalErrorKind::InvalidCnab => "invalid_cnab",
+        }
+    }
+}
+
+// Erro que impede o processamento do arquivo. Ao contrário de um
+// `ValidationError`, não se refere a uma linha rejeitada: nenhum resultado é
+// devolvido. No JavaScript vira uma exceção cuja mensagem começa pelo tipo,
+// como "bad_header: ...", com o tipo, a linha e a posição também nas
+// propriedades `kind`, `line` e `byte`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FatalError {
+    kind: FatalErrorKind,
+    message: String,
+    // Linha e posição em bytes do registro que causou o erro; 0 quando o
+    // erro não vem de um registro
+    line: u64,
+    byte_offset: u64,
+}
+
+impl FatalError {
+    pub(crate) fn new(kind: FatalErrorKind, message: impl Into<String>, line: u64, byte_offset: u64) -> Self {
+        FatalError { kind, message: message.into(), line, byte_offset }
+    }
+
+    pub fn kind(&self) -> FatalErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    pub fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+}
+
+impl fmt::Display for FatalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.as_str(), self.message)?;
+        // Erros do arquivo inteiro, como uma planilha ilegível, não têm linha
+        if self.line > 0 {
+            write!(f, " (line {})", self.line)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FatalError {}
+
+// Na API de leitura em pedaços, o erro fatal chega como um `io::Error` do
+// tipo InvalidData; o `FatalError` original pode ser obtido com `downcast`
+impl From<FatalError> for std::io::Error {
+    fn from(error: FatalError) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
+
+// Erro de configuração: opção, schema ou dialeto inválido. No JavaScript vira
+// uma exceção `Error` com a mesma mensagem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    message: String,
+}
+
+impl ConfigError {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        ConfigError { message: message.into() }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+// Só é chamado na fronteira com o JavaScript; fora do wasm o JsError não
+// pode ser criado
+impl From<ConfigError> for JsValue {
+    fn from(error: ConfigError) -> JsValue {
+        JsError::new(&error.message).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn codes_serialize_in_snake_case() {
+        let codes = [
+            (ErrorCode::EmptyFields, "empty_fields"),
+            (ErrorCode::FieldCountMismatch, "field_count_mismatch"),
+            (ErrorCode::InvalidUtf8, "invalid_utf8"),
+            (ErrorCode::InvalidJson, "invalid_json"),
+            (ErrorCode::DuplicateHeader, "duplicate_header"),
+        ];
+        for (code, name) in codes {
+            assert_eq!(serde_json::to_value(code).unwrap(), json!(name));
+            assert_eq!(serde_json::from_value::<ErrorCode>(json!(name)).unwrap(), code);
+        }
+        assert_eq!(serde_json::to_value(FileErrorCode::TrailerSum).unwrap(), json!("trailer_sum"));
+        assert_eq!(serde_json::to_value(Severity::Warning).unwrap(), json!("warning"));
+    }
+
+    #[test]
+    fn fatal_kind_names_match_the_serialized_kind() {
+        let kinds = [
+            FatalErrorKind::BadHeader,
+            FatalErrorKind::InvalidUtf8,
+            FatalErrorKind::LimitExceeded,
+            FatalErrorKind::BadSpreadsheet,
+            FatalErrorKind::InvalidJson,
+            FatalErrorKind::InvalidCnab,
+        ];
+        for kind in kinds {
+            assert_eq!(serde_json::to_value(kind).unwrap(), json!(kind.as_str()));
+        }
+    }
+
+    #[test]
+    fn fatal_error_display_and_io_error() {
+        let error = FatalError::new(FatalErrorKind::LimitExceeded, "record larger than 8 bytes", 3, 12);
+        assert_eq!(error.to_string(), "limit_exceeded: record larger than 8 bytes (line 3)");
+        // Sem linha, a mensagem fica sem o "(line N)"
+        let sheet = FatalError::new(FatalErrorKind::BadSpreadsheet, "unknown format", 0, 0);
+        assert_eq!(sheet.to_string(), "bad_spreadsheet: unknown format");
+
+        let io = std::io::Error::from(error.clone());
+        assert_eq!(io.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(io.into_inner().unwrap().downcast::<FatalError>().unwrap().as_ref(), &error);
+    }
+}
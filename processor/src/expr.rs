@@ -0,0 +1,805 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::values;
+
+// Linguagem de expressões das regras de linha, como
+// `data_fim >= data_inicio` ou `valor_total == round(quantidade * valor_unitario, 2)`.
+// Só lê os valores da linha: não há atribuição, laço nem acesso a nada fora
+// dela, então uma regra vinda de um schema qualquer é segura de avaliar.
+pub(crate) enum Expr {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    // Índice na lista de colunas usadas pelas regras do schema
+    Column(usize),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Arithmetic(Arithmetic, Box<Expr>, Box<Expr>),
+    Compare(Comparison, Box<Expr>, Box<Expr>),
+    In { value: Box<Expr>, list: Vec<Expr>, negated: bool },
+    Call(Function, Vec<Expr>),
+    Lookup(Arc<HashMap<String, String>>, Box<Expr>),
+    Matches(Box<Expr>, Regex),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Arithmetic {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Function {
+    Empty,
+    Len,
+    Upper,
+    Lower,
+    Trim,
+    Abs,
+    Round,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        Some(match name {
+            "empty" => Function::Empty,
+            "len" => Function::Len,
+            "upper" => Function::Upper,
+            "lower" => Function::Lower,
+            "trim" => Function::Trim,
+            "abs" => Function::Abs,
+            "round" => Function::Round,
+            _ => return None,
+        })
+    }
+
+    // Quantidade mínima e máxima de argumentos
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Function::Round => (1, 2),
+            _ => (1, 1),
+        }
+    }
+}
+
+// Valor durante a avaliação. Células vazias são `Null`; o texto das células
+// é emprestado da linha.
+#[derive(Clone, Debug)]
+pub(crate) enum Value<'a> {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(Cow<'a, str>),
+}
+
+impl Value<'_> {
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::Null => true,
+            Value::Text(text) => text.trim().is_empty(),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn to_text(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => format_number(*n),
+            Value::Text(text) => text.to_string(),
+        }
+    }
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+// Linha vista pelas expressões: os valores e, para cada coluna usada pelas
+// regras, a posição dela no cabeçalho deste arquivo
+pub(crate) struct Row<'a> {
+    pub(crate) values: &'a [&'a str],
+    pub(crate) slots: &'a [usize],
+    pub(crate) decimal_separator: Option<char>,
+}
+
+impl Row<'_> {
+    fn column(&self, index: usize) -> Value<'_> {
+        match self.slots.get(index).and_then(|&slot| self.values.get(slot)) {
+            Some(value) if !value.trim().is_empty() => Value::Text(Cow::Borrowed(value)),
+            _ => Value::Null,
+        }
+    }
+
+    fn number(&self, value: &Value<'_>) -> Result<Option<f64>, String> {
+        match value {
+            Value::Null => Ok(None),
+            Value::Number(n) => Ok(Some(*n)),
+            Value::Text(text) => values::parse_decimal(text.trim(), self.decimal_separator)
+                .and_then(|n| n.as_f64())
+                .map(Some)
+                .ok_or_else(|| format!("'{}' is not a number", text)),
+            Value::Bool(b) => Err(format!("{} is not a number", b)),
+        }
+    }
+
+    fn as_number(&self, value: &Value<'_>) -> Option<f64> {
+        self.number(value).ok().flatten()
+    }
+}
+
+// Resultado de `and`, `or` e `not` em lógica de três valores: `None` é
+// desconhecido, como em uma comparação com um campo vazio
+fn truth(value: &Value<'_>) -> Result<Option<bool>, String> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Bool(b) => Ok(Some(*b)),
+        other => Err(format!("expected true or false, got '{}'", other.to_text())),
+    }
+}
+
+fn from_truth(value: Option<bool>) -> Value<'static> {
+    value.map_or(Value::Null, Value::Bool)
+}
+
+impl Expr {
+    pub(crate) fn eval<'a>(&'a self, row: &'a Row<'a>) -> Result<Value<'a>, String> {
+        Ok(match self {
+            Expr::Null => Value::Null,
+            Expr::Bool(b) => Value::Bool(*b),
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Text(text) => Value::Text(Cow::Borrowed(text)),
+            Expr::Column(index) => row.column(*index),
+            Expr::Negate(inner) => match row.number(&inner.eval(row)?)? {
+                Some(n) => Value::Number(-n),
+                None => Value::Null,
+            },
+            Expr::Not(inner) => from_truth(truth(&inner.eval(row)?)?.map(|b| !b)),
+            Expr::And(left, right) => match truth(&left.eval(row)?)? {
+                Some(false) => Value::Bool(false),
+                left => match (left, truth(&right.eval(row)?)?) {
+                    (_, Some(false)) => Value::Bool(false),
+                    (Some(true), Some(true)) => Value::Bool(true),
+                    _ => Value::Null,
+                },
+            },
+            Expr::Or(left, right) => match truth(&left.eval(row)?)? {
+                Some(true) => Value::Bool(true),
+                left => match (left, truth(&right.eval(row)?)?) {
+                    (_, Some(true)) => Value::Bool(true),
+                    (Some(false), Some(false)) => Value::Bool(false),
+                    _ => Value::Null,
+                },
+            },
+            Expr::Arithmetic(op, left, right) => {
+                let (left, right) = (row.number(&left.eval(row)?)?, row.number(&right.eval(row)?)?);
+                let (Some(left), Some(right)) = (left, right) else {
+                    return Ok(Value::Null);
+                };
+                Value::Number(match op {
+                    Arithmetic::Add => left + right,
+                    Arithmetic::Subtract => left - right,
+                    Arithmetic::Multiply => left * right,
+                    Arithmetic::Divide if right == 0.0 => return Err("division by zero".to_string()),
+                    Arithmetic::Divide => left / right,
+                })
+            }
+            Expr::Compare(op, left, right) => compare(*op, &left.eval(row)?, &right.eval(row)?, row),
+            Expr::In { value, list, negated } => {
+                let value = value.eval(row)?;
+                let mut found = false;
+                for item in list {
+                    if equal(&value, &item.eval(row)?, row) {
+                        found = true;
+                        break;
+                    }
+                }
+                Value::Bool(found != *negated)
+            }
+            Expr::Call(function, args) => call(*function, args, row)?,
+            Expr::Lookup(table, key) => match key.eval(row)? {
+                Value::Null => Value::Null,
+                key => match table.get(key.to_text().as_str()) {
+                    Some(value) => Value::Text(Cow::Borrowed(value)),
+                    None => Value::Null,
+                },
+            },
+            Expr::Matches(value, regex) => match value.eval(row)? {
+                Value::Null => Value::Null,
+                value => Value::Bool(regex.is_match(&value.to_text())),
+            },
+        })
+    }
+}
+
+fn call<'a>(function: Function, args: &'a [Expr], row: &'a Row<'a>) -> Result<Value<'a>, String> {
+    let value = args[0].eval(row)?;
+    let text = |f: fn(&str) -> String| match &value {
+        Value::Null => Value::Null,
+        other => Value::Text(Cow::Owned(f(&other.to_text()))),
+    };
+    Ok(match function {
+        Function::Empty => Value::Bool(value.is_empty()),
+        Function::Len => Value::Number(if value.is_empty() { 0.0 } else { value.to_text().chars().count() as f64 }),
+        Function::Upper => text(|s| s.to_uppercase()),
+        Function::Lower => text(|s| s.to_lowercase()),
+        Function::Trim => text(|s| s.trim().to_string()),
+        Function::Abs => row.number(&value)?.map_or(Value::Null, |n| Value::Number(n.abs())),
+        Function::Round => {
+            let places = match args.get(1) {
+                Some(places) => row.number(&places.eval(row)?)?.unwrap_or(0.0),
+                None => 0.0,
+            };
+            let factor = 10f64.powi(places as i32);
+            row.number(&value)?.map_or(Value::Null, |n| Value::Number((n * factor).round() / factor))
+        }
+    })
+}
+
+// Números iguais a menos do erro de arredondamento do f64, para que
+// `3 * 0.1 == 0.3` seja verdadeiro
+fn same_number(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+}
+
+// Ordem entre dois valores: como números, se os dois são números; como
+// datas (aaaa-mm-dd ou dd/mm/aaaa), se os dois são datas; senão como texto
+fn order(a: &Value<'_>, b: &Value<'_>, row: &Row<'_>) -> Option<Ordering> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    if let (Some(x), Some(y)) = (row.as_number(a), row.as_number(b)) {
+        return Some(if same_number(x, y) { Ordering::Equal } else { x.total_cmp(&y) });
+    }
+    if let (Value::Bool(x), Value::Bool(y)) = (a, b) {
+        return Some(x.cmp(y));
+    }
+    let (a, b) = (a.to_text(), b.to_text());
+    if let (Some(x), Some(y)) = (values::parse_date(&a, None), values::parse_date(&b, None)) {
+        return Some(x.cmp(&y));
+    }
+    Some(a.cmp(&b))
+}
+
+fn equal(a: &Value<'_>, b: &Value<'_>, row: &Row<'_>) -> bool {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => true,
+        (false, false) => order(a, b, row) == Some(Ordering::Equal),
+        _ => false,
+    }
+}
+
+// `==` e `!=` com um vazio dizem se o outro lado também é vazio; `<`, `>`
+// etc. com um vazio são desconhecidos
+fn compare(op: Comparison, a: &Value<'_>, b: &Value<'_>, row: &Row<'_>) -> Value<'static> {
+    match op {
+        Comparison::Equal => Value::Bool(equal(a, b, row)),
+        Comparison::NotEqual => Value::Bool(!equal(a, b, row)),
+        _ => from_truth(order(a, b, row).map(|ordering| match op {
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            _ => ordering != Ordering::Less,
+        })),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    // Nome entre crases, como `nome completo`: nunca é palavra reservada
+    Quoted(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 17] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "(", ")", "[", "]", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<(usize, char)> = source.char_indices().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|(_, n)| n.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].1.is_ascii_digit() || chars[i].1 == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().map(|(_, c)| c).collect();
+            let number = text.parse().map_err(|_| format!("invalid number '{}' at position {}", text, at))?;
+            tokens.push((Token::Number(number), at));
+        } else if c == '\'' || c == '"' || c == '`' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(format!("unterminated {} at position {}", if c == '`' { "name" } else { "string" }, at)),
+                    Some((_, '\\')) if c != '`' && i + 1 < chars.len() => {
+                        text.push(chars[i + 1].1);
+                        i += 2;
+                    }
+                    Some(&(_, q)) if q == c => {
+                        i += 1;
+                        break;
+                    }
+                    Some(&(_, other)) => {
+                        text.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push((if c == '`' { Token::Quoted(text) } else { Token::Text(text) }, at));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].1.is_alphanumeric() || chars[i].1 == '_' || chars[i].1 == '.') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().map(|(_, c)| c).collect()), at));
+        } else {
+            let rest = &source[at..];
+            let symbol = SYMBOLS
+                .iter()
+                .chain(&["!"])
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| format!("unexpected character '{}' at position {}", c, at))?;
+            tokens.push((Token::Symbol(symbol), at));
+            i += symbol.chars().count();
+        }
+    }
+    Ok(tokens)
+}
+
+// Altura máxima da árvore de uma expressão e níveis de parênteses. A análise
+// e a avaliação são recursivas: sem limite, uma regra com milhares de
+// parênteses ou operadores esgotaria a pilha.
+const MAX_DEPTH: usize = 64;
+
+impl Expr {
+    // Altura da árvore. Os filhos já passaram pelo limite, então a recursão
+    // também é limitada.
+    fn height(&self) -> usize {
+        1 + match self {
+            Expr::Null | Expr::Bool(_) | Expr::Number(_) | Expr::Text(_) | Expr::Column(_) => 0,
+            Expr::Negate(expr) | Expr::Not(expr) | Expr::Lookup(_, expr) | Expr::Matches(expr, _) => expr.height(),
+            Expr::And(left, right)
+            | Expr::Or(left, right)
+            | Expr::Arithmetic(_, left, right)
+            | Expr::Compare(_, left, right) => left.height().max(right.height()),
+            Expr::In { value, list, .. } => list.iter().map(Expr::height).max().unwrap_or(0).max(value.height()),
+            Expr::Call(_, args) => args.iter().map(Expr::height).max().unwrap_or(0),
+        }
+    }
+}
+
+// Analisador descendente das expressões. As colunas citadas são acumuladas em
+// `columns`, e cada uma vira um índice nessa lista.
+pub(crate) struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+    // Níveis de parênteses, argumentos, `not` e `-` abertos
+    depth: usize,
+    columns: &'a mut Vec<String>,
+    lookups: &'a HashMap<String, Arc<HashMap<String, String>>>,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn parse(
+        source: &str,
+        columns: &'a mut Vec<String>,
+        lookups: &'a HashMap<String, Arc<HashMap<String, String>>>,
+    ) -> Result<Expr, String> {
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0, end: source.len(), depth: 0, columns, lookups };
+        let expr = parser.or()?;
+        let expr = limited(expr, 0)?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some((token, at)) => Err(format!("unexpected {} at position {}", describe(token), at)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, at)| *at)
+    }
+
+    fn symbol(&mut self, symbols: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Symbol(s)) if symbols.contains(s) => {
+                let s = *s;
+                self.pos += 1;
+                Some(s)
+            }
+            _ => None,
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(name)) if name == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        if self.symbol(&[symbol]).is_some() {
+            return Ok(());
+        }
+        let found = self.peek().map_or("end of expression".to_string(), describe);
+        Err(format!("expected '{}' at position {}, found {}", symbol, self.position(), found))
+    }
+
+    // Analisa um nível aninhado, recusando passar de MAX_DEPTH níveis ou
+    // devolver uma árvore mais alta que isso
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+        let at = self.position();
+        if self.depth >= MAX_DEPTH {
+            return Err(too_deep(at));
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        limited(expr?, at)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let at = self.position();
+        let mut left = self.and()?;
+        while self.keyword("or") || self.symbol(&["||"]).is_some() {
+            left = limited(Expr::Or(Box::new(left), Box::new(self.and()?)), at)?;
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let at = self.position();
+        let mut left = self.not()?;
+        while self.keyword("and") || self.symbol(&["&&"]).is_some() {
+            left = limited(Expr::And(Box::new(left), Box::new(self.not()?)), at)?;
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("not") || self.symbol(&["!"]).is_some() {
+            return Ok(Expr::Not(Box::new(self.nested(Self::not)?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.sum()?;
+        let op = match self.symbol(&["==", "!=", "<=", ">=", "<", ">"]) {
+            Some("==") => Comparison::Equal,
+            Some("!=") => Comparison::NotEqual,
+            Some("<=") => Comparison::LessOrEqual,
+            Some(">=") => Comparison::GreaterOrEqual,
+            Some("<") => Comparison::Less,
+            Some(_) => Comparison::Greater,
+            None => {
+                let negated = matches!(
+                    (self.peek(), self.tokens.get(self.pos + 1)),
+                    (Some(Token::Ident(not)), Some((Token::Ident(is_in), _))) if not == "not" && is_in == "in"
+                );
+                if negated {
+                    self.pos += 1;
+                }
+                if !self.keyword("in") {
+                    return Ok(left);
+                }
+                return Ok(Expr::In { value: Box::new(left), list: self.list()?, negated });
+            }
+        };
+        Ok(Expr::Compare(op, Box::new(left), Box::new(self.sum()?)))
+    }
+
+    fn list(&mut self) -> Result<Vec<Expr>, String> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        if self.symbol(&["]"]).is_some() {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.sum()?);
+            if self.symbol(&[","]).is_none() {
+                self.expect("]")?;
+                return Ok(items);
+            }
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let at = self.position();
+        let mut left = self.product()?;
+        while let Some(op) = self.symbol(&["+", "-"]) {
+            let op = if op == "+" { Arithmetic::Add } else { Arithmetic::Subtract };
+            left = limited(Expr::Arithmetic(op, Box::new(left), Box::new(self.product()?)), at)?;
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let at = self.position();
+        let mut left = self.unary()?;
+        while let Some(op) = self.symbol(&["*", "/"]) {
+            let op = if op == "*" { Arithmetic::Multiply } else { Arithmetic::Divide };
+            left = limited(Expr::Arithmetic(op, Box::new(left), Box::new(self.unary()?)), at)?;
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.symbol(&["-"]).is_some() {
+            return Ok(Expr::Negate(Box::new(self.nested(Self::unary)?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let at = self.position();
+        let Some((token, _)) = self.tokens.get(self.pos).cloned() else {
+            return Err(format!("unexpected end of expression at position {}", at));
+        };
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Text(text) => Ok(Expr::Text(text)),
+            Token::Quoted(name) => Ok(self.column(name)),
+            Token::Symbol("(") => {
+                let expr = self.nested(Self::or)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Expr::Bool(true)),
+                "false" => Ok(Expr::Bool(false)),
+                "null" => Ok(Expr::Null),
+                "and" | "or" | "not" | "in" => Err(format!("unexpected '{}' at position {}", name, at)),
+                _ if self.peek() == Some(&Token::Symbol("(")) => self.call(&name, at),
+                _ => Ok(self.column(name)),
+            },
+            other => Err(format!("unexpected {} at position {}", describe(&other), at)),
+        }
+    }
+
+    fn column(&mut self, name: String) -> Expr {
+        let index = match self.columns.iter().position(|c| *c == name) {
+            Some(index) => index,
+            None => {
+                self.columns.push(name);
+                self.columns.len() - 1
+            }
+        };
+        Expr::Column(index)
+    }
+
+    fn call(&mut self, name: &str, at: usize) -> Result<Expr, String> {
+        self.expect("(")?;
+        let mut args = Vec::new();
+        if self.symbol(&[")"]).is_none() {
+            loop {
+                args.push(self.nested(Self::or)?);
+                if self.symbol(&[","]).is_none() {
+                    self.expect(")")?;
+                    break;
+                }
+            }
+        }
+
+        // `lookup` e `matches` recebem um literal, resolvido agora
+        let literal = |args: &mut Vec<Expr>, what: &str| match args.pop() {
+            Some(Expr::Text(text)) => Ok(text),
+            _ => Err(format!("{}() at position {} expects the {} as a string literal", name, at, what)),
+        };
+        match name {
+            "lookup" | "matches" if args.len() != 2 => {
+                Err(format!("{}() at position {} expects 2 arguments, got {}", name, at, args.len()))
+            }
+            "lookup" => {
+                let key = args.pop().map(Box::new).unwrap_or_else(|| Box::new(Expr::Null));
+                let table = literal(&mut args, "table name")?;
+                match self.lookups.get(&table) {
+                    Some(table) => Ok(Expr::Lookup(Arc::clone(table), key)),
+                    None => Err(format!("unknown lookup table '{}' at position {}", table, at)),
+                }
+            }
+            "matches" => {
+                let pattern = literal(&mut args, "pattern")?;
+                let regex = Regex::new(&pattern).map_err(|e| format!("invalid pattern at position {}: {}", at, e))?;
+                Ok(Expr::Matches(Box::new(args.remove(0)), regex))
+            }
+            _ => {
+                let function =
+                    Function::from_name(name).ok_or_else(|| format!("unknown function '{}' at position {}", name, at))?;
+                let (min, max) = function.arity();
+                if args.len() < min || args.len() > max {
+                    return Err(format!("{}() at position {} expects {} argument(s), got {}", name, at, max, args.len()));
+                }
+                Ok(Expr::Call(function, args))
+            }
+        }
+    }
+}
+
+// A expressão, se a árvore não passa de MAX_DEPTH níveis
+fn limited(expr: Expr, at: usize) -> Result<Expr, String> {
+    if expr.height() > MAX_DEPTH {
+        return Err(too_deep(at));
+    }
+    Ok(expr)
+}
+
+fn too_deep(at: usize) -> String {
+    format!("expression nested too deeply at position {} (limit {})", at, MAX_DEPTH)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(n) => format!("number {}", format_number(*n)),
+        Token::Text(text) => format!("string '{}'", text),
+        Token::Ident(name) | Token::Quoted(name) => format!("'{}'", name),
+        Token::Symbol(symbol) => format!("'{}'", symbol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<(Expr, Vec<String>), String> {
+        let mut lookups = HashMap::new();
+        let table = [("SP", "São Paulo"), ("1", "um")].into_iter().map(|(k, v)| (k.to_string(), v.to_string()));
+        lookups.insert("ufs".to_string(), Arc::new(table.collect()));
+        let mut columns = Vec::new();
+        let expr = Parser::parse(source, &mut columns, &lookups)?;
+        Ok((expr, columns))
+    }
+
+    // Resultado da expressão como texto, com as colunas vindas de `row`
+    fn eval_with(source: &str, row: &[(&str, &str)], decimal_separator: Option<char>) -> Result<String, String> {
+        let (expr, columns) = parse(source)?;
+        let values: Vec<&str> = row.iter().map(|(_, value)| *value).collect();
+        let slots: Vec<usize> = columns
+            .iter()
+            .map(|c| row.iter().position(|(name, _)| name == c).unwrap_or(usize::MAX))
+            .collect();
+        let row = Row { values: &values, slots: &slots, decimal_separator };
+        Ok(expr.eval(&row)?.to_text())
+    }
+
+    fn eval(source: &str, row: &[(&str, &str)]) -> String {
+        eval_with(source, row, None).unwrap()
+    }
+
+    #[test]
+    fn arithmetic_and_precedence() {
+        assert_eq!(eval("1 + 2 * 3", &[]), "7");
+        assert_eq!(eval("(1 + 2) * 3", &[]), "9");
+        assert_eq!(eval("10 - 4 - 3", &[]), "3");
+        assert_eq!(eval("-2 * -3 / 4", &[]), "1.5");
+        assert_eq!(eval("3 * 0.1 == 0.3", &[]), "true");
+        assert_eq!(eval("round(2.345, 2) + abs(-1) + round(2.5)", &[]), "6.35");
+        assert_eq!(eval_with("1 / (2 - 2)", &[], None).unwrap_err(), "division by zero");
+        assert_eq!(eval_with("1 + 'a'", &[], None).unwrap_err(), "'a' is not a number");
+    }
+
+    #[test]
+    fn columns_and_decimal_separator() {
+        let row = [("quantidade", "3"), ("valor_unitario", "1,10"), ("valor total", "3,30")];
+        let rule = "`valor total` == round(quantidade * valor_unitario, 2)";
+        assert_eq!(eval_with(rule, &row, Some(',')).unwrap(), "true");
+        assert_eq!(parse(rule).unwrap().1, ["valor total", "quantidade", "valor_unitario"]);
+        assert_eq!(eval("endereco.cidade == 'Recife'", &[("endereco.cidade", "Recife")]), "true");
+    }
+
+    #[test]
+    fn compares_numbers_dates_and_text() {
+        let row = [("inicio", "31/12/2023"), ("fim", "2024-01-02"), ("a", "10"), ("b", "9"), ("nome", "Ana")];
+        assert_eq!(eval("fim >= inicio", &row), "true");
+        assert_eq!(eval("a > b", &row), "true");
+        assert_eq!(eval("nome < 'Bia' and nome != 'ana'", &row), "true");
+        assert_eq!(eval("nome in ['Bia', 'Ana'] and a not in [1, 2]", &row), "true");
+        assert_eq!(eval("b in [9.0]", &row), "true");
+    }
+
+    #[test]
+    fn empty_values_are_unknown() {
+        let row = [("vazio", "  "), ("um", "1")];
+        assert_eq!(eval("vazio > 1", &row), "null");
+        assert_eq!(eval("vazio == null and vazio != um", &row), "true");
+        assert_eq!(eval("not (vazio > 1)", &row), "null");
+        assert_eq!(eval("vazio > 1 or um == 1", &row), "true");
+        assert_eq!(eval("vazio > 1 and um == 2", &row), "false");
+        assert_eq!(eval("vazio > 1 and um == 1", &row), "null");
+        assert_eq!(eval("vazio + 1", &row), "null");
+        assert_eq!(eval("empty(vazio) && !empty(um) && len(vazio) == 0", &row), "true");
+        assert_eq!(eval("faltando == null", &row), "true");
+        assert_eq!(eval_with("um and true", &row, None).unwrap_err(), "expected true or false, got '1'");
+    }
+
+    #[test]
+    fn functions_lookups_and_patterns() {
+        let row = [("nome", " Ana "), ("uf", "SP"), ("n", "1"), ("cep", "01310-100")];
+        assert_eq!(eval("upper(trim(nome))", &row), "ANA");
+        assert_eq!(eval("lower('ÁGUA')", &[]), "água");
+        assert_eq!(eval("len(nome)", &row), "5");
+        assert_eq!(eval("lookup('ufs', uf)", &row), "São Paulo");
+        assert_eq!(eval("lookup('ufs', n)", &row), "um");
+        assert_eq!(eval("lookup('ufs', 'RJ')", &row), "null");
+        // A barra invertida escapa o caractere seguinte no texto: `\\d` chega como `\d`
+        assert_eq!(eval("matches(cep, '^[0-9]{5}-\\\\d{3}$')", &row), "true");
+        assert_eq!(eval("matches(uf, '^[a-z]+$')", &row), "false");
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let error = |source: &str| parse(source).err().unwrap();
+        assert_eq!(error("1 +"), "unexpected end of expression at position 3");
+        assert_eq!(error("(1 + 2"), "expected ')' at position 6, found end of expression");
+        assert_eq!(error("1 2"), "unexpected number 2 at position 2");
+        assert_eq!(error("'abc"), "unterminated string at position 0");
+        assert_eq!(error("`abc"), "unterminated name at position 0");
+        assert_eq!(error("a @ b"), "unexpected character '@' at position 2");
+        assert_eq!(error("1..2"), "invalid number '1..2' at position 0");
+        assert_eq!(error("a and or b"), "unexpected 'or' at position 6");
+        assert_eq!(error("foo(1)"), "unknown function 'foo' at position 0");
+        assert_eq!(error("round(1, 2, 3)"), "round() at position 0 expects 2 argument(s), got 3");
+        assert_eq!(error("lookup(ufs, a)"), "lookup() at position 0 expects the table name as a string literal");
+        assert_eq!(error("lookup('cidades', a)"), "unknown lookup table 'cidades' at position 0");
+        assert_eq!(error("matches(a)"), "matches() at position 0 expects 2 arguments, got 1");
+        assert!(error("matches(a, '(')").starts_with("invalid pattern at position 0: "));
+        assert_eq!(error("a in 1"), "expected '[' at position 5, found number 1");
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let parens = |n: usize| format!("{}1{}", "(".repeat(n), ")".repeat(n));
+        assert_eq!(eval(&parens(MAX_DEPTH), &[]), "1");
+        assert_eq!(parse(&parens(MAX_DEPTH + 1)).err().unwrap(), "expression nested too deeply at position 65 (limit 64)");
+        assert!(parse(&parens(50_000)).is_err());
+
+        let chain = |n: usize| vec!["1"; n].join(" + ");
+        assert_eq!(eval(&chain(MAX_DEPTH), &[]), "64");
+        assert_eq!(parse(&chain(MAX_DEPTH + 1)).err().unwrap(), "expression nested too deeply at position 0 (limit 64)");
+
+        assert!(parse(&format!("{}true", "not ".repeat(10_000))).is_err());
+        assert!(parse(&format!("{}1", "-".repeat(10_000))).is_err());
+        assert!(parse(&format!("{}1{}", "abs(".repeat(10_000), ")".repeat(10_000))).is_err());
+        assert!(parse(&vec!["a == 1"; 10_000].join(" or ")).is_err());
+    }
+}
@@ -0,0 +1,674 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::encoding::{Encoding, Transcoder};
+use crate::errors::{ConfigError, ErrorCode, FatalError, FatalErrorKind};
+use crate::pipeline::CsvPipeline;
+use crate::reader::Position;
+use crate::schema::CompiledSchema;
+use crate::stats::Stats;
+use crate::{CsvOptions, ProcessingResult, ProcessingTotals, ValidationError};
+
+// Arquivos posicionais (largura fixa), como os do CNAB e as exportações de
+// mainframe: cada campo ocupa sempre as mesmas colunas do registro. O layout
+// diz onde cada campo começa, o tamanho, o tipo e o preenchimento; cada
+// registro vira uma linha com a mesma validação, hash e deduplicação do CSV.
+//
+// Um arquivo pode ter vários tipos de registro (header, detalhe, trailer),
+// cada um com seus campos, reconhecidos por valores em posições fixas:
+// { "record_length": 240,
+//   "records": [
+//     { "name": "header", "match": [{ "start": 8, "value": "0" }], "fields": [...] },
+//     { "name": "detalhe", "match": [{ "start": 8, "value": "3" }], "fields": [
+//         { "name": "valor", "start": 120, "length": 15, "type": "decimal", "decimals": 2 } ] } ] }
+// Com um único tipo, os campos podem ficar direto em "fields".
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutDef {
+    // Tamanho de todos os registros, em caracteres. Sem quebras de linha no
+    // arquivo, os registros são separados por esse tamanho.
+    record_length: Option<usize>,
+    // Layout de um único tipo de registro
+    #[serde(default)]
+    fields: Vec<FieldDef>,
+    #[serde(default)]
+    rules: Vec<Value>,
+    file: Option<Value>,
+    #[serde(default)]
+    records: Vec<RecordDef>,
+    // Tabelas de consulta das regras, como no schema
+    #[serde(default)]
+    lookups: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecordDef {
+    name: String,
+    // Condições que reconhecem o tipo; sem nenhuma, aceita qualquer registro
+    #[serde(default, rename = "match")]
+    conditions: Vec<ConditionDef>,
+    fields: Vec<FieldDef>,
+    // Regras de linha e verificações do arquivo, como no schema
+    #[serde(default)]
+    rules: Vec<Value>,
+    file: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConditionDef {
+    start: usize,
+    value: OneOrMany,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+// Campo do layout. Além da posição, aceita as regras de uma coluna do schema
+// (type, nullable, format, values, pattern...), usadas na validação.
+#[derive(Deserialize)]
+struct FieldDef {
+    name: String,
+    // Posição do primeiro caractere, a partir de 1, como nos manuais
+    start: usize,
+    length: usize,
+    // "space", "zero" ou um caractere; padrão "zero" para int e decimal
+    padding: Option<String>,
+    align: Option<Align>,
+    // Casas decimais implícitas: "000000000012345" com 2 vira "123.45"
+    decimals: Option<usize>,
+    #[serde(flatten)]
+    rules: Map<String, Value>,
+}
+
+// Lado do valor dentro do campo; o preenchimento fica do outro lado.
+// Padrão "right" para int e decimal, "left" para o resto.
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Align {
+    Left,
+    Right,
+}
+
+struct Condition {
+    // Posição a partir de 0, em caracteres
+    start: usize,
+    length: usize,
+    values: Vec<String>,
+}
+
+pub(crate) struct FieldLayout {
+    pub(crate) name: String,
+    start: usize,
+    length: usize,
+    padding: char,
+    align: Align,
+    decimals: Option<usize>,
+    date: bool,
+}
+
+pub(crate) struct RecordLayout {
+    pub(crate) name: Option<String>,
+    conditions: Vec<Condition>,
+    pub(crate) fields: Vec<FieldLayout>,
+    schema: Arc<CompiledSchema>,
+}
+
+pub(crate) struct CompiledLayout {
+    record_length: Option<usize>,
+    pub(crate) records: Vec<RecordLayout>,
+}
+
+impl CompiledLayout {
+    pub(crate) fn from_json(layout_json: &str) -> Result<CompiledLayout, String> {
+        let def: LayoutDef = serde_json::from_str(layout_json).map_err(|e| format!("Invalid layout: {}", e))?;
+        let record_length = def.record_length;
+        let lookups = def.lookups;
+        if record_length == Some(0) {
+            return Err("Invalid layout: record_length must be at least 1".to_string());
+        }
+
+        let records = match (def.fields.is_empty(), def.records.is_empty()) {
+            (false, true) => vec![(None, Vec::new(), def.fields, def.rules, def.file)],
+            (true, false) if def.rules.is_empty() && def.file.is_none() => def
+                .records
+                .into_iter()
+                .map(|r| (Some(r.name), r.conditions, r.fields, r.rules, r.file))
+                .collect(),
+            (true, false) => return Err("Invalid layout: with 'records', put 'rules' and 'file' in each record".to_string()),
+            _ => return Err("Invalid layout: expected either 'fields' or 'records'".to_string()),
+        };
+
+        let mut names = HashSet::new();
+        let mut compiled = Vec::with_capacity(records.len());
+        for (name, conditions, fields, rules, file) in records {
+            let label = name.as_deref().map_or_else(String::new, |n| format!(" of record '{}'", n));
+            if let Some(name) = &name {
+                if !names.insert(name.clone()) {
+                    return Err(format!("Invalid layout: duplicate record name '{}'", name));
+                }
+            }
+            if fields.is_empty() {
+                return Err(format!("Invalid layout: no fields{}", label));
+            }
+
+            let conditions = conditions
+                .into_iter()
+                .map(|c| compile_condition(c, record_length).map_err(|e| format!("Invalid layout: match{}: {}", label, e)))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut columns = Map::new();
+            let mut layouts: Vec<FieldLayout> = Vec::with_capacity(fields.len());
+            for field in fields {
+                let invalid = |e: String| format!("Invalid layout: field '{}'{}: {}", field.name, label, e);
+                let layout = compile_field(&field, record_length).map_err(invalid)?;
+                // As regras de cada campo são conferidas sozinhas, para o erro
+                // apontar o campo
+                CompiledSchema::from_value(json!({ "columns": { &field.name: &field.rules } }))
+                    .map_err(|e| invalid(e.split_once(": ").map_or(e.clone(), |(_, message)| message.to_string())))?;
+                if let Some(other) = layouts.iter().find(|o| o.start < layout.start + layout.length && layout.start < o.start + o.length) {
+                    return Err(invalid(format!("overlaps field '{}'", other.name)));
+                }
+                if columns.insert(field.name.clone(), Value::Object(field.rules)).is_some() {
+                    return Err(format!("Invalid layout: duplicate field name '{}'{}", field.name, label));
+                }
+                layouts.push(layout);
+            }
+
+            let mut schema = json!({ "columns": columns, "rules": rules, "lookups": &lookups });
+            if let Some(file) = file {
+                schema["file"] = file;
+            }
+            let schema = CompiledSchema::from_value(schema).map_err(|e| match &name {
+                Some(name) => format!("record '{}': {}", name, e),
+                None => e,
+            })?;
+            compiled.push(RecordLayout { name, conditions, fields: layouts, schema: Arc::new(schema) });
+        }
+        Ok(CompiledLayout { record_length, records: compiled })
+    }
+
+    pub(crate) fn record_length(&self) -> Option<usize> {
+        self.record_length
+    }
+
+    // Primeiro tipo de registro cujas condições o registro satisfaz
+    pub(crate) fn record_for(&self, record: &str) -> Option<usize> {
+        self.records.iter().position(|layout| {
+            layout
+                .conditions
+                .iter()
+                .all(|c| c.values.iter().any(|v| v.as_str() == slice(record, c.start, c.length)))
+        })
+    }
+}
+
+fn compile_condition(def: ConditionDef, record_length: Option<usize>) -> Result<Condition, String> {
+    let values = match def.value {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    };
+    let length = values.first().map_or(0, |v| v.chars().count());
+    if def.start == 0 {
+        return Err("start must be at least 1".to_string());
+    }
+    if length == 0 || values.iter().any(|v| v.chars().count() != length) {
+        return Err("values must be non-empty and all of the same length".to_string());
+    }
+    if record_length.is_some_and(|max| def.start - 1 + length > max) {
+        return Err("goes past record_length".to_string());
+    }
+    Ok(Condition { start: def.start - 1, length, values })
+}
+
+fn compile_field(def: &FieldDef, record_length: Option<usize>) -> Result<FieldLayout, String> {
+    if def.start == 0 || def.length == 0 {
+        return Err("start and length must be at least 1".to_string());
+    }
+    if let Some(max) = record_length.filter(|&max| def.start - 1 + def.length > max) {
+        return Err(format!("ends at {}, past record_length ({})", def.start - 1 + def.length, max));
+    }
+    let numeric = matches!(def.rules.get("type").and_then(Value::as_str), Some("int" | "decimal"));
+    if def.decimals.is_some() && def.rules.get("type").and_then(Value::as_str) != Some("decimal") {
+        return Err("decimals requires type 'decimal'".to_string());
+    }
+    let padding = match def.padding.as_deref() {
+        None if numeric => '0',
+        None | Some("space") => ' ',
+        Some("zero") => '0',
+        Some(other) => {
+            let mut chars = other.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(format!("invalid padding '{}': expected 'space', 'zero' or a single character", other)),
+            }
+        }
+    };
+    let align = def.align.unwrap_or(if numeric { Align::Right } else { Align::Left });
+    Ok(FieldLayout {
+        name: def.name.clone(),
+        start: def.start - 1,
+        length: def.length,
+        padding,
+        align,
+        decimals: def.decimals,
+        date: def.rules.get("type").and_then(Value::as_str) == Some("date"),
+    })
+}
+
+// Trecho do registro em caracteres; o que passa do fim do registro fica vazio
+fn slice(record: &str, start: usize, length: usize) -> &str {
+    if record.is_ascii() {
+        let end = (start + length).min(record.len());
+        return record.get(start.min(end)..end).unwrap_or("");
+    }
+    let mut indices = record.char_indices().map(|(i, _)| i).chain(std::iter::once(record.len()));
+    let Some(from) = indices.nth(start) else { return "" };
+    let to = indices.nth(length - 1).unwrap_or(record.len());
+    &record[from..to]
+}
+
+impl FieldLayout {
+    // Valor do campo sem o preenchimento. Um número preenchido só com zeros
+    // vale "0"; um campo em branco, ou uma data só com zeros (data não
+    // informada, como no CNAB), fica vazio. Com `decimals`, tira também o
+    // sinal antes de inserir a vírgula implícita; o que sobra precisa ser só
+    // dígitos, senão o campo é inválido.
+    pub(crate) fn value(&self, record: &str) -> Result<String, String> {
+        let raw = slice(record, self.start, self.length);
+        if self.date && !raw.is_empty() && raw.bytes().all(|b| b == b'0') {
+            return Ok(String::new());
+        }
+        let value = match self.align {
+            Align::Left => raw.trim_end_matches(self.padding),
+            Align::Right => raw.trim_start_matches(self.padding),
+        };
+        if let Some(decimals) = self.decimals {
+            let value = value.trim();
+            let (sign, digits) = match value.strip_prefix('-') {
+                Some(rest) => ("-", rest),
+                None => ("", value.strip_prefix('+').unwrap_or(value)),
+            };
+            if !digits.is_empty() {
+                if !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(format!("'{}' is not a number with {} implied decimals", raw, decimals));
+                }
+                let digits = format!("{:0>width$}", digits.trim_start_matches('0'), width = decimals + 1);
+                let (int, frac) = digits.split_at(digits.len() - decimals);
+                let sign = if digits.bytes().all(|b| b == b'0') { "" } else { sign };
+                return Ok(match decimals {
+                    0 => format!("{}{}", sign, int),
+                    _ => format!("{}{}.{}", sign, int, frac),
+                });
+            }
+            if !sign.is_empty() {
+                return Err(format!("'{}' is not a number with {} implied decimals", raw, decimals));
+            }
+            if !raw.is_empty() && raw.bytes().all(|b| b == b'0') {
+                return Ok(if decimals == 0 { "0".to_string() } else { format!("0.{}", "0".repeat(decimals)) });
+            }
+        }
+        if value.trim().is_empty() {
+            let zeros = self.padding == '0' && !raw.is_empty() && raw.bytes().all(|b| b == b'0');
+            return Ok(if zeros { "0".to_string() } else { String::new() });
+        }
+        Ok(value.to_string())
+    }
+}
+
+impl RecordLayout {
+    // Valores dos campos do registro, ou o primeiro campo inválido com a
+    // mensagem do erro
+    pub(crate) fn values(&self, record: &str) -> Result<Vec<String>, (&FieldLayout, String)> {
+        self.fields.iter().map(|field| field.value(record).map_err(|message| (field, message))).collect()
+    }
+
+    fn headers(&self) -> Vec<String> {
+        self.fields.iter().map(|field| field.name.clone()).collect()
+    }
+}
+
+// Texto do arquivo em UTF-8 e a codificação original. Arquivos posicionais
+// costumam vir em Latin-1 ou Windows-1252; a detecção da codificação é a
+// mesma do CSV.
+pub(crate) fn decode(input: &[u8], options: &CsvOptions) -> (String, Encoding) {
+    let mut transcoder = Transcoder::new(options.dialect.encoding);
+    let mut utf8 = Vec::with_capacity(input.len());
+    transcoder.feed(input, |bytes, _| utf8.extend_from_slice(bytes));
+    transcoder.finish(|bytes, _| utf8.extend_from_slice(bytes));
+    let encoding = transcoder.encoding().unwrap_or(Encoding::Utf8);
+    (String::from_utf8_lossy(&utf8).into_owned(), encoding)
+}
+
+// Registros do texto: um por linha, ou a cada `record_length` caracteres
+// quando o arquivo não tem quebras de linha. Linhas em branco são ignoradas.
+// As posições em bytes são as da entrada em `encoding`.
+pub(crate) fn records(text: &str, record_length: Option<usize>, encoding: Encoding) -> Vec<(Position, &str)> {
+    let body = text.trim_start_matches('\u{feff}');
+    let offset = encoding.original_len(&text.as_bytes()[..text.len() - body.len()]);
+    let text = body;
+    let mut records = Vec::new();
+    if let Some(length) = record_length.filter(|_| !text.contains('\n')) {
+        let mut rest = text;
+        let mut byte = offset;
+        let mut line = 1;
+        while !rest.is_empty() {
+            let end = rest.char_indices().nth(length).map_or(rest.len(), |(i, _)| i);
+            let (record, tail) = rest.split_at(end);
+            if !record.trim().is_empty() {
+                records.push((Position { line, byte }, record));
+            }
+            byte += encoding.original_len(record.as_bytes());
+            line += 1;
+            rest = tail;
+        }
+        return records;
+    }
+    let mut byte = offset;
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let position = Position { line: index as u64 + 1, byte };
+        byte += encoding.original_len(line.as_bytes());
+        let record = line.trim_end_matches(['\n', '\r']);
+        // O fim de arquivo do DOS (^Z) aparece no fim de exportações antigas
+        if !record.trim_matches(['\u{1a}', ' ']).is_empty() {
+            records.push((position, record));
+        }
+    }
+    records
+}
+
+// Marca as linhas e os erros de um lote com o tipo de registro
+fn tag(batch: &mut ProcessingResult, record: Option<&String>) {
+    let Some(record) = record else { return };
+    for row in &mut batch.processed_rows {
+        row.record = Some(record.clone());
+    }
+    for error in &mut batch.errors {
+        error.record = Some(record.clone());
+    }
+    for error in &mut batch.file_errors {
+        error.record = Some(record.clone());
+    }
+}
+
+pub(crate) fn process(
+    input: &[u8],
+    layout: &CompiledLayout,
+    options: &CsvOptions,
+    out: &mut ProcessingResult,
+) -> Result<ProcessingTotals, FatalError> {
+    let (text, encoding) = decode(input, options);
+    let mut totals = process_records(&records(&text, layout.record_length, encoding), layout, options, out)?;
+    totals.bytes_processed = input.len() as u64;
+    Ok(totals)
+}
+
+// Valida os registros já separados, cada um pelo seu tipo de registro
+pub(crate) fn process_records(
+    records: &[(Position, &str)],
+    layout: &CompiledLayout,
+    options: &CsvOptions,
+    out: &mut ProcessingResult,
+) -> Result<ProcessingTotals, FatalError> {
+    // Cada tipo de registro tem as próprias colunas, validação, hash e
+    // deduplicação. Os limites valem para o arquivo inteiro.
+    let mut record_options = options.clone();
+    record_options.dialect.has_headers = true;
+    record_options.limits.max_rows = None;
+    let mut pipelines = Vec::with_capacity(layout.records.len());
+    for record in &layout.records {
+        let mut options = record_options.clone();
+        options.schema = Some(Arc::clone(&record.schema));
+        let mut pipeline = CsvPipeline::for_records(&options, 0, true);
+        pipeline.push_fields(&record.headers(), Position { line: 0, byte: 0 }, &mut ProcessingResult::default())?;
+        pipelines.push(pipeline);
+    }
+    if let [single] = layout.records.as_slice() {
+        out.headers = Some(single.headers());
+    }
+
+    let mut unknown = 0;
+    for (rows, &(position, record)) in records.iter().enumerate() {
+        let fatal = |message: String| FatalError::new(FatalErrorKind::LimitExceeded, message, position.line, position.byte);
+        if let Some(max) = options.limits.max_record_bytes.filter(|&max| record.len() > max) {
+            return Err(fatal(format!("record is larger than max_record_bytes ({})", max)));
+        }
+        if let Some(max) = options.limits.max_rows.filter(|&max| rows as u64 >= max) {
+            return Err(fatal(format!("file has more than max_rows ({}) rows", max)));
+        }
+
+        let Some(index) = layout.record_for(record) else {
+            unknown += 1;
+            let mut error = ValidationError::new(
+                position.line,
+                ErrorCode::UnknownRecord,
+                "Record does not match any record type of the layout.".to_string(),
+                Value::Null,
+            );
+            error.byte_offset = Some(position.byte);
+            error.fields = Some(vec![record.to_string()]);
+            out.errors.push(error);
+            continue;
+        };
+
+        let spec = &layout.records[index];
+        let mut batch = ProcessingResult::default();
+        let length = record.chars().count();
+        match layout.record_length.filter(|&expected| expected != length) {
+            Some(expected) => {
+                let mut error = ValidationError::new(
+                    position.line,
+                    ErrorCode::RecordLength,
+                    format!("Expected a record of {} characters, got {}.", expected, length),
+                    Value::Null,
+                );
+                error.byte_offset = Some(position.byte);
+                error.expected = Some(format!("{} characters", expected));
+                error.actual = Some(format!("{} characters", length));
+                error.fields = Some(vec![record.to_string()]);
+                pipelines[index].reject(error, &mut batch)?;
+            }
+            None => match spec.values(record) {
+                Ok(values) => pipelines[index].push_fields(&values, position, &mut batch)?,
+                Err((field, message)) => {
+                    let mut error =
+                        ValidationError::new(position.line, ErrorCode::InvalidValue, format!("{}.", message), Value::Null);
+                    error.byte_offset = Some(position.byte);
+                    error.column = Some(field.name.clone());
+                    error.rule = Some("decimals".to_string());
+                    error.expected = field.decimals.map(|decimals| format!("digits with {} implied decimals", decimals));
+                    error.actual = Some(slice(record, field.start, field.length).to_string());
+                    error.fields = Some(vec![record.to_string()]);
+                    pipelines[index].reject(error, &mut batch)?;
+                }
+            },
+        }
+        tag(&mut batch, spec.name.as_ref());
+        out.append(batch);
+    }
+
+    let mut totals = ProcessingTotals::default();
+    totals.total_rows += unknown;
+    totals.invalid_rows += unknown;
+    let mut columns = Vec::new();
+    for (pipeline, record) in pipelines.iter_mut().zip(&layout.records) {
+        let mut batch = ProcessingResult::default();
+        pipeline.finish(&mut batch)?;
+        let partial = pipeline.totals();
+        totals.total_rows += partial.total_rows;
+        totals.valid_rows += partial.valid_rows;
+        totals.invalid_rows += partial.invalid_rows;
+        totals.duplicate_rows += partial.duplicate_rows;
+        // Com vários tipos, as colunas do perfil levam o nome do registro
+        if let Some(stats) = batch.stats.take() {
+            columns.extend(stats.columns.into_iter().map(|mut column| {
+                if let Some(name) = &record.name {
+                    column.name = format!("{}.{}", name, column.name);
+                }
+                column
+            }));
+        }
+        tag(&mut batch, record.name.as_ref());
+        out.append(batch);
+    }
+    if options.stats.enabled {
+        out.stats = Some(Stats { totals, columns });
+    }
+    Ok(totals)
+}
+
+// Layout posicional compilado exposto ao JavaScript, como o `CsvSchema`
+#[wasm_bindgen]
+pub struct FixedWidthLayout {
+    inner: Arc<CompiledLayout>,
+}
+
+#[wasm_bindgen]
+impl FixedWidthLayout {
+    #[wasm_bindgen(constructor)]
+    pub fn new(layout_json: &str) -> Result<FixedWidthLayout, ConfigError> {
+        let compiled = CompiledLayout::from_json(layout_json).map_err(ConfigError::new)?;
+        Ok(FixedWidthLayout { inner: Arc::new(compiled) })
+    }
+}
+
+impl FixedWidthLayout {
+    pub(crate) fn compiled(&self) -> &CompiledLayout {
+        &self.inner
+    }
+
+    // Nomes dos tipos de registro, na ordem do layout; vazio com um único
+    // tipo sem nome
+    pub fn record_types(&self) -> Vec<String> {
+        self.inner.records.iter().filter_map(|record| record.name.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(json: &str) -> Result<CompiledLayout, String> {
+        CompiledLayout::from_json(json)
+    }
+
+    // Valores de um registro com o único tipo do layout
+    fn values(fields: &str, record: &str) -> Result<Vec<String>, String> {
+        let layout = layout(&format!(r#"{{ "fields": {} }}"#, fields))?;
+        layout.records[0].values(record).map_err(|(field, message)| format!("{}: {}", field.name, message))
+    }
+
+    #[test]
+    fn extracts_text_and_numbers() {
+        let fields = r#"[
+            { "name": "nome", "start": 1, "length": 6 },
+            { "name": "codigo", "start": 7, "length": 4, "align": "right", "padding": "space" },
+            { "name": "qtd", "start": 11, "length": 4, "type": "int" },
+            { "name": "pedido", "start": 15, "length": 5, "padding": "*", "align": "right" }
+        ]"#;
+        assert_eq!(values(fields, "José    420012**123").unwrap(), ["José", "42", "12", "123"]);
+        assert_eq!(values(fields, "          0000*****").unwrap(), ["", "", "0", ""]);
+        // Registro mais curto que o layout: os campos que faltam ficam vazios
+        assert_eq!(values(fields, "Ana").unwrap(), ["Ana", "", "", ""]);
+    }
+
+    #[test]
+    fn implied_decimals_with_padding_and_sign() {
+        let value = |raw: &str| {
+            let fields = r#"[{ "name": "valor", "start": 1, "length": 8, "type": "decimal", "decimals": 2 }]"#;
+            values(fields, raw).map(|mut v| v.remove(0))
+        };
+        assert_eq!(value("00012345").unwrap(), "123.45");
+        assert_eq!(value("   12345").unwrap(), "123.45");
+        assert_eq!(value("-0012345").unwrap(), "-123.45");
+        assert_eq!(value("   -1234").unwrap(), "-12.34");
+        assert_eq!(value("+0000100").unwrap(), "1.00");
+        assert_eq!(value("00000005").unwrap(), "0.05");
+        assert_eq!(value("-0000000").unwrap(), "0.00");
+        assert_eq!(value("00000000").unwrap(), "0.00");
+        assert_eq!(value("        ").unwrap(), "");
+        assert_eq!(value("  12a345").unwrap_err(), "valor: '  12a345' is not a number with 2 implied decimals");
+        assert_eq!(value("-       ").unwrap_err(), "valor: '-       ' is not a number with 2 implied decimals");
+        assert_eq!(value("12.34567").unwrap_err(), "valor: '12.34567' is not a number with 2 implied decimals");
+
+        let whole = r#"[{ "name": "n", "start": 1, "length": 4, "type": "decimal", "decimals": 0 }]"#;
+        assert_eq!(values(whole, "0042").unwrap(), ["42"]);
+        assert_eq!(values(whole, "0000").unwrap(), ["0"]);
+    }
+
+    #[test]
+    fn zero_dates_are_empty() {
+        let fields = r#"[{ "name": "data", "start": 1, "length": 8, "type": "date", "format": "%d%m%Y" }]"#;
+        assert_eq!(values(fields, "00000000").unwrap(), [""]);
+        assert_eq!(values(fields, "15012024").unwrap(), ["15012024"]);
+    }
+
+    #[test]
+    fn picks_the_record_type() {
+        let layout = layout(
+            r#"{ "record_length": 6, "records": [
+                { "name": "header", "match": [{ "start": 1, "value": "0" }], "fields": [{ "name": "a", "start": 2, "length": 5 }] },
+                { "name": "detalhe", "match": [{ "start": 1, "value": ["1", "2"] }, { "start": 6, "value": "X" }],
+                  "fields": [{ "name": "b", "start": 2, "length": 4 }] }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(layout.record_for("0abcde"), Some(0));
+        assert_eq!(layout.record_for("2abcdX"), Some(1));
+        assert_eq!(layout.record_for("1abcdY"), None);
+        assert_eq!(layout.record_for(""), None);
+    }
+
+    #[test]
+    fn rejects_invalid_layouts() {
+        let error = |json: &str| layout(json).err().unwrap();
+        assert_eq!(
+            error(r#"{ "fields": [{ "name": "a", "start": 1, "length": 4 }, { "name": "b", "start": 4, "length": 2 }] }"#),
+            "Invalid layout: field 'b': overlaps field 'a'"
+        );
+        assert_eq!(
+            error(r#"{ "record_length": 4, "fields": [{ "name": "a", "start": 2, "length": 4 }] }"#),
+            "Invalid layout: field 'a': ends at 5, past record_length (4)"
+        );
+        assert_eq!(
+            error(r#"{ "fields": [{ "name": "a", "start": 1, "length": 4, "decimals": 2 }] }"#),
+            "Invalid layout: field 'a': decimals requires type 'decimal'"
+        );
+        assert_eq!(
+            error(r#"{ "fields": [{ "name": "a", "start": 0, "length": 4 }] }"#),
+            "Invalid layout: field 'a': start and length must be at least 1"
+        );
+        assert_eq!(
+            error(r#"{ "fields": [{ "name": "a", "start": 1, "length": 4, "padding": "ab" }] }"#),
+            "Invalid layout: field 'a': invalid padding 'ab': expected 'space', 'zero' or a single character"
+        );
+        assert_eq!(error(r#"{ "record_length": 0, "fields": [] }"#), "Invalid layout: record_length must be at least 1");
+        assert_eq!(error(r#"{ }"#), "Invalid layout: expected either 'fields' or 'records'");
+    }
+
+    fn split(text: &str, record_length: Option<usize>, encoding: Encoding) -> Vec<(u64, u64, &str)> {
+        records(text, record_length, encoding).into_iter().map(|(p, record)| (p.line, p.byte, record)).collect()
+    }
+
+    #[test]
+    fn splits_records_by_line_or_length() {
+        assert_eq!(
+            split("\u{feff}0001\r\n\r\n0002\n0003\n\u{1a}", Some(4), Encoding::Utf8),
+            [(1, 3, "0001"), (3, 11, "0002"), (4, 16, "0003")]
+        );
+        assert_eq!(split("0001    0002", Some(4), Encoding::Utf8), [(1, 0, "0001"), (3, 8, "0002")]);
+        // Sem quebras de linha, cada registro tem `record_length` caracteres
+        assert_eq!(split("Joséàbcd", Some(4), Encoding::Windows1252), [(1, 0, "José"), (2, 4, "àbcd")]);
+        assert_eq!(split("Joséàbcd", Some(4), Encoding::Utf8), [(1, 0, "José"), (2, 5, "àbcd")]);
+    }
+}
@@ -0,0 +1,288 @@
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+use xxhash_rust::xxh3::Xxh3;
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum HashAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+    Blake3,
+    // Rápido, mas não criptográfico: não aceita chave
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    pub(crate) fn from_name(name: &str) -> Option<HashAlgorithm> {
+        match name {
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "sha512" => Some(HashAlgorithm::Sha512),
+            "blake3" => Some(HashAlgorithm::Blake3),
+            "xxh3" => Some(HashAlgorithm::Xxh3),
+            _ => None,
+        }
+    }
+}
+
+// Como a linha é serializada antes de ir para o hash
+#[derive(Clone, Copy, Default)]
+pub(crate) enum HashEncoding {
+    // Valores unidos por vírgula, como nas versões anteriores. Ambíguo quando
+    // os valores contêm vírgulas.
+    #[default]
+    Joined,
+    // Linhas `nome=valor` ordenadas pelo nome da coluna, com `\`, `=` e
+    // quebras de linha escapados; não depende da ordem das colunas no arquivo
+    Canonical,
+    // `<bytes>:<nome><bytes>:<valor>` para cada coluna, na ordem configurada
+    LengthPrefixed,
+    // Objeto JSON com as colunas na ordem configurada
+    Json,
+}
+
+impl HashEncoding {
+    pub(crate) fn from_name(name: &str) -> Option<HashEncoding> {
+        match name {
+            "joined" => Some(HashEncoding::Joined),
+            "canonical" => Some(HashEncoding::Canonical),
+            "length_prefixed" => Some(HashEncoding::LengthPrefixed),
+            "json" => Some(HashEncoding::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct HashConfig {
+    pub(crate) algorithm: HashAlgorithm,
+    pub(crate) encoding: HashEncoding,
+    // Colunas usadas no hash, nessa ordem; None usa todas na ordem do arquivo
+    pub(crate) columns: Option<Vec<String>>,
+    // Com chave, o hash vira um HMAC (ou BLAKE3 com chave)
+    pub(crate) key: Option<Vec<u8>>,
+}
+
+// Hash das linhas de um arquivo, com as colunas já resolvidas pelo cabeçalho
+pub(crate) struct RowHasher {
+    config: HashConfig,
+    // Índice de cada coluna usada, na ordem em que entra no hash
+    indices: Vec<usize>,
+}
+
+impl RowHasher {
+    // Colunas configuradas que não existem no cabeçalho são um erro, como as
+    // colunas do schema: sem elas o hash deixaria de identificar a linha
+    pub(crate) fn new(config: &HashConfig, headers: &[String]) -> Result<Self, String> {
+        let mut indices: Vec<usize> = match &config.columns {
+            Some(columns) => columns
+                .iter()
+                .map(|c| {
+                    headers
+                        .iter()
+                        .position(|h| h == c)
+                        .ok_or_else(|| format!("column '{}' used by hash.columns is not in the header", c))
+                })
+                .collect::<Result<_, _>>()?,
+            None => (0..headers.len()).collect(),
+        };
+        if let HashEncoding::Canonical = config.encoding {
+            indices.sort_by(|&a, &b| headers[a].cmp(&headers[b]));
+        }
+        Ok(RowHasher { config: config.clone(), indices })
+    }
+
+    pub(crate) fn hash(&self, headers: &[String], values: &[&str]) -> String {
+        let mut hasher = Hasher::new(&self.config);
+        // Linhas curtas (modo flexível) não têm todas as colunas
+        let columns = self
+            .indices
+            .iter()
+            .filter(|&&i| i < values.len())
+            .map(|&i| (headers[i].as_str(), values[i]));
+
+        match self.config.encoding {
+            HashEncoding::Joined => {
+                for (n, (_, value)) in columns.enumerate() {
+                    if n > 0 {
+                        hasher.update(b",");
+                    }
+                    hasher.update(value.as_bytes());
+                }
+            }
+            HashEncoding::Canonical => {
+                for (name, value) in columns {
+                    hasher.update(escape(name).as_bytes());
+                    hasher.update(b"=");
+                    hasher.update(escape(value).as_bytes());
+                    hasher.update(b"\n");
+                }
+            }
+            HashEncoding::LengthPrefixed => {
+                for (name, value) in columns {
+                    for part in [name, value] {
+                        hasher.update(part.len().to_string().as_bytes());
+                        hasher.update(b":");
+                        hasher.update(part.as_bytes());
+                    }
+                }
+            }
+            HashEncoding::Json => {
+                // O objeto é escrito aqui: o Map do serde_json ordena as chaves
+                // e perderia a ordem configurada
+                hasher.update(b"{");
+                for (n, (name, value)) in columns.enumerate() {
+                    if n > 0 {
+                        hasher.update(b",");
+                    }
+                    hasher.update(serde_json::Value::from(name).to_string().as_bytes());
+                    hasher.update(b":");
+                    hasher.update(serde_json::Value::from(value).to_string().as_bytes());
+                }
+                hasher.update(b"}");
+            }
+        }
+        hasher.finalize_hex()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\n', "\\n")
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    HmacSha256(Hmac<Sha256>),
+    HmacSha512(Hmac<Sha512>),
+    Blake3(Box<blake3::Hasher>),
+    Xxh3(Box<Xxh3>),
+}
+
+impl Hasher {
+    fn new(config: &HashConfig) -> Self {
+        match (config.algorithm, &config.key) {
+            (HashAlgorithm::Sha256, None) => Hasher::Sha256(Sha256::new()),
+            (HashAlgorithm::Sha512, None) => Hasher::Sha512(Sha512::new()),
+            (HashAlgorithm::Sha256, Some(key)) => Hasher::HmacSha256(
+                Hmac::new_from_slice(key).expect("HMAC accepts keys of any length"),
+            ),
+            (HashAlgorithm::Sha512, Some(key)) => Hasher::HmacSha512(
+                Hmac::new_from_slice(key).expect("HMAC accepts keys of any length"),
+            ),
+            (HashAlgorithm::Blake3, None) => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            // O BLAKE3 com chave exige 32 bytes, derivados da chave informada
+            (HashAlgorithm::Blake3, Some(key)) => Hasher::Blake3(Box::new(blake3::Hasher::new_keyed(
+                blake3::hash(key).as_bytes(),
+            ))),
+            (HashAlgorithm::Xxh3, _) => Hasher::Xxh3(Box::new(Xxh3::new())),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(bytes),
+            Hasher::Sha512(h) => h.update(bytes),
+            Hasher::HmacSha256(h) => h.update(bytes),
+            Hasher::HmacSha512(h) => h.update(bytes),
+            Hasher::Blake3(h) => {
+                h.update(bytes);
+            }
+            Hasher::Xxh3(h) => h.update(bytes),
+        }
+    }
+
+    fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha512(h) => format!("{:x}", h.finalize()),
+            Hasher::HmacSha256(h) => format!("{:x}", h.finalize().into_bytes()),
+            Hasher::HmacSha512(h) => format!("{:x}", h.finalize().into_bytes()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Xxh3(h) => format!("{:016x}", h.digest()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn hash(config: &HashConfig, names: &[&str], values: &[&str]) -> String {
+        let headers = headers(names);
+        RowHasher::new(config, &headers).unwrap().hash(&headers, values)
+    }
+
+    fn sha256(text: &str) -> String {
+        format!("{:x}", Sha256::digest(text.as_bytes()))
+    }
+
+    fn config(encoding: HashEncoding, columns: Option<&[&str]>) -> HashConfig {
+        HashConfig { encoding, columns: columns.map(headers), ..HashConfig::default() }
+    }
+
+    #[test]
+    fn serializes_each_encoding() {
+        let names = ["nome", "obs", "a=b"];
+        let values = ["Ana", "x,\"y\"\nz", "1\\2"];
+        assert_eq!(hash(&config(HashEncoding::Joined, None), &names, &values), sha256("Ana,x,\"y\"\nz,1\\2"));
+        assert_eq!(
+            hash(&config(HashEncoding::Canonical, None), &names, &values),
+            sha256("a\\=b=1\\\\2\nnome=Ana\nobs=x,\"y\"\\nz\n")
+        );
+        assert_eq!(
+            hash(&config(HashEncoding::LengthPrefixed, None), &names, &values),
+            sha256("4:nome3:Ana3:obs7:x,\"y\"\nz3:a=b3:1\\2")
+        );
+        assert_eq!(
+            hash(&config(HashEncoding::Json, Some(&["obs", "nome"])), &names, &values),
+            sha256("{\"obs\":\"x,\\\"y\\\"\\nz\",\"nome\":\"Ana\"}")
+        );
+    }
+
+    #[test]
+    fn canonical_ignores_column_order() {
+        let canonical = config(HashEncoding::Canonical, None);
+        assert_eq!(hash(&canonical, &["a", "b"], &["1", "2"]), hash(&canonical, &["b", "a"], &["2", "1"]));
+        let joined = config(HashEncoding::Joined, None);
+        assert_ne!(hash(&joined, &["a", "b"], &["1", "2"]), hash(&joined, &["b", "a"], &["2", "1"]));
+        // Unidos por vírgula, valores diferentes podem dar o mesmo hash
+        assert_eq!(hash(&joined, &["a", "b"], &["1,2", "3"]), hash(&joined, &["a", "b"], &["1", "2,3"]));
+        let prefixed = config(HashEncoding::LengthPrefixed, None);
+        assert_ne!(hash(&prefixed, &["a", "b"], &["1,2", "3"]), hash(&prefixed, &["a", "b"], &["1", "2,3"]));
+    }
+
+    #[test]
+    fn selected_columns_and_short_rows() {
+        let selected = config(HashEncoding::Joined, Some(&["c", "a"]));
+        assert_eq!(hash(&selected, &["a", "b", "c"], &["1", "2", "3"]), sha256("3,1"));
+        assert_eq!(hash(&selected, &["a", "b", "c"], &["1", "2"]), sha256("1"));
+        let missing = config(HashEncoding::Joined, Some(&["a", "z"]));
+        assert_eq!(
+            RowHasher::new(&missing, &headers(&["a", "b"])).err().unwrap(),
+            "column 'z' used by hash.columns is not in the header"
+        );
+    }
+
+    #[test]
+    fn algorithms_and_keys() {
+        let with = |algorithm, key: Option<&[u8]>| {
+            let config = HashConfig { algorithm, key: key.map(<[u8]>::to_vec), ..HashConfig::default() };
+            hash(&config, &["a"], &["abc"])
+        };
+        assert_eq!(with(HashAlgorithm::Sha256, None), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(with(HashAlgorithm::Sha512, None).len(), 128);
+        assert_eq!(with(HashAlgorithm::Blake3, None), blake3::hash(b"abc").to_hex().to_string());
+        assert_eq!(with(HashAlgorithm::Xxh3, None).len(), 16);
+        assert_ne!(with(HashAlgorithm::Sha256, Some(b"chave")), with(HashAlgorithm::Sha256, None));
+        assert_ne!(with(HashAlgorithm::Sha256, Some(b"chave")), with(HashAlgorithm::Sha256, Some(b"outra")));
+        assert_ne!(with(HashAlgorithm::Blake3, Some(b"chave")), with(HashAlgorithm::Blake3, None));
+        assert_eq!(with(HashAlgorithm::Xxh3, Some(b"chave")), with(HashAlgorithm::Xxh3, None));
+    }
+}
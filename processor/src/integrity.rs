@@ -0,0 +1,434 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::errors::FileErrorCode;
+use crate::expr::Expr;
+use crate::values;
+use crate::FileError;
+
+// Verificações do arquivo inteiro, que só fazem sentido olhando várias
+// linhas: chaves únicas, colunas crescentes, quantidade de linhas e os
+// totais do registro trailer. Compiladas junto com o schema.
+#[derive(Default)]
+pub(crate) struct FileRules {
+    // Conjuntos de colunas que não podem se repetir, como [["cpf"], ["id", "data"]]
+    pub(crate) unique: Vec<Vec<String>>,
+    // Colunas que precisam crescer a cada linha; `true` aceita valores iguais
+    pub(crate) monotonic: Vec<(String, bool)>,
+    pub(crate) max_rows: Option<u64>,
+    pub(crate) trailer: Option<TrailerRules>,
+}
+
+// Registro final com os totais do arquivo, reconhecido por uma expressão.
+// Não é validado como linha de dados.
+pub(crate) struct TrailerRules {
+    pub(crate) when: Expr,
+    pub(crate) source: String,
+    // Coluna do trailer com a quantidade de linhas de dados
+    pub(crate) count: Option<String>,
+    // Coluna somada -> coluna do trailer com a soma esperada
+    pub(crate) sums: Vec<(String, String)>,
+}
+
+impl FileRules {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.unique.is_empty() && self.monotonic.is_empty() && self.max_rows.is_none() && self.trailer.is_none()
+    }
+
+    // Colunas usadas pelas verificações, com o nome da verificação
+    pub(crate) fn columns(&self) -> impl Iterator<Item = (&str, &'static str)> {
+        let unique = self.unique.iter().flatten().map(|c| (c.as_str(), "unique"));
+        let monotonic = self.monotonic.iter().map(|(c, _)| (c.as_str(), "monotonic"));
+        let trailer = self.trailer.iter().flat_map(|t| {
+            let sums = t.sums.iter().flat_map(|(column, total)| [column.as_str(), total.as_str()]);
+            t.count.iter().map(String::as_str).chain(sums).map(|c| (c, "trailer"))
+        });
+        unique.chain(monotonic).chain(trailer)
+    }
+}
+
+// Número decimal exato, em unidades da menor casa: somas de valores
+// monetários não acumulam o erro de arredondamento do f64
+#[derive(Clone, Copy, Default)]
+struct Fixed {
+    units: i128,
+    scale: u32,
+}
+
+impl Fixed {
+    fn parse(value: &str, decimal_separator: Option<char>) -> Option<Fixed> {
+        let normalized = values::normalize_number(value.trim(), decimal_separator)?;
+        let (int_part, frac_part) = normalized.split_once('.').unwrap_or((&normalized, ""));
+        if frac_part.len() > 18 {
+            return None;
+        }
+        let units = format!("{}{}", int_part, frac_part).parse().ok()?;
+        Some(Fixed { units, scale: frac_part.len() as u32 })
+    }
+
+    fn rescale(self, scale: u32) -> Option<i128> {
+        self.units.checked_mul(10i128.checked_pow(scale - self.scale)?)
+    }
+
+    fn checked_add(self, other: Fixed) -> Option<Fixed> {
+        let scale = self.scale.max(other.scale);
+        let units = self.rescale(scale)?.checked_add(other.rescale(scale)?)?;
+        Some(Fixed { units, scale })
+    }
+
+    fn same(self, other: Fixed) -> bool {
+        let scale = self.scale.max(other.scale);
+        self.rescale(scale) == other.rescale(scale)
+    }
+
+    fn to_text(self) -> String {
+        let digits = self.units.unsigned_abs().to_string();
+        let sign = if self.units < 0 { "-" } else { "" };
+        if self.scale == 0 {
+            return format!("{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = self.scale as usize + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - self.scale as usize);
+        format!("{}{}.{}", sign, int_part, frac_part)
+    }
+}
+
+struct Monotonic {
+    index: usize,
+    allow_equal: bool,
+    last: Option<(String, u64)>,
+}
+
+struct Sum {
+    index: usize,
+    total: Fixed,
+    // Primeiro valor que não é número: a soma não pode ser conferida
+    invalid: Option<(String, u64)>,
+}
+
+// Estado das verificações durante a leitura, criado quando o cabeçalho é
+// conhecido. Recebe as linhas na ordem do arquivo, mesmo no processamento
+// em paralelo. A quantidade de linhas conta todos os registros de dados; as
+// chaves únicas, as colunas crescentes e as somas só olham as linhas aceitas,
+// com os valores já validados e os padrões do schema aplicados.
+pub(crate) struct FileValidator {
+    decimal_separator: Option<char>,
+    rows: u64,
+    unique: Vec<(Vec<usize>, HashMap<String, u64>)>,
+    monotonic: Vec<Monotonic>,
+    sums: Vec<Sum>,
+    trailer: Option<(Vec<String>, u64)>,
+}
+
+fn position(headers: &[String], column: &str) -> usize {
+    headers.iter().position(|h| h == column).unwrap_or(usize::MAX)
+}
+
+fn field<'a>(values: &[&'a str], index: usize) -> &'a str {
+    values.get(index).copied().unwrap_or("")
+}
+
+impl FileValidator {
+    pub(crate) fn new(rules: &FileRules, headers: &[String], decimal_separator: Option<char>) -> Self {
+        FileValidator {
+            decimal_separator,
+            rows: 0,
+            unique: rules
+                .unique
+                .iter()
+                .map(|key| (key.iter().map(|c| position(headers, c)).collect(), HashMap::new()))
+                .collect(),
+            monotonic: rules
+                .monotonic
+                .iter()
+                .map(|(column, allow_equal)| Monotonic {
+                    index: position(headers, column),
+                    allow_equal: *allow_equal,
+                    last: None,
+                })
+                .collect(),
+            sums: rules.trailer.iter().flat_map(|t| &t.sums).map(|(column, _)| Sum {
+                index: position(headers, column),
+                total: Fixed::default(),
+                invalid: None,
+            }).collect(),
+            trailer: None,
+        }
+    }
+
+    // Guarda o registro trailer; um segundo trailer é um erro
+    pub(crate) fn trailer(&mut self, values: &[&str], line: u64, out: &mut Vec<FileError>) {
+        match &self.trailer {
+            Some((_, first)) => out.push(FileError::new(
+                FileErrorCode::TrailerRepeated,
+                format!("Trailer record repeated: the first one is at line {}", first),
+                Some(line),
+            )),
+            None => self.trailer = Some((values.iter().map(|v| v.to_string()).collect(), line)),
+        }
+    }
+
+    // Conta um registro de dados, aceito ou não
+    pub(crate) fn count(&mut self, rules: &FileRules, line: u64, out: &mut Vec<FileError>) {
+        self.rows += 1;
+        if rules.max_rows.is_some_and(|max| self.rows == max + 1) {
+            let max = rules.max_rows.unwrap_or(0);
+            let mut error = FileError::new(
+                FileErrorCode::MaxRows,
+                format!("File has more than max_rows ({}) rows", max),
+                Some(line),
+            );
+            error.expected = Some(format!("at most {} rows", max));
+            out.push(error);
+        }
+    }
+
+    // Confere uma linha aceita contra as linhas aceitas anteriores
+    pub(crate) fn record(&mut self, rules: &FileRules, values: &[&str], line: u64, out: &mut Vec<FileError>) {
+        for ((indices, seen), columns) in self.unique.iter_mut().zip(&rules.unique) {
+            let key: Vec<&str> = indices.iter().map(|&i| field(values, i)).collect();
+            // Chaves vazias não se repetem, como NULL em uma chave única do SQL
+            if key.iter().all(|v| v.trim().is_empty()) {
+                continue;
+            }
+            let joined = key.join("\u{1f}");
+            match seen.get(&joined) {
+                Some(&first) => {
+                    let mut error = FileError::new(
+                        FileErrorCode::Unique,
+                        format!("Duplicate value for unique key ({}): same as line {}", columns.join(", "), first),
+                        Some(line),
+                    );
+                    error.columns = Some(columns.clone());
+                    error.actual = Some(key.join(", "));
+                    error.duplicate_of = Some(first);
+                    out.push(error);
+                }
+                None => {
+                    seen.insert(joined, line);
+                }
+            }
+        }
+
+        for (monotonic, (column, _)) in self.monotonic.iter_mut().zip(&rules.monotonic) {
+            let value = field(values, monotonic.index).trim();
+            if value.is_empty() {
+                continue;
+            }
+            if let Some((last, last_line)) = &monotonic.last {
+                let ordering = match (Fixed::parse(value, self.decimal_separator), Fixed::parse(last, self.decimal_separator)) {
+                    (Some(a), Some(b)) => {
+                        let scale = a.scale.max(b.scale);
+                        a.rescale(scale).cmp(&b.rescale(scale))
+                    }
+                    _ => value.cmp(last.as_str()),
+                };
+                if ordering == Ordering::Less || (ordering == Ordering::Equal && !monotonic.allow_equal) {
+                    let mut error = FileError::new(
+                        FileErrorCode::Monotonic,
+                        format!("Column '{}' must increase: '{}' comes after '{}' (line {})", column, value, last, last_line),
+                        Some(line),
+                    );
+                    error.columns = Some(vec![column.clone()]);
+                    error.expected = Some(format!("{} '{}'", if monotonic.allow_equal { ">=" } else { ">" }, last));
+                    error.actual = Some(value.to_string());
+                    out.push(error);
+                }
+            }
+            monotonic.last = Some((value.to_string(), line));
+        }
+
+        for sum in &mut self.sums {
+            let value = field(values, sum.index);
+            if value.trim().is_empty() || sum.invalid.is_some() {
+                continue;
+            }
+            match Fixed::parse(value, self.decimal_separator).and_then(|n| sum.total.checked_add(n)) {
+                Some(total) => sum.total = total,
+                None => sum.invalid = Some((value.to_string(), line)),
+            }
+        }
+    }
+
+    // Verificações que dependem do arquivo inteiro, feitas no fim
+    pub(crate) fn finish(&mut self, rules: &FileRules, headers: &[String], out: &mut Vec<FileError>) {
+        let Some(trailer_rules) = &rules.trailer else {
+            return;
+        };
+        let Some((trailer, line)) = &self.trailer else {
+            let mut error = FileError::new(
+                FileErrorCode::TrailerMissing,
+                format!("No trailer record found (when: {})", trailer_rules.source),
+                None,
+            );
+            error.expected = Some(trailer_rules.source.clone());
+            out.push(error);
+            return;
+        };
+        let trailer_value = |column: &str| trailer.get(position(headers, column)).map_or("", String::as_str);
+
+        if let Some(column) = &trailer_rules.count {
+            let value = trailer_value(column);
+            let count = values::parse_int(value.trim(), self.decimal_separator);
+            if count != Some(self.rows as i64) {
+                let mut error = FileError::new(
+                    FileErrorCode::TrailerCount,
+                    format!("Trailer column '{}' says '{}' rows, but the file has {}", column, value, self.rows),
+                    Some(*line),
+                );
+                error.columns = Some(vec![column.clone()]);
+                error.expected = Some(value.to_string());
+                error.actual = Some(self.rows.to_string());
+                out.push(error);
+            }
+        }
+
+        for (sum, (column, total_column)) in self.sums.iter().zip(&trailer_rules.sums) {
+            let value = trailer_value(total_column);
+            let message = match (&sum.invalid, Fixed::parse(value, self.decimal_separator)) {
+                (Some((invalid, invalid_line)), _) => format!(
+                    "Cannot sum column '{}': '{}' at line {} is not a number",
+                    column, invalid, invalid_line
+                ),
+                (None, Some(expected)) if expected.same(sum.total) => continue,
+                (None, _) => format!(
+                    "Sum of column '{}' is {}, but trailer column '{}' says '{}'",
+                    column,
+                    sum.total.to_text(),
+                    total_column,
+                    value
+                ),
+            };
+            let mut error = FileError::new(FileErrorCode::TrailerSum, message, Some(*line));
+            error.columns = Some(vec![column.clone(), total_column.clone()]);
+            error.expected = Some(value.to_string());
+            error.actual = sum.invalid.is_none().then(|| sum.total.to_text());
+            out.push(error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process, CsvOptions, CsvSchema};
+
+    fn file_errors(schema: &str, csv: &str) -> Vec<FileError> {
+        let mut options = CsvOptions::default();
+        options.set_schema(&CsvSchema::new(schema).unwrap());
+        process(csv.as_bytes(), &options).unwrap().file_errors().to_vec()
+    }
+
+    // Código, linha e mensagem de cada erro
+    fn summary(errors: &[FileError]) -> Vec<(FileErrorCode, Option<u64>, &str)> {
+        errors.iter().map(|e| (e.code(), e.line(), e.message())).collect()
+    }
+
+    #[test]
+    fn unique_keys() {
+        let schema = r#"{ "file": { "unique": [["cpf"], ["pedido", "item"]] } }"#;
+        let csv = "cpf,pedido,item\n111,1,1\n222,1,2\n111,2,1\n,3,1\n,3,2\n333,1,2\n";
+        let errors = file_errors(schema, csv);
+        assert_eq!(
+            summary(&errors),
+            [
+                (FileErrorCode::Unique, Some(4), "Duplicate value for unique key (cpf): same as line 2"),
+                (FileErrorCode::Unique, Some(7), "Duplicate value for unique key (pedido, item): same as line 3"),
+            ]
+        );
+        assert_eq!((errors[1].duplicate_of(), errors[1].actual()), (Some(3), Some("1, 2")));
+        assert_eq!(errors[1].columns(), Some(&["pedido".to_string(), "item".to_string()][..]));
+    }
+
+    #[test]
+    fn monotonic_columns() {
+        let schema = r#"{ "file": { "monotonic": [{ "column": "id" }, { "column": "data", "allow_equal": true }] } }"#;
+        // "10" vem depois de "9" como número; "b" e "a" se comparam como texto
+        let csv = "id,data\n9,a\n10,a\n10,b\n11,a\n,a\n12,c\n";
+        let errors = file_errors(schema, csv);
+        assert_eq!(
+            summary(&errors),
+            [
+                (FileErrorCode::Monotonic, Some(4), "Column 'id' must increase: '10' comes after '10' (line 3)"),
+                (FileErrorCode::Monotonic, Some(5), "Column 'data' must increase: 'a' comes after 'b' (line 4)"),
+            ]
+        );
+        assert_eq!((errors[0].expected(), errors[0].actual()), (Some("> '10'"), Some("10")));
+        assert_eq!(errors[1].expected(), Some(">= 'b'"));
+    }
+
+    #[test]
+    fn max_rows_counts_rejected_rows() {
+        let schema = r#"{ "columns": { "id": { "type": "int" } }, "file": { "max_rows": 2 } }"#;
+        let errors = file_errors(schema, "id\n1\nx\n3\n4\n");
+        assert_eq!(summary(&errors), [(FileErrorCode::MaxRows, Some(4), "File has more than max_rows (2) rows")]);
+        assert_eq!(errors[0].expected(), Some("at most 2 rows"));
+    }
+
+    #[test]
+    fn trailer_count_and_sums() {
+        let schema = r#"{
+            "columns": { "qtd": { "nullable": true } },
+            "file": { "trailer": { "when": "id == 'TOTAL'", "count": "qtd", "sums": { "valor": "valor" } } }
+        }"#;
+        // Em f64, 0.1 + 0.2 daria 0.30000000000000004
+        let csv = "id,valor,qtd\n1,0.1,\n2,0.2,\n3,\"1.234,56\",\nTOTAL,\"1.234,86\",3\n";
+        assert_eq!(summary(&file_errors(schema, csv)), []);
+
+        let csv = "id,valor,qtd\n1,0.1,\n2,0.2,\nTOTAL,0.31,3\n";
+        let errors = file_errors(schema, csv);
+        assert_eq!(
+            summary(&errors),
+            [
+                (FileErrorCode::TrailerCount, Some(4), "Trailer column 'qtd' says '3' rows, but the file has 2"),
+                (
+                    FileErrorCode::TrailerSum,
+                    Some(4),
+                    "Sum of column 'valor' is 0.3, but trailer column 'valor' says '0.31'",
+                ),
+            ]
+        );
+        assert_eq!((errors[1].expected(), errors[1].actual()), (Some("0.31"), Some("0.3")));
+
+        let csv = "id,valor,qtd\n1,abc,\n2,-0.05,\nTOTAL,0,2\n";
+        assert_eq!(
+            summary(&file_errors(schema, csv)),
+            [(FileErrorCode::TrailerSum, Some(4), "Cannot sum column 'valor': 'abc' at line 2 is not a number")]
+        );
+    }
+
+    #[test]
+    fn trailer_missing_or_repeated() {
+        let schema = r#"{
+            "columns": { "qtd": { "nullable": true } },
+            "file": { "trailer": { "when": "id == 'TOTAL'", "count": "qtd" } }
+        }"#;
+        assert_eq!(
+            summary(&file_errors(schema, "id,qtd\n1,\n")),
+            [(FileErrorCode::TrailerMissing, None, "No trailer record found (when: id == 'TOTAL')")]
+        );
+        assert_eq!(
+            summary(&file_errors(schema, "id,qtd\n1,\nTOTAL,1\nTOTAL,1\n")),
+            [(FileErrorCode::TrailerRepeated, Some(4), "Trailer record repeated: the first one is at line 3")]
+        );
+    }
+
+    #[test]
+    fn value_checks_see_accepted_rows_with_defaults() {
+        let schema = r#"{
+            "columns": { "id": { "type": "int" }, "cpf": { "default": "000" }, "valor": { "type": "decimal" } },
+            "file": {
+                "unique": [["cpf"]],
+                "monotonic": [{ "column": "id" }],
+                "trailer": { "when": "id == 'TOTAL'", "count": "cpf", "sums": { "valor": "valor" } }
+            }
+        }"#;
+        // A linha 3 é rejeitada: não repete o cpf, não quebra a ordem e não
+        // entra na soma, mas conta como linha. Os cpfs vazios recebem o padrão.
+        let csv = "id,cpf,valor\n5,111,1.5\nx,111,1.0\n6,,2.5\n7,,1\nTOTAL,4,5\n";
+        assert_eq!(
+            summary(&file_errors(schema, csv)),
+            [(FileErrorCode::Unique, Some(5), "Duplicate value for unique key (cpf): same as line 4")]
+        );
+    }
+}
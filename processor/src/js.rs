@@ -0,0 +1,85 @@
+use js_sys::{Array, Float64Array, Object, Reflect};
+use serde::Serialize;
+use serde_json::Value;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::prelude::*;
+
+use crate::{FatalError, ProcessingResult, ProcessingTotals};
+
+// Converte direto para objetos JavaScript, sem passar por uma string JSON.
+// Mapas viram objetos comuns e inteiros de 64 bits viram `number`.
+pub(crate) fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsError> {
+    Ok(value.serialize(&Serializer::json_compatible())?)
+}
+
+// Resultado em colunas: um array por coluna em vez de um objeto por linha.
+// Colunas só com números (e vazios) viram Float64Array, com NaN no lugar dos
+// vazios; as demais, um Array com os valores. Os erros continuam por linha.
+pub(crate) fn to_columnar(result: &ProcessingResult, totals: ProcessingTotals) -> Result<JsValue, JsError> {
+    let rows = result.processed_rows();
+    let headers = result.headers().unwrap_or_default();
+    let output = Object::new();
+
+    set(&output, "headers", &to_js(headers)?)?;
+    let lines: Vec<f64> = rows.iter().map(|row| row.line as f64).collect();
+    set(&output, "line", &Float64Array::from(lines.as_slice()))?;
+    let hashes: Array = rows.iter().map(|row| JsValue::from_str(&row.hash)).collect();
+    set(&output, "hash", &hashes)?;
+
+    let columns = Object::new();
+    for header in headers {
+        let values = rows.iter().map(|row| row.data.get(header).unwrap_or(&Value::Null));
+        let numeric = values.clone().all(|v| v.is_number() || v.is_null()) && values.clone().any(|v| v.is_number());
+        let column: JsValue = if numeric {
+            let numbers: Vec<f64> = values.map(|v| v.as_f64().unwrap_or(f64::NAN)).collect();
+            Float64Array::from(numbers.as_slice()).into()
+        } else {
+            values.map(cell).collect::<Array>().into()
+        };
+        set(&columns, header, &column)?;
+    }
+    set(&output, "columns", &columns)?;
+
+    set(&output, "errors", &to_js(result.errors())?)?;
+    set(&output, "file_errors", &to_js(result.file_errors())?)?;
+    if let Some(stats) = result.stats() {
+        set(&output, "stats", &to_js(stats)?)?;
+    }
+    if let Some(dialect) = &result.dialect {
+        set(&output, "dialect", &to_js(dialect)?)?;
+    }
+    Object::assign(&output, &to_js(&totals)?.unchecked_into());
+    Ok(output.into())
+}
+
+// Erro fatal como exceção JavaScript: além da mensagem, o tipo, a linha e a
+// posição em bytes ficam em `kind`, `line` e `byte`, sem precisar ler a
+// mensagem para tratar o erro
+pub(crate) fn fatal(error: FatalError) -> JsError {
+    let exception = JsError::new(&error.to_string());
+    let target = JsValue::from(exception.clone());
+    for (key, value) in [
+        ("kind", JsValue::from_str(error.kind().as_str())),
+        ("line", JsValue::from_f64(error.line() as f64)),
+        ("byte", JsValue::from_f64(error.byte_offset() as f64)),
+    ] {
+        // Sem as propriedades, a mensagem ainda traz o tipo
+        let _ = Reflect::set(&target, &JsValue::from_str(key), &value);
+    }
+    exception
+}
+
+fn cell(value: &Value) -> JsValue {
+    match value {
+        Value::String(s) => JsValue::from_str(s),
+        Value::Bool(b) => JsValue::from_bool(*b),
+        Value::Number(n) => JsValue::from_f64(n.as_f64().unwrap_or(f64::NAN)),
+        _ => JsValue::NULL,
+    }
+}
+
+fn set(target: &Object, key: &str, value: &JsValue) -> Result<(), JsError> {
+    Reflect::set(target, &JsValue::from_str(key), value)
+        .map(|_| ())
+        .map_err(|_| JsError::new("Failed to build the result object"))
+}
@@ -0,0 +1,556 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use crate::errors::{ErrorCode, FatalError, FatalErrorKind};
+use crate::pipeline::CsvPipeline;
+use crate::reader::Position;
+use crate::{CsvOptions, ProcessingResult, ProcessingTotals, ValidationError};
+
+// Entrada em JSON: um array de objetos ou um objeto por linha (NDJSON). Cada
+// objeto vira um registro, com os objetos aninhados achatados em colunas como
+// "endereco.cidade", e passa pela mesma validação, hash e deduplicação das
+// linhas de um CSV. As colunas são as chaves de todos os objetos, na ordem em
+// que aparecem; a chave que falta em um objeto vira um campo vazio.
+
+#[derive(Clone)]
+pub(crate) struct JsonConfig {
+    // Entre as chaves de um objeto aninhado: "endereco" + "." + "cidade"
+    pub(crate) separator: String,
+    // Níveis de objetos aninhados achatados; além deles, o valor fica como
+    // texto JSON. Sem limite por padrão.
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) arrays: ArrayMode,
+}
+
+impl Default for JsonConfig {
+    fn default() -> Self {
+        JsonConfig { separator: ".".to_string(), max_depth: None, arrays: ArrayMode::Json }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum ArrayMode {
+    // O array inteiro em um campo, como texto JSON: "[\"a\",\"b\"]"
+    #[default]
+    Json,
+    // Um campo por item, achatado como os objetos: "telefones.0", "telefones.1"
+    Index,
+}
+
+// Campos de um objeto achatado, na ordem das chaves
+type Fields = Vec<(String, String)>;
+
+// Achata as chaves de um objeto em `out`. Um objeto vazio aninhado ainda vira
+// um campo vazio, para a coluna não sumir.
+fn flatten_object<'de, A: MapAccess<'de>>(
+    mut map: A,
+    config: &JsonConfig,
+    prefix: Option<&str>,
+    depth: usize,
+    out: &mut Fields,
+) -> Result<(), A::Error> {
+    let mut empty = true;
+    while let Some(key) = map.next_key::<String>()? {
+        empty = false;
+        let key = match prefix {
+            Some(prefix) => format!("{}{}{}", prefix, config.separator, key),
+            None => key,
+        };
+        map.next_value_seed(FieldSeed { config, key, depth, out: &mut *out })?;
+    }
+    if let (true, Some(prefix)) = (empty, prefix) {
+        out.push((prefix.to_string(), String::new()));
+    }
+    Ok(())
+}
+
+// Valor de uma chave: textos e números como estão, booleanos como
+// "true"/"false", null como campo vazio; objetos e arrays achatados ou em
+// texto JSON, conforme a configuração
+struct FieldSeed<'a> {
+    config: &'a JsonConfig,
+    key: String,
+    // Objetos já achatados acima deste valor
+    depth: usize,
+    out: &'a mut Fields,
+}
+
+impl FieldSeed<'_> {
+    fn push(self, value: String) {
+        self.out.push((self.key, value));
+    }
+
+    fn flattens(&self) -> bool {
+        self.config.max_depth.is_none_or(|max| self.depth < max)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for FieldSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FieldSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<(), E> {
+        self.push(value.to_string());
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<(), E> {
+        self.push(value.to_string());
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<(), E> {
+        self.push(value.to_string());
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<(), E> {
+        self.push(serde_json::Number::from_f64(value).map_or_else(|| value.to_string(), |n| n.to_string()));
+        Ok(())
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<(), E> {
+        self.push(value.to_string());
+        Ok(())
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<(), E> {
+        self.push(value);
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        self.push(String::new());
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<(), A::Error> {
+        if !self.flattens() {
+            let value = serde_json::Value::deserialize(MapAccessDeserializer::new(map))?;
+            self.push(value.to_string());
+            return Ok(());
+        }
+        flatten_object(map, self.config, Some(&self.key), self.depth + 1, self.out)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        if self.config.arrays == ArrayMode::Json || !self.flattens() {
+            let value = serde_json::Value::deserialize(SeqAccessDeserializer::new(seq))?;
+            self.push(value.to_string());
+            return Ok(());
+        }
+        let mut index = 0;
+        loop {
+            let key = format!("{}{}{}", self.key, self.config.separator, index);
+            let item = FieldSeed { config: self.config, key, depth: self.depth + 1, out: &mut *self.out };
+            if seq.next_element_seed(item)?.is_none() {
+                break;
+            }
+            index += 1;
+        }
+        if index == 0 {
+            self.push(String::new());
+        }
+        Ok(())
+    }
+}
+
+// Um registro: um objeto achatado, ou None para qualquer outro valor
+struct RecordSeed<'a> {
+    config: &'a JsonConfig,
+}
+
+impl<'de> DeserializeSeed<'de> for RecordSeed<'_> {
+    type Value = Option<Fields>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RecordSeed<'_> {
+    type Value = Option<Fields>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let mut fields = Vec::new();
+        flatten_object(map, self.config, None, 0, &mut fields)?;
+        Ok(Some(fields))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(None)
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+}
+
+// O documento JSON: um array de registros ou um único objeto
+struct DocumentSeed<'a> {
+    config: &'a JsonConfig,
+}
+
+impl<'de> DeserializeSeed<'de> for DocumentSeed<'_> {
+    type Value = Vec<Option<Fields>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DocumentSeed<'_> {
+    type Value = Vec<Option<Fields>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an array of objects")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut records = Vec::new();
+        while let Some(record) = seq.next_element_seed(RecordSeed { config: self.config })? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        Ok(vec![RecordSeed { config: self.config }.visit_map(map)?])
+    }
+}
+
+// Mensagem do serde_json sem o " at line X column Y" do fim, que é informado
+// à parte
+fn json_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    }
+}
+
+struct JsonRecord {
+    position: Position,
+    fields: Result<Fields, String>,
+}
+
+const NOT_AN_OBJECT: &str = "expected a JSON object";
+
+// Array de objetos (ou um único objeto). `line` é a posição do objeto no
+// array, a partir de 0. Um documento que não é JSON válido é um erro fatal; um
+// item que não é objeto é uma linha rejeitada.
+pub(crate) fn process_json(input: &str, options: &CsvOptions, out: &mut ProcessingResult) -> Result<ProcessingTotals, FatalError> {
+    let text = input.trim_start_matches('\u{feff}');
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    let records = DocumentSeed { config: &options.json }
+        .deserialize(&mut deserializer)
+        .and_then(|records| deserializer.end().map(|_| records))
+        .map_err(|e| {
+            let message = format!("invalid JSON: {} at column {}", json_message(&e), e.column());
+            FatalError::new(FatalErrorKind::InvalidJson, message, e.line() as u64, 0)
+        })?;
+    let records = records
+        .into_iter()
+        .enumerate()
+        .map(|(index, fields)| JsonRecord {
+            position: Position { line: index as u64, byte: 0 },
+            fields: fields.ok_or_else(|| NOT_AN_OBJECT.to_string()),
+        })
+        .collect();
+    feed(records, options, input.len(), false, out)
+}
+
+// Um objeto por linha (NDJSON, JSON Lines). `line` é a linha no texto e
+// `byte_offset` o início dela; linhas em branco são ignoradas. Uma linha que
+// não é um objeto JSON válido é rejeitada com o código `invalid_json`.
+pub(crate) fn process_ndjson(input: &str, options: &CsvOptions, out: &mut ProcessingResult) -> Result<ProcessingTotals, FatalError> {
+    let text = input.trim_start_matches('\u{feff}');
+    let mut byte = (input.len() - text.len()) as u64;
+    let mut records = Vec::new();
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let position = Position { line: index as u64 + 1, byte };
+        byte += line.len() as u64;
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut deserializer = serde_json::Deserializer::from_str(line);
+        let record = RecordSeed { config: &options.json }
+            .deserialize(&mut deserializer)
+            .and_then(|record| deserializer.end().map(|_| record));
+        let fields = match record {
+            Ok(Some(fields)) => Ok(fields),
+            Ok(None) => Err(NOT_AN_OBJECT.to_string()),
+            Err(e) => Err(format!("invalid JSON: {} at column {}", json_message(&e), e.column())),
+        };
+        records.push(JsonRecord { position, fields });
+    }
+    feed(records, options, input.len(), true, out)
+}
+
+fn feed(
+    mut records: Vec<JsonRecord>,
+    options: &CsvOptions,
+    bytes: usize,
+    byte_offsets: bool,
+    out: &mut ProcessingResult,
+) -> Result<ProcessingTotals, FatalError> {
+    // As chaves fazem o papel do cabeçalho
+    let mut options = options.clone();
+    options.dialect.has_headers = true;
+
+    // Chave repetida, ou que colide com outra depois de achatada, como "a.b"
+    // e {"a": {"b": ...}}: um dos valores se perderia, então o objeto é
+    // rejeitado e suas chaves não entram nas colunas
+    for record in &mut records {
+        let Ok(fields) = &record.fields else { continue };
+        let mut keys = HashSet::with_capacity(fields.len());
+        if let Some((key, _)) = fields.iter().find(|(key, _)| !keys.insert(key.as_str())) {
+            record.fields = Err(format!("key '{}' appears more than once in the object", key));
+        }
+    }
+
+    let mut columns: HashMap<String, usize> = HashMap::new();
+    let mut headers: Vec<String> = Vec::new();
+    for fields in records.iter().filter_map(|record| record.fields.as_ref().ok()) {
+        for (key, _) in fields {
+            if !columns.contains_key(key) {
+                columns.insert(key.clone(), headers.len());
+                headers.push(key.clone());
+            }
+        }
+    }
+
+    let mut pipeline = CsvPipeline::for_records(&options, bytes, byte_offsets);
+    // Mesmo sem nenhuma chave, os objetos vazios contam como linhas
+    if records.iter().any(|record| record.fields.is_ok()) {
+        pipeline.push_fields(&headers, Position { line: 0, byte: 0 }, out)?;
+    }
+    let mut values: Vec<String> = Vec::with_capacity(headers.len());
+    for record in records {
+        match record.fields {
+            Ok(fields) => {
+                values.clear();
+                values.resize(headers.len(), String::new());
+                for (key, value) in fields {
+                    values[columns[&key]] = value;
+                }
+                pipeline.push_fields(&values, record.position, out)?;
+            }
+            Err(message) => {
+                let mut error = ValidationError::new(record.position.line, ErrorCode::InvalidJson, message, serde_json::Value::Null);
+                error.byte_offset = Some(record.position.byte);
+                pipeline.reject(error, out)?;
+            }
+        }
+    }
+    pipeline.finish(out)?;
+    Ok(pipeline.totals())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn json(input: &str, options: &CsvOptions) -> (ProcessingResult, ProcessingTotals) {
+        let mut result = ProcessingResult::default();
+        let totals = process_json(input, options, &mut result).unwrap();
+        (result, totals)
+    }
+
+    fn ndjson(input: &str) -> (ProcessingResult, ProcessingTotals) {
+        let mut result = ProcessingResult::default();
+        let totals = process_ndjson(input, &CsvOptions::default(), &mut result).unwrap();
+        (result, totals)
+    }
+
+    fn rows(result: &ProcessingResult) -> Vec<(u64, &Value)> {
+        result.processed_rows().iter().map(|row| (row.line(), row.data())).collect()
+    }
+
+    // Linha, código e mensagem de cada erro
+    fn errors(result: &ProcessingResult) -> Vec<(u64, ErrorCode, &str)> {
+        result.errors().iter().map(|e| (e.line(), e.code(), e.message())).collect()
+    }
+
+    #[test]
+    fn nested_objects_become_columns() {
+        let input = r#"[
+            {"nome": "Ana", "endereco": {"cidade": "Recife", "uf": "PE"}, "ativo": true, "saldo": 1.5},
+            {"endereco": {"uf": "SP", "cidade": "Santos"}, "saldo": -2, "ativo": false, "nome": "Bia"},
+            {"nome": "Caio", "endereco": {"cidade": null}}
+        ]"#;
+        let (result, totals) = json(input, &CsvOptions::default());
+        assert_eq!(result.headers().unwrap(), ["nome", "endereco.cidade", "endereco.uf", "ativo", "saldo"]);
+        let row = |nome, cidade, uf, ativo, saldo| {
+            json!({"nome": nome, "endereco.cidade": cidade, "endereco.uf": uf, "ativo": ativo, "saldo": saldo})
+        };
+        assert_eq!(
+            rows(&result),
+            [(0, &row("Ana", "Recife", "PE", "true", "1.5")), (1, &row("Bia", "Santos", "SP", "false", "-2"))]
+        );
+        // A chave que falta e o null viram campos vazios
+        let empty = ["endereco.cidade", "endereco.uf", "ativo", "saldo"].map(String::from);
+        assert_eq!(result.errors().len(), 1);
+        assert_eq!((result.errors()[0].line(), result.errors()[0].empty_columns()), (2, Some(&empty[..])));
+        assert_eq!((totals.total_rows, totals.valid_rows, totals.invalid_rows), (3, 2, 1));
+    }
+
+    #[test]
+    fn separator_depth_and_arrays() {
+        let input = r#"[{"a": {"b": {"c": 1}}, "tel": ["1", "2"], "lista": [[1], {"x": true}]}]"#;
+
+        let (result, _) = json(input, &CsvOptions::default());
+        assert_eq!(
+            rows(&result)[0].1,
+            &json!({"a.b.c": "1", "tel": r#"["1","2"]"#, "lista": r#"[[1],{"x":true}]"#})
+        );
+
+        let mut options = CsvOptions::default();
+        options.set_json_separator("__").unwrap();
+        options.set_json_arrays("index").unwrap();
+        let (result, _) = json(input, &options);
+        assert_eq!(
+            rows(&result)[0].1,
+            &json!({"a__b__c": "1", "tel__0": "1", "tel__1": "2", "lista__0__0": "1", "lista__1__x": "true"})
+        );
+
+        // Além do limite, o objeto ou array fica como texto JSON
+        options.set_json_max_depth(Some(1));
+        let (result, _) = json(input, &options);
+        assert_eq!(
+            rows(&result)[0].1,
+            &json!({"a__b": r#"{"c":1}"#, "tel__0": "1", "tel__1": "2", "lista__0": "[1]", "lista__1": r#"{"x":true}"#})
+        );
+        options.set_json_max_depth(Some(0));
+        let (result, _) = json(input, &options);
+        assert_eq!(
+            rows(&result)[0].1,
+            &json!({"a": r#"{"b":{"c":1}}"#, "tel": r#"["1","2"]"#, "lista": r#"[[1],{"x":true}]"#})
+        );
+    }
+
+    #[test]
+    fn empty_objects_are_counted() {
+        // Sem nenhuma chave, os objetos ainda contam como linhas
+        let (result, totals) = json("[{}, {}]", &CsvOptions::default());
+        assert_eq!(rows(&result), [(0, &json!({})), (1, &json!({}))]);
+        assert_eq!((totals.total_rows, totals.valid_rows), (2, 2));
+
+        // Um objeto ou array vazio aninhado mantém a coluna, como um campo vazio
+        let mut options = CsvOptions::default();
+        options.set_json_arrays("index").unwrap();
+        let (result, _) = json(r#"[{"a": {}, "b": [], "c": 1}]"#, &options);
+        assert_eq!(result.headers().unwrap(), ["a", "b", "c"]);
+        let empty = ["a", "b"].map(String::from);
+        assert_eq!(result.errors()[0].empty_columns(), Some(&empty[..]));
+
+        let (result, totals) = json("[]", &CsvOptions::default());
+        assert!(result.processed_rows().is_empty() && result.headers().is_none());
+        assert_eq!(totals.total_rows, 0);
+    }
+
+    #[test]
+    fn colliding_keys_reject_the_object() {
+        let input = r#"[{"a.b": 1, "a": {"b": 2}}, {"x": 1, "x": 2}, {"ok": 1}]"#;
+        let (result, totals) = json(input, &CsvOptions::default());
+        assert_eq!(
+            errors(&result),
+            [
+                (0, ErrorCode::InvalidJson, "key 'a.b' appears more than once in the object"),
+                (1, ErrorCode::InvalidJson, "key 'x' appears more than once in the object"),
+            ]
+        );
+        // As chaves dos objetos rejeitados não entram nas colunas
+        assert_eq!(result.headers().unwrap(), ["ok"]);
+        assert_eq!(rows(&result), [(2, &json!({"ok": "1"}))]);
+        assert_eq!((totals.total_rows, totals.valid_rows, totals.invalid_rows), (3, 1, 2));
+    }
+
+    #[test]
+    fn invalid_documents_and_items() {
+        let mut result = ProcessingResult::default();
+        let error = process_json("[{\"a\": 1},\n {\"a\" 2}]", &CsvOptions::default(), &mut result).unwrap_err();
+        assert_eq!(error.kind(), FatalErrorKind::InvalidJson);
+        assert_eq!(error.line(), 2);
+        assert_eq!(error.message(), "invalid JSON: expected `:` at column 7");
+
+        // Um item que não é objeto é só uma linha rejeitada; um objeto sozinho é um registro
+        let (result, _) = json(r#"[{"a": 1}, 2, "x", [1]]"#, &CsvOptions::default());
+        assert_eq!(
+            errors(&result).iter().map(|&(line, _, message)| (line, message)).collect::<Vec<_>>(),
+            [(1, NOT_AN_OBJECT), (2, NOT_AN_OBJECT), (3, NOT_AN_OBJECT)]
+        );
+        let (result, _) = json("\u{feff}{\"a\": 1}", &CsvOptions::default());
+        assert_eq!(rows(&result), [(0, &json!({"a": "1"}))]);
+    }
+
+    #[test]
+    fn ndjson_lines_and_offsets() {
+        let input = "{\"a\": 1, \"b\": \"x\"}\r\n\n{\"b\": \"y\", \"a\": 2}\n{\"a\": \n[1]\n{\"a\": 3, \"b\": \"z\"}";
+        let (result, totals) = ndjson(input);
+        assert_eq!(result.headers().unwrap(), ["a", "b"]);
+        // Linhas em branco são puladas mas contam na numeração
+        assert_eq!(
+            rows(&result),
+            [
+                (1, &json!({"a": "1", "b": "x"})),
+                (3, &json!({"a": "2", "b": "y"})),
+                (6, &json!({"a": "3", "b": "z"})),
+            ]
+        );
+        assert_eq!(
+            errors(&result),
+            [
+                (4, ErrorCode::InvalidJson, "invalid JSON: EOF while parsing a value at column 6"),
+                (5, ErrorCode::InvalidJson, NOT_AN_OBJECT),
+            ]
+        );
+        let offsets: Vec<_> = result.errors().iter().map(|e| e.byte_offset()).collect();
+        assert_eq!(offsets, [Some(40), Some(47)]);
+        assert_eq!((totals.total_rows, totals.valid_rows, totals.invalid_rows), (5, 3, 2));
+        assert_eq!(totals.bytes_processed, input.len() as u64);
+    }
+}
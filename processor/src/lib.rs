@@ -1,99 +1,621 @@
+use std::io::{self, Read};
+
 use wasm_bindgen::prelude::*;
-use sha2::{Sha256, Digest};
 use serde::{Serialize, Deserialize};
 
+mod brazilian;
+mod cnab;
+mod dedup;
+mod encoding;
+mod errors;
+mod expr;
+mod fixed_width;
+mod hashing;
+mod integrity;
+mod js;
+mod json;
+mod ndjson;
+mod ods;
+mod options;
+mod pipeline;
+mod reader;
+mod schema;
+mod sniff;
+mod spreadsheet;
+mod stats;
+mod stream;
+mod values;
+mod writer;
+mod xls;
+mod xlsx;
+mod xml;
+mod zip;
+
+use options::Dialect;
+use pipeline::CsvPipeline;
+pub use cnab::{CnabDirection, CnabEntry, CnabFile, CnabFormat};
+pub use errors::{ConfigError, ErrorCode, FatalError, FatalErrorKind, FileErrorCode, Severity};
+pub use fixed_width::FixedWidthLayout;
+pub use ndjson::{write_ndjson, write_ndjson_summary};
+pub use options::CsvOptions;
+pub use schema::CsvSchema;
+pub use sniff::sniff_csv;
+pub use stats::{ColumnStats, CsvProfiler, LengthBucket, LengthStats, Stats, TopValue};
+pub use stream::CsvStreamProcessor;
+pub use writer::{CsvSplitter, SplitWriter};
+
 // Estrutura para uma linha que foi processada com sucesso
 #[derive(Serialize, Deserialize)]
 pub struct ProcessedRow {
     line: u64,
     data: serde_json::Value,
     hash: String,
+    // Tipo de registro, em arquivos posicionais com mais de um
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record: Option<String>,
 }
 
-// Estrutura para um erro de validação
+// Estrutura para um erro de validação. `code` identifica o tipo do erro de
+// forma estável; `error` é a mensagem para exibição.
 #[derive(Serialize, Deserialize)]
 pub struct ValidationError {
     line: u64,
+    code: ErrorCode,
+    #[serde(default)]
+    severity: Severity,
     error: String,
     data: serde_json::Value,
+    // Coluna e regra que falharam, quando o erro vem do schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    column: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+    // Colunas vazias, quando a linha é rejeitada por campos vazios
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    empty_columns: Option<Vec<String>>,
+    // Posição do início do registro no arquivo lido, em bytes da codificação
+    // original
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    byte_offset: Option<u64>,
+    // O que a regra esperava e o valor encontrado
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    actual: Option<String>,
+    // Linha da ocorrência mantida, quando o erro é uma linha repetida
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<u64>,
+    // Tipo de registro, em arquivos posicionais com mais de um
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record: Option<String>,
+    // Campos do registro como foram lidos, para reescrever a linha rejeitada
+    #[serde(skip)]
+    fields: Option<Vec<String>>,
+}
+
+impl ValidationError {
+    pub(crate) fn new(line: u64, code: ErrorCode, error: String, data: serde_json::Value) -> Self {
+        ValidationError {
+            line,
+            code,
+            severity: Severity::Error,
+            error,
+            data,
+            column: None,
+            rule: None,
+            empty_columns: None,
+            byte_offset: None,
+            expected: None,
+            actual: None,
+            duplicate_of: None,
+            record: None,
+            fields: None,
+        }
+    }
+}
+
+// Erro de uma verificação do arquivo inteiro (seção `file` do schema). Não
+// rejeita linhas: informa que o arquivo, como um todo, não passou.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileError {
+    code: FileErrorCode,
+    error: String,
+    // Linha onde o problema apareceu, quando há uma
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    columns: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    actual: Option<String>,
+    // Linha com o mesmo valor de uma chave única
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duplicate_of: Option<u64>,
+    // Tipo de registro verificado, em arquivos posicionais com mais de um
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record: Option<String>,
+}
+
+impl FileError {
+    pub(crate) fn new(code: FileErrorCode, error: String, line: Option<u64>) -> Self {
+        FileError {
+            code,
+            error,
+            line,
+            columns: None,
+            expected: None,
+            actual: None,
+            duplicate_of: None,
+            record: None,
+        }
+    }
+
+    pub fn code(&self) -> FileErrorCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.error
+    }
+
+    pub fn line(&self) -> Option<u64> {
+        self.line
+    }
+
+    pub fn columns(&self) -> Option<&[String]> {
+        self.columns.as_deref()
+    }
+
+    pub fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    pub fn actual(&self) -> Option<&str> {
+        self.actual.as_deref()
+    }
+
+    pub fn duplicate_of(&self) -> Option<u64> {
+        self.duplicate_of
+    }
+
+    pub fn record(&self) -> Option<&str> {
+        self.record.as_deref()
+    }
 }
 
 // Estrutura para o resultado final que será retornado como JSON
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct ProcessingResult {
     processed_rows: Vec<ProcessedRow>,
     errors: Vec<ValidationError>,
+    // Falhas das verificações do arquivo inteiro
+    #[serde(default)]
+    file_errors: Vec<FileError>,
+    // Perfil das colunas, com a opção `stats`; só no último lote do streaming
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stats: Option<Stats>,
+    // Planilha lida, quando a entrada é um .xlsx, .xls ou .ods
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    sheet: Option<String>,
+    // Formato, banco e títulos de um arquivo CNAB
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    cnab: Option<Box<CnabFile>>,
+    // Dialeto usado na leitura; fica de fora dos lotes intermediários do streaming
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    dialect: Option<Dialect>,
+    // Nomes das colunas, na ordem do arquivo. No streaming, só vem no lote em
+    // que o cabeçalho foi lido.
+    #[serde(skip)]
+    headers: Option<Vec<String>>,
+    // Nomes como estão no arquivo, antes dos apelidos do schema e da troca de
+    // nomes repetidos; vêm junto com `headers`
+    #[serde(skip)]
+    source_headers: Option<Vec<String>>,
 }
 
-// A função principal que será exposta ao JavaScript
-#[wasm_bindgen]
-pub fn process_csv_data(csv_content: &str) -> String {
-    let mut rdr = csv::Reader::from_reader(csv_content.as_bytes());
-    let mut processed_rows = Vec::new();
-    let mut errors = Vec::new();
-    let headers = rdr.headers().cloned().unwrap_or_default();
-
-    for (i, result) in rdr.records().enumerate() {
-        let line_num = (i + 2) as u64; // +1 for zero-index, +1 for header
-
-        match result {
-            Ok(record) => {
-                let mut is_valid = true;
-                let mut row_string = String::new();
-                
-                // Converte o registro para um JSON e valida campos vazios
-                let json_map: serde_json::Map<String, serde_json::Value> = headers.iter()
-                    .zip(record.iter())
-                    .map(|(h, v)| {
-                        if v.trim().is_empty() {
-                            is_valid = false;
-                        }
-                        row_string.push_str(v);
-                        row_string.push(',');
-                        (h.to_string(), serde_json::Value::String(v.to_string()))
-                    })
-                    .collect();
-                
-                let json_data = serde_json::Value::Object(json_map);
-
-                if is_valid {
-                    // Remove a última vírgula
-                    row_string.pop();
-                    
-                    // Gera o hash
-                    let mut hasher = Sha256::new();
-                    hasher.update(row_string.as_bytes());
-                    let hash_result = hasher.finalize();
-                    let hash_hex = format!("{:x}", hash_result);
-
-                    processed_rows.push(ProcessedRow {
-                        line: line_num,
-                        data: json_data,
-                        hash: hash_hex,
-                    });
-                } else {
-                    errors.push(ValidationError {
-                        line: line_num,
-                        error: "Row contains empty fields.".to_string(),
-                        data: json_data,
-                    });
-                }
-            },
-            Err(e) => {
-                errors.push(ValidationError {
-                    line: line_num,
-                    error: format!("Failed to parse row: {}", e),
-                    data: serde_json::Value::Null,
-                });
-            }
+// Totais de um arquivo processado
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug)]
+pub struct ProcessingTotals {
+    // Linhas de dados lidas, sem o cabeçalho
+    pub total_rows: u64,
+    pub valid_rows: u64,
+    pub invalid_rows: u64,
+    // Linhas repetidas encontradas, marcadas como erro ou descartadas
+    pub duplicate_rows: u64,
+    pub bytes_processed: u64,
+}
+
+impl ProcessedRow {
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    pub fn data(&self) -> &serde_json::Value {
+        &self.data
+    }
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    pub fn record(&self) -> Option<&str> {
+        self.record.as_deref()
+    }
+}
+
+impl ValidationError {
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    // Mensagem para exibição; para tratar o erro, use `code`
+    pub fn message(&self) -> &str {
+        &self.error
+    }
+
+    pub fn data(&self) -> &serde_json::Value {
+        &self.data
+    }
+
+    pub fn column(&self) -> Option<&str> {
+        self.column.as_deref()
+    }
+
+    pub fn rule(&self) -> Option<&str> {
+        self.rule.as_deref()
+    }
+
+    pub fn empty_columns(&self) -> Option<&[String]> {
+        self.empty_columns.as_deref()
+    }
+
+    pub fn byte_offset(&self) -> Option<u64> {
+        self.byte_offset
+    }
+
+    pub fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    pub fn actual(&self) -> Option<&str> {
+        self.actual.as_deref()
+    }
+
+    pub fn duplicate_of(&self) -> Option<u64> {
+        self.duplicate_of
+    }
+
+    pub fn record(&self) -> Option<&str> {
+        self.record.as_deref()
+    }
+}
+
+impl ProcessingResult {
+    pub fn processed_rows(&self) -> &[ProcessedRow] {
+        &self.processed_rows
+    }
+
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    pub fn file_errors(&self) -> &[FileError] {
+        &self.file_errors
+    }
+
+    pub fn headers(&self) -> Option<&[String]> {
+        self.headers.as_deref()
+    }
+
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    pub fn sheet(&self) -> Option<&str> {
+        self.sheet.as_deref()
+    }
+
+    pub fn cnab(&self) -> Option<&CnabFile> {
+        self.cnab.as_deref()
+    }
+
+    // Se alguma linha foi rejeitada; avisos não contam
+    pub fn has_errors(&self) -> bool {
+        self.errors.iter().any(|e| e.severity == Severity::Error)
+    }
+
+    pub fn into_parts(self) -> (Vec<ProcessedRow>, Vec<ValidationError>) {
+        (self.processed_rows, self.errors)
+    }
+
+    // Junta um lote do streaming ao resultado acumulado
+    pub fn append(&mut self, batch: ProcessingResult) {
+        self.processed_rows.extend(batch.processed_rows);
+        self.errors.extend(batch.errors);
+        self.file_errors.extend(batch.file_errors);
+        if batch.stats.is_some() {
+            self.stats = batch.stats;
+        }
+        if batch.sheet.is_some() {
+            self.sheet = batch.sheet;
+        }
+        if batch.cnab.is_some() {
+            self.cnab = batch.cnab;
+        }
+        if batch.dialect.is_some() {
+            self.dialect = batch.dialect;
+        }
+        if batch.headers.is_some() {
+            self.headers = batch.headers;
+            self.source_headers = batch.source_headers;
         }
     }
+}
+
+// A função principal que será exposta ao JavaScript
+#[wasm_bindgen]
+pub fn process_csv_data(csv_content: &str) -> Result<JsValue, JsError> {
+    process_with(csv_content.as_bytes(), &CsvOptions::default().utf8_input())
+}
+
+// Processa o CSV validando cada coluna pelo schema compilado
+#[wasm_bindgen]
+pub fn process_csv_with_schema(csv_content: &str, schema: &CsvSchema) -> Result<JsValue, JsError> {
+    let mut options = CsvOptions::default().utf8_input();
+    options.set_schema(schema);
+    process_with(csv_content.as_bytes(), &options)
+}
+
+// Processa o CSV com o dialeto e o schema definidos em `options`
+#[wasm_bindgen]
+pub fn process_csv_with_options(csv_content: &str, options: &CsvOptions) -> Result<JsValue, JsError> {
+    process_with(csv_content.as_bytes(), &options.clone().utf8_input())
+}
+
+// Processa o CSV a partir dos bytes do arquivo, convertendo para UTF-8 pela
+// codificação definida em `options` ou detectada automaticamente
+#[wasm_bindgen]
+pub fn process_csv_bytes(csv_bytes: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    process_with(csv_bytes, options)
+}
+
+// Processa o CSV a partir dos bytes e devolve NDJSON: uma linha JSON por
+// linha processada ou erro (`type` "row" ou "error") e, no fim, uma linha
+// `type` "summary" com os totais
+#[wasm_bindgen]
+pub fn process_csv_ndjson(csv_bytes: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    let mut pipeline = CsvPipeline::new(options);
+    let mut result = ProcessingResult::default();
+    pipeline.push(csv_bytes, &mut result).map_err(js::fatal)?;
+    pipeline.finish(&mut result).map_err(js::fatal)?;
+
+    let mut output = Vec::new();
+    write_ndjson(&mut output, &result)?;
+    write_ndjson_summary(&mut output, None, pipeline.totals())?;
+    Ok(JsValue::from_str(&String::from_utf8_lossy(&output)))
+}
+
+// Como `process_csv_bytes`, mas devolve o resultado como objeto JavaScript,
+// sem a string JSON intermediária. Opcional: no V8 a string com JSON.parse
+// ainda é mais rápida, e é ela que o index.js usa (bench/RESULTS.md)
+#[wasm_bindgen]
+pub fn process_csv_object(csv_bytes: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    js::to_js(&process(csv_bytes, options).map_err(js::fatal)?)
+}
+
+// Como `process_csv_object`, mas com as linhas válidas em colunas:
+// `{ headers, line, hash, columns, errors, dialect, ...totais }`. Colunas
+// numéricas vêm como Float64Array.
+#[wasm_bindgen]
+pub fn process_csv_columnar(csv_bytes: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    let mut pipeline = CsvPipeline::new(options);
+    let mut result = ProcessingResult::default();
+    pipeline.push(csv_bytes, &mut result).map_err(js::fatal)?;
+    pipeline.finish(&mut result).map_err(js::fatal)?;
+    js::to_columnar(&result, pipeline.totals())
+}
+
+// Só o perfil das colunas (o bloco `stats`), como JSON. O arquivo é lido em
+// pedaços e as linhas não ficam em memória.
+#[wasm_bindgen]
+pub fn profile_csv(csv_bytes: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    Ok(JsValue::from_str(&serde_json::to_string(&profile(csv_bytes, options).map_err(js::fatal)?)?))
+}
+
+// Resultado das entradas que não são CSV, com as colunas em `headers` na
+// ordem do arquivo: as chaves de `data` são serializadas em ordem alfabética
+#[derive(Serialize)]
+struct RecordsResult<'a> {
+    headers: &'a [String],
+    #[serde(flatten)]
+    result: &'a ProcessingResult,
+}
+
+fn records_json(result: &ProcessingResult) -> Result<JsValue, JsError> {
+    let report = RecordsResult { headers: result.headers().unwrap_or_default(), result };
+    Ok(JsValue::from_str(&serde_json::to_string(&report)?))
+}
+
+// Processa uma planilha (.xlsx, .xlsm, .xls ou .ods) a partir dos bytes do
+// arquivo, com a planilha e o intervalo de `options`. O resultado tem o mesmo
+// formato do CSV, com o nome da planilha lida em `sheet` no lugar do dialeto
+// e as colunas em `headers`, na ordem da planilha.
+#[wasm_bindgen]
+pub fn process_spreadsheet_bytes(bytes: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    let (result, _) = process_spreadsheet(bytes, options).map_err(js::fatal)?;
+    records_json(&result)
+}
 
-    let final_result = ProcessingResult {
-        processed_rows,
-        errors,
-    };
+// Processa um array JSON de objetos, achatando os objetos aninhados em
+// colunas como "endereco.cidade". `line` de cada linha é a posição no array,
+// a partir de 0; as colunas ficam em `headers`, na ordem em que aparecem.
+#[wasm_bindgen]
+pub fn process_json_data(json_content: &str, options: &CsvOptions) -> Result<JsValue, JsError> {
+    let (result, _) = process_json(json_content, options).map_err(js::fatal)?;
+    records_json(&result)
+}
+
+// Processa NDJSON (um objeto JSON por linha), como `process_json_data`; `line`
+// é a linha no texto e as linhas que não são um objeto JSON vão para `errors`
+#[wasm_bindgen]
+pub fn process_ndjson_data(ndjson_content: &str, options: &CsvOptions) -> Result<JsValue, JsError> {
+    let (result, _) = process_ndjson(ndjson_content, options).map_err(js::fatal)?;
+    records_json(&result)
+}
+
+// Processa um arquivo posicional (largura fixa) a partir dos bytes, com os
+// campos de `layout`. Com vários tipos de registro, cada linha e cada erro
+// trazem o tipo em `record`; `headers` só vem com um único tipo.
+#[wasm_bindgen]
+pub fn process_fixed_width_bytes(bytes: &[u8], layout: &FixedWidthLayout, options: &CsvOptions) -> Result<JsValue, JsError> {
+    let (result, _) = process_fixed_width(bytes, layout, options).map_err(js::fatal)?;
+    records_json(&result)
+}
+
+// Processa um arquivo CNAB 240 ou 400 de cobrança, com o layout da Febraban
+// (240) ou do Bradesco (400), reconhecido pelo conteúdo. Os registros vêm
+// marcados em `record` ("segmento_p", "trailer_lote"...); a estrutura do
+// arquivo é conferida em `file_errors` e os títulos, com os valores tipados,
+// ficam em `cnab.entries`.
+#[wasm_bindgen]
+pub fn process_cnab_bytes(bytes: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    let (result, _) = process_cnab(bytes, options).map_err(js::fatal)?;
+    records_json(&result)
+}
+
+// Nomes das planilhas (abas) do arquivo, como array JSON
+#[wasm_bindgen]
+pub fn list_sheets(bytes: &[u8]) -> Result<JsValue, JsError> {
+    Ok(JsValue::from_str(&serde_json::to_string(&sheet_names(bytes).map_err(js::fatal)?)?))
+}
+
+// Os erros fatais e as falhas de serialização viram exceções no JavaScript;
+// os erros de validação de cada linha continuam em `errors`
+fn process_with(input: &[u8], options: &CsvOptions) -> Result<JsValue, JsError> {
+    let final_result = process(input, options).map_err(js::fatal)?;
 
     // Serializa o resultado final para uma string JSON
-    serde_json::to_string(&final_result).unwrap_or_else(|_| "{\"errors\":[\"Failed to serialize result\"]}".to_string())
-}
\ No newline at end of file
+    Ok(JsValue::from_str(&serde_json::to_string(&final_result)?))
+}
+
+// API Rust: as mesmas funções, devolvendo o resultado sem passar por JSON
+
+// Processa o CSV a partir dos bytes do arquivo. Só um erro fatal (cabeçalho
+// inválido, limite excedido) impede o resultado; linhas rejeitadas vão para
+// `errors`.
+pub fn process(input: &[u8], options: &CsvOptions) -> Result<ProcessingResult, FatalError> {
+    let mut pipeline = CsvPipeline::new(options);
+    let mut result = ProcessingResult::default();
+
+    pipeline.push(input, &mut result)?;
+    pipeline.finish(&mut result)?;
+    Ok(result)
+}
+
+// Perfil das colunas do CSV, como `stats` de `process` com a opção ativa
+pub fn profile(input: &[u8], options: &CsvOptions) -> Result<Stats, FatalError> {
+    let mut profiler = CsvProfiler::new(options);
+    for chunk in input.chunks(1024 * 1024) {
+        profiler.push(chunk)?;
+    }
+    profiler.finish_stats()
+}
+
+// Processa uma planilha (.xlsx, .xlsm, .xls ou .ods). O formato vem do
+// conteúdo do arquivo; uma planilha ilegível é um erro fatal `BadSpreadsheet`.
+pub fn process_spreadsheet(input: &[u8], options: &CsvOptions) -> Result<(ProcessingResult, ProcessingTotals), FatalError> {
+    let mut result = ProcessingResult::default();
+    let totals = spreadsheet::process(input, options, &mut result)?;
+    Ok((result, totals))
+}
+
+// Nomes das planilhas (abas) de um .xlsx, .xls ou .ods, na ordem do arquivo
+pub fn sheet_names(input: &[u8]) -> Result<Vec<String>, FatalError> {
+    spreadsheet::sheet_names(input)
+}
+
+// Processa um array JSON de objetos (ou um único objeto). Um documento que
+// não é JSON válido é um erro fatal `InvalidJson`.
+pub fn process_json(input: &str, options: &CsvOptions) -> Result<(ProcessingResult, ProcessingTotals), FatalError> {
+    let mut result = ProcessingResult::default();
+    let totals = json::process_json(input, options, &mut result)?;
+    Ok((result, totals))
+}
+
+// Processa NDJSON, um objeto JSON por linha
+pub fn process_ndjson(input: &str, options: &CsvOptions) -> Result<(ProcessingResult, ProcessingTotals), FatalError> {
+    let mut result = ProcessingResult::default();
+    let totals = json::process_ndjson(input, options, &mut result)?;
+    Ok((result, totals))
+}
+
+// Processa um arquivo posicional. A codificação é detectada como no CSV, ou
+// vem de `options`; os registros são as linhas do arquivo, ou blocos de
+// `record_length` caracteres quando não há quebras de linha.
+pub fn process_fixed_width(
+    input: &[u8],
+    layout: &FixedWidthLayout,
+    options: &CsvOptions,
+) -> Result<(ProcessingResult, ProcessingTotals), FatalError> {
+    let mut result = ProcessingResult::default();
+    let totals = fixed_width::process(input, layout.compiled(), options, &mut result)?;
+    Ok((result, totals))
+}
+
+// Processa um arquivo CNAB 240 ou 400. Um arquivo que não é CNAB é um erro
+// fatal `InvalidCnab`; o schema de `options` não é usado.
+pub fn process_cnab(input: &[u8], options: &CsvOptions) -> Result<(ProcessingResult, ProcessingTotals), FatalError> {
+    let mut result = ProcessingResult::default();
+    let totals = cnab::process(input, options, &mut result)?;
+    Ok((result, totals))
+}
+
+// Processa um CSV que já está em texto, sem detecção de codificação
+pub fn process_str(input: &str, options: &CsvOptions) -> Result<ProcessingResult, FatalError> {
+    process(input.as_bytes(), &options.clone().utf8_input())
+}
+
+// Processa o CSV lido de `reader` em pedaços, sem carregar o arquivo inteiro
+// em memória. `on_batch` recebe as linhas completadas por cada pedaço; o
+// último lote traz também o dialeto usado. Um erro fatal chega como
+// `io::Error` do tipo InvalidData, com o `FatalError` dentro.
+pub fn process_reader<R, F>(mut reader: R, options: &CsvOptions, mut on_batch: F) -> io::Result<ProcessingTotals>
+where
+    R: Read,
+    F: FnMut(ProcessingResult),
+{
+    let mut pipeline = CsvPipeline::new(options);
+    // Pedaços grandes o bastante para a validação em paralelo do build nativo
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let mut batch = ProcessingResult::default();
+        pipeline.push(&buffer[..n], &mut batch)?;
+        if !batch.processed_rows.is_empty()
+            || !batch.errors.is_empty()
+            || !batch.file_errors.is_empty()
+            || batch.headers.is_some()
+        {
+            on_batch(batch);
+        }
+    }
+
+    let mut batch = ProcessingResult::default();
+    pipeline.finish(&mut batch)?;
+    on_batch(batch);
+    Ok(pipeline.totals())
+}
@@ -0,0 +1,135 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::{CnabEntry, FileError, ProcessedRow, ProcessingResult, ProcessingTotals, Stats, ValidationError};
+
+// Uma linha da saída NDJSON (JSON Lines), marcada pelo campo `type`
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum NdjsonRecord<'a> {
+    Row(&'a ProcessedRow),
+    Error(&'a ValidationError),
+    FileError(&'a FileError),
+    // Título de um arquivo CNAB
+    Entry(&'a CnabEntry),
+    Stats(&'a Stats),
+    Summary {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file: Option<&'a str>,
+        #[serde(flatten)]
+        totals: ProcessingTotals,
+    },
+}
+
+// Escreve as linhas e os erros de um lote, um objeto JSON por linha, na
+// ordem das linhas do arquivo; os erros do arquivo (`file_error`), os títulos
+// do CNAB (`entry`) e o perfil das colunas (`stats`) vêm depois
+pub fn write_ndjson<W: Write>(writer: &mut W, batch: &ProcessingResult) -> io::Result<()> {
+    let mut rows = batch.processed_rows().iter().peekable();
+    let mut errors = batch.errors().iter().peekable();
+    loop {
+        // Um erro vem antes da linha válida de mesmo número (avisos)
+        let record = match (rows.peek(), errors.peek()) {
+            (Some(row), Some(error)) if error.line <= row.line => NdjsonRecord::Error(errors.next().unwrap()),
+            (Some(_), _) => NdjsonRecord::Row(rows.next().unwrap()),
+            (None, Some(_)) => NdjsonRecord::Error(errors.next().unwrap()),
+            (None, None) => break,
+        };
+        write_record(writer, &record)?;
+    }
+    for error in batch.file_errors() {
+        write_record(writer, &NdjsonRecord::FileError(error))?;
+    }
+    for entry in batch.cnab().map_or(&[][..], |cnab| cnab.entries()) {
+        write_record(writer, &NdjsonRecord::Entry(entry))?;
+    }
+    if let Some(stats) = batch.stats() {
+        write_record(writer, &NdjsonRecord::Stats(stats))?;
+    }
+    Ok(())
+}
+
+// Escreve a linha final com os totais, opcionalmente com o nome do arquivo
+pub fn write_ndjson_summary<W: Write>(writer: &mut W, file: Option<&str>, totals: ProcessingTotals) -> io::Result<()> {
+    write_record(writer, &NdjsonRecord::Summary { file, totals })
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &NdjsonRecord<'_>) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{process, CsvOptions, CsvSchema};
+
+    fn lines(output: &[u8]) -> Vec<Value> {
+        let text = std::str::from_utf8(output).unwrap();
+        assert!(text.ends_with('\n'));
+        text.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn records_follow_the_file_order() {
+        let schema = r#"{
+            "columns": { "idade": { "type": "int", "severity": "warning" }, "id": { "type": "int" } },
+            "file": { "unique": [["id"]] }
+        }"#;
+        let mut options = CsvOptions::default();
+        options.set_schema(&CsvSchema::new(schema).unwrap());
+        options.set_stats(true);
+        let batch = process(b"id,idade\n1,10\nx,20\n1,trinta\n", &options).unwrap();
+
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &batch).unwrap();
+        let records = lines(&output);
+        let summary: Vec<_> = records.iter().map(|r| (r["type"].as_str().unwrap(), r["line"].as_u64())).collect();
+        // O aviso vem antes da linha válida de mesmo número; os erros do
+        // arquivo e o perfil vêm no fim
+        assert_eq!(
+            summary,
+            [
+                ("row", Some(2)),
+                ("error", Some(3)),
+                ("error", Some(4)),
+                ("row", Some(4)),
+                ("file_error", Some(4)),
+                ("stats", None),
+            ]
+        );
+        assert_eq!(records[0]["data"], json!({"id": "1", "idade": "10"}));
+        assert_eq!((&records[1]["code"], &records[2]["severity"]), (&json!("invalid_value"), &json!("warning")));
+        assert_eq!(records[4]["code"], json!("unique"));
+    }
+
+    #[test]
+    fn summary_with_and_without_the_file() {
+        let totals =
+            ProcessingTotals { total_rows: 3, valid_rows: 2, invalid_rows: 1, duplicate_rows: 0, bytes_processed: 42 };
+        let mut output = Vec::new();
+        write_ndjson_summary(&mut output, Some("a.csv"), totals).unwrap();
+        write_ndjson_summary(&mut output, None, totals).unwrap();
+        let summary = |file: Option<&str>| {
+            let mut line = json!({
+                "type": "summary", "total_rows": 3, "valid_rows": 2, "invalid_rows": 1,
+                "duplicate_rows": 0, "bytes_processed": 42
+            });
+            if let Some(file) = file {
+                line["file"] = json!(file);
+            }
+            line
+        };
+        assert_eq!(lines(&output), [summary(Some("a.csv")), summary(None)]);
+    }
+
+    #[test]
+    fn empty_batch_writes_nothing() {
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &ProcessingResult::default()).unwrap();
+        assert!(output.is_empty());
+    }
+}
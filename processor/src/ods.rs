@@ -0,0 +1,302 @@
+use crate::spreadsheet::{self, SheetRow};
+use crate::xml::{Event, XmlReader};
+use crate::zip::ZipArchive;
+
+// Planilha do LibreOffice/OpenDocument (.ods). Todas as abas ficam no mesmo
+// content.xml, então o arquivo é lido uma vez só.
+pub(crate) struct Ods {
+    names: Vec<String>,
+    sheets: Vec<Vec<SheetRow>>,
+}
+
+// Linhas e colunas repetidas além disso não são expandidas: são as sobras
+// vazias que o LibreOffice grava até o fim da planilha
+const MAX_REPEAT: u32 = 1 << 16;
+
+impl Ods {
+    pub(crate) fn is_ods(zip: &ZipArchive<'_>) -> Result<bool, String> {
+        let mimetype = zip.read("mimetype")?.unwrap_or_default();
+        Ok(mimetype.starts_with(b"application/vnd.oasis.opendocument.spreadsheet"))
+    }
+
+    pub(crate) fn new(zip: &ZipArchive<'_>) -> Result<Self, String> {
+        let xml = zip.read_text("content.xml")?.ok_or("not a spreadsheet: the ODS file has no content.xml")?;
+        let mut ods = Ods { names: Vec::new(), sheets: Vec::new() };
+        let mut reader = XmlReader::new(&xml);
+        while let Some(event) = reader.next_event()? {
+            if let Event::Start(tag, empty) = event {
+                if tag.name() == "table" && !empty {
+                    ods.names.push(tag.attr("name").unwrap_or_default().into_owned());
+                    ods.sheets.push(read_table(&mut reader)?);
+                }
+            }
+        }
+        Ok(ods)
+    }
+
+    pub(crate) fn sheet_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub(crate) fn rows(&mut self, index: usize) -> Result<Vec<SheetRow>, String> {
+        Ok(std::mem::take(&mut self.sheets[index]))
+    }
+}
+
+fn repeat(value: Option<std::borrow::Cow<'_, str>>) -> u32 {
+    value.and_then(|v| v.parse().ok()).unwrap_or(1).max(1)
+}
+
+// Valor de uma célula pelo tipo: números e datas vêm dos atributos, já sem a
+// formatação de exibição; textos vêm dos parágrafos <text:p>
+struct CellState {
+    col: u32,
+    repeat: u32,
+    value: Option<String>,
+    text: String,
+    paragraphs: u32,
+    in_paragraph: bool,
+}
+
+// Lê as linhas de uma <table:table>, até o fechamento dela
+fn read_table(reader: &mut XmlReader<'_>) -> Result<Vec<SheetRow>, String> {
+    let mut rows: Vec<SheetRow> = Vec::new();
+    let mut next_row = 0u32;
+    let mut row: Option<(SheetRow, u32)> = None;
+    let mut next_col = 0u32;
+    let mut cell: Option<CellState> = None;
+
+    while let Some(event) = reader.next_event()? {
+        match event {
+            Event::Start(tag, empty) => match tag.name() {
+                "table-row" => {
+                    let count = repeat(tag.attr("number-rows-repeated"));
+                    next_col = 0;
+                    if empty {
+                        next_row = next_row.saturating_add(count);
+                    } else {
+                        row = Some((SheetRow { index: next_row, cells: Vec::new() }, count));
+                    }
+                }
+                "table-cell" | "covered-table-cell" => {
+                    let count = repeat(tag.attr("number-columns-repeated"));
+                    let value = match tag.attr("value-type").as_deref() {
+                        Some("float" | "percentage" | "currency") => tag
+                            .attr("value")
+                            .and_then(|v| v.parse::<f64>().ok())
+                            .map(spreadsheet::number_text),
+                        Some("date") => tag.attr("date-value").map(|v| date_value(&v)),
+                        Some("time") => tag.attr("time-value").map(|v| time_value(&v)),
+                        Some("boolean") => tag.attr("boolean-value").map(|v| v.into_owned()),
+                        _ => None,
+                    };
+                    let state = CellState { col: next_col, repeat: count, value, text: String::new(), paragraphs: 0, in_paragraph: false };
+                    next_col = next_col.saturating_add(count);
+                    if empty {
+                        push_cell(&mut row, state);
+                    } else {
+                        cell = Some(state);
+                    }
+                }
+                "p" if !empty => {
+                    if let Some(cell) = &mut cell {
+                        if cell.paragraphs > 0 {
+                            cell.text.push('\n');
+                        }
+                        cell.paragraphs += 1;
+                        cell.in_paragraph = true;
+                    }
+                }
+                // Espaços repetidos, tabulações e quebras dentro do texto
+                "s" => {
+                    if let Some(cell) = &mut cell {
+                        let count = repeat(tag.attr("c")).min(1024) as usize;
+                        cell.text.push_str(&" ".repeat(count));
+                    }
+                }
+                "tab" => {
+                    if let Some(cell) = &mut cell {
+                        cell.text.push('\t');
+                    }
+                }
+                "line-break" => {
+                    if let Some(cell) = &mut cell {
+                        cell.text.push('\n');
+                    }
+                }
+                // Comentários e anotações não fazem parte do valor
+                "annotation" if !empty => reader.skip()?,
+                _ => {}
+            },
+            Event::Text(text) => {
+                if let Some(cell) = &mut cell {
+                    if cell.in_paragraph {
+                        cell.text.push_str(&text);
+                    }
+                }
+            }
+            Event::End(name) => match name {
+                "p" => {
+                    if let Some(cell) = &mut cell {
+                        cell.in_paragraph = false;
+                    }
+                }
+                "table-cell" | "covered-table-cell" => {
+                    if let Some(state) = cell.take() {
+                        push_cell(&mut row, state);
+                    }
+                }
+                "table-row" => {
+                    if let Some((current, count)) = row.take() {
+                        next_row = next_row.saturating_add(count);
+                        if !current.cells.is_empty() {
+                            for offset in 0..count.min(MAX_REPEAT) {
+                                rows.push(SheetRow { index: current.index + offset, cells: current.cells.clone() });
+                            }
+                        }
+                    }
+                }
+                "table" => break,
+                _ => {}
+            },
+        }
+    }
+    Ok(rows)
+}
+
+fn push_cell(row: &mut Option<(SheetRow, u32)>, state: CellState) {
+    let Some((row, _)) = row else { return };
+    let value = state.value.unwrap_or(state.text);
+    if value.is_empty() {
+        return;
+    }
+    let last = state.col.saturating_add(state.repeat).min(spreadsheet::MAX_COLUMNS);
+    for col in state.col..last {
+        row.cells.push((col, value.clone()));
+    }
+}
+
+// "2024-01-02T00:00:00" vira "2024-01-02"; com hora, fica como está
+fn date_value(value: &str) -> String {
+    value.strip_suffix("T00:00:00").unwrap_or(value).to_string()
+}
+
+// Duração ISO-8601 como "PT10H30M00S" vira "10:30:00"
+fn time_value(value: &str) -> String {
+    let Some(rest) = value.strip_prefix("PT") else {
+        return value.to_string();
+    };
+    let mut parts = [0u64; 3];
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            'H' | 'M' | 'S' => {
+                let slot = match c {
+                    'H' => 0,
+                    'M' => 1,
+                    _ => 2,
+                };
+                parts[slot] = number.split('.').next().and_then(|n| n.parse().ok()).unwrap_or(0);
+                number.clear();
+            }
+            c => number.push(c),
+        }
+    }
+    format!("{:02}:{:02}:{:02}", parts[0], parts[1], parts[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::tests::archive;
+
+    const MIMETYPE: &[u8] = b"application/vnd.oasis.opendocument.spreadsheet";
+
+    fn ods(tables: &str) -> Vec<u8> {
+        let content = format!(
+            r#"<?xml version="1.0"?><office:document-content><office:body>
+                <office:spreadsheet>{}</office:spreadsheet>
+            </office:body></office:document-content>"#,
+            tables
+        );
+        archive(&[("mimetype", MIMETYPE), ("content.xml", content.as_bytes())])
+    }
+
+    fn rows(data: &[u8], index: usize) -> Vec<(u32, Vec<(u32, String)>)> {
+        let mut ods = Ods::new(&ZipArchive::new(data).unwrap()).unwrap();
+        ods.rows(index).unwrap().into_iter().map(|row| (row.index, row.cells)).collect()
+    }
+
+    fn cells(values: &[(u32, &str)]) -> Vec<(u32, String)> {
+        values.iter().map(|(col, value)| (*col, value.to_string())).collect()
+    }
+
+    #[test]
+    fn detects_and_lists_sheets() {
+        let data = ods(r#"<table:table table:name="Um"/><table:table table:name="Dois &amp; três"><table:table-row/></table:table>"#);
+        let zip = ZipArchive::new(&data).unwrap();
+        assert!(Ods::is_ods(&zip).unwrap());
+        assert_eq!(Ods::new(&zip).unwrap().sheet_names(), ["Dois & três"]);
+
+        let other = archive(&[("mimetype", b"application/zip")]);
+        assert!(!Ods::is_ods(&ZipArchive::new(&other).unwrap()).unwrap());
+        let empty = archive(&[("mimetype", MIMETYPE)]);
+        assert_eq!(Ods::new(&ZipArchive::new(&empty).unwrap()).err().unwrap(), "not a spreadsheet: the ODS file has no content.xml");
+    }
+
+    #[test]
+    fn expands_repeated_rows_and_columns() {
+        let data = ods(r#"<table:table table:name="Dados">
+            <table:table-row>
+                <table:table-cell office:value-type="string"><text:p>a</text:p></table:table-cell>
+                <table:table-cell table:number-columns-repeated="2"/>
+                <table:table-cell office:value-type="float" office:value="7" table:number-columns-repeated="3"/>
+            </table:table-row>
+            <table:table-row table:number-rows-repeated="3"/>
+            <table:table-row table:number-rows-repeated="2">
+                <table:table-cell table:number-columns-repeated="2"><text:p>x</text:p></table:table-cell>
+            </table:table-row>
+            <table:table-row table:number-rows-repeated="1048570">
+                <table:table-cell table:number-columns-repeated="16384"/>
+            </table:table-row>
+            <table:table-row><table:covered-table-cell/><table:table-cell><text:p>fim</text:p></table:table-cell></table:table-row>
+        </table:table>"#);
+        assert_eq!(
+            rows(&data, 0),
+            [
+                (0, cells(&[(0, "a"), (3, "7"), (4, "7"), (5, "7")])),
+                (4, cells(&[(0, "x"), (1, "x")])),
+                (5, cells(&[(0, "x"), (1, "x")])),
+                (1_048_576, cells(&[(1, "fim")])),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_values_by_type() {
+        let data = ods(r#"<table:table table:name="Tipos"><table:table-row>
+            <table:table-cell office:value-type="float" office:value="0.30000000000000004"><text:p>0,30</text:p></table:table-cell>
+            <table:table-cell office:value-type="percentage" office:value="0.15"><text:p>15%</text:p></table:table-cell>
+            <table:table-cell office:value-type="date" office:date-value="2024-01-15T00:00:00"><text:p>15/01/24</text:p></table:table-cell>
+            <table:table-cell office:value-type="date" office:date-value="2024-01-15T10:30:00"/>
+            <table:table-cell office:value-type="time" office:time-value="PT10H05M30.5S"/>
+            <table:table-cell office:value-type="boolean" office:boolean-value="true"><text:p>VERDADEIRO</text:p></table:table-cell>
+            <table:table-cell office:value-type="string">
+                <text:p>a<text:s text:c="2"/>b<text:tab/>c</text:p><text:p>d<text:line-break/>e</text:p>
+                <office:annotation><text:p>nota</text:p></office:annotation>
+            </table:table-cell>
+        </table:table-row></table:table>"#);
+        assert_eq!(
+            rows(&data, 0)[0].1,
+            cells(&[
+                (0, "0.3"),
+                (1, "0.15"),
+                (2, "2024-01-15"),
+                (3, "2024-01-15T10:30:00"),
+                (4, "10:05:30"),
+                (5, "true"),
+                (6, "a  b\tc\nd\ne"),
+            ])
+        );
+    }
+}
@@ -0,0 +1,513 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::dedup::{DedupConfig, DedupMode, Keep};
+use crate::encoding::Encoding;
+use crate::errors::ConfigError;
+use crate::hashing::{HashAlgorithm, HashConfig, HashEncoding};
+use crate::schema::CompiledSchema;
+use crate::json::{ArrayMode, JsonConfig};
+use crate::spreadsheet::{CellRange, SheetConfig, SheetRef};
+use crate::stats::StatsConfig;
+use crate::values::{TypeConfig, TypeMode};
+use crate::CsvSchema;
+
+// Quais campos têm espaços removidos antes da validação
+#[derive(Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Trim {
+    #[default]
+    None,
+    Headers,
+    Fields,
+    All,
+}
+
+impl Trim {
+    pub(crate) fn headers(self) -> bool {
+        matches!(self, Trim::Headers | Trim::All)
+    }
+
+    pub(crate) fn fields(self) -> bool {
+        matches!(self, Trim::Fields | Trim::All)
+    }
+}
+
+// O que fazer com linhas que têm mais ou menos campos que o cabeçalho.
+// `flexible` no dialeto continua aceitando essas linhas como vierem.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum FieldCount {
+    // Rejeita a linha com "expected N fields, got M", sem os valores
+    #[default]
+    Strict,
+    // Rejeita com "expected N fields, got M", guardando os campos a mais
+    Report,
+    // Completa linhas curtas com campos vazios; longas são rejeitadas como em Report
+    Pad,
+    // Completa linhas curtas e guarda os campos a mais em `_extra`
+    Extra,
+}
+
+// Dialeto do CSV. É devolvido junto com o resultado para indicar como o
+// arquivo foi lido.
+#[derive(Clone, Serialize)]
+pub(crate) struct Dialect {
+    pub(crate) delimiter: char,
+    pub(crate) quote: char,
+    pub(crate) escape: Option<char>,
+    pub(crate) double_quote: bool,
+    pub(crate) comment: Option<char>,
+    pub(crate) has_headers: bool,
+    pub(crate) trim: Trim,
+    pub(crate) flexible: bool,
+    // None detecta a codificação a partir do conteúdo
+    pub(crate) encoding: Option<Encoding>,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect {
+            delimiter: ',',
+            quote: '"',
+            escape: None,
+            double_quote: true,
+            comment: None,
+            has_headers: true,
+            trim: Trim::None,
+            flexible: false,
+            encoding: None,
+        }
+    }
+}
+
+impl Dialect {
+    pub(crate) fn reader(&self) -> csv_core::Reader {
+        csv_core::ReaderBuilder::new()
+            .delimiter(self.delimiter as u8)
+            .quote(self.quote as u8)
+            .escape(self.escape.map(|c| c as u8))
+            .double_quote(self.double_quote)
+            .comment(self.comment.map(|c| c as u8))
+            .build()
+    }
+}
+
+// Opções de processamento expostas ao JavaScript: o dialeto do arquivo, o
+// hash das linhas, a deduplicação, a conversão de tipos e, opcionalmente, o
+// schema compilado
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct CsvOptions {
+    pub(crate) dialect: Dialect,
+    pub(crate) schema: Option<Arc<CompiledSchema>>,
+    pub(crate) hash: HashConfig,
+    pub(crate) dedup: DedupConfig,
+    pub(crate) types: TypeConfig,
+    pub(crate) limits: Limits,
+    pub(crate) field_count: FieldCount,
+    pub(crate) stats: StatsConfig,
+    // Planilha e intervalo lidos de um .xlsx, .xls ou .ods
+    pub(crate) sheet: SheetConfig,
+    // Achatamento dos objetos aninhados de uma entrada JSON ou NDJSON
+    pub(crate) json: JsonConfig,
+    // Só no build nativo: valida e calcula os hashes em uma única thread
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) sequential: bool,
+}
+
+// Limites de tamanho da entrada; acima deles o processamento para com um
+// erro fatal `limit_exceeded`
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Limits {
+    pub(crate) max_rows: Option<u64>,
+    pub(crate) max_record_bytes: Option<usize>,
+}
+
+// O csv_core trabalha com bytes, então os caracteres especiais precisam ser ASCII
+fn ascii(name: &str, c: char) -> Result<char, String> {
+    if c.is_ascii() {
+        Ok(c)
+    } else {
+        Err(format!("{} must be an ASCII character, got '{}'", name, c))
+    }
+}
+
+#[wasm_bindgen]
+impl CsvOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CsvOptions {
+        CsvOptions::default()
+    }
+
+    pub fn set_delimiter(&mut self, delimiter: char) -> Result<(), ConfigError> {
+        self.dialect.delimiter = ascii("delimiter", delimiter).map_err(ConfigError::new)?;
+        Ok(())
+    }
+
+    pub fn set_quote(&mut self, quote: char) -> Result<(), ConfigError> {
+        self.dialect.quote = ascii("quote", quote).map_err(ConfigError::new)?;
+        Ok(())
+    }
+
+    // Caractere de escape dentro de campos entre aspas, como `\"`.
+    // `undefined` desativa.
+    pub fn set_escape(&mut self, escape: Option<char>) -> Result<(), ConfigError> {
+        self.dialect.escape = escape.map(|c| ascii("escape", c)).transpose().map_err(ConfigError::new)?;
+        Ok(())
+    }
+
+    // Se `""` dentro de um campo entre aspas representa uma aspa literal
+    pub fn set_double_quote(&mut self, double_quote: bool) {
+        self.dialect.double_quote = double_quote;
+    }
+
+    // Linhas que começam com este caractere são ignoradas. `undefined` desativa.
+    pub fn set_comment(&mut self, comment: Option<char>) -> Result<(), ConfigError> {
+        self.dialect.comment = comment.map(|c| ascii("comment", c)).transpose().map_err(ConfigError::new)?;
+        Ok(())
+    }
+
+    // Sem cabeçalho, as colunas recebem os nomes column_1, column_2, ...
+    pub fn set_has_headers(&mut self, has_headers: bool) {
+        self.dialect.has_headers = has_headers;
+    }
+
+    // "none", "headers", "fields" ou "all"
+    pub fn set_trim(&mut self, trim: &str) -> Result<(), ConfigError> {
+        self.dialect.trim = match trim {
+            "none" => Trim::None,
+            "headers" => Trim::Headers,
+            "fields" => Trim::Fields,
+            "all" => Trim::All,
+            other => return Err(ConfigError::new(format!("unknown trim mode '{}'", other))),
+        };
+        Ok(())
+    }
+
+    // Aceita linhas com quantidade de campos diferente do cabeçalho
+    pub fn set_flexible(&mut self, flexible: bool) {
+        self.dialect.flexible = flexible;
+    }
+
+    // "strict" (padrão), "report", "pad" ou "extra": o que fazer com linhas
+    // com quantidade de campos diferente do cabeçalho
+    pub fn set_field_count(&mut self, mode: &str) -> Result<(), ConfigError> {
+        self.field_count = match mode {
+            "strict" => FieldCount::Strict,
+            "report" => FieldCount::Report,
+            "pad" => FieldCount::Pad,
+            "extra" => FieldCount::Extra,
+            other => return Err(ConfigError::new(format!("unknown field count mode '{}'", other))),
+        };
+        Ok(())
+    }
+
+    // "auto" (padrão), "utf-8", "utf-16le", "utf-16be" ou "windows-1252".
+    // Só tem efeito nas entradas em bytes.
+    pub fn set_encoding(&mut self, encoding: &str) -> Result<(), ConfigError> {
+        self.dialect.encoding = match encoding {
+            "auto" => None,
+            label => match Encoding::from_label(label) {
+                Some(encoding) => Some(encoding),
+                None => return Err(ConfigError::new(format!("unknown encoding '{}'", label))),
+            },
+        };
+        Ok(())
+    }
+
+    pub fn set_schema(&mut self, schema: &CsvSchema) {
+        self.schema = Some(schema.compiled());
+    }
+
+    // "sha256" (padrão), "sha512", "blake3" ou "xxh3"
+    pub fn set_hash_algorithm(&mut self, algorithm: &str) -> Result<(), ConfigError> {
+        let algorithm = HashAlgorithm::from_name(algorithm)
+            .ok_or_else(|| ConfigError::new(format!("unknown hash algorithm '{}'", algorithm)))?;
+        if algorithm == HashAlgorithm::Xxh3 && self.hash.key.is_some() {
+            return Err(ConfigError::new("xxh3 is not a cryptographic hash and cannot be keyed"));
+        }
+        self.hash.algorithm = algorithm;
+        Ok(())
+    }
+
+    // "joined" (padrão), "canonical", "length_prefixed" ou "json"
+    pub fn set_hash_encoding(&mut self, encoding: &str) -> Result<(), ConfigError> {
+        self.hash.encoding = HashEncoding::from_name(encoding)
+            .ok_or_else(|| ConfigError::new(format!("unknown hash encoding '{}'", encoding)))?;
+        Ok(())
+    }
+
+    // Colunas usadas no hash, nessa ordem. Uma lista vazia volta a usar todas.
+    pub fn set_hash_columns(&mut self, columns: Vec<String>) {
+        self.hash.columns = if columns.is_empty() { None } else { Some(columns) };
+    }
+
+    // Chave secreta: o hash passa a ser um HMAC, que só quem tem a chave
+    // consegue reproduzir
+    pub fn set_hash_key(&mut self, key: &[u8]) -> Result<(), ConfigError> {
+        if self.hash.algorithm == HashAlgorithm::Xxh3 {
+            return Err(ConfigError::new("xxh3 is not a cryptographic hash and cannot be keyed"));
+        }
+        self.hash.key = Some(key.to_vec());
+        Ok(())
+    }
+
+    // "off" (padrão), "flag" para marcar as repetições como erro ou "drop"
+    // para descartá-las
+    pub fn set_dedup(&mut self, mode: &str) -> Result<(), ConfigError> {
+        self.dedup.mode = match mode {
+            "off" => DedupMode::Off,
+            "flag" => DedupMode::Flag,
+            "drop" => DedupMode::Drop,
+            other => return Err(ConfigError::new(format!("unknown dedup mode '{}'", other))),
+        };
+        Ok(())
+    }
+
+    // "first" (padrão) ou "last": qual ocorrência continua valendo. Com
+    // "last", as linhas válidas só são entregues no fim do arquivo.
+    pub fn set_dedup_keep(&mut self, keep: &str) -> Result<(), ConfigError> {
+        self.dedup.keep = match keep {
+            "first" => Keep::First,
+            "last" => Keep::Last,
+            other => return Err(ConfigError::new(format!("unknown dedup keep '{}'", other))),
+        };
+        Ok(())
+    }
+
+    // Colunas que identificam uma linha repetida, como ["cpf"]. Uma lista
+    // vazia volta a comparar todas as colunas.
+    pub fn set_dedup_key(&mut self, columns: Vec<String>) {
+        self.dedup.key = if columns.is_empty() { None } else { Some(columns) };
+    }
+
+    // "off" (padrão) mantém todos os valores como string; "schema" converte
+    // as colunas int, decimal, date e bool do schema; "infer" também deduz o
+    // tipo das colunas fora do schema
+    pub fn set_types(&mut self, mode: &str) -> Result<(), ConfigError> {
+        self.types.mode = match mode {
+            "off" => TypeMode::Off,
+            "schema" => TypeMode::Schema,
+            "infer" => TypeMode::Infer,
+            other => return Err(ConfigError::new(format!("unknown types mode '{}'", other))),
+        };
+        Ok(())
+    }
+
+    // Separador decimal dos números convertidos: '.' ou ','. `undefined`
+    // decide por valor, com o último separador sendo o decimal.
+    pub fn set_decimal_separator(&mut self, separator: Option<char>) -> Result<(), ConfigError> {
+        self.types.decimal_separator = match separator {
+            None | Some('.') | Some(',') => separator,
+            Some(other) => {
+                return Err(ConfigError::new(format!("decimal separator must be '.' or ',', got '{}'", other)))
+            }
+        };
+        Ok(())
+    }
+
+    // Quantidade máxima de linhas de dados; `undefined` não limita
+    pub fn set_max_rows(&mut self, max_rows: Option<u32>) {
+        self.limits.max_rows = max_rows.map(u64::from);
+    }
+
+    // Tamanho máximo de um registro, em bytes. Evita que um campo com aspas
+    // sem fechamento leve o arquivo inteiro para a memória.
+    pub fn set_max_record_bytes(&mut self, max_record_bytes: Option<u32>) {
+        self.limits.max_record_bytes = max_record_bytes.map(|max| max as usize);
+    }
+
+    // Inclui no resultado o bloco `stats`, com o perfil de cada coluna
+    pub fn set_stats(&mut self, enabled: bool) {
+        self.stats.enabled = enabled;
+    }
+
+    // Quantos valores mais frequentes de cada coluna entram em `stats` (padrão 10)
+    pub fn set_stats_top_k(&mut self, top_k: u32) {
+        self.stats.top_k = top_k as usize;
+    }
+
+    // Planilha lida de um .xlsx, .xls ou .ods, pelo nome da aba (padrão: a primeira)
+    pub fn set_sheet(&mut self, name: &str) {
+        self.sheet.sheet = Some(SheetRef::Name(name.to_string()));
+    }
+
+    // Planilha pela posição da aba, a partir de 0
+    pub fn set_sheet_index(&mut self, index: u32) {
+        self.sheet.sheet = Some(SheetRef::Index(index as usize));
+    }
+
+    // Intervalo de células lido da planilha, como "B3:F200", "A:D" ou "B3";
+    // a primeira linha do intervalo é o cabeçalho
+    pub fn set_range(&mut self, range: &str) -> Result<(), ConfigError> {
+        self.sheet.range = Some(
+            CellRange::parse(range)
+                .ok_or_else(|| ConfigError::new(format!("invalid range '{}': expected e.g. 'B3:F200', 'A:D' or 'B3'", range)))?,
+        );
+        Ok(())
+    }
+
+    // Separador entre as chaves de objetos JSON aninhados (padrão "."):
+    // {"endereco": {"cidade": ...}} vira a coluna "endereco.cidade"
+    pub fn set_json_separator(&mut self, separator: &str) -> Result<(), ConfigError> {
+        if separator.is_empty() {
+            return Err(ConfigError::new("JSON key separator must not be empty"));
+        }
+        self.json.separator = separator.to_string();
+        Ok(())
+    }
+
+    // Quantos níveis de objetos aninhados são achatados; os mais fundos ficam
+    // como texto JSON. `None` (padrão) achata todos; 0 não achata nenhum.
+    pub fn set_json_max_depth(&mut self, depth: Option<u32>) {
+        self.json.max_depth = depth.map(|depth| depth as usize);
+    }
+
+    // Arrays em uma entrada JSON: "json" (padrão) guarda o array como texto
+    // JSON em um campo; "index" cria um campo por item ("telefones.0", ...)
+    pub fn set_json_arrays(&mut self, mode: &str) -> Result<(), ConfigError> {
+        self.json.arrays = match mode {
+            "json" => ArrayMode::Json,
+            "index" => ArrayMode::Index,
+            other => {
+                return Err(ConfigError::new(format!("invalid JSON array mode '{}': expected 'json' or 'index'", other)))
+            }
+        };
+        Ok(())
+    }
+}
+
+impl CsvOptions {
+    // No build nativo, arquivos grandes são validados em paralelo (padrão),
+    // com o mesmo resultado e a mesma ordem do processamento sequencial
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.sequential = !parallel;
+    }
+
+    // Entradas que já chegam como string do JavaScript são sempre UTF-8
+    pub(crate) fn utf8_input(mut self) -> CsvOptions {
+        self.dialect.encoding = Some(Encoding::Utf8);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(result: Result<(), ConfigError>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn dialect_characters_must_be_ascii() {
+        let mut options = CsvOptions::new();
+        options.set_delimiter(';').unwrap();
+        options.set_escape(Some('\\')).unwrap();
+        options.set_comment(Some('#')).unwrap();
+        assert_eq!(message(options.set_delimiter('§')), "delimiter must be an ASCII character, got '§'");
+        assert_eq!(message(options.set_quote('“')), "quote must be an ASCII character, got '“'");
+        assert_eq!(message(options.set_escape(Some('´'))), "escape must be an ASCII character, got '´'");
+        assert_eq!(message(options.set_comment(Some('¶'))), "comment must be an ASCII character, got '¶'");
+        // Um valor recusado não muda o que já estava configurado
+        assert_eq!(
+            (options.dialect.delimiter, options.dialect.escape, options.dialect.comment),
+            (';', Some('\\'), Some('#'))
+        );
+        options.set_escape(None).unwrap();
+        assert_eq!(options.dialect.escape, None);
+    }
+
+    #[test]
+    fn modes_are_checked_by_name() {
+        let mut options = CsvOptions::new();
+        options.set_trim("all").unwrap();
+        assert!(options.dialect.trim.headers() && options.dialect.trim.fields());
+        options.set_trim("headers").unwrap();
+        assert!(options.dialect.trim.headers() && !options.dialect.trim.fields());
+
+        assert_eq!(message(options.set_trim("both")), "unknown trim mode 'both'");
+        assert_eq!(message(options.set_field_count("loose")), "unknown field count mode 'loose'");
+        assert_eq!(message(options.set_dedup("keep")), "unknown dedup mode 'keep'");
+        assert_eq!(message(options.set_dedup_keep("middle")), "unknown dedup keep 'middle'");
+        assert_eq!(message(options.set_types("all")), "unknown types mode 'all'");
+        assert_eq!(message(options.set_hash_algorithm("md5")), "unknown hash algorithm 'md5'");
+        assert_eq!(message(options.set_hash_encoding("csv")), "unknown hash encoding 'csv'");
+        assert_eq!(
+            message(options.set_json_arrays("flat")),
+            "invalid JSON array mode 'flat': expected 'json' or 'index'"
+        );
+    }
+
+    #[test]
+    fn encodings_and_decimal_separators() {
+        let mut options = CsvOptions::new();
+        options.set_encoding("latin1").unwrap();
+        assert!(options.dialect.encoding == Some(Encoding::Windows1252));
+        options.set_encoding("UTF-16").unwrap();
+        assert!(options.dialect.encoding == Some(Encoding::Utf16Le));
+        options.set_encoding("auto").unwrap();
+        assert!(options.dialect.encoding.is_none());
+        assert_eq!(message(options.set_encoding("ebcdic")), "unknown encoding 'ebcdic'");
+        // Entradas em string são sempre UTF-8, qualquer que seja a opção
+        assert!(options.clone().utf8_input().dialect.encoding == Some(Encoding::Utf8));
+
+        options.set_decimal_separator(Some(',')).unwrap();
+        options.set_decimal_separator(None).unwrap();
+        assert_eq!(
+            message(options.set_decimal_separator(Some(';'))),
+            "decimal separator must be '.' or ',', got ';'"
+        );
+        assert_eq!(options.types.decimal_separator, None);
+    }
+
+    #[test]
+    fn xxh3_cannot_be_keyed() {
+        let expected = "xxh3 is not a cryptographic hash and cannot be keyed";
+        let mut options = CsvOptions::new();
+        options.set_hash_key(b"segredo").unwrap();
+        assert_eq!(message(options.set_hash_algorithm("xxh3")), expected);
+        assert!(options.hash.algorithm == HashAlgorithm::Sha256);
+
+        let mut options = CsvOptions::new();
+        options.set_hash_algorithm("xxh3").unwrap();
+        assert_eq!(message(options.set_hash_key(b"segredo")), expected);
+        assert!(options.hash.key.is_none());
+    }
+
+    #[test]
+    fn empty_column_lists_mean_all_columns() {
+        let mut options = CsvOptions::new();
+        options.set_hash_columns(vec!["cpf".to_string()]);
+        options.set_dedup_key(vec!["cpf".to_string()]);
+        assert_eq!(options.hash.columns.as_deref(), Some(&["cpf".to_string()][..]));
+        options.set_hash_columns(Vec::new());
+        options.set_dedup_key(Vec::new());
+        assert!(options.hash.columns.is_none() && options.dedup.key.is_none());
+    }
+
+    #[test]
+    fn limits_ranges_and_json() {
+        let mut options = CsvOptions::new();
+        options.set_max_rows(Some(10));
+        options.set_max_record_bytes(Some(1024));
+        assert_eq!((options.limits.max_rows, options.limits.max_record_bytes), (Some(10), Some(1024)));
+        options.set_max_rows(None);
+        assert_eq!(options.limits.max_rows, None);
+
+        options.set_range("B3:F200").unwrap();
+        assert_eq!(
+            message(options.set_range("3B")),
+            "invalid range '3B': expected e.g. 'B3:F200', 'A:D' or 'B3'"
+        );
+
+        options.set_json_separator("__").unwrap();
+        assert_eq!(message(options.set_json_separator("")), "JSON key separator must not be empty");
+        assert_eq!(options.json.separator, "__");
+        options.set_json_max_depth(Some(0));
+        assert_eq!(options.json.max_depth, Some(0));
+    }
+}
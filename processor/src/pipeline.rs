@@ -0,0 +1,1010 @@
+use std::sync::Arc;
+
+use crate::dedup::{DedupConfig, DedupMode, Deduplicator, Occurrence};
+use crate::encoding::{Encoding, Transcoder};
+use crate::errors::{ErrorCode, FatalError, FatalErrorKind, Severity};
+use crate::hashing::{HashConfig, RowHasher};
+use crate::integrity::FileValidator;
+use crate::options::{Dialect, FieldCount, Limits};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::reader::OwnedRecord;
+use crate::reader::{Position, RawRecord, RecordReader};
+use crate::expr::Row;
+use crate::schema::{resolve_headers, CompiledSchema, RowFailure, RuleFailure};
+use crate::stats::{Profiler, Stats, StatsConfig};
+use crate::values::{self, TypeConfig, TypeMode};
+use crate::{CsvOptions, ProcessedRow, ProcessingResult, ProcessingTotals, ValidationError};
+
+// Leitura completa de um CSV: conversão para UTF-8, separação dos registros e
+// processamento de cada linha. Usada tanto para o arquivo inteiro quanto em
+// streaming.
+pub(crate) struct CsvPipeline {
+    transcoder: Transcoder,
+    reader: RecordReader,
+    rows: RowProcessor,
+    bytes_processed: u64,
+    #[cfg(not(target_arch = "wasm32"))]
+    parallel: bool,
+}
+
+// Chave de `data` com os campos além das colunas do cabeçalho
+pub(crate) const EXTRA_FIELDS: &str = "_extra";
+
+// Registros avaliados de uma vez em paralelo; abaixo do mínimo, o custo de
+// dividir o trabalho entre as threads não compensa
+#[cfg(not(target_arch = "wasm32"))]
+const PARALLEL_BATCH: usize = 4096;
+#[cfg(not(target_arch = "wasm32"))]
+const PARALLEL_MIN: usize = 256;
+
+impl CsvPipeline {
+    pub(crate) fn new(options: &CsvOptions) -> Self {
+        CsvPipeline {
+            transcoder: Transcoder::new(options.dialect.encoding),
+            reader: RecordReader::new(
+                options.dialect.reader(),
+                options.dialect.comment.map(|c| c as u8),
+                options.limits.max_record_bytes,
+            ),
+            rows: RowProcessor::new(options),
+            bytes_processed: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            parallel: !options.sequential,
+        }
+    }
+
+    // Pipeline de registros que não vêm de um CSV (planilha, JSON): chegam já
+    // separados em campos por `push_fields`, com a linha informada por quem
+    // leu. Sem `byte_offsets`, os erros ficam sem a posição em bytes.
+    pub(crate) fn for_records(options: &CsvOptions, bytes: usize, byte_offsets: bool) -> Self {
+        let mut pipeline = CsvPipeline::new(options);
+        pipeline.rows.record_lines = true;
+        pipeline.rows.byte_offsets = byte_offsets;
+        pipeline.bytes_processed = bytes as u64;
+        pipeline
+    }
+
+    pub(crate) fn totals(&self) -> ProcessingTotals {
+        ProcessingTotals {
+            total_rows: self.rows.rows_seen,
+            valid_rows: self.rows.valid_rows,
+            invalid_rows: self.rows.invalid_rows(),
+            duplicate_rows: self.rows.duplicate_rows,
+            bytes_processed: self.bytes_processed,
+        }
+    }
+
+    // Processa um pedaço da entrada. Depois de um erro fatal, o restante da
+    // entrada é ignorado e o mesmo erro é devolvido de novo.
+    pub(crate) fn push(&mut self, chunk: &[u8], out: &mut ProcessingResult) -> Result<(), FatalError> {
+        self.check()?;
+        self.bytes_processed += chunk.len() as u64;
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.parallel {
+            self.push_parallel(chunk, out);
+            return self.check();
+        }
+        let reader = &mut self.reader;
+        let rows = &mut self.rows;
+        self.transcoder.feed(chunk, |utf8, encoding| {
+            reader.set_encoding(encoding);
+            reader.feed(utf8, |record| rows.handle_record(record, out));
+        });
+        self.check()
+    }
+
+    // Processa um registro já separado em campos, como uma linha de planilha
+    pub(crate) fn push_fields(&mut self, fields: &[String], position: Position, out: &mut ProcessingResult) -> Result<(), FatalError> {
+        self.check()?;
+        let mut data = Vec::new();
+        let mut ends = Vec::with_capacity(fields.len());
+        for field in fields {
+            data.extend_from_slice(field.as_bytes());
+            ends.push(data.len());
+        }
+        self.rows.handle_record(RawRecord::new(&data, &ends, position), out);
+        self.check()
+    }
+
+    // Registro que não pôde ser lido, como uma linha de NDJSON inválida: conta
+    // como linha rejeitada, sem passar pela validação
+    pub(crate) fn reject(&mut self, error: ValidationError, out: &mut ProcessingResult) -> Result<(), FatalError> {
+        self.check()?;
+        self.rows.reject(error, out);
+        self.check()
+    }
+
+    // Encerra a entrada e informa em `out` o dialeto efetivamente usado
+    pub(crate) fn finish(&mut self, out: &mut ProcessingResult) -> Result<(), FatalError> {
+        self.check()?;
+        let reader = &mut self.reader;
+        let rows = &mut self.rows;
+        self.transcoder.finish(|utf8, encoding| {
+            reader.set_encoding(encoding);
+            reader.feed(utf8, |record| rows.handle_record(record, out));
+        });
+        reader.finish(|record| rows.handle_record(record, out));
+        rows.finish(out);
+        if self.rows.stats.enabled {
+            out.stats = Some(self.rows.stats(self.totals()));
+        }
+
+        // Sem CSV, não há dialeto para informar
+        if self.rows.record_lines {
+            return self.check();
+        }
+        let mut dialect = self.rows.dialect.clone();
+        // Entrada só com ASCII é lida igual em UTF-8
+        dialect.encoding = Some(self.transcoder.encoding().unwrap_or(Encoding::Utf8));
+        out.dialect = Some(dialect);
+        self.check()
+    }
+
+    // Separa os registros em sequência, avalia lotes deles em paralelo e
+    // aplica os resultados na ordem do arquivo. A numeração das linhas, a
+    // deduplicação e os totais ficam nas partes sequenciais, então o
+    // resultado é idêntico ao do processamento em uma thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn push_parallel(&mut self, chunk: &[u8], out: &mut ProcessingResult) {
+        let reader = &mut self.reader;
+        let rows = &mut self.rows;
+        let mut pending = Vec::new();
+        self.transcoder.feed(chunk, |utf8, encoding| {
+            reader.set_encoding(encoding);
+            reader.feed(utf8, |record| {
+                if let Some(number) = rows.begin_record(&record, out) {
+                    pending.push((record.to_owned(), number));
+                    if pending.len() >= PARALLEL_BATCH {
+                        rows.evaluate_batch(&mut pending, out);
+                    }
+                }
+            });
+        });
+        rows.evaluate_batch(&mut pending, out);
+    }
+
+    fn check(&mut self) -> Result<(), FatalError> {
+        if let (Some(position), None) = (self.reader.exceeded(), &self.rows.fatal) {
+            self.rows.fatal = Some(FatalError::new(
+                FatalErrorKind::LimitExceeded,
+                format!("record is larger than max_record_bytes ({})", self.rows.limits.max_record_bytes.unwrap_or(0)),
+                position.line,
+                position.byte,
+            ));
+        }
+        match &self.rows.fatal {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+// Resultado da avaliação de um registro de dados
+pub(crate) enum Outcome {
+    // Linha rejeitada, com um erro por problema encontrado
+    Rejected(Vec<ValidationError>),
+    Valid {
+        row: ProcessedRow,
+        // Regras com severidade "warning" que falharam
+        warnings: Vec<ValidationError>,
+        // Chave da deduplicação, quando ativa
+        fingerprint: Option<u128>,
+        fields: Option<Vec<String>>,
+        // Valores validados, com os padrões aplicados, para as verificações
+        // do arquivo inteiro
+        checked: Option<Vec<String>>,
+    },
+}
+
+// Estado do processamento linha a linha, compartilhado entre a função que
+// recebe o arquivo inteiro e o processador em streaming
+pub(crate) struct RowProcessor {
+    dialect: Dialect,
+    headers: Option<Vec<String>>,
+    schema: Option<Arc<CompiledSchema>>,
+    hash: HashConfig,
+    types: TypeConfig,
+    // Criado junto com o cabeçalho, quando as colunas do hash são conhecidas
+    hasher: Option<RowHasher>,
+    dedup: DedupConfig,
+    deduplicator: Option<Deduplicator>,
+    // Posição no cabeçalho das colunas usadas pelas regras de linha do schema
+    rule_slots: Vec<usize>,
+    // Verificações do arquivo inteiro; criado junto com o cabeçalho
+    file: Option<FileValidator>,
+    // Registros trailer, que não contam como linhas de dados
+    trailer_rows: u64,
+    stats: StatsConfig,
+    // Perfil das colunas; criado junto com o cabeçalho
+    profiler: Option<Profiler>,
+    limits: Limits,
+    field_count: FieldCount,
+    // Erro que interrompeu o processamento; os registros seguintes são ignorados
+    fatal: Option<FatalError>,
+    // Registros que não vêm de um CSV: a linha vem da posição do registro e
+    // a posição em bytes só vale com `byte_offsets`
+    record_lines: bool,
+    byte_offsets: bool,
+    rows_seen: u64,
+    valid_rows: u64,
+    duplicate_rows: u64,
+}
+
+impl RowProcessor {
+    pub(crate) fn new(options: &CsvOptions) -> Self {
+        RowProcessor {
+            dialect: options.dialect.clone(),
+            headers: None,
+            schema: options.schema.clone(),
+            hash: options.hash.clone(),
+            types: options.types,
+            hasher: None,
+            dedup: options.dedup.clone(),
+            deduplicator: None,
+            rule_slots: Vec::new(),
+            file: None,
+            trailer_rows: 0,
+            stats: options.stats,
+            profiler: None,
+            limits: options.limits,
+            field_count: options.field_count,
+            fatal: None,
+            record_lines: false,
+            byte_offsets: true,
+            rows_seen: 0,
+            valid_rows: 0,
+            duplicate_rows: 0,
+        }
+    }
+
+    // Linhas que viraram erro; repetições descartadas não contam
+    fn invalid_rows(&self) -> u64 {
+        let dropped = match self.dedup.mode {
+            DedupMode::Drop => self.duplicate_rows,
+            _ => 0,
+        };
+        self.rows_seen - self.valid_rows - dropped
+    }
+
+    // Entrega as linhas retidas pela deduplicação que mantém a última
+    // ocorrência e faz as verificações que dependem do arquivo inteiro
+    pub(crate) fn finish(&mut self, out: &mut ProcessingResult) {
+        if let Some(deduplicator) = &mut self.deduplicator {
+            out.processed_rows.extend(deduplicator.release());
+        }
+        if let (Some(schema), Some(file), Some(headers)) = (&self.schema, &mut self.file, &self.headers) {
+            if let Some(rules) = schema.file_rules() {
+                file.finish(rules, headers, &mut out.file_errors);
+            }
+        }
+    }
+
+    // Bloco `stats`; sem cabeçalho lido, só os totais
+    fn stats(&self, totals: ProcessingTotals) -> Stats {
+        match &self.profiler {
+            Some(profiler) => profiler.finish(totals),
+            None => Stats { totals, columns: Vec::new() },
+        }
+    }
+
+    // Prepara as regras, o hash e a deduplicação para o cabeçalho. Uma coluna
+    // do hash ou da chave de deduplicação que não está no cabeçalho é um erro.
+    fn bind_headers(&mut self, headers: &[String]) -> Result<(), String> {
+        if let Some(schema) = &self.schema {
+            self.rule_slots = schema.rule_slots(headers);
+            self.file = schema
+                .file_rules()
+                .map(|rules| FileValidator::new(rules, headers, self.types.decimal_separator));
+        }
+        if self.stats.enabled {
+            self.profiler = Some(Profiler::new(&self.stats, headers, self.types.decimal_separator));
+        }
+        if !self.stats.only {
+            self.hasher = Some(RowHasher::new(&self.hash, headers)?);
+            self.deduplicator = Deduplicator::new(&self.dedup, headers)?;
+        }
+        Ok(())
+    }
+
+    // Define as colunas a partir dos nomes lidos, com um aviso para cada nome
+    // repetido que foi renomeado
+    fn set_headers(&mut self, names: Vec<String>, position: Position, out: &mut ProcessingResult) -> Result<(), String> {
+        let (headers, renamed) = resolve_headers(self.schema.as_deref(), names.clone())?;
+        self.bind_headers(&headers)?;
+        let warnings = renamed.into_iter().map(|(name, renamed)| renamed_header(position, name, renamed)).collect();
+        out.errors.extend(self.located(warnings));
+        out.headers = Some(headers.clone());
+        out.source_headers = Some(names);
+        self.headers = Some(headers);
+        Ok(())
+    }
+
+    // Linha informada nos resultados do registro de dados `number`: a contagem
+    // dos registros, ou a posição dada por quem leu o registro
+    fn line_of(&self, record: &RawRecord<'_>, number: u64) -> u64 {
+        if self.record_lines {
+            record.position.line
+        } else {
+            number + u64::from(self.dialect.has_headers)
+        }
+    }
+
+    // Trata um registro completo: o primeiro é o cabeçalho, os demais são
+    // validados e vão para `processed_rows` ou `errors` de `out`
+    pub(crate) fn handle_record(&mut self, record: RawRecord<'_>, out: &mut ProcessingResult) {
+        if let Some(number) = self.begin_record(&record, out) {
+            let outcome = self.evaluate(&record, number);
+            self.apply(outcome, out);
+        }
+    }
+
+    // Parte sequencial do início de cada registro: lê o cabeçalho, aplica os
+    // limites e conta a linha. Devolve o número do registro de dados, ou None
+    // se não há linha para validar.
+    pub(crate) fn begin_record(&mut self, record: &RawRecord<'_>, out: &mut ProcessingResult) -> Option<u64> {
+        if self.fatal.is_some() {
+            return None;
+        }
+        let fatal = |kind, message| FatalError::new(kind, message, record.position.line, record.position.byte);
+
+        if self.headers.is_none() && self.dialect.has_headers {
+            // Um cabeçalho ilegível, com nomes repetidos ou fora das regras do
+            // schema deixaria todas as linhas sem colunas ou com colunas erradas
+            let trim = self.dialect.trim.headers();
+            let mut names: Vec<String> = Vec::with_capacity(record.len());
+            for field in record.fields() {
+                let name = match std::str::from_utf8(field) {
+                    Ok(name) if trim => name.trim(),
+                    Ok(name) => name,
+                    Err(e) => {
+                        self.fatal = Some(fatal(FatalErrorKind::InvalidUtf8, format!("header is not valid UTF-8: {}", e)));
+                        return None;
+                    }
+                };
+                names.push(name.to_string());
+            }
+            if let Err(message) = self.set_headers(names, record.position, out) {
+                self.fatal = Some(fatal(FatalErrorKind::BadHeader, message));
+            }
+            return None;
+        }
+
+        // Sem cabeçalho, o primeiro registro define a quantidade de colunas;
+        // os apelidos do schema podem dar nome a column_1, column_2...
+        if self.headers.is_none() {
+            let names = (1..=record.len()).map(|i| format!("column_{}", i)).collect();
+            if let Err(message) = self.set_headers(names, record.position, out) {
+                self.fatal = Some(fatal(FatalErrorKind::BadHeader, message));
+                return None;
+            }
+        }
+
+        // O trailer sai das linhas de dados; as demais passam pelas
+        // verificações do arquivo e pelo perfil, na ordem em que aparecem
+        let number = self.rows_seen + self.trailer_rows + 1;
+        let trim = self.dialect.trim.fields();
+        let values: Option<Vec<&str>> = (self.file.is_some() || self.profiler.is_some()).then(|| {
+            record
+                .fields()
+                .map(|f| std::str::from_utf8(f).unwrap_or(""))
+                .map(|v| if trim { v.trim() } else { v })
+                .collect()
+        });
+        let line = self.line_of(record, number);
+        if let (Some(schema), Some(file), Some(values)) = (&self.schema, &mut self.file, &values) {
+            let row = Row {
+                values,
+                slots: &self.rule_slots,
+                decimal_separator: self.types.decimal_separator,
+            };
+            if schema.is_trailer(&row) {
+                self.trailer_rows += 1;
+                file.trailer(values, line, &mut out.file_errors);
+                return None;
+            }
+            if let Some(rules) = schema.file_rules() {
+                file.count(rules, line, &mut out.file_errors);
+            }
+        }
+
+        if self.over_max_rows(record.position) {
+            return None;
+        }
+        if let (Some(profiler), Some(values)) = (&mut self.profiler, &values) {
+            profiler.record(values);
+        }
+        self.rows_seen += 1;
+        Some(number)
+    }
+
+    // Uma linha a mais passaria de `max_rows`: para com um erro fatal
+    fn over_max_rows(&mut self, position: Position) -> bool {
+        let Some(max) = self.limits.max_rows.filter(|&max| self.rows_seen >= max) else {
+            return false;
+        };
+        self.fatal = Some(FatalError::new(
+            FatalErrorKind::LimitExceeded,
+            format!("file has more than max_rows ({}) rows", max),
+            position.line,
+            position.byte,
+        ));
+        true
+    }
+
+    // Linha rejeitada antes da validação, por não ter podido ser lida
+    pub(crate) fn reject(&mut self, error: ValidationError, out: &mut ProcessingResult) {
+        if self.fatal.is_some() || self.over_max_rows(Position { line: error.line, byte: error.byte_offset.unwrap_or(0) }) {
+            return;
+        }
+        self.rows_seen += 1;
+        out.errors.extend(self.located(vec![error]));
+    }
+
+    // Valida, converte e calcula o hash de um registro de dados. Só lê o
+    // estado, então registros diferentes podem ser avaliados em paralelo.
+    pub(crate) fn evaluate(&self, record: &RawRecord<'_>, number: u64) -> Outcome {
+        let Some(headers) = &self.headers else {
+            return Outcome::Rejected(Vec::new());
+        };
+        let line_num = self.line_of(record, number);
+
+        let byte_offset = record.position.byte;
+        let raw_fields = || Some(record.fields().map(|f| String::from_utf8_lossy(f).into_owned()).collect());
+        let mismatch = record.len() != headers.len() && !self.dialect.flexible;
+        let too_long = record.len() > headers.len();
+        if mismatch {
+            match self.field_count {
+                // Sem os valores em `data`, que não se sabe a que coluna pertencem
+                FieldCount::Strict => {
+                    let mut error = field_count_error(record, headers, line_num);
+                    error.data = serde_json::Value::Null;
+                    return Outcome::Rejected(vec![error]);
+                }
+                FieldCount::Report => return Outcome::Rejected(vec![field_count_error(record, headers, line_num)]),
+                FieldCount::Pad if too_long => return Outcome::Rejected(vec![field_count_error(record, headers, line_num)]),
+                FieldCount::Pad | FieldCount::Extra => {}
+            }
+        }
+
+        let trim = self.dialect.trim.fields();
+        let mut values = match record.fields()
+            .map(std::str::from_utf8)
+            .map(|v| v.map(|v| if trim { v.trim() } else { v }))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(values) => values,
+            Err(e) => {
+                let mut error = ValidationError::new(
+                    line_num,
+                    ErrorCode::InvalidUtf8,
+                    format!("Failed to parse row: invalid UTF-8: {}", e),
+                    serde_json::Value::Null,
+                );
+                error.byte_offset = Some(byte_offset);
+                error.fields = raw_fields();
+                return Outcome::Rejected(vec![error]);
+            }
+        };
+
+        // Linhas curtas ganham campos vazios, que passam pela validação como
+        // qualquer campo vazio; os campos a mais ficam fora da validação
+        let mut extra = Vec::new();
+        if mismatch {
+            if too_long {
+                extra = values.split_off(headers.len());
+            } else {
+                values.resize(headers.len(), "");
+            }
+        }
+
+        // Campos vazios de colunas com valor padrão no schema recebem o padrão
+        // antes da validação, do hash e da deduplicação
+        if let Some(schema) = &self.schema {
+            for (h, value) in headers.iter().zip(values.iter_mut()) {
+                if value.trim().is_empty() {
+                    if let Some(default) = schema.default_for(h) {
+                        *value = default;
+                    }
+                }
+            }
+        }
+
+        let mut is_valid = true;
+        let mut failures = Vec::new();
+        let mut empty_columns = Vec::new();
+
+        // Valida cada campo: pelo schema, quando houver, ou apenas contra
+        // campos vazios. Com a conversão de tipos ativa, guarda também o valor
+        // convertido. Falhas com severidade "warning" não rejeitam a linha.
+        let mut typed = Vec::with_capacity(if self.types.enabled() { values.len() } else { 0 });
+        for (h, v) in headers.iter().zip(values.iter()) {
+            let converted = match &self.schema {
+                Some(schema) => match schema.check(h, v, &self.types) {
+                    Ok(converted) => converted,
+                    Err(failure) => {
+                        if failure.severity == Severity::Error {
+                            is_valid = false;
+                        }
+                        failures.push((h, *v, failure));
+                        None
+                    }
+                },
+                None => {
+                    if v.trim().is_empty() {
+                        is_valid = false;
+                        empty_columns.push(h.clone());
+                    }
+                    None
+                }
+            };
+            if self.types.enabled() {
+                typed.push(converted.unwrap_or_else(|| match self.types.mode {
+                    TypeMode::Infer => values::infer(v, self.types.decimal_separator),
+                    _ => serde_json::Value::String(v.to_string()),
+                }));
+            }
+        }
+
+        // Regras entre colunas, só nas linhas cujas colunas passaram, para que
+        // um valor inválido não gere também falhas nas regras que o usam
+        let row_failures = match &self.schema {
+            Some(schema) if is_valid && schema.has_row_rules() => schema.check_row(&Row {
+                values: &values,
+                slots: &self.rule_slots,
+                decimal_separator: self.types.decimal_separator,
+            }),
+            _ => Vec::new(),
+        };
+        if row_failures.iter().any(|f| f.severity == Severity::Error) {
+            is_valid = false;
+        }
+
+        // Linhas rejeitadas mantêm os valores originais, como strings
+        let json_map: serde_json::Map<String, serde_json::Value> = if is_valid && self.types.enabled() {
+            headers.iter().cloned().zip(typed).collect()
+        } else {
+            headers.iter()
+                .zip(values.iter())
+                .map(|(h, v)| (h.to_string(), serde_json::Value::String(v.to_string())))
+                .collect()
+        };
+
+        let mut json_data = serde_json::Value::Object(json_map);
+        if !extra.is_empty() {
+            json_data[EXTRA_FIELDS] = extra.into();
+        }
+
+        let rule_errors = failures
+            .into_iter()
+            .map(|(column, value, failure)| rule_error(line_num, byte_offset, column, value, failure, &json_data))
+            .chain(row_failures.into_iter().map(|failure| row_rule_error(line_num, byte_offset, failure, &json_data)));
+
+        if is_valid {
+            let warnings = rule_errors.collect();
+
+            // Gera o hash; sem hasher, no perfil, a linha é descartada
+            let hash_hex = self.hasher.as_ref().map(|h| h.hash(headers, &values)).unwrap_or_default();
+
+            let row = ProcessedRow {
+                line: line_num,
+                data: json_data,
+                hash: hash_hex,
+                record: None,
+            };
+            let fingerprint = self.deduplicator.as_ref().map(|d| d.fingerprint(&values));
+            // Campos originais, caso a linha seja marcada como repetida
+            let fields = match &self.deduplicator {
+                Some(d) if d.mode() == DedupMode::Flag => raw_fields(),
+                _ => None,
+            };
+            let checked = self.file.is_some().then(|| values.iter().map(|v| v.to_string()).collect());
+            Outcome::Valid {
+                row,
+                warnings,
+                fingerprint,
+                fields,
+                checked,
+            }
+        } else if self.schema.is_none() {
+            let mut error = ValidationError::new(
+                line_num,
+                ErrorCode::EmptyFields,
+                format!("Row contains empty fields: {}.", empty_columns.join(", ")),
+                json_data,
+            );
+            error.byte_offset = Some(byte_offset);
+            error.empty_columns = Some(empty_columns);
+            error.fields = raw_fields();
+            Outcome::Rejected(vec![error])
+        } else {
+            // Um erro por coluna que falhou, nomeando a coluna e a regra
+            Outcome::Rejected(rule_errors.map(|mut error| {
+                error.fields = raw_fields();
+                error
+            }).collect())
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn evaluate_batch(&mut self, pending: &mut Vec<(OwnedRecord, u64)>, out: &mut ProcessingResult) {
+        use rayon::prelude::*;
+
+        let evaluate = |(record, number): &(OwnedRecord, u64)| self.evaluate(&record.as_raw(), *number);
+        let outcomes: Vec<Outcome> = if pending.len() >= PARALLEL_MIN {
+            pending.par_iter().map(evaluate).collect()
+        } else {
+            pending.iter().map(evaluate).collect()
+        };
+        for outcome in outcomes {
+            self.apply(outcome, out);
+        }
+        pending.clear();
+    }
+
+    // Erros de registros sem posição em bytes, como as linhas de uma
+    // planilha, ficam sem `byte_offset`
+    fn located(&self, mut errors: Vec<ValidationError>) -> Vec<ValidationError> {
+        if !self.byte_offsets {
+            for error in &mut errors {
+                error.byte_offset = None;
+            }
+        }
+        errors
+    }
+
+    // Parte sequencial do fim de cada registro: conta as linhas válidas,
+    // aplica a deduplicação e entrega o resultado em `out`
+    pub(crate) fn apply(&mut self, outcome: Outcome, out: &mut ProcessingResult) {
+        let (row, fingerprint, fields) = match outcome {
+            Outcome::Rejected(errors) => {
+                out.errors.extend(self.located(errors));
+                return;
+            }
+            Outcome::Valid { row, warnings, fingerprint, fields, checked } => {
+                out.errors.extend(self.located(warnings));
+                if let (Some(schema), Some(file), Some(values)) = (&self.schema, &mut self.file, checked) {
+                    if let Some(rules) = schema.file_rules() {
+                        let values: Vec<&str> = values.iter().map(String::as_str).collect();
+                        file.record(rules, &values, row.line, &mut out.file_errors);
+                    }
+                }
+                (row, fingerprint, fields)
+            }
+        };
+
+        let (Some(deduplicator), Some(fingerprint)) = (&mut self.deduplicator, fingerprint) else {
+            self.valid_rows += 1;
+            out.processed_rows.push(row);
+            return;
+        };
+        match deduplicator.check(&row, fingerprint) {
+            Occurrence::Unique => {
+                self.valid_rows += 1;
+                if deduplicator.holds_rows() {
+                    deduplicator.hold(row, fields);
+                } else {
+                    out.processed_rows.push(row);
+                }
+            }
+            Occurrence::DuplicateOf(first) => {
+                self.duplicate_rows += 1;
+                if deduplicator.mode() == DedupMode::Flag {
+                    out.errors.push(duplicate_error(row, first, &self.dedup, fields));
+                }
+            }
+            // A ocorrência anterior sai e a nova fica retida em seu lugar
+            Occurrence::Replaces(previous, previous_fields) => {
+                self.duplicate_rows += 1;
+                if deduplicator.mode() == DedupMode::Flag {
+                    out.errors.push(duplicate_error(previous, row.line, &self.dedup, previous_fields));
+                }
+                deduplicator.hold(row, fields);
+            }
+        }
+    }
+}
+
+// Linha com quantidade de campos diferente do cabeçalho. `data` traz as
+// colunas presentes e os campos a mais em `_extra`.
+fn field_count_error(record: &RawRecord<'_>, headers: &[String], line: u64) -> ValidationError {
+    let fields: Vec<String> = record.fields().map(|f| String::from_utf8_lossy(f).into_owned()).collect();
+    let mut data: serde_json::Map<String, serde_json::Value> = headers
+        .iter()
+        .zip(&fields)
+        .map(|(h, v)| (h.clone(), serde_json::Value::String(v.clone())))
+        .collect();
+    if fields.len() > headers.len() {
+        data.insert(EXTRA_FIELDS.to_string(), fields[headers.len()..].into());
+    }
+    let mut error = ValidationError::new(
+        line,
+        ErrorCode::FieldCountMismatch,
+        format!("Expected {} fields, got {}.", headers.len(), fields.len()),
+        serde_json::Value::Object(data),
+    );
+    error.byte_offset = Some(record.position.byte);
+    error.expected = Some(format!("{} fields", headers.len()));
+    error.actual = Some(format!("{} fields", fields.len()));
+    error.fields = Some(fields);
+    error
+}
+
+// Aviso de uma coluna repetida no cabeçalho, que recebeu outro nome
+fn renamed_header(position: Position, name: String, renamed: String) -> ValidationError {
+    let mut warning = ValidationError::new(
+        position.line,
+        ErrorCode::DuplicateHeader,
+        format!("Duplicate column name '{}' renamed to '{}'", name, renamed),
+        serde_json::Value::Null,
+    );
+    warning.severity = Severity::Warning;
+    warning.column = Some(renamed);
+    warning.rule = Some("duplicates".to_string());
+    warning.byte_offset = Some(position.byte);
+    warning.actual = Some(name);
+    warning
+}
+
+// Erro de uma regra do schema em uma coluna
+fn rule_error(
+    line: u64,
+    byte_offset: u64,
+    column: &str,
+    value: &str,
+    failure: RuleFailure,
+    data: &serde_json::Value,
+) -> ValidationError {
+    let mut error = ValidationError::new(
+        line,
+        failure.code,
+        format!("Column '{}' failed rule '{}': {}", column, failure.rule, failure.message),
+        data.clone(),
+    );
+    error.severity = failure.severity;
+    error.column = Some(column.to_string());
+    error.rule = Some(failure.rule.to_string());
+    error.byte_offset = Some(byte_offset);
+    error.expected = failure.expected;
+    error.actual = Some(value.to_string());
+    error
+}
+
+// Erro de uma regra de linha, nomeando a regra e a expressão que falhou
+fn row_rule_error(line: u64, byte_offset: u64, failure: RowFailure<'_>, data: &serde_json::Value) -> ValidationError {
+    let mut error = ValidationError::new(
+        line,
+        ErrorCode::RowRule,
+        format!("Row failed rule '{}': {}", failure.rule, failure.message),
+        data.clone(),
+    );
+    error.severity = failure.severity;
+    error.rule = Some(failure.rule.to_string());
+    error.byte_offset = Some(byte_offset);
+    error.expected = Some(failure.check.to_string());
+    error
+}
+
+// Erro de uma ocorrência repetida, apontando a linha da ocorrência mantida
+fn duplicate_error(
+    row: ProcessedRow,
+    kept: u64,
+    dedup: &DedupConfig,
+    fields: Option<Vec<String>>,
+) -> ValidationError {
+    let error = match &dedup.key {
+        Some(key) => format!("Duplicate key ({}): same values as line {}", key.join(", "), kept),
+        None => format!("Duplicate row: same values as line {}", kept),
+    };
+    let mut validation_error = ValidationError::new(row.line, ErrorCode::Duplicate, error, row.data);
+    validation_error.rule = Some("duplicate".to_string());
+    validation_error.duplicate_of = Some(kept);
+    validation_error.fields = fields;
+    validation_error
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{process, CsvSchema};
+
+    #[test]
+    fn renamed_headers_are_warnings() {
+        let result = process(b"a,b,a\n1,2,3\n", &CsvOptions::default()).unwrap();
+        assert_eq!(result.headers(), Some(&["a".to_string(), "b".to_string(), "a_2".to_string()][..]));
+        let warning = &result.errors()[0];
+        assert_eq!(
+            (warning.line(), warning.code(), warning.severity(), warning.column(), warning.message()),
+            (1, ErrorCode::DuplicateHeader, Severity::Warning, Some("a_2"), "Duplicate column name 'a' renamed to 'a_2'")
+        );
+        // O aviso não rejeita nenhuma linha
+        assert!(!result.has_errors());
+        assert_eq!(result.processed_rows()[0].data()["a_2"], "3");
+    }
+
+    fn with_field_count(csv: &str, mode: &str) -> ProcessingResult {
+        let mut options = CsvOptions::default();
+        options.set_field_count(mode).unwrap();
+        process(csv.as_bytes(), &options).unwrap()
+    }
+
+    // Linha, código, mensagem e `data` de cada erro
+    fn errors(result: &ProcessingResult) -> Vec<(u64, ErrorCode, &str, &serde_json::Value)> {
+        result.errors().iter().map(|e| (e.line(), e.code(), e.message(), e.data())).collect()
+    }
+
+    fn lines(result: &ProcessingResult) -> Vec<u64> {
+        result.processed_rows().iter().map(ProcessedRow::line).collect()
+    }
+
+    fn long_row() -> serde_json::Value {
+        json!({ "id": "1", "nome": "Ana", "_extra": ["x", "y"] })
+    }
+
+    const SHORT_AND_LONG: &str = "id,nome\n1,Ana,x,y\n2\n3,Bia\n";
+
+    #[test]
+    fn strict_field_count() {
+        let result = with_field_count(SHORT_AND_LONG, "strict");
+        assert_eq!(
+            errors(&result),
+            [
+                (2, ErrorCode::FieldCountMismatch, "Expected 2 fields, got 4.", &json!(null)),
+                (3, ErrorCode::FieldCountMismatch, "Expected 2 fields, got 1.", &json!(null)),
+            ]
+        );
+        let error = &result.errors()[0];
+        assert_eq!((error.byte_offset(), error.expected(), error.actual()), (Some(8), Some("2 fields"), Some("4 fields")));
+        assert_eq!(result.errors()[1].byte_offset(), Some(18));
+        assert_eq!(lines(&result), [4]);
+    }
+
+    #[test]
+    fn report_field_count() {
+        let result = with_field_count(SHORT_AND_LONG, "report");
+        assert_eq!(
+            errors(&result),
+            [
+                (2, ErrorCode::FieldCountMismatch, "Expected 2 fields, got 4.", &long_row()),
+                (3, ErrorCode::FieldCountMismatch, "Expected 2 fields, got 1.", &json!({ "id": "2" })),
+            ]
+        );
+        assert_eq!(lines(&result), [4]);
+    }
+
+    #[test]
+    fn pad_field_count() {
+        let result = with_field_count(SHORT_AND_LONG, "pad");
+        // A linha curta ganha um campo vazio, rejeitado como qualquer outro
+        assert_eq!(
+            errors(&result),
+            [
+                (2, ErrorCode::FieldCountMismatch, "Expected 2 fields, got 4.", &long_row()),
+                (3, ErrorCode::EmptyFields, "Row contains empty fields: nome.", &json!({ "id": "2", "nome": "" })),
+            ]
+        );
+
+        // Com um padrão no schema, a linha curta passa
+        let mut options = CsvOptions::default();
+        options.set_field_count("pad").unwrap();
+        options.set_schema(&CsvSchema::new(r#"{ "columns": { "nome": { "default": "-" } } }"#).unwrap());
+        let result = process(SHORT_AND_LONG.as_bytes(), &options).unwrap();
+        assert_eq!(lines(&result), [3, 4]);
+        assert_eq!(result.processed_rows()[0].data(), &json!({ "id": "2", "nome": "-" }));
+    }
+
+    #[test]
+    fn extra_field_count() {
+        let result = with_field_count(SHORT_AND_LONG, "extra");
+        let short = json!({ "id": "2", "nome": "" });
+        assert_eq!(errors(&result), [(3, ErrorCode::EmptyFields, "Row contains empty fields: nome.", &short)]);
+        assert_eq!(lines(&result), [2, 4]);
+        let row = &result.processed_rows()[0];
+        assert_eq!(row.data(), &long_row());
+        // Os campos a mais não entram no hash
+        let plain = process(b"id,nome\n1,Ana\n", &CsvOptions::default()).unwrap();
+        assert_eq!(row.hash(), plain.processed_rows()[0].hash());
+    }
+
+    #[test]
+    fn empty_fields_name_the_columns() {
+        // Campos só com espaços também contam como vazios
+        let csv = "id,nome,cidade\n1,, \n2,Bia,Recife\n,Caio,\n";
+        let result = process(csv.as_bytes(), &CsvOptions::default()).unwrap();
+        let empty: Vec<_> = result.errors().iter().map(|e| (e.line(), e.empty_columns().unwrap().join(","))).collect();
+        assert_eq!(empty, [(2, "nome,cidade".to_string()), (4, "id,cidade".to_string())]);
+        assert_eq!(result.errors()[1].message(), "Row contains empty fields: id, cidade.");
+        assert_eq!(lines(&result), [3]);
+
+        // O padrão do schema entra antes da validação e do hash; sem padrão,
+        // a coluna obrigatória vazia é um erro da coluna, sem a lista
+        let mut options = CsvOptions::default();
+        let schema = r#"{ "columns": { "cidade": { "default": "Recife" }, "id": { "type": "int" } } }"#;
+        options.set_schema(&CsvSchema::new(schema).unwrap());
+        let result = process(b"id,nome,cidade\n1,Ana, \n1,Ana,Recife\n,Caio,\n".as_slice(), &options).unwrap();
+        assert_eq!(result.processed_rows()[0].data(), &json!({ "id": "1", "nome": "Ana", "cidade": "Recife" }));
+        assert_eq!(result.processed_rows()[0].hash(), result.processed_rows()[1].hash());
+        let error = &result.errors()[0];
+        assert_eq!(
+            (error.line(), error.code(), error.column(), error.empty_columns()),
+            (4, ErrorCode::Required, Some("id"), None)
+        );
+    }
+
+    #[test]
+    fn flexible_dialect_skips_the_field_count() {
+        let mut options = CsvOptions::default();
+        options.set_flexible(true);
+        let result = process(SHORT_AND_LONG.as_bytes(), &options).unwrap();
+        assert_eq!(lines(&result), [2, 3, 4]);
+        assert_eq!(result.processed_rows()[1].data(), &json!({ "id": "2" }));
+    }
+
+    // Resultado, campos guardados para a saída CSV e totais
+    #[cfg(not(target_arch = "wasm32"))]
+    type Run = (serde_json::Value, Vec<Option<Vec<String>>>, serde_json::Value);
+
+    // Processa a entrada em pedaços de `chunk` bytes
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_chunked(input: &[u8], options: &CsvOptions, chunk: usize) -> Run {
+        let mut pipeline = CsvPipeline::new(options);
+        let mut result = ProcessingResult::default();
+        for piece in input.chunks(chunk) {
+            pipeline.push(piece, &mut result).unwrap();
+        }
+        pipeline.finish(&mut result).unwrap();
+        let fields = result.errors.iter().map(|e| e.fields.clone()).collect();
+        let totals = serde_json::to_value(pipeline.totals()).unwrap();
+        (serde_json::to_value(&result).unwrap(), fields, totals)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn parallel_matches_sequential() {
+        // Bem mais linhas que um lote, com repetições, campos vazios e linhas
+        // com campos a mais
+        let mut input = String::from("id,nome,valor\n");
+        for i in 0..PARALLEL_BATCH * 3 + 100 {
+            match i % 17 {
+                0 => input.push_str(&format!("{},,{}\n", i, i)),
+                1 => input.push_str(&format!("{},Ana,{},x\n", i, i)),
+                _ => input.push_str(&format!("{},Nome {},{}\n", i % 5000, i % 3, i % 11)),
+            }
+        }
+        let schema = CsvSchema::new(
+            r#"{ "columns": { "id": { "type": "int" }, "valor": { "type": "int", "max_length": 1 } },
+                 "rules": [{ "name": "par", "check": "valor != 7", "severity": "warning" }],
+                 "file": { "unique": [["id"]], "monotonic": [{ "column": "id" }] } }"#,
+        )
+        .unwrap();
+        let setups: [&dyn Fn(&mut CsvOptions); 4] = [
+            &|_| {},
+            &|options| options.set_dedup("flag").unwrap(),
+            &|options| {
+                options.set_dedup("drop").unwrap();
+                options.set_dedup_keep("last").unwrap();
+                options.set_dedup_key(vec!["id".to_string()]);
+            },
+            &|options| {
+                options.set_schema(&schema);
+                options.set_types("infer").unwrap();
+                options.set_stats(true);
+                options.set_dedup("flag").unwrap();
+                options.set_dedup_keep("last").unwrap();
+                options.set_dedup_key(vec!["nome".to_string()]);
+            },
+        ];
+        for setup in setups {
+            let mut options = CsvOptions::default();
+            setup(&mut options);
+            options.set_parallel(false);
+            let sequential = run_chunked(input.as_bytes(), &options, input.len());
+            assert!(sequential.0["errors"].as_array().is_some_and(|errors| !errors.is_empty()));
+            options.set_parallel(true);
+            assert!(sequential == run_chunked(input.as_bytes(), &options, input.len()), "parallel output differs");
+            // Em pedaços, com lotes cortados no meio
+            assert!(sequential == run_chunked(input.as_bytes(), &options, 10_000), "chunked parallel output differs");
+        }
+    }
+}
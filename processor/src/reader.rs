@@ -0,0 +1,317 @@
+use csv_core::{ReadRecordResult, Reader};
+
+use crate::encoding::Encoding;
+
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+// Leitor incremental de registros CSV. Recebe os bytes em pedaços de qualquer
+// tamanho e só entrega um registro quando ele está completo, inclusive quando
+// um campo entre aspas atravessa a fronteira entre dois pedaços.
+pub(crate) struct RecordReader {
+    core: Reader,
+    // Codificação de onde vieram os bytes, para as posições no arquivo lido
+    encoding: Encoding,
+    output: Vec<u8>,
+    output_len: usize,
+    ends: Vec<usize>,
+    ends_len: usize,
+    // Os primeiros bytes ficam retidos até ser possível descartar o BOM,
+    // mesmo quando ele chega dividido entre pedaços
+    bom_buffer: Option<Vec<u8>>,
+    position: Position,
+    record_start: Position,
+    // Entre dois registros: as quebras de linha e os comentários lidos aqui
+    // não contam na posição do próximo registro
+    between_records: bool,
+    in_comment: bool,
+    comment: Option<u8>,
+    finished: bool,
+    // Tamanho máximo de um registro, contando um byte por delimitador; ao
+    // passar dele a leitura para
+    max_record_bytes: Option<usize>,
+    exceeded: Option<Position>,
+}
+
+// Posição no arquivo original: linha física (a partir de 1) e byte
+#[derive(Clone, Copy)]
+pub(crate) struct Position {
+    pub(crate) line: u64,
+    pub(crate) byte: u64,
+}
+
+// Um registro completo, com os campos ainda em bytes
+pub(crate) struct RawRecord<'a> {
+    data: &'a [u8],
+    ends: &'a [usize],
+    pub(crate) position: Position,
+}
+
+// Cópia de um registro, para ser avaliada depois que o leitor já avançou
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct OwnedRecord {
+    data: Vec<u8>,
+    ends: Vec<usize>,
+    position: Position,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl OwnedRecord {
+    pub(crate) fn as_raw(&self) -> RawRecord<'_> {
+        RawRecord {
+            data: &self.data,
+            ends: &self.ends,
+            position: self.position,
+        }
+    }
+}
+
+impl<'a> RawRecord<'a> {
+    // Registro montado fora do leitor de CSV, como uma linha de planilha:
+    // `ends` é o fim de cada campo em `data`
+    pub(crate) fn new(data: &'a [u8], ends: &'a [usize], position: Position) -> Self {
+        RawRecord { data, ends, position }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn to_owned(&self) -> OwnedRecord {
+        OwnedRecord {
+            data: self.data.to_vec(),
+            ends: self.ends.to_vec(),
+            position: self.position,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub(crate) fn fields(&self) -> impl Iterator<Item = &[u8]> {
+        let mut start = 0;
+        self.ends.iter().map(move |&end| {
+            let field = &self.data[start..end];
+            start = end;
+            field
+        })
+    }
+}
+
+impl RecordReader {
+    pub(crate) fn new(core: Reader, comment: Option<u8>, max_record_bytes: Option<usize>) -> Self {
+        RecordReader {
+            core,
+            encoding: Encoding::Utf8,
+            output: vec![0; 1024],
+            output_len: 0,
+            ends: vec![0; 32],
+            ends_len: 0,
+            bom_buffer: Some(Vec::new()),
+            position: Position { line: 1, byte: 0 },
+            record_start: Position { line: 1, byte: 0 },
+            between_records: true,
+            in_comment: false,
+            comment,
+            finished: false,
+            max_record_bytes,
+            exceeded: None,
+        }
+    }
+
+    // Codificação original dos próximos bytes, para contar as posições
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    // Início do registro que passou de `max_record_bytes`, se algum passou
+    pub(crate) fn exceeded(&self) -> Option<Position> {
+        self.exceeded
+    }
+
+    // Consome um pedaço da entrada, chamando `on_record` para cada registro
+    // completo. O que sobrar fica guardado até o próximo pedaço.
+    pub(crate) fn feed<F>(&mut self, mut input: &[u8], mut on_record: F)
+    where
+        F: FnMut(RawRecord<'_>),
+    {
+        if self.finished || input.is_empty() {
+            return;
+        }
+        if let Some(mut pending) = self.bom_buffer.take() {
+            pending.extend_from_slice(input);
+            if pending.len() < BOM.len() && BOM.starts_with(&pending) {
+                self.bom_buffer = Some(pending);
+                return;
+            }
+            let input = self.strip_bom(&pending);
+            self.feed(input, on_record);
+            return;
+        }
+        while !input.is_empty() && !self.finished {
+            let nin = self.step(input, &mut on_record);
+            input = &input[nin..];
+        }
+    }
+
+    // Sinaliza o fim da entrada e entrega o último registro, caso o arquivo
+    // não termine com quebra de linha.
+    pub(crate) fn finish<F>(&mut self, mut on_record: F)
+    where
+        F: FnMut(RawRecord<'_>),
+    {
+        if let Some(pending) = self.bom_buffer.take() {
+            let input = self.strip_bom(&pending);
+            self.feed(input, &mut on_record);
+        }
+        while !self.finished {
+            self.step(&[], &mut on_record);
+        }
+    }
+
+    fn strip_bom<'a>(&mut self, input: &'a [u8]) -> &'a [u8] {
+        match input.strip_prefix(BOM) {
+            Some(rest) => {
+                self.position.byte += self.encoding.original_len(BOM);
+                rest
+            }
+            None => input,
+        }
+    }
+
+    fn step<F>(&mut self, mut input: &[u8], on_record: &mut F) -> usize
+    where
+        F: FnMut(RawRecord<'_>),
+    {
+        // O "\n" de um "\r\n", linhas em branco e comentários antes de um
+        // registro vão sozinhos para o csv_core, para o registro começar depois
+        if self.between_records && !input.is_empty() {
+            let skip = if self.in_comment {
+                input.iter().position(|&b| b == b'\n').map_or(input.len(), |i| {
+                    self.in_comment = false;
+                    i + 1
+                })
+            } else if self.comment == Some(input[0]) {
+                self.in_comment = true;
+                1
+            } else {
+                input.iter().take_while(|&&b| b == b'\r' || b == b'\n').count()
+            };
+            if skip > 0 {
+                input = &input[..skip];
+            } else {
+                self.between_records = false;
+                self.record_start = self.position;
+            }
+        }
+
+        let (res, nin, nout, nend) = self.core.read_record(
+            input,
+            &mut self.output[self.output_len..],
+            &mut self.ends[self.ends_len..],
+        );
+        self.output_len += nout;
+        self.ends_len += nend;
+        self.position = Position {
+            line: self.core.line(),
+            byte: self.position.byte + self.encoding.original_len(&input[..nin]),
+        };
+        if self.max_record_bytes.is_some_and(|max| self.output_len + self.ends_len > max) {
+            self.exceeded = Some(self.record_start);
+            self.finished = true;
+            return nin;
+        }
+
+        match res {
+            ReadRecordResult::InputEmpty => {}
+            ReadRecordResult::OutputFull => {
+                let len = self.output.len();
+                self.output.resize(len * 2, 0);
+            }
+            ReadRecordResult::OutputEndsFull => {
+                let len = self.ends.len();
+                self.ends.resize(len * 2, 0);
+            }
+            ReadRecordResult::Record => {
+                on_record(RawRecord {
+                    data: &self.output[..self.output_len],
+                    ends: &self.ends[..self.ends_len],
+                    position: self.record_start,
+                });
+                self.between_records = true;
+                self.output_len = 0;
+                self.ends_len = 0;
+            }
+            ReadRecordResult::End => self.finished = true,
+        }
+        nin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csv_core::ReaderBuilder;
+
+    use super::*;
+
+    // Registros lidos com a entrada em pedaços de `chunk` bytes: campos,
+    // linha e byte de início
+    fn read(input: &[u8], chunk: usize, encoding: Encoding) -> Vec<(Vec<String>, u64, u64)> {
+        let mut reader = RecordReader::new(ReaderBuilder::new().comment(Some(b'#')).build(), Some(b'#'), None);
+        reader.set_encoding(encoding);
+        let mut records = Vec::new();
+        let mut push = |record: RawRecord<'_>| {
+            let fields = record.fields().map(|f| String::from_utf8_lossy(f).into_owned()).collect();
+            records.push((fields, record.position.line, record.position.byte));
+        };
+        for piece in input.chunks(chunk) {
+            reader.feed(piece, &mut push);
+        }
+        reader.finish(&mut push);
+        records
+    }
+
+    fn record(fields: &[&str], line: u64, byte: u64) -> (Vec<String>, u64, u64) {
+        (fields.iter().map(|f| f.to_string()).collect(), line, byte)
+    }
+
+    #[test]
+    fn same_records_at_any_chunk_boundary() {
+        let input = "\u{feff}nome,obs\r\n\"Silva, Ana\",\"linha 1\nlinha \"\"2\"\"\"\r\nJosé,\n\n# comentário, \"aspas\r\nfim,sem quebra";
+        let input = input.as_bytes();
+        let expected = vec![
+            record(&["nome", "obs"], 1, 3),
+            record(&["Silva, Ana", "linha 1\nlinha \"2\""], 2, 13),
+            record(&["José", ""], 4, 49),
+            record(&["fim", "sem quebra"], 7, 80),
+        ];
+        for chunk in 1..=input.len() {
+            assert_eq!(read(input, chunk, Encoding::Utf8), expected, "chunk of {} bytes", chunk);
+        }
+    }
+
+    #[test]
+    fn bom_split_or_alone() {
+        assert_eq!(read(b"\xef\xbb", 1, Encoding::Utf8), [record(&["\u{fffd}"], 1, 0)]);
+        assert_eq!(read(b"\xef\xbb\xbf", 1, Encoding::Utf8), []);
+        assert_eq!(read(b"\xef\xbb\xbfa", 2, Encoding::Utf8), [record(&["a"], 1, 3)]);
+    }
+
+    #[test]
+    fn positions_in_the_original_encoding() {
+        // Texto já convertido de windows-1252: "é" tem 2 bytes aqui e 1 no arquivo
+        let input = "é,1\nb,2\n".as_bytes();
+        for chunk in 1..=input.len() {
+            assert_eq!(read(input, chunk, Encoding::Windows1252), [record(&["é", "1"], 1, 0), record(&["b", "2"], 2, 4)]);
+        }
+    }
+
+    #[test]
+    fn stops_at_the_record_limit() {
+        let mut reader = RecordReader::new(Reader::new(), None, Some(8));
+        let mut lines = Vec::new();
+        for piece in b"a,b\n\"muito longo\nmesmo\",c\nd\n".chunks(3) {
+            reader.feed(piece, |record| lines.push(record.position.line));
+        }
+        reader.finish(|record| lines.push(record.position.line));
+        assert_eq!(lines, [1]);
+        assert_eq!(reader.exceeded().map(|p| (p.line, p.byte)), Some((2, 4)));
+    }
+}
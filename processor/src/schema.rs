@@ -0,0 +1,909 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::brazilian;
+use crate::errors::{ConfigError, ErrorCode, Severity};
+use crate::expr::{Expr, Parser, Row, Value as ExprValue};
+use crate::integrity::{FileRules, TrailerRules};
+use crate::values::{self, TypeConfig};
+
+// Schema como chega do JavaScript, por exemplo:
+// { "columns": { "idade": { "type": "int" }, "email": { "type": "email", "nullable": true } } }
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaDef {
+    #[serde(default)]
+    columns: BTreeMap<String, ColumnDef>,
+    #[serde(default)]
+    headers: HeaderDef,
+    // Regras entre colunas da mesma linha, na ordem em que são avaliadas
+    #[serde(default)]
+    rules: Vec<RowRuleDef>,
+    // Tabelas de consulta das regras, como { "uf_da_cidade": { "Campinas": "SP" } }
+    #[serde(default)]
+    lookups: BTreeMap<String, HashMap<String, String>>,
+    // Verificações do arquivo inteiro, com o resultado em `file_errors`
+    #[serde(default)]
+    file: FileDef,
+}
+
+// Por exemplo:
+// { "unique": [["cpf"]], "monotonic": [{ "column": "id" }], "max_rows": 100000,
+//   "trailer": { "when": "id == 'TOTAL'", "count": "quantidade", "sums": { "valor": "valor" } } }
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileDef {
+    #[serde(default)]
+    unique: Vec<Vec<String>>,
+    #[serde(default)]
+    monotonic: Vec<MonotonicDef>,
+    max_rows: Option<u64>,
+    trailer: Option<TrailerDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MonotonicDef {
+    column: String,
+    // Aceita valores iguais ao anterior (não decrescente)
+    #[serde(default)]
+    allow_equal: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrailerDef {
+    // Expressão que reconhece o registro trailer
+    when: String,
+    count: Option<String>,
+    // Coluna somada -> coluna do trailer com o total
+    #[serde(default)]
+    sums: BTreeMap<String, String>,
+}
+
+// Regra de linha, por exemplo:
+// { "name": "cnpj_pj", "when": "tipo == 'PJ'", "check": "not empty(cnpj)" }
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RowRuleDef {
+    name: String,
+    check: String,
+    // Condição para a regra valer; sem ela, vale para todas as linhas
+    when: Option<String>,
+    // Mensagem no lugar da expressão, quando a regra falha
+    message: Option<String>,
+    #[serde(default)]
+    severity: Severity,
+}
+
+// Regras do cabeçalho, aplicadas uma vez antes da primeira linha, por exemplo:
+// { "aliases": { "CPF": "cpf", "documento": "cpf" }, "required": ["cpf"], "unknown": "error" }
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct HeaderDef {
+    // Nome no arquivo -> nome canônico, usado na validação e no resultado
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    // Colunas que precisam existir, já com o nome canônico
+    #[serde(default)]
+    required: Vec<String>,
+    // Colunas que não podem existir
+    #[serde(default)]
+    forbidden: Vec<String>,
+    #[serde(default)]
+    unknown: UnknownColumns,
+    #[serde(default)]
+    duplicates: DuplicateHeaders,
+}
+
+// O que fazer com colunas fora de `columns` e de `required`
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum UnknownColumns {
+    #[default]
+    Allow,
+    Error,
+}
+
+// O que fazer com nomes de coluna repetidos, que sobrescreveriam uns aos
+// outros em `data`
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum DuplicateHeaders {
+    // Erro se o schema usa a coluna, que ficaria ambígua; as demais são
+    // renomeadas como em `Rename`, com um aviso
+    #[default]
+    Error,
+    // A segunda ocorrência de "a" vira "a_2", a terceira "a_3"...
+    Rename,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnDef {
+    #[serde(default, rename = "type")]
+    kind: ColumnType,
+    // Coluna opcional: pode ficar vazia
+    #[serde(default)]
+    nullable: bool,
+    // Valor usado no lugar de um campo vazio, validado como qualquer outro
+    default: Option<String>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    // Valores aceitos pelo tipo "enum"
+    values: Option<Vec<String>>,
+    // Expressão regular do tipo "regex"
+    pattern: Option<String>,
+    // "warning" reporta as falhas sem rejeitar a linha
+    #[serde(default)]
+    severity: Severity,
+    // Formato strftime do tipo "date" (padrão "%Y-%m-%d"; com conversão de
+    // tipos, também aceita "%d/%m/%Y")
+    format: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ColumnType {
+    #[default]
+    String,
+    Int,
+    Decimal,
+    Date,
+    Bool,
+    Email,
+    Enum,
+    Regex,
+    Cpf,
+    Cnpj,
+    Cep,
+    Uf,
+}
+
+// Tipo da coluna já compilado, pronto para validar valores
+enum Check {
+    String,
+    Int,
+    Decimal,
+    Date(Option<String>),
+    Bool,
+    Email,
+    Enum(HashSet<String>),
+    Regex(Regex),
+    Cpf,
+    Cnpj,
+    Cep,
+    Uf,
+}
+
+struct ColumnRules {
+    check: Check,
+    nullable: bool,
+    default: Option<String>,
+    severity: Severity,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+}
+
+// Falha de uma regra em uma célula: o nome da regra, o código estável, o que
+// era esperado e a mensagem para exibição
+pub(crate) struct RuleFailure {
+    pub(crate) rule: &'static str,
+    pub(crate) code: ErrorCode,
+    pub(crate) severity: Severity,
+    pub(crate) expected: Option<String>,
+    pub(crate) message: String,
+}
+
+impl RuleFailure {
+    fn new(rule: &'static str, code: ErrorCode, expected: Option<String>, message: String) -> Self {
+        RuleFailure { rule, code, severity: Severity::Error, expected, message }
+    }
+
+    fn required() -> Self {
+        RuleFailure::new("required", ErrorCode::Required, None, "value is required".to_string())
+    }
+}
+
+struct RowRule {
+    name: String,
+    source: String,
+    check: Expr,
+    when: Option<Expr>,
+    message: Option<String>,
+    severity: Severity,
+}
+
+// Falha de uma regra de linha: a regra, sua expressão e o motivo
+pub(crate) struct RowFailure<'a> {
+    pub(crate) rule: &'a str,
+    pub(crate) check: &'a str,
+    pub(crate) severity: Severity,
+    pub(crate) message: String,
+}
+
+// Schema compilado uma única vez e reutilizado em todas as linhas e chamadas
+pub(crate) struct CompiledSchema {
+    columns: HashMap<String, ColumnRules>,
+    headers: HeaderDef,
+    rules: Vec<RowRule>,
+    // Colunas citadas pelas regras e a primeira regra que cita cada uma
+    rule_columns: Vec<String>,
+    rule_columns_used_by: Vec<String>,
+    file: FileRules,
+}
+
+impl CompiledSchema {
+    pub(crate) fn from_json(schema_json: &str) -> Result<CompiledSchema, String> {
+        let def: SchemaDef = serde_json::from_str(schema_json)
+            .map_err(|e| format!("Invalid schema: {}", e))?;
+        CompiledSchema::compile(def)
+    }
+
+    // Schema montado em código, como o de cada tipo de registro de um layout
+    // posicional
+    pub(crate) fn from_value(schema: Value) -> Result<CompiledSchema, String> {
+        let def: SchemaDef = serde_json::from_value(schema).map_err(|e| format!("Invalid schema: {}", e))?;
+        CompiledSchema::compile(def)
+    }
+
+    fn compile(def: SchemaDef) -> Result<CompiledSchema, String> {
+        let mut columns = HashMap::new();
+        for (name, column) in def.columns {
+            let rules = ColumnRules::compile(column)
+                .map_err(|e| format!("Invalid schema for column '{}': {}", name, e))?;
+            columns.insert(name, rules);
+        }
+
+        let lookups: HashMap<String, Arc<HashMap<String, String>>> =
+            def.lookups.into_iter().map(|(name, table)| (name, Arc::new(table))).collect();
+        let mut rules = Vec::with_capacity(def.rules.len());
+        let mut rule_columns = Vec::new();
+        let mut rule_columns_used_by = Vec::new();
+        for rule in def.rules {
+            if rules.iter().any(|r: &RowRule| r.name == rule.name) {
+                return Err(format!("Invalid schema: duplicate rule name '{}'", rule.name));
+            }
+            let invalid = |what: &str, e: String| format!("Invalid rule '{}' ({}): {}", rule.name, what, e);
+            let check = Parser::parse(&rule.check, &mut rule_columns, &lookups).map_err(|e| invalid("check", e))?;
+            let when = match &rule.when {
+                Some(when) => Some(Parser::parse(when, &mut rule_columns, &lookups).map_err(|e| invalid("when", e))?),
+                None => None,
+            };
+            rule_columns_used_by.resize(rule_columns.len(), rule.name.clone());
+            rules.push(RowRule {
+                name: rule.name,
+                source: rule.check,
+                check,
+                when,
+                message: rule.message,
+                severity: rule.severity,
+            });
+        }
+
+        let trailer = match def.file.trailer {
+            Some(trailer) => {
+                let when = Parser::parse(&trailer.when, &mut rule_columns, &lookups)
+                    .map_err(|e| format!("Invalid trailer condition: {}", e))?;
+                rule_columns_used_by.resize(rule_columns.len(), "trailer".to_string());
+                Some(TrailerRules {
+                    when,
+                    source: trailer.when,
+                    count: trailer.count,
+                    sums: trailer.sums.into_iter().collect(),
+                })
+            }
+            None => None,
+        };
+        if def.file.unique.iter().any(Vec::is_empty) {
+            return Err("Invalid schema: unique keys need at least one column".to_string());
+        }
+        let file = FileRules {
+            unique: def.file.unique,
+            monotonic: def.file.monotonic.into_iter().map(|m| (m.column, m.allow_equal)).collect(),
+            max_rows: def.file.max_rows,
+            trailer,
+        };
+
+        Ok(CompiledSchema {
+            columns,
+            headers: def.headers,
+            rules,
+            rule_columns,
+            rule_columns_used_by,
+            file,
+        })
+    }
+
+    // Verificações do arquivo inteiro, quando o schema tem alguma
+    pub(crate) fn file_rules(&self) -> Option<&FileRules> {
+        (!self.file.is_empty()).then_some(&self.file)
+    }
+
+    // Se um registro é o trailer do arquivo
+    pub(crate) fn is_trailer(&self, row: &Row<'_>) -> bool {
+        self.file.trailer.as_ref().is_some_and(|t| matches!(t.when.eval(row), Ok(ExprValue::Bool(true))))
+    }
+
+    pub(crate) fn has_row_rules(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    // Posição no cabeçalho de cada coluna citada pelas regras. As colunas já
+    // foram conferidas em `resolve_headers`.
+    pub(crate) fn rule_slots(&self, headers: &[String]) -> Vec<usize> {
+        self.rule_columns
+            .iter()
+            .map(|column| headers.iter().position(|h| h == column).unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    // Avalia as regras de linha. Uma regra falha quando `check` é falso ou
+    // não pode ser calculado; com um campo vazio numa comparação o resultado
+    // é desconhecido e a regra passa, como um CHECK do SQL.
+    pub(crate) fn check_row(&self, row: &Row<'_>) -> Vec<RowFailure<'_>> {
+        let mut failures = Vec::new();
+        for rule in &self.rules {
+            let applies = match &rule.when {
+                Some(when) => when.eval(row).map(|v| matches!(v, ExprValue::Bool(true))),
+                None => Ok(true),
+            };
+            let message = match applies.and_then(|applies| if applies { rule.check.eval(row).map(Some) } else { Ok(None) }) {
+                Ok(Some(ExprValue::Bool(false))) => rule.message.clone().unwrap_or_else(|| rule.source.clone()),
+                Ok(Some(ExprValue::Bool(true) | ExprValue::Null) | None) => continue,
+                Ok(Some(other)) => format!("expected true or false, got '{}'", other.to_text()),
+                Err(e) => e,
+            };
+            failures.push(RowFailure {
+                rule: &rule.name,
+                check: &rule.source,
+                severity: rule.severity,
+                message,
+            });
+        }
+        failures
+    }
+
+    // Valor padrão de uma coluna, usado quando o campo chega vazio
+    pub(crate) fn default_for(&self, column: &str) -> Option<&str> {
+        self.columns.get(column)?.default.as_deref()
+    }
+
+    // Valida o valor de uma coluna. Colunas fora do schema só não podem
+    // ficar vazias. Com a conversão de tipos ativa, devolve o valor já
+    // convertido pelo tipo da coluna; None mantém a string original.
+    pub(crate) fn check(&self, column: &str, value: &str, types: &TypeConfig) -> Result<Option<Value>, RuleFailure> {
+        match self.columns.get(column) {
+            Some(rules) => rules.check(value, types),
+            None if value.trim().is_empty() => Err(RuleFailure::required()),
+            None => Ok(None),
+        }
+    }
+}
+
+impl ColumnRules {
+    fn compile(def: ColumnDef) -> Result<ColumnRules, String> {
+        let check = match def.kind {
+            ColumnType::String => Check::String,
+            ColumnType::Int => Check::Int,
+            ColumnType::Decimal => Check::Decimal,
+            ColumnType::Date => Check::Date(def.format),
+            ColumnType::Bool => Check::Bool,
+            ColumnType::Email => Check::Email,
+            ColumnType::Enum => match def.values {
+                Some(values) => Check::Enum(values.into_iter().collect()),
+                None => return Err("type 'enum' requires 'values'".to_string()),
+            },
+            ColumnType::Regex => match def.pattern {
+                Some(pattern) => Check::Regex(Regex::new(&pattern).map_err(|e| e.to_string())?),
+                None => return Err("type 'regex' requires 'pattern'".to_string()),
+            },
+            ColumnType::Cpf => Check::Cpf,
+            ColumnType::Cnpj => Check::Cnpj,
+            ColumnType::Cep => Check::Cep,
+            ColumnType::Uf => Check::Uf,
+        };
+
+        let rules = ColumnRules {
+            check,
+            nullable: def.nullable,
+            default: None,
+            severity: def.severity,
+            min_length: def.min_length,
+            max_length: def.max_length,
+        };
+        // O padrão precisa passar pelas regras da própria coluna
+        if let Some(default) = &def.default {
+            if let Err(failure) = rules.validate(default, &TypeConfig::default()) {
+                return Err(format!("default '{}' is invalid: {}", default, failure.message));
+            }
+        }
+        Ok(ColumnRules { default: def.default, ..rules })
+    }
+
+    fn check(&self, raw: &str, types: &TypeConfig) -> Result<Option<Value>, RuleFailure> {
+        self.validate(raw, types).map_err(|failure| RuleFailure { severity: self.severity, ..failure })
+    }
+
+    fn validate(&self, raw: &str, types: &TypeConfig) -> Result<Option<Value>, RuleFailure> {
+        let value = raw.trim();
+        if value.is_empty() {
+            return if self.nullable {
+                Ok(types.enabled().then_some(Value::Null))
+            } else {
+                Err(RuleFailure::required())
+            };
+        }
+
+        let length = value.chars().count();
+        if let Some(min) = self.min_length {
+            if length < min {
+                return Err(RuleFailure::new(
+                    "min_length",
+                    ErrorCode::MinLength,
+                    Some(format!("min_length {}", min)),
+                    format!("length {} is below the minimum of {}", length, min),
+                ));
+            }
+        }
+        if let Some(max) = self.max_length {
+            if length > max {
+                return Err(RuleFailure::new(
+                    "max_length",
+                    ErrorCode::MaxLength,
+                    Some(format!("max_length {}", max)),
+                    format!("length {} exceeds the maximum of {}", length, max),
+                ));
+            }
+        }
+
+        // Os tipos com conversão são validados pela própria conversão em
+        // qualquer modo de `types`: a linha válida num modo é válida em todos
+        if let Some(result) = self.coerce(value, types) {
+            return if types.enabled() { result } else { result.map(|_| None) };
+        }
+
+        let (rule, valid) = match &self.check {
+            Check::Int | Check::Decimal | Check::Date(_) | Check::Bool => unreachable!("validated by coerce"),
+            Check::String => ("string", true),
+            Check::Email => ("email", is_email(value)),
+            Check::Enum(values) => ("enum", values.contains(value)),
+            Check::Regex(regex) => ("regex", regex.is_match(value)),
+            Check::Cpf => ("cpf", brazilian::validate_cpf(value)),
+            Check::Cnpj => ("cnpj", brazilian::validate_cnpj(value)),
+            Check::Cep => ("cep", brazilian::validate_cep(value)),
+            Check::Uf => ("uf", brazilian::validate_uf(value)),
+        };
+        if valid {
+            return Ok(types.enabled().then(|| Value::String(raw.to_string())));
+        }
+
+        Err(match &self.check {
+            Check::Enum(values) => {
+                let mut allowed: Vec<&str> = values.iter().map(String::as_str).collect();
+                allowed.sort_unstable();
+                RuleFailure::new(
+                    rule,
+                    ErrorCode::NotAllowed,
+                    Some(format!("one of {}", allowed.join(", "))),
+                    format!("'{}' is not one of the allowed values", value),
+                )
+            }
+            Check::Regex(regex) => RuleFailure::new(
+                rule,
+                ErrorCode::PatternMismatch,
+                Some(format!("pattern {}", regex.as_str())),
+                format!("'{}' does not match pattern '{}'", value, regex.as_str()),
+            ),
+            _ => RuleFailure::new(
+                rule,
+                ErrorCode::InvalidValue,
+                Some(rule.to_string()),
+                format!("'{}' is not a valid {}", value, rule),
+            ),
+        })
+    }
+
+    // Valida e converte os tipos com representação própria no JSON. Aceita
+    // números com separador de milhar e datas dd/mm/aaaa; None para os demais
+    // tipos, que continuam string.
+    fn coerce(&self, value: &str, types: &TypeConfig) -> Option<Result<Option<Value>, RuleFailure>> {
+        let separator = types.decimal_separator;
+        let (rule, coerced) = match &self.check {
+            Check::Int => ("int", values::parse_int(value, separator).map(|n| Value::Number(n.into()))),
+            Check::Decimal => ("decimal", values::parse_decimal(value, separator).map(Value::Number)),
+            Check::Date(format) => ("date", values::parse_date(value, format.as_deref()).map(Value::String)),
+            Check::Bool => ("bool", values::parse_bool(value).map(Value::Bool)),
+            _ => return None,
+        };
+        Some(match coerced {
+            Some(coerced) => Ok(Some(coerced)),
+            None => Err(match &self.check {
+                Check::Date(Some(format)) => RuleFailure::new(
+                    rule,
+                    ErrorCode::InvalidValue,
+                    Some(format!("date {}", format)),
+                    format!("'{}' is not a valid date in format '{}'", value, format),
+                ),
+                Check::Date(None) => RuleFailure::new(
+                    rule,
+                    ErrorCode::InvalidValue,
+                    Some("date yyyy-mm-dd or dd/mm/yyyy".to_string()),
+                    format!("'{}' is not a valid date (expected yyyy-mm-dd or dd/mm/yyyy)", value),
+                ),
+                _ => RuleFailure::new(
+                    rule,
+                    ErrorCode::InvalidValue,
+                    Some(rule.to_string()),
+                    format!("'{}' cannot be converted to {}", value, rule),
+                ),
+            }),
+        })
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !value.contains(char::is_whitespace)
+        && domain.split('.').count() >= 2
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+// Colunas repetidas que foram renomeadas: (nome repetido, novo nome)
+pub(crate) type Renamed = Vec<(String, String)>;
+
+// Nomes das colunas como serão usados: os apelidos viram o nome canônico e
+// as regras do cabeçalho do schema são verificadas. Sem schema, nomes
+// repetidos viram "a_2", "a_3"... Devolve também as colunas renomeadas sem
+// `duplicates: "rename"`, para o aviso.
+pub(crate) fn resolve_headers(schema: Option<&CompiledSchema>, names: Vec<String>) -> Result<(Vec<String>, Renamed), String> {
+    let default = HeaderDef::default();
+    let rules = schema.map_or(&default, |schema| &schema.headers);
+    // Colunas citadas pelo schema: regras de coluna, de linha, do arquivo e
+    // `required`
+    let known = |h: &String| {
+        schema.is_some_and(|s| {
+            s.columns.contains_key(h) || s.rule_columns.contains(h) || s.file.columns().any(|(c, _)| c == h)
+        }) || rules.required.contains(h)
+    };
+
+    let mut headers: Vec<String> = Vec::with_capacity(names.len());
+    let mut renamed = Vec::new();
+    for name in names {
+        let canonical = rules.aliases.get(&name).cloned().unwrap_or(name);
+        if !headers.contains(&canonical) {
+            headers.push(canonical);
+            continue;
+        }
+        match rules.duplicates {
+            DuplicateHeaders::Error if known(&canonical) => {
+                return Err(format!("duplicate column name '{}'", canonical));
+            }
+            DuplicateHeaders::Error | DuplicateHeaders::Rename => {
+                let name = (2..)
+                    .map(|n| format!("{}_{}", canonical, n))
+                    .find(|candidate| !headers.contains(candidate))
+                    .unwrap_or_default();
+                if rules.duplicates == DuplicateHeaders::Error {
+                    renamed.push((canonical, name.clone()));
+                }
+                headers.push(name);
+            }
+        }
+    }
+
+    if let Some(name) = headers.iter().find(|h| rules.forbidden.contains(h)) {
+        return Err(format!("forbidden column '{}'", name));
+    }
+    if rules.unknown == UnknownColumns::Error {
+        let unknown: Vec<&str> = headers.iter().filter(|h| !known(h)).map(String::as_str).collect();
+        if !unknown.is_empty() {
+            return Err(format!("unknown columns: {}", unknown.join(", ")));
+        }
+    }
+    if let Some(schema) = schema {
+        let missing = schema.rule_columns.iter().position(|c| !headers.contains(c));
+        if let Some(i) = missing {
+            return Err(format!(
+                "column '{}' used by rule '{}' is not in the header",
+                schema.rule_columns[i], schema.rule_columns_used_by[i]
+            ));
+        }
+        if let Some((column, check)) = schema.file.columns().find(|(c, _)| !headers.iter().any(|h| h == c)) {
+            return Err(format!("column '{}' used by file check '{}' is not in the header", column, check));
+        }
+    }
+    let missing: Vec<&str> = rules.required.iter().filter(|r| !headers.contains(r)).map(String::as_str).collect();
+    if !missing.is_empty() {
+        return Err(format!("missing required columns: {}", missing.join(", ")));
+    }
+    Ok((headers, renamed))
+}
+
+// Schema compilado exposto ao JavaScript. Pode ser criado uma vez e passado
+// para várias chamadas de processamento.
+#[wasm_bindgen]
+pub struct CsvSchema {
+    inner: Arc<CompiledSchema>,
+}
+
+#[wasm_bindgen]
+impl CsvSchema {
+    #[wasm_bindgen(constructor)]
+    pub fn new(schema_json: &str) -> Result<CsvSchema, ConfigError> {
+        let compiled = CompiledSchema::from_json(schema_json).map_err(ConfigError::new)?;
+        Ok(CsvSchema { inner: Arc::new(compiled) })
+    }
+}
+
+impl CsvSchema {
+    pub(crate) fn compiled(&self) -> Arc<CompiledSchema> {
+        Arc::clone(&self.inner)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::values::TypeMode;
+
+    fn column(def: Value) -> CompiledSchema {
+        CompiledSchema::from_value(json!({ "columns": { "c": def } })).unwrap()
+    }
+
+    fn typed() -> TypeConfig {
+        TypeConfig { mode: TypeMode::Schema, decimal_separator: None }
+    }
+
+    // Valor convertido de um campo que passou
+    fn converted(schema: &CompiledSchema, value: &str) -> Option<Value> {
+        schema.check("c", value, &typed()).ok().unwrap()
+    }
+
+    // Regra, código, o que era esperado e mensagem da falha
+    fn failure(schema: &CompiledSchema, value: &str) -> (&'static str, ErrorCode, Option<String>, String) {
+        match schema.check("c", value, &TypeConfig::default()) {
+            Ok(_) => panic!("'{}' passed", value),
+            Err(f) => (f.rule, f.code, f.expected, f.message),
+        }
+    }
+
+    fn expected(
+        rule: &'static str,
+        code: ErrorCode,
+        expected: Option<&str>,
+        message: &str,
+    ) -> (&'static str, ErrorCode, Option<String>, String) {
+        (rule, code, expected.map(str::to_string), message.to_string())
+    }
+
+    #[test]
+    fn numbers_and_dates() {
+        let int = column(json!({ "type": "int" }));
+        assert_eq!(converted(&int, "42"), Some(json!(42)));
+        assert_eq!(converted(&int, "1.234.567"), Some(json!(1234567)));
+        // Sem conversão de tipos, o valor válido continua string
+        assert_eq!(int.check("c", "42", &TypeConfig::default()).ok(), Some(None));
+        assert_eq!(
+            failure(&int, "12a"),
+            expected("int", ErrorCode::InvalidValue, Some("int"), "'12a' cannot be converted to int")
+        );
+
+        let decimal = column(json!({ "type": "decimal" }));
+        assert_eq!(converted(&decimal, "1.234,56"), Some(json!(1234.56)));
+        assert_eq!(converted(&decimal, "-0.5"), Some(json!(-0.5)));
+        assert_eq!(
+            failure(&decimal, "1,23,4"),
+            expected("decimal", ErrorCode::InvalidValue, Some("decimal"), "'1,23,4' cannot be converted to decimal")
+        );
+
+        let date = column(json!({ "type": "date" }));
+        assert_eq!(converted(&date, "10/02/2024"), Some(json!("2024-02-10")));
+        assert_eq!(converted(&date, "2024-02-10"), Some(json!("2024-02-10")));
+        assert_eq!(
+            failure(&date, "31/02/2024"),
+            expected(
+                "date",
+                ErrorCode::InvalidValue,
+                Some("date yyyy-mm-dd or dd/mm/yyyy"),
+                "'31/02/2024' is not a valid date (expected yyyy-mm-dd or dd/mm/yyyy)"
+            )
+        );
+        let formatted = column(json!({ "type": "date", "format": "%d.%m.%Y" }));
+        assert_eq!(converted(&formatted, "10.02.2024"), Some(json!("2024-02-10")));
+        assert_eq!(
+            failure(&formatted, "2024-02-10"),
+            expected(
+                "date",
+                ErrorCode::InvalidValue,
+                Some("date %d.%m.%Y"),
+                "'2024-02-10' is not a valid date in format '%d.%m.%Y'"
+            )
+        );
+
+        let boolean = column(json!({ "type": "bool" }));
+        assert_eq!(converted(&boolean, "Sim"), Some(json!(true)));
+        assert_eq!(failure(&boolean, "talvez").1, ErrorCode::InvalidValue);
+    }
+
+    #[test]
+    fn email_enum_and_regex() {
+        let email = column(json!({ "type": "email" }));
+        assert_eq!(converted(&email, "ana@example.com.br"), Some(json!("ana@example.com.br")));
+        for invalid in ["ana@example", "@example.com", "ana@@example.com", "ana silva@example.com", "ana@example..com"] {
+            let message = format!("'{}' is not a valid email", invalid);
+            assert_eq!(failure(&email, invalid), expected("email", ErrorCode::InvalidValue, Some("email"), &message));
+        }
+
+        let allowed = column(json!({ "type": "enum", "values": ["PJ", "PF"] }));
+        assert_eq!(converted(&allowed, "PF"), Some(json!("PF")));
+        assert_eq!(
+            failure(&allowed, "pf"),
+            expected("enum", ErrorCode::NotAllowed, Some("one of PF, PJ"), "'pf' is not one of the allowed values")
+        );
+
+        let pattern = column(json!({ "type": "regex", "pattern": "^[0-9]{3}$" }));
+        assert_eq!(converted(&pattern, "123"), Some(json!("123")));
+        assert_eq!(
+            failure(&pattern, "12"),
+            expected(
+                "regex",
+                ErrorCode::PatternMismatch,
+                Some("pattern ^[0-9]{3}$"),
+                "'12' does not match pattern '^[0-9]{3}$'"
+            )
+        );
+
+        let cpf = column(json!({ "type": "cpf" }));
+        assert_eq!(
+            failure(&cpf, "529.982.247-24"),
+            expected("cpf", ErrorCode::InvalidValue, Some("cpf"), "'529.982.247-24' is not a valid cpf")
+        );
+    }
+
+    #[test]
+    fn lengths_in_characters() {
+        let name = column(json!({ "min_length": 3, "max_length": 5 }));
+        assert_eq!(converted(&name, "ção"), Some(json!("ção")));
+        // O tamanho é medido no valor sem os espaços das pontas
+        assert_eq!(converted(&name, " abcde "), Some(json!(" abcde ")));
+        assert_eq!(
+            failure(&name, "ab"),
+            expected("min_length", ErrorCode::MinLength, Some("min_length 3"), "length 2 is below the minimum of 3")
+        );
+        assert_eq!(
+            failure(&name, "abcdef"),
+            expected("max_length", ErrorCode::MaxLength, Some("max_length 5"), "length 6 exceeds the maximum of 5")
+        );
+        // O tamanho é conferido antes do tipo
+        let code = column(json!({ "type": "int", "max_length": 2 }));
+        assert_eq!(failure(&code, "abc").1, ErrorCode::MaxLength);
+    }
+
+    #[test]
+    fn nullable_and_required() {
+        let optional = column(json!({ "type": "int", "nullable": true }));
+        assert_eq!(optional.check("c", " ", &TypeConfig::default()).ok(), Some(None));
+        assert_eq!(converted(&optional, ""), Some(Value::Null));
+
+        let required = column(json!({ "type": "int" }));
+        assert_eq!(failure(&required, "  "), expected("required", ErrorCode::Required, None, "value is required"));
+
+        // Colunas fora do schema só não podem ficar vazias
+        assert_eq!(required.check("outra", "x", &typed()).ok(), Some(None));
+        assert_eq!(required.check("outra", "", &typed()).err().map(|f| f.code), Some(ErrorCode::Required));
+    }
+
+    #[test]
+    fn warning_severity() {
+        let warning = column(json!({ "type": "int", "severity": "warning" }));
+        let failure = warning.check("c", "x", &TypeConfig::default()).err().unwrap();
+        assert_eq!((failure.code, failure.severity), (ErrorCode::InvalidValue, Severity::Warning));
+        let error = column(json!({ "type": "int" })).check("c", "x", &TypeConfig::default()).err().unwrap();
+        assert_eq!(error.severity, Severity::Error);
+    }
+
+    #[test]
+    fn defaults_are_checked_when_compiling() {
+        let schema = column(json!({ "type": "int", "default": "0" }));
+        assert_eq!(schema.default_for("c"), Some("0"));
+        assert_eq!(schema.default_for("outra"), None);
+
+        let invalid = |def: Value| CompiledSchema::from_value(json!({ "columns": { "c": def } })).err().unwrap();
+        assert_eq!(
+            invalid(json!({ "type": "int", "default": "zero" })),
+            "Invalid schema for column 'c': default 'zero' is invalid: 'zero' cannot be converted to int"
+        );
+        assert_eq!(
+            invalid(json!({ "type": "enum", "values": ["A"], "default": "B" })),
+            "Invalid schema for column 'c': default 'B' is invalid: 'B' is not one of the allowed values"
+        );
+        assert_eq!(
+            invalid(json!({ "max_length": 2, "default": "abc" })),
+            "Invalid schema for column 'c': default 'abc' is invalid: length 3 exceeds the maximum of 2"
+        );
+        assert_eq!(invalid(json!({ "type": "enum" })), "Invalid schema for column 'c': type 'enum' requires 'values'");
+        assert_eq!(
+            invalid(json!({ "type": "regex" })),
+            "Invalid schema for column 'c': type 'regex' requires 'pattern'"
+        );
+        let regex = invalid(json!({ "type": "regex", "pattern": "(" }));
+        assert!(regex.starts_with("Invalid schema for column 'c': regex parse error"));
+        assert!(invalid(json!({ "type": "inteiro" })).starts_with("Invalid schema: unknown variant `inteiro`"));
+    }
+
+    fn resolve(schema: Option<&CompiledSchema>, names: &[&str]) -> Result<(Vec<String>, Renamed), String> {
+        resolve_headers(schema, strings(names))
+    }
+
+    fn strings(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn pairs(renamed: &[(&str, &str)]) -> Renamed {
+        renamed.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect()
+    }
+
+    fn header_schema(headers: Value) -> CompiledSchema {
+        CompiledSchema::from_value(json!({ "columns": { "cpf": { "type": "cpf" } }, "headers": headers })).unwrap()
+    }
+
+    #[test]
+    fn aliases_become_the_canonical_name() {
+        let schema = header_schema(json!({ "aliases": { "CPF": "cpf", "Nome": "nome" } }));
+        assert_eq!(resolve(Some(&schema), &["Nome", "CPF", "obs"]), Ok((strings(&["nome", "cpf", "obs"]), vec![])));
+        // Sem cabeçalho, os apelidos dão nome às colunas geradas
+        let schema = header_schema(json!({ "aliases": { "column_2": "cpf" } }));
+        assert_eq!(resolve(Some(&schema), &["column_1", "column_2"]).unwrap().0, ["column_1", "cpf"]);
+    }
+
+    #[test]
+    fn required_forbidden_and_unknown_columns() {
+        let schema = header_schema(json!({ "aliases": { "documento": "cpf" }, "required": ["cpf", "nome"] }));
+        assert_eq!(resolve(Some(&schema), &["documento", "nome"]).unwrap().0, ["cpf", "nome"]);
+        assert_eq!(resolve(Some(&schema), &["obs"]), Err("missing required columns: cpf, nome".to_string()));
+
+        let schema = header_schema(json!({ "forbidden": ["senha"] }));
+        assert_eq!(resolve(Some(&schema), &["cpf", "senha"]), Err("forbidden column 'senha'".to_string()));
+
+        let schema = header_schema(json!({ "required": ["nome"], "unknown": "error" }));
+        assert!(resolve(Some(&schema), &["cpf", "nome"]).is_ok());
+        assert_eq!(resolve(Some(&schema), &["cpf", "nome", "obs", "x"]), Err("unknown columns: obs, x".to_string()));
+        // Colunas usadas só pelas regras de linha também são conhecidas
+        let schema = CompiledSchema::from_value(json!({
+            "rules": [{ "name": "total", "check": "total >= 0" }],
+            "headers": { "unknown": "error" }
+        }))
+        .unwrap();
+        assert!(resolve(Some(&schema), &["total"]).is_ok());
+        assert_eq!(resolve(Some(&schema), &["valor"]), Err("unknown columns: valor".to_string()));
+    }
+
+    #[test]
+    fn duplicate_headers() {
+        // Sem schema, as repetidas são renomeadas e informadas
+        assert_eq!(
+            resolve(None, &["a", "a", "a_2", "a"]),
+            Ok((strings(&["a", "a_2", "a_2_2", "a_3"]), pairs(&[("a", "a_2"), ("a_2", "a_2_2"), ("a", "a_3")])))
+        );
+        // Repetir uma coluna usada pelo schema é um erro, também por apelido
+        let schema = header_schema(json!({ "aliases": { "CPF": "cpf" } }));
+        assert_eq!(resolve(Some(&schema), &["cpf", "CPF"]), Err("duplicate column name 'cpf'".to_string()));
+        assert_eq!(resolve(Some(&schema), &["cpf", "obs", "obs"]).unwrap().1, pairs(&[("obs", "obs_2")]));
+        // Com "rename", renomeia sem aviso
+        let schema = header_schema(json!({ "duplicates": "rename" }));
+        assert_eq!(resolve(Some(&schema), &["cpf", "cpf"]), Ok((strings(&["cpf", "cpf_2"]), vec![])));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::encoding::{self, Encoding};
+
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+const QUOTES: [char; 2] = ['"', '\''];
+// Quantidade de registros da amostra usados para escolher o separador
+const SAMPLE_RECORDS: usize = 50;
+
+// Resultado da detecção, devolvido como JSON por `sniff_csv`
+#[derive(Serialize)]
+pub(crate) struct Sniffed {
+    pub(crate) encoding: Encoding,
+    pub(crate) bom: bool,
+    pub(crate) delimiter: char,
+    pub(crate) quote: char,
+    pub(crate) has_quotes: bool,
+    pub(crate) has_headers: bool,
+}
+
+pub(crate) fn sniff(sample: &[u8]) -> Sniffed {
+    let (encoding, bom) = match encoding::detect(sample) {
+        Some(detected) => (detected.encoding, detected.bom),
+        None => (Encoding::Utf8, false),
+    };
+    let text = encoding::decode_sample(sample, encoding);
+
+    // Descarta a última linha quando a amostra foi cortada no meio dela
+    let text = match text.rfind('\n') {
+        Some(i) if i + 1 < text.len() => &text[..=i],
+        _ => text.as_str(),
+    };
+
+    let (quote, has_quotes) = detect_quote(text);
+    let delimiter = detect_delimiter(text, quote);
+    let has_headers = detect_headers(text, delimiter, quote);
+
+    Sniffed { encoding, bom, delimiter, quote, has_quotes, has_headers }
+}
+
+// Aspas são reconhecidas quando abrem um campo: no início da linha ou logo
+// depois de um separador possível
+fn detect_quote(text: &str) -> (char, bool) {
+    let mut counts = [0usize; QUOTES.len()];
+    let mut previous = '\n';
+    for c in text.chars() {
+        if let Some(i) = QUOTES.iter().position(|&q| q == c) {
+            if previous == '\n' || DELIMITERS.contains(&previous) {
+                counts[i] += 1;
+            }
+        }
+        previous = c;
+    }
+
+    match (counts[0], counts[1]) {
+        (0, 0) => ('"', false),
+        (double, single) if single > double => ('\'', true),
+        _ => ('"', true),
+    }
+}
+
+// Conta as ocorrências de cada separador candidato por registro, fora das
+// aspas, e escolhe o que aparece com a mesma frequência no maior número de
+// registros
+fn detect_delimiter(text: &str, quote: char) -> char {
+    let mut per_record: Vec<[usize; DELIMITERS.len()]> = Vec::new();
+    let mut current = [0usize; DELIMITERS.len()];
+    let mut in_quotes = false;
+
+    for c in text.chars() {
+        if c == quote {
+            in_quotes = !in_quotes;
+        } else if in_quotes {
+            continue;
+        } else if c == '\n' {
+            if current.iter().any(|&n| n > 0) {
+                per_record.push(current);
+            }
+            current = [0; DELIMITERS.len()];
+            if per_record.len() >= SAMPLE_RECORDS {
+                break;
+            }
+        } else if let Some(i) = DELIMITERS.iter().position(|&d| d == c) {
+            current[i] += 1;
+        }
+    }
+    if current.iter().any(|&n| n > 0) {
+        per_record.push(current);
+    }
+
+    let mut best = (',', 0usize, 0usize);
+    for (i, &delimiter) in DELIMITERS.iter().enumerate() {
+        let counts: Vec<usize> = per_record.iter().map(|r| r[i]).collect();
+        let Some(mode) = mode(&counts) else { continue };
+        let consistent = counts.iter().filter(|&&n| n == mode).count();
+        if mode > 0 && (consistent, mode) > (best.1, best.2) {
+            best = (delimiter, consistent, mode);
+        }
+    }
+    best.0
+}
+
+fn mode(values: &[usize]) -> Option<usize> {
+    let mut seen: Vec<(usize, usize)> = Vec::new();
+    for &v in values {
+        match seen.iter_mut().find(|(value, _)| *value == v) {
+            Some((_, n)) => *n += 1,
+            None => seen.push((v, 1)),
+        }
+    }
+    seen.into_iter().max_by_key(|&(value, n)| (n, value)).map(|(value, _)| value)
+}
+
+// O primeiro registro é tratado como cabeçalho quando todos os campos estão
+// preenchidos, são distintos e nenhum parece número ou data
+fn detect_headers(text: &str, delimiter: char, quote: char) -> bool {
+    let mut reader = csv_core::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .quote(quote as u8)
+        .build();
+    let mut output = vec![0; text.len().max(1)];
+    let mut ends = vec![0; text.len().max(1)];
+    let (mut result, nin, nout, mut nend) = reader.read_record(text.as_bytes(), &mut output, &mut ends);
+    if result == csv_core::ReadRecordResult::InputEmpty && nin == text.len() {
+        // Amostra com uma única linha, sem quebra no fim
+        let (last, _, _, more_ends) = reader.read_record(&[], &mut output[nout..], &mut ends[nend..]);
+        result = last;
+        nend += more_ends;
+    }
+    if result != csv_core::ReadRecordResult::Record {
+        return true;
+    }
+
+    let mut start = 0;
+    let mut unique = HashSet::new();
+    ends[..nend].iter().all(|&end| {
+        let field = String::from_utf8_lossy(&output[start..end]).trim().to_string();
+        start = end;
+        !field.is_empty() && !looks_numeric(&field) && unique.insert(field)
+    })
+}
+
+fn looks_numeric(value: &str) -> bool {
+    value.chars().any(|c| c.is_ascii_digit())
+        && value.chars().all(|c| c.is_ascii_digit() || "+-.,/: ".contains(c))
+}
+
+// Detecta codificação, separador, aspas e cabeçalho a partir de uma amostra
+// do início do arquivo e devolve o resultado como JSON
+#[wasm_bindgen]
+pub fn sniff_csv(sample: &[u8]) -> Result<JsValue, JsError> {
+    Ok(JsValue::from_str(&serde_json::to_string(&sniff(sample))?))
+}
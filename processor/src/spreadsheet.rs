@@ -0,0 +1,319 @@
+use chrono::{Duration, NaiveDate};
+
+use crate::errors::{FatalError, FatalErrorKind};
+use crate::ods::Ods;
+use crate::pipeline::CsvPipeline;
+use crate::reader::Position;
+use crate::xls::Xls;
+use crate::xlsx::Xlsx;
+use crate::zip::ZipArchive;
+use crate::{CsvOptions, ProcessingResult, ProcessingTotals};
+
+// Planilhas (.xlsx, .xlsm, .xls e .ods) lidas como registros: cada linha da
+// planilha vira um registro com as células como texto e passa pela mesma
+// validação, hash e deduplicação das linhas de um CSV.
+
+// Qual planilha ler e qual parte dela
+#[derive(Clone, Default)]
+pub(crate) struct SheetConfig {
+    // Sem planilha escolhida, lê a primeira
+    pub(crate) sheet: Option<SheetRef>,
+    pub(crate) range: Option<CellRange>,
+}
+
+#[derive(Clone)]
+pub(crate) enum SheetRef {
+    Name(String),
+    // A partir de 0, na ordem das abas
+    Index(usize),
+}
+
+// Intervalo de células como "B3:F200"; sem o fim, vai até a última linha ou
+// coluna preenchida. Linhas e colunas a partir de 0.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CellRange {
+    first_row: u32,
+    first_col: u32,
+    last_row: Option<u32>,
+    last_col: Option<u32>,
+}
+
+// Referência de célula como "B3", "B" ou "3"
+fn cell_ref(text: &str) -> Option<(Option<u32>, Option<u32>)> {
+    let letters = text.bytes().take_while(u8::is_ascii_alphabetic).count();
+    let (col, row) = text.split_at(letters);
+    if text.is_empty() || letters > 3 || !row.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let col = (!col.is_empty()).then(|| {
+        col.bytes().fold(0u32, |n, b| n * 26 + u32::from(b.to_ascii_uppercase() - b'A') + 1) - 1
+    });
+    let row = match row {
+        "" => None,
+        row => Some(row.parse::<u32>().ok().filter(|&r| r > 0)? - 1),
+    };
+    Some((col, row))
+}
+
+impl CellRange {
+    pub(crate) fn parse(text: &str) -> Option<CellRange> {
+        let (start, end) = match text.trim().split_once(':') {
+            Some((start, end)) => (start, Some(end)),
+            None => (text.trim(), None),
+        };
+        let (first_col, first_row) = cell_ref(start)?;
+        let (last_col, last_row) = match end {
+            Some(end) => cell_ref(end)?,
+            None => (None, None),
+        };
+        let range = CellRange {
+            first_row: first_row.unwrap_or(0),
+            first_col: first_col.unwrap_or(0),
+            last_row,
+            last_col,
+        };
+        let backwards = range.last_row.is_some_and(|r| r < range.first_row)
+            || range.last_col.is_some_and(|c| c < range.first_col);
+        (!backwards).then_some(range)
+    }
+
+    fn rows(&self, row: u32) -> bool {
+        row >= self.first_row && self.last_row.is_none_or(|last| row <= last)
+    }
+
+    fn cols(&self, col: u32) -> bool {
+        col >= self.first_col && self.last_col.is_none_or(|last| col <= last)
+    }
+}
+
+// Colunas além do limite do Excel (XFD) só aparecem em arquivos corrompidos
+pub(crate) const MAX_COLUMNS: u32 = 16_384;
+
+// Uma linha lida da planilha: a posição (a partir de 0) e as células
+// preenchidas, em ordem de coluna
+pub(crate) struct SheetRow {
+    pub(crate) index: u32,
+    pub(crate) cells: Vec<(u32, String)>,
+}
+
+enum Workbook<'a> {
+    Xlsx(Xlsx<'a>),
+    Ods(Ods),
+    Xls(Xls),
+}
+
+impl<'a> Workbook<'a> {
+    // O formato vem do conteúdo, não da extensão do arquivo
+    fn open(data: &'a [u8]) -> Result<Self, String> {
+        if Xls::is_xls(data) {
+            return Ok(Workbook::Xls(Xls::new(data)?));
+        }
+        if !ZipArchive::is_zip(data) {
+            return Err("not a spreadsheet: expected an .xlsx, .xls or .ods file".to_string());
+        }
+        let zip = ZipArchive::new(data)?;
+        if Ods::is_ods(&zip)? {
+            return Ok(Workbook::Ods(Ods::new(&zip)?));
+        }
+        Ok(Workbook::Xlsx(Xlsx::new(zip)?))
+    }
+
+    fn sheet_names(&self) -> &[String] {
+        match self {
+            Workbook::Xlsx(book) => book.sheet_names(),
+            Workbook::Ods(book) => book.sheet_names(),
+            Workbook::Xls(book) => book.sheet_names(),
+        }
+    }
+
+    fn rows(&mut self, index: usize) -> Result<Vec<SheetRow>, String> {
+        match self {
+            Workbook::Xlsx(book) => book.rows(index),
+            Workbook::Ods(book) => book.rows(index),
+            Workbook::Xls(book) => book.rows(index),
+        }
+    }
+}
+
+fn bad_spreadsheet(message: String) -> FatalError {
+    FatalError::new(FatalErrorKind::BadSpreadsheet, message, 0, 0)
+}
+
+// Nomes das planilhas (abas), na ordem do arquivo
+pub(crate) fn sheet_names(data: &[u8]) -> Result<Vec<String>, FatalError> {
+    Ok(Workbook::open(data).map_err(bad_spreadsheet)?.sheet_names().to_vec())
+}
+
+// Processa uma planilha do arquivo. `line` nos resultados é o número da
+// linha na planilha; linhas em branco são ignoradas.
+pub(crate) fn process(data: &[u8], options: &CsvOptions, out: &mut ProcessingResult) -> Result<ProcessingTotals, FatalError> {
+    let mut workbook = Workbook::open(data).map_err(bad_spreadsheet)?;
+    let names = workbook.sheet_names();
+    let index = match &options.sheet.sheet {
+        None if names.is_empty() => return Err(bad_spreadsheet("workbook has no sheets".to_string())),
+        None => 0,
+        Some(SheetRef::Name(name)) => names
+            .iter()
+            .position(|n| n == name)
+            .ok_or_else(|| bad_spreadsheet(format!("sheet '{}' not found; sheets: {}", name, names.join(", "))))?,
+        Some(SheetRef::Index(index)) if *index < names.len() => *index,
+        Some(SheetRef::Index(index)) => {
+            return Err(bad_spreadsheet(format!("sheet index {} out of range; the workbook has {} sheets", index, names.len())))
+        }
+    };
+    let name = names[index].clone();
+    let rows = workbook.rows(index).map_err(|e| bad_spreadsheet(format!("sheet '{}': {}", name, e)))?;
+
+    // Sem intervalo, lê a área usada: da primeira coluna preenchida em diante
+    let range = options.sheet.range.unwrap_or_else(|| CellRange {
+        first_col: rows.iter().flat_map(|row| row.cells.iter().find(|(_, v)| !v.is_empty())).map(|(col, _)| *col).min().unwrap_or(0),
+        ..CellRange::default()
+    });
+    let mut pipeline = CsvPipeline::for_records(options, data.len(), false);
+    // Largura do intervalo, ou da primeira linha preenchida (o cabeçalho)
+    let mut width = range.last_col.map(|last| (last - range.first_col + 1) as usize);
+    let mut fields: Vec<String> = Vec::new();
+    for row in rows.into_iter().filter(|row| range.rows(row.index)) {
+        fields.clear();
+        for (col, value) in row.cells.into_iter().filter(|(col, _)| range.cols(*col) && *col < MAX_COLUMNS) {
+            let at = (col - range.first_col) as usize;
+            if at < fields.len() {
+                fields[at] = value;
+            } else {
+                fields.resize(at, String::new());
+                fields.push(value);
+            }
+        }
+        while fields.last().is_some_and(|v| v.is_empty()) {
+            fields.pop();
+        }
+        if fields.is_empty() {
+            continue;
+        }
+        let width = *width.get_or_insert(fields.len());
+        if fields.len() < width {
+            fields.resize(width, String::new());
+        }
+        pipeline.push_fields(&fields, Position { line: u64::from(row.index) + 1, byte: 0 }, out)?;
+    }
+    pipeline.finish(out)?;
+    out.sheet = Some(name);
+    Ok(pipeline.totals())
+}
+
+// Número como texto, com os 15 dígitos significativos que o Excel mostra:
+// 0.1 + 0.2 vira "0.3", 30 vira "30"
+pub(crate) fn number_text(value: f64) -> String {
+    let rounded: f64 = format!("{:.14e}", value).parse().unwrap_or(value);
+    if rounded.fract() == 0.0 && rounded.abs() < 1e15 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{}", rounded)
+    }
+}
+
+// Data serial do Excel em ISO-8601: "2024-01-02", "2024-01-02T10:30:00" ou,
+// abaixo de 1, só a hora ("10:30:00"). No sistema de 1900 o Excel considera
+// 1900 bissexto, então as datas a partir de março de 1900 contam de 30/12/1899.
+pub(crate) fn serial_to_iso(serial: f64, date1904: bool) -> Option<String> {
+    if !(0.0..2_958_466.0).contains(&serial) {
+        return None;
+    }
+    let mut days = serial.trunc() as i64;
+    let mut seconds = ((serial - serial.trunc()) * 86_400.0).round() as i64;
+    if seconds == 86_400 {
+        days += 1;
+        seconds = 0;
+    }
+    let time = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if days == 0 && !date1904 {
+        return Some(time);
+    }
+    let base = match (date1904, days < 60) {
+        (true, _) => NaiveDate::from_ymd_opt(1904, 1, 1)?,
+        (false, true) => NaiveDate::from_ymd_opt(1899, 12, 31)?,
+        (false, false) => NaiveDate::from_ymd_opt(1899, 12, 30)?,
+    };
+    let date = base.checked_add_signed(Duration::days(days))?.format("%Y-%m-%d");
+    Some(if seconds == 0 { date.to_string() } else { format!("{}T{}", date, time) })
+}
+
+// Formatos numéricos embutidos que são datas ou horas
+pub(crate) fn is_builtin_date_format(id: u32) -> bool {
+    matches!(id, 14..=22 | 27..=36 | 45..=47 | 50..=58)
+}
+
+// Um formato personalizado é de data quando usa d, m, y, h ou s fora de
+// textos entre aspas, caracteres escapados e seções entre colchetes (cores,
+// moedas); [h], [mm] e [ss] são horas acumuladas
+pub(crate) fn is_date_format(code: &str) -> bool {
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c.to_ascii_lowercase() {
+            '"' => {
+                chars.by_ref().find(|&c| c == '"');
+            }
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            '[' => {
+                let section: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let section = section.to_ascii_lowercase();
+                if !section.is_empty() && section.chars().all(|c| matches!(c, 'h' | 'm' | 's')) {
+                    return true;
+                }
+            }
+            'd' | 'm' | 'y' | 'h' | 's' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_serial_dates_in_both_systems() {
+        // O 29/02/1900 que o Excel inventa fica entre 28/02 e 01/03
+        assert_eq!(serial_to_iso(59.0, false).as_deref(), Some("1900-02-28"));
+        assert_eq!(serial_to_iso(61.0, false).as_deref(), Some("1900-03-01"));
+        assert_eq!(serial_to_iso(45292.5, false).as_deref(), Some("2024-01-01T12:00:00"));
+        assert_eq!(serial_to_iso(0.75, false).as_deref(), Some("18:00:00"));
+        assert_eq!(serial_to_iso(0.0, true).as_deref(), Some("1904-01-01"));
+        assert_eq!(serial_to_iso(43830.0, true).as_deref(), Some("2024-01-01"));
+        // Arredondado para o segundo, 23:59:59.9 vira o dia seguinte
+        assert_eq!(serial_to_iso(1.0 - 0.1 / 86_400.0, true).as_deref(), Some("1904-01-02"));
+        assert_eq!(serial_to_iso(-1.0, false), None);
+        assert_eq!(serial_to_iso(3_000_000.0, false), None);
+    }
+
+    #[test]
+    fn recognizes_date_formats() {
+        assert!(is_builtin_date_format(14) && is_builtin_date_format(22) && !is_builtin_date_format(2));
+        assert!(is_date_format("dd/mm/yyyy") && is_date_format("[h]:mm") && is_date_format("[$-416]mmm/yy"));
+        assert!(!is_date_format("0.00") && !is_date_format("\"dias\" 0") && !is_date_format("[Red]#,##0") && !is_date_format("0\\d"));
+    }
+
+    #[test]
+    fn formats_numbers_like_excel() {
+        assert_eq!(number_text(0.1 + 0.2), "0.3");
+        assert_eq!(number_text(30.0), "30");
+        assert_eq!(number_text(-1.5), "-1.5");
+        assert_eq!(number_text(1e20), "100000000000000000000");
+    }
+
+    #[test]
+    fn parses_cell_ranges() {
+        let range = CellRange::parse("B3:F200").unwrap();
+        assert_eq!((range.first_col, range.first_row, range.last_col, range.last_row), (1, 2, Some(5), Some(199)));
+        let range = CellRange::parse("a:d").unwrap();
+        assert_eq!((range.first_col, range.first_row, range.last_col, range.last_row), (0, 0, Some(3), None));
+        let range = CellRange::parse("AA10").unwrap();
+        assert_eq!((range.first_col, range.first_row, range.last_col, range.last_row), (26, 9, None, None));
+        assert!(CellRange::parse("F1:B1").is_none());
+        assert!(CellRange::parse("A0").is_none());
+        assert!(CellRange::parse("ABCD1").is_none());
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::js::fatal;
+use crate::pipeline::CsvPipeline;
+use crate::values;
+use crate::{CsvOptions, FatalError, ProcessingResult, ProcessingTotals};
+
+// Perfil das colunas (`stats`), calculado durante a leitura, sem uma segunda
+// passada pelo arquivo
+#[derive(Clone, Copy)]
+pub(crate) struct StatsConfig {
+    pub(crate) enabled: bool,
+    // Quantos valores mais frequentes entram em `top`
+    pub(crate) top_k: usize,
+    // Só o perfil (`CsvProfiler`): as linhas são descartadas, então o hash e
+    // a deduplicação não são calculados
+    pub(crate) only: bool,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        StatsConfig { enabled: false, top_k: 10, only: false }
+    }
+}
+
+// Bloco `stats` do resultado: os totais e o perfil de cada coluna
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Stats {
+    #[serde(flatten)]
+    pub totals: ProcessingTotals,
+    pub columns: Vec<ColumnStats>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ColumnStats {
+    pub name: String,
+    // "int", "decimal", "date", "bool", "string" ou "empty" (só vazios)
+    #[serde(rename = "type")]
+    pub kind: String,
+    // Valores preenchidos e vazios; `null_rate` é a fração de vazios
+    pub count: u64,
+    pub nulls: u64,
+    pub null_rate: f64,
+    // Estimativa de valores distintos (HyperLogLog, erro típico de 1,6%)
+    pub distinct: u64,
+    // Pelo tipo da coluna: números, datas ISO ou texto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Value>,
+    pub top: Vec<TopValue>,
+    // false quando a coluna tem valores distintos demais para contar todos:
+    // as contagens de `top` passam a ser aproximadas (para baixo)
+    pub top_exact: bool,
+    pub lengths: LengthStats,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TopValue {
+    pub value: String,
+    pub count: u64,
+}
+
+// Tamanho dos valores preenchidos, em caracteres
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LengthStats {
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    // Faixas em potências de 2 (1, 2-3, 4-7, 8-15...); só as que têm valores
+    pub histogram: Vec<LengthBucket>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LengthBucket {
+    pub min: u64,
+    pub max: u64,
+    pub count: u64,
+}
+
+// 2^12 registradores de um byte por coluna
+const HLL_BITS: u32 = 12;
+
+// Contagem aproximada de valores distintos em memória fixa
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        HyperLogLog { registers: vec![0; 1 << HLL_BITS] }
+    }
+
+    fn add(&mut self, value: &str) {
+        let hash = xxh3_64(value.as_bytes());
+        let index = (hash >> (64 - HLL_BITS)) as usize;
+        let rank = (hash << HLL_BITS).leading_zeros().min(64 - HLL_BITS) as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| (-f64::from(r)).exp2()).sum();
+        let raw = alpha * m * m / sum;
+        // Poucos valores: a contagem linear dos registradores zerados é mais precisa
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 { m * (m / zeros as f64).ln() } else { raw };
+        estimate.round() as u64
+    }
+}
+
+// Valores mais frequentes pelo algoritmo de Misra-Gries: até `capacity`
+// contadores; quando enchem, todos perdem um e os zerados saem
+struct TopK {
+    counts: HashMap<String, u64>,
+    capacity: usize,
+    exact: bool,
+}
+
+impl TopK {
+    fn new(top_k: usize) -> Self {
+        TopK { counts: HashMap::new(), capacity: (top_k * 10).max(1000), exact: true }
+    }
+
+    fn add(&mut self, value: &str) {
+        if let Some(count) = self.counts.get_mut(value) {
+            *count += 1;
+        } else if self.counts.len() < self.capacity {
+            self.counts.insert(value.to_string(), 1);
+        } else {
+            self.exact = false;
+            self.counts.retain(|_, count| {
+                *count -= 1;
+                *count > 0
+            });
+        }
+    }
+
+    fn top(&self, k: usize) -> Vec<TopValue> {
+        let mut top: Vec<(&String, &u64)> = self.counts.iter().collect();
+        top.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        top.into_iter()
+            .take(k)
+            .map(|(value, &count)| TopValue { value: value.clone(), count })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Int,
+    Decimal,
+    Date,
+    Bool,
+    Text,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Int => "int",
+            Kind::Decimal => "decimal",
+            Kind::Date => "date",
+            Kind::Bool => "bool",
+            Kind::Text => "string",
+        }
+    }
+
+    // Tipo que acomoda os dois: int com decimal vira decimal, o resto vira texto
+    fn merge(current: Option<Kind>, kind: Kind) -> Kind {
+        match (current, kind) {
+            (None, kind) => kind,
+            (Some(a), b) if a == b => a,
+            (Some(Kind::Int), Kind::Decimal) | (Some(Kind::Decimal), Kind::Int) => Kind::Decimal,
+            _ => Kind::Text,
+        }
+    }
+}
+
+// Tipo de um valor, pelas mesmas regras de `types: "infer"`
+fn classify(value: &str, decimal_separator: Option<char>) -> (Kind, Option<f64>, Option<String>) {
+    match values::infer(value, decimal_separator) {
+        Value::Number(n) if n.is_i64() => (Kind::Int, n.as_f64(), None),
+        Value::Number(n) => (Kind::Decimal, n.as_f64(), None),
+        Value::Bool(_) => (Kind::Bool, None, None),
+        _ => {
+            let value = value.trim();
+            match values::parse_date(value, None).or_else(|| values::parse_datetime(value)) {
+                Some(iso) => (Kind::Date, None, Some(iso)),
+                None => (Kind::Text, None, None),
+            }
+        }
+    }
+}
+
+fn widen<T: PartialOrd + Clone>(range: &mut Option<(T, T)>, value: T) {
+    match range {
+        Some((min, max)) => {
+            if value < *min {
+                *min = value;
+            } else if value > *max {
+                *max = value;
+            }
+        }
+        None => *range = Some((value.clone(), value)),
+    }
+}
+
+struct ColumnProfile {
+    count: u64,
+    nulls: u64,
+    // Deixa de ser calculado quando a coluna vira texto
+    kind: Option<Kind>,
+    numbers: Option<(f64, f64)>,
+    dates: Option<(String, String)>,
+    texts: Option<(String, String)>,
+    distinct: HyperLogLog,
+    top: TopK,
+    // Quantidade de valores por faixa de tamanho: 1, 2-3, 4-7...
+    lengths: [u64; 65],
+    length_sum: u64,
+    length_range: Option<(u64, u64)>,
+}
+
+impl ColumnProfile {
+    fn new(top_k: usize) -> Self {
+        ColumnProfile {
+            count: 0,
+            nulls: 0,
+            kind: None,
+            numbers: None,
+            dates: None,
+            texts: None,
+            distinct: HyperLogLog::new(),
+            top: TopK::new(top_k),
+            lengths: [0; 65],
+            length_sum: 0,
+            length_range: None,
+        }
+    }
+
+    fn record(&mut self, value: &str, top_k: usize, decimal_separator: Option<char>) {
+        if value.trim().is_empty() {
+            self.nulls += 1;
+            return;
+        }
+        self.count += 1;
+        self.distinct.add(value);
+        if top_k > 0 {
+            self.top.add(value);
+        }
+
+        let length = value.chars().count() as u64;
+        self.lengths[64 - length.leading_zeros() as usize] += 1;
+        self.length_sum += length;
+        widen(&mut self.length_range, length);
+        if self.texts.as_ref().is_none_or(|(min, max)| value < min.as_str() || value > max.as_str()) {
+            widen(&mut self.texts, value.to_string());
+        }
+
+        if self.kind != Some(Kind::Text) {
+            let (kind, number, date) = classify(value, decimal_separator);
+            self.kind = Some(Kind::merge(self.kind, kind));
+            if let Some(number) = number {
+                widen(&mut self.numbers, number);
+            }
+            if let Some(date) = date {
+                widen(&mut self.dates, date);
+            }
+        }
+    }
+
+    fn finish(&self, name: &str, rows: u64, top_k: usize) -> ColumnStats {
+        let range: Option<(Value, Value)> = match self.kind {
+            Some(Kind::Int) => self.numbers.map(|(min, max)| ((min as i64).into(), (max as i64).into())),
+            Some(Kind::Decimal) => self.numbers.map(|(min, max)| (min.into(), max.into())),
+            Some(Kind::Date) => self.dates.clone().map(|(min, max)| (min.into(), max.into())),
+            _ => self.texts.clone().map(|(min, max)| (min.into(), max.into())),
+        };
+        let (min, max) = range.unzip();
+        let (length_min, length_max) = self.length_range.unwrap_or((0, 0));
+        let histogram = self
+            .lengths
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(bucket, &count)| match bucket {
+                0 => LengthBucket { min: 0, max: 0, count },
+                _ => LengthBucket { min: 1 << (bucket - 1), max: u64::MAX >> (64 - bucket), count },
+            })
+            .collect();
+
+        ColumnStats {
+            name: name.to_string(),
+            kind: self.kind.map_or("empty", Kind::name).to_string(),
+            count: self.count,
+            nulls: self.nulls,
+            null_rate: if rows == 0 { 0.0 } else { self.nulls as f64 / rows as f64 },
+            distinct: self.distinct.estimate().min(self.count),
+            min,
+            max,
+            top: self.top.top(top_k),
+            top_exact: self.top.exact,
+            lengths: LengthStats {
+                min: length_min,
+                max: length_max,
+                mean: if self.count == 0 { 0.0 } else { self.length_sum as f64 / self.count as f64 },
+                histogram,
+            },
+        }
+    }
+}
+
+// Perfil das colunas de um arquivo, criado junto com o cabeçalho. Recebe as
+// linhas de dados na ordem do arquivo, com os valores como foram lidos.
+pub(crate) struct Profiler {
+    headers: Vec<String>,
+    columns: Vec<ColumnProfile>,
+    top_k: usize,
+    decimal_separator: Option<char>,
+    rows: u64,
+}
+
+impl Profiler {
+    pub(crate) fn new(config: &StatsConfig, headers: &[String], decimal_separator: Option<char>) -> Self {
+        Profiler {
+            headers: headers.to_vec(),
+            columns: headers.iter().map(|_| ColumnProfile::new(config.top_k)).collect(),
+            top_k: config.top_k,
+            decimal_separator,
+            rows: 0,
+        }
+    }
+
+    // Campos que faltam contam como vazios; os que sobram ficam de fora
+    pub(crate) fn record(&mut self, values: &[&str]) {
+        self.rows += 1;
+        for (index, column) in self.columns.iter_mut().enumerate() {
+            column.record(values.get(index).copied().unwrap_or(""), self.top_k, self.decimal_separator);
+        }
+    }
+
+    pub(crate) fn finish(&self, totals: ProcessingTotals) -> Stats {
+        Stats {
+            totals,
+            columns: self
+                .headers
+                .iter()
+                .zip(&self.columns)
+                .map(|(name, column)| column.finish(name, self.rows, self.top_k))
+                .collect(),
+        }
+    }
+}
+
+// Só o perfil do arquivo, sem guardar as linhas: recebe o arquivo em pedaços
+// e devolve `stats` no fim
+#[wasm_bindgen]
+pub struct CsvProfiler {
+    pipeline: CsvPipeline,
+}
+
+impl CsvProfiler {
+    pub fn new(options: &CsvOptions) -> CsvProfiler {
+        let mut options = options.clone();
+        options.stats.enabled = true;
+        options.stats.only = true;
+        CsvProfiler { pipeline: CsvPipeline::new(&options) }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), FatalError> {
+        // As linhas de cada pedaço são descartadas
+        self.pipeline.push(chunk, &mut ProcessingResult::default())
+    }
+
+    pub fn finish_stats(&mut self) -> Result<Stats, FatalError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.finish(&mut batch)?;
+        Ok(batch.stats.unwrap_or_default())
+    }
+}
+
+#[wasm_bindgen]
+impl CsvProfiler {
+    pub fn with_options(options: &CsvOptions) -> CsvProfiler {
+        CsvProfiler::new(options)
+    }
+
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<(), JsError> {
+        self.push(chunk).map_err(fatal)
+    }
+
+    // Encerra a entrada e devolve `stats` como JSON
+    pub fn finish(&mut self) -> Result<JsValue, JsError> {
+        let stats = self.finish_stats().map_err(fatal)?;
+        Ok(JsValue::from_str(&serde_json::to_string(&stats)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(values: &[&str]) -> ColumnStats {
+        let mut profile = ColumnProfile::new(3);
+        for value in values {
+            profile.record(value, 3, None);
+        }
+        profile.finish("coluna", values.len() as u64, 3)
+    }
+
+    #[test]
+    fn hyperloglog_small_counts() {
+        let mut hll = HyperLogLog::new();
+        assert_eq!(hll.estimate(), 0);
+        for i in 0..100 {
+            // Repetir um valor não muda a contagem
+            hll.add(&i.to_string());
+            hll.add(&i.to_string());
+        }
+        // Poucos valores caem na contagem linear, que erra por um ou dois
+        assert!(hll.estimate().abs_diff(100) <= 2, "estimativa {}", hll.estimate());
+    }
+
+    #[test]
+    fn hyperloglog_accuracy() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..100_000 {
+            hll.add(&format!("valor-{}", i));
+        }
+        let error = (hll.estimate() as f64 - 100_000.0).abs() / 100_000.0;
+        assert!(error < 0.03, "estimativa {}", hll.estimate());
+    }
+
+    #[test]
+    fn top_k_exact_counts() {
+        let mut top = TopK::new(2);
+        for value in ["b", "a", "c", "a", "b", "a"] {
+            top.add(value);
+        }
+        let top_values = top.top(3);
+        let values: Vec<(&str, u64)> = top_values.iter().map(|t| (t.value.as_str(), t.count)).collect();
+        // Empates saem em ordem alfabética
+        assert_eq!(values, [("a", 3), ("b", 2), ("c", 1)]);
+        assert!(top.exact);
+    }
+
+    #[test]
+    fn top_k_heavy_hitter() {
+        // Um valor em 10% de 200 mil linhas, no meio de valores que só
+        // aparecem uma vez e enchem os contadores
+        let mut top = TopK::new(10);
+        for i in 0..200_000 {
+            top.add(&if i % 10 == 0 { "frequente".to_string() } else { i.to_string() });
+        }
+        assert!(!top.exact);
+        let first = &top.top(1)[0];
+        assert_eq!(first.value, "frequente");
+        // Misra-Gries conta para baixo, com erro de até n / capacidade
+        assert!(first.count <= 20_000 && first.count >= 20_000 - 200_000 / top.capacity as u64);
+    }
+
+    #[test]
+    fn type_merge() {
+        assert!(Kind::merge(None, Kind::Int) == Kind::Int);
+        assert!(Kind::merge(Some(Kind::Int), Kind::Decimal) == Kind::Decimal);
+        assert!(Kind::merge(Some(Kind::Decimal), Kind::Int) == Kind::Decimal);
+        assert!(Kind::merge(Some(Kind::Date), Kind::Date) == Kind::Date);
+        assert!(Kind::merge(Some(Kind::Int), Kind::Date) == Kind::Text);
+        assert!(Kind::merge(Some(Kind::Bool), Kind::Int) == Kind::Text);
+
+        let numbers = column(&["3", "1,5", "", "10"]);
+        assert_eq!((numbers.kind.as_str(), numbers.count, numbers.nulls), ("decimal", 3, 1));
+        assert_eq!((numbers.min, numbers.max), (Some(1.5.into()), Some(10.0.into())));
+
+        let dates = column(&["31/12/2023", "2024-01-15"]);
+        assert_eq!(dates.kind, "date");
+        assert_eq!((dates.min, dates.max), (Some("2023-12-31".into()), Some("2024-01-15".into())));
+
+        // Um texto no meio dos números faz a coluna virar texto, com min e max de texto
+        let mixed = column(&["10", "abc", "2"]);
+        assert_eq!(mixed.kind, "string");
+        assert_eq!((mixed.min, mixed.max), (Some("10".into()), Some("abc".into())));
+
+        assert_eq!(column(&["", " "]).kind, "empty");
+    }
+
+    #[test]
+    fn profile_skips_hash_and_dedup() {
+        let mut options = CsvOptions::default();
+        options.set_hash_columns(vec!["nao_existe".to_string()]);
+        options.set_dedup("drop").unwrap();
+        let csv = b"id,nome\n1,Ana\n1,Ana\n2,Bia\n";
+
+        // O hash precisaria de uma coluna que não existe; no perfil ele nem é criado
+        assert!(crate::process(csv, &options).is_err());
+        let stats = crate::profile(csv, &options).unwrap();
+        assert_eq!((stats.totals.total_rows, stats.totals.valid_rows, stats.totals.duplicate_rows), (3, 3, 0));
+        assert_eq!(stats.columns[1].top[0].value, "Ana");
+        assert_eq!(stats.columns[1].top[0].count, 2);
+    }
+}
@@ -0,0 +1,201 @@
+use wasm_bindgen::prelude::*;
+use serde::Serialize;
+
+use crate::js::{fatal, to_js};
+use crate::pipeline::CsvPipeline;
+use crate::{write_ndjson, write_ndjson_summary, CsvOptions, CsvSchema, ProcessingResult, ProcessingTotals};
+
+// Resumo devolvido por `finish()`: o último lote de linhas mais os totais
+#[derive(Serialize)]
+struct StreamSummary {
+    #[serde(flatten)]
+    batch: ProcessingResult,
+    #[serde(flatten)]
+    totals: ProcessingTotals,
+}
+
+// Processador em streaming: recebe o arquivo em pedaços de bytes e devolve
+// as linhas processadas e os erros em lotes, sem precisar do arquivo inteiro
+// em memória. Depois de um erro fatal, toda chamada lança o mesmo erro.
+#[wasm_bindgen]
+pub struct CsvStreamProcessor {
+    pipeline: CsvPipeline,
+}
+
+impl Default for CsvStreamProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl CsvStreamProcessor {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CsvStreamProcessor {
+        CsvStreamProcessor::with_options(&CsvOptions::default())
+    }
+
+    // Processador em streaming que valida cada coluna pelo schema compilado
+    pub fn with_schema(schema: &CsvSchema) -> CsvStreamProcessor {
+        let mut options = CsvOptions::default();
+        options.set_schema(schema);
+        CsvStreamProcessor::with_options(&options)
+    }
+
+    // Processador em streaming com o dialeto e o schema definidos em `options`
+    pub fn with_options(options: &CsvOptions) -> CsvStreamProcessor {
+        CsvStreamProcessor {
+            pipeline: CsvPipeline::new(options),
+        }
+    }
+
+    // Processa um pedaço do arquivo e devolve, como JSON, o lote de linhas
+    // que ficaram completas com ele
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.push(chunk, &mut batch).map_err(fatal)?;
+
+        Ok(JsValue::from_str(&serde_json::to_string(&batch)?))
+    }
+
+    // Encerra a entrada e devolve o último lote junto com os totais
+    pub fn finish(&mut self) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.finish(&mut batch).map_err(fatal)?;
+
+        let summary = StreamSummary {
+            batch,
+            totals: self.pipeline.totals(),
+        };
+        Ok(JsValue::from_str(&serde_json::to_string(&summary)?))
+    }
+
+    // Como `push_chunk`, mas devolve o lote como objeto JavaScript, sem a
+    // string JSON intermediária. Opcional, como `process_csv_object`
+    pub fn push_chunk_object(&mut self, chunk: &[u8]) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.push(chunk, &mut batch).map_err(fatal)?;
+        to_js(&batch)
+    }
+
+    // Como `finish`, devolvendo um objeto JavaScript
+    pub fn finish_object(&mut self) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.finish(&mut batch).map_err(fatal)?;
+        to_js(&StreamSummary {
+            batch,
+            totals: self.pipeline.totals(),
+        })
+    }
+
+    // Como `push_chunk`, mas devolve o lote em NDJSON: um objeto JSON por
+    // linha, com `type` "row" ou "error"
+    pub fn push_chunk_ndjson(&mut self, chunk: &[u8]) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.push(chunk, &mut batch).map_err(fatal)?;
+
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &batch)?;
+        Ok(JsValue::from_str(&String::from_utf8_lossy(&output)))
+    }
+
+    // Como `finish`, em NDJSON: o último lote e uma linha `type` "summary"
+    // com os totais
+    pub fn finish_ndjson(&mut self) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.finish(&mut batch).map_err(fatal)?;
+
+        let mut output = Vec::new();
+        write_ndjson(&mut output, &batch)?;
+        write_ndjson_summary(&mut output, None, self.pipeline.totals())?;
+        Ok(JsValue::from_str(&String::from_utf8_lossy(&output)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::errors::FatalErrorKind;
+
+    // Lotes de `push_chunk` juntos e o resumo de `finish`, como o JavaScript recebe
+    fn stream(options: &CsvOptions, chunks: &[&[u8]]) -> (ProcessingResult, Value) {
+        let mut processor = CsvStreamProcessor::with_options(options);
+        let mut result = ProcessingResult::default();
+        for chunk in chunks {
+            let mut batch = ProcessingResult::default();
+            processor.pipeline.push(chunk, &mut batch).unwrap();
+            result.append(batch);
+        }
+        let mut batch = ProcessingResult::default();
+        processor.pipeline.finish(&mut batch).unwrap();
+        let summary = serde_json::to_value(StreamSummary { batch, totals: processor.pipeline.totals() }).unwrap();
+        (result, summary)
+    }
+
+    fn rows_and_errors(result: &ProcessingResult, summary: &Value) -> (Value, Value) {
+        let mut rows = serde_json::to_value(result.processed_rows()).unwrap();
+        let mut errors = serde_json::to_value(result.errors()).unwrap();
+        rows.as_array_mut().unwrap().extend(summary["processed_rows"].as_array().unwrap().clone());
+        errors.as_array_mut().unwrap().extend(summary["errors"].as_array().unwrap().clone());
+        (rows, errors)
+    }
+
+    fn lines(items: &Value) -> Vec<u64> {
+        items.as_array().unwrap().iter().map(|item| item["line"].as_u64().unwrap()).collect()
+    }
+
+    #[test]
+    fn same_result_at_any_chunk_boundary() {
+        // BOM, CRLF, campo entre aspas com quebra de linha, acentos, linha
+        // repetida, linha inválida e a última linha sem quebra
+        let input = "\u{feff}id,nome,idade\r\n1,\"Ana\r\nMaria\",30\r\n2,José,x\r\n3,Çá,40\r\n3,Çá,40\r\n4,Bia,50";
+        let input = input.as_bytes();
+        let mut options = CsvOptions::default();
+        options.set_schema(&CsvSchema::new(r#"{ "columns": { "idade": { "type": "int" } } }"#).unwrap());
+        options.set_dedup("flag").unwrap();
+
+        let (whole, summary) = stream(&options, &[input]);
+        let expected = rows_and_errors(&whole, &summary);
+        assert_eq!((lines(&expected.0), lines(&expected.1)), (vec![2, 4, 6], vec![3, 5]));
+        assert_eq!(expected.0[0]["data"]["nome"], "Ana\r\nMaria");
+
+        for split in 0..=input.len() {
+            let (start, end) = input.split_at(split);
+            let (result, summary) = stream(&options, &[start, end]);
+            assert_eq!(rows_and_errors(&result, &summary), expected, "split at {}", split);
+            assert_eq!(summary["total_rows"], 5);
+        }
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        let (result, summary) = stream(&options, &bytes);
+        assert_eq!(rows_and_errors(&result, &summary), expected);
+    }
+
+    #[test]
+    fn finish_returns_the_last_batch_and_the_totals() {
+        let (result, summary) = stream(&CsvOptions::default(), &[b"a,b\n1,2\n3,", b"4"]);
+        // A linha sem quebra no fim só sai no `finish`
+        assert_eq!(result.processed_rows().len(), 1);
+        assert_eq!(summary["processed_rows"][0]["data"], json!({"a": "3", "b": "4"}));
+        assert_eq!(
+            (&summary["total_rows"], &summary["valid_rows"], &summary["invalid_rows"], &summary["bytes_processed"]),
+            (&json!(2), &json!(2), &json!(0), &json!(11))
+        );
+    }
+
+    #[test]
+    fn fatal_error_is_repeated() {
+        let mut options = CsvOptions::default();
+        options.set_max_record_bytes(Some(8));
+        let mut processor = CsvStreamProcessor::with_options(&options);
+        let mut batch = ProcessingResult::default();
+        processor.pipeline.push(b"a,b\n1,2\n", &mut batch).unwrap();
+        let error = processor.pipeline.push(b"muito,longo\n", &mut batch).unwrap_err();
+        assert_eq!(error.kind(), FatalErrorKind::LimitExceeded);
+        // O restante da entrada é ignorado e o mesmo erro volta em toda chamada
+        assert_eq!(processor.pipeline.push(b"3,4\n", &mut batch), Err(error.clone()));
+        assert_eq!(processor.pipeline.finish(&mut batch), Err(error));
+        assert_eq!(batch.processed_rows().len(), 1);
+    }
+}
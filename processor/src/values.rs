@@ -0,0 +1,243 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde_json::{Number, Value};
+
+// Como os valores das células aparecem em `processed_rows`
+#[derive(Clone, Copy, Default, PartialEq)]
+pub(crate) enum TypeMode {
+    // Tudo como string, como nas versões anteriores
+    #[default]
+    Off,
+    // Colunas int, decimal, date e bool do schema são convertidas
+    Schema,
+    // Além do schema, as colunas fora dele têm o tipo inferido pelo valor
+    Infer,
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct TypeConfig {
+    pub(crate) mode: TypeMode,
+    // Separador decimal dos números; None decide por valor ("1.234,56" ou "1,234.56")
+    pub(crate) decimal_separator: Option<char>,
+}
+
+impl TypeConfig {
+    pub(crate) fn enabled(&self) -> bool {
+        self.mode != TypeMode::Off
+    }
+}
+
+// Reescreve um número com '.' decimal e sem separador de milhar, como
+// "-1234.56". Sem separador configurado, quando os dois aparecem o último é
+// o decimal; quando só um aparece, ele é decimal se aparece uma vez e de
+// milhar se aparece mais de uma.
+pub(crate) fn normalize_number(value: &str, decimal_separator: Option<char>) -> Option<String> {
+    let (sign, digits) = match value.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", value.strip_prefix('+').unwrap_or(value)),
+    };
+    if !digits.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',') {
+        return None;
+    }
+
+    let (decimal, thousands) = match decimal_separator {
+        Some('.') => (Some('.'), Some(',')),
+        Some(_) => (Some(','), Some('.')),
+        None => {
+            let dot = digits.rfind('.');
+            let comma = digits.rfind(',');
+            match (dot, comma) {
+                (Some(d), Some(c)) if d > c => (Some('.'), Some(',')),
+                (Some(_), Some(_)) => (Some(','), Some('.')),
+                (Some(_), None) if digits.matches('.').count() == 1 => (Some('.'), None),
+                (Some(_), None) => (None, Some('.')),
+                (None, Some(_)) if digits.matches(',').count() == 1 => (Some(','), None),
+                (None, Some(_)) => (None, Some(',')),
+                (None, None) => (None, None),
+            }
+        }
+    };
+
+    let (int_part, frac_part) = match decimal.and_then(|d| digits.split_once(d)) {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (digits, None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    // Grupos de milhar: o primeiro com 1 a 3 dígitos, os demais com 3
+    let int_digits = match thousands.filter(|&t| int_part.contains(t)) {
+        Some(t) => {
+            let mut groups = int_part.split(t);
+            let first = groups.next().unwrap_or("");
+            if !all_digits(first) || first.len() > 3 {
+                return None;
+            }
+            let mut int_digits = first.to_string();
+            for group in groups {
+                if group.len() != 3 || !all_digits(group) {
+                    return None;
+                }
+                int_digits.push_str(group);
+            }
+            int_digits
+        }
+        None if all_digits(int_part) => int_part.to_string(),
+        None => return None,
+    };
+
+    match frac_part {
+        Some(frac) if all_digits(frac) => Some(format!("{}{}.{}", sign, int_digits, frac)),
+        Some(_) => None,
+        None => Some(format!("{}{}", sign, int_digits)),
+    }
+}
+
+pub(crate) fn parse_int(value: &str, decimal_separator: Option<char>) -> Option<i64> {
+    normalize_number(value, decimal_separator)?.parse().ok()
+}
+
+pub(crate) fn parse_decimal(value: &str, decimal_separator: Option<char>) -> Option<Number> {
+    let number: f64 = normalize_number(value, decimal_separator)?.parse().ok()?;
+    Number::from_f64(number)
+}
+
+// Datas ISO (aaaa-mm-dd) e brasileiras (dd/mm/aaaa), devolvidas em ISO-8601
+pub(crate) fn parse_date(value: &str, format: Option<&str>) -> Option<String> {
+    let date = match format {
+        Some(format) => NaiveDate::parse_from_str(value, format).ok()?,
+        None => ["%Y-%m-%d", "%d/%m/%Y"]
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(value, format).ok())?,
+    };
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+// Data e hora, com ou sem fuso, devolvidas em ISO-8601
+pub(crate) fn parse_datetime(value: &str) -> Option<String> {
+    if DateTime::parse_from_rfc3339(value).is_ok() {
+        return Some(value.to_string());
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%d/%m/%Y %H:%M:%S", "%d/%m/%Y %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .map(|datetime| datetime.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+// Valores aceitos pelo tipo "bool" do schema
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "sim" | "s" => Some(true),
+        "false" | "0" | "não" | "nao" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+// Tipo inferido pelo próprio valor. Na dúvida o valor continua string: zeros
+// à esquerda (CEP, códigos) e números longos demais para um f64 (CPF, CNPJ
+// com pontuação, cartões) não viram números.
+pub(crate) fn infer(value: &str, decimal_separator: Option<char>) -> Value {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Value::Null;
+    }
+    match trimmed.to_lowercase().as_str() {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+
+    if let Some(normalized) = normalize_number(trimmed, decimal_separator) {
+        let digits = normalized.trim_start_matches('-');
+        let int_part = digits.split('.').next().unwrap_or("");
+        let significant = digits.bytes().filter(u8::is_ascii_digit).count();
+        if (int_part.len() == 1 || !int_part.starts_with('0')) && significant <= 15 {
+            if let Ok(int) = normalized.parse::<i64>() {
+                return Value::Number(int.into());
+            }
+            if let Some(number) = normalized.parse().ok().and_then(Number::from_f64) {
+                return Value::Number(number);
+            }
+        }
+    }
+
+    match parse_date(trimmed, None).or_else(|| parse_datetime(trimmed)) {
+        Some(iso) => Value::String(iso),
+        None => Value::String(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn numbers_with_either_separator() {
+        let cases = [
+            ("1234", None, Some("1234")),
+            ("-1.234,56", None, Some("-1234.56")),
+            ("+1,234.56", None, Some("1234.56")),
+            ("1.234.567", None, Some("1234567")),
+            ("1,5", None, Some("1.5")),
+            ("1.5", None, Some("1.5")),
+            // Com o separador fixo, o outro só pode ser de milhar
+            ("1.234", Some(','), Some("1234")),
+            ("1,234", Some('.'), Some("1234")),
+            ("1.23.4", None, None),
+            ("12,34,5", None, None),
+            ("1.2345,6", None, None),
+            ("1,", None, None),
+            ("abc", None, None),
+            ("", None, None),
+        ];
+        for (value, separator, expected) in cases {
+            assert_eq!(normalize_number(value, separator).as_deref(), expected, "{:?}", value);
+        }
+        assert_eq!(parse_int("1.234", Some(',')), Some(1234));
+        assert_eq!(parse_int("1,5", None), None);
+        assert_eq!(parse_decimal("1.234,5", None), Number::from_f64(1234.5));
+    }
+
+    #[test]
+    fn dates_and_booleans() {
+        assert_eq!(parse_date("31/12/2023", None).as_deref(), Some("2023-12-31"));
+        assert_eq!(parse_date("2024-02-29", None).as_deref(), Some("2024-02-29"));
+        assert_eq!(parse_date("2023-02-29", None), None);
+        assert_eq!(parse_date("12-31-2023", Some("%m-%d-%Y")).as_deref(), Some("2023-12-31"));
+        assert_eq!(parse_datetime("31/12/2023 23:59").as_deref(), Some("2023-12-31T23:59:00"));
+        assert_eq!(parse_datetime("2023-12-31T23:59:00-03:00").as_deref(), Some("2023-12-31T23:59:00-03:00"));
+        assert_eq!(parse_datetime("2023-12-31"), None);
+
+        assert_eq!(parse_bool("Sim"), Some(true));
+        assert_eq!(parse_bool("NÃO"), Some(false));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("talvez"), None);
+    }
+
+    #[test]
+    fn infer_types() {
+        let cases = [
+            ("42", json!(42)),
+            ("-1.234,56", json!(-1234.56)),
+            (" 3.5 ", json!(3.5)),
+            ("0", json!(0)),
+            ("0,5", json!(0.5)),
+            ("TRUE", json!(true)),
+            ("31/12/2023", json!("2023-12-31")),
+            ("", Value::Null),
+            ("  ", Value::Null),
+            // Zeros à esquerda e números longos demais continuam texto
+            ("01310-100", json!("01310-100")),
+            ("00123", json!("00123")),
+            ("1234567890123456", json!("1234567890123456")),
+            ("123.456.789-09", json!("123.456.789-09")),
+            ("sim", json!("sim")),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(infer(value, None), expected, "{:?}", value);
+        }
+        // O separador configurado muda a leitura de "1.234"
+        assert_eq!(infer("1.234", None), json!(1.234));
+        assert_eq!(infer("1.234", Some(',')), json!(1234));
+    }
+}
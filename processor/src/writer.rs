@@ -0,0 +1,443 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::errors::Severity;
+use crate::options::{Dialect, FieldCount};
+use crate::js::fatal;
+use crate::pipeline::{CsvPipeline, EXTRA_FIELDS};
+use crate::{CsvOptions, FileError, ProcessedRow, ProcessingResult, ProcessingTotals, Stats, ValidationError};
+
+// Escreve registros CSV com o mesmo dialeto da leitura
+struct RecordWriter {
+    core: csv_core::Writer,
+}
+
+impl RecordWriter {
+    fn new(dialect: &Dialect) -> Self {
+        let mut builder = csv_core::WriterBuilder::new();
+        builder
+            .delimiter(dialect.delimiter as u8)
+            .quote(dialect.quote as u8)
+            .double_quote(dialect.double_quote);
+        if let Some(escape) = dialect.escape {
+            builder.escape(escape as u8);
+        }
+        RecordWriter { core: builder.build() }
+    }
+
+    fn write<'a>(&mut self, fields: impl IntoIterator<Item = &'a str>, out: &mut Vec<u8>) {
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                // Aspas de fechamento e o delimitador
+                self.put(out, 2, |core, buf| core.delimiter(buf).1);
+            }
+            // Pior caso do csv_core: o campo inteiro de aspas, escapadas
+            self.put(out, 2 + 2 * field.len(), |core, buf| core.field(field.as_bytes(), buf).2);
+        }
+        self.put(out, 8, |core, buf| core.terminator(buf).1);
+    }
+
+    fn put<F>(&mut self, out: &mut Vec<u8>, max: usize, write: F)
+    where
+        F: FnOnce(&mut csv_core::Writer, &mut [u8]) -> usize,
+    {
+        let start = out.len();
+        out.resize(start + max, 0);
+        let n = write(&mut self.core, &mut out[start..]);
+        out.truncate(start + n);
+    }
+}
+
+// Escreve o resultado de volta como dois CSVs: as linhas válidas e as
+// rejeitadas, com as colunas na ordem e com os nomes do arquivo original
+pub struct SplitWriter {
+    records: RecordWriter,
+    has_headers: bool,
+    include_line: bool,
+    include_hash: bool,
+    // Coluna `_extra` nas válidas, com os campos a mais como array JSON
+    include_extra: bool,
+    // Nomes usados em `data` e nomes escritos no cabeçalho
+    headers: Option<Vec<String>>,
+    names: Option<Vec<String>>,
+    // Cabeçalho já escrito; só é repetido se mudar entre arquivos
+    written_headers: Option<Vec<String>>,
+    // As rejeitadas só ganham a coluna `_extra` quando alguma linha tem
+    // campos a mais. Até aparecer uma, o cabeçalho e as linhas ficam retidos
+    // e saem em `finish` (ou quando o cabeçalho muda), sem a coluna.
+    rejected_extra: bool,
+    rejected_names: Option<Vec<String>>,
+    held: Vec<Vec<String>>,
+    valid: Vec<u8>,
+    rejected: Vec<u8>,
+}
+
+impl SplitWriter {
+    pub fn new(options: &CsvOptions) -> Self {
+        SplitWriter {
+            records: RecordWriter::new(&options.dialect),
+            has_headers: options.dialect.has_headers,
+            include_line: false,
+            include_hash: false,
+            include_extra: options.field_count == FieldCount::Extra,
+            headers: None,
+            names: None,
+            written_headers: None,
+            rejected_extra: false,
+            rejected_names: None,
+            held: Vec::new(),
+            valid: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+
+    // Acrescenta a coluna `line` nos dois arquivos
+    pub fn set_include_line(&mut self, include_line: bool) {
+        self.include_line = include_line;
+    }
+
+    // Acrescenta a coluna `hash` nas linhas válidas
+    pub fn set_include_hash(&mut self, include_hash: bool) {
+        self.include_hash = include_hash;
+    }
+
+    // Escreve um lote: as linhas válidas em `valid` e as rejeitadas em
+    // `rejected`, com as colunas `error_code` e `error_message`
+    pub fn write_batch<V, R>(&mut self, batch: &ProcessingResult, valid: &mut V, rejected: &mut R) -> io::Result<()>
+    where
+        V: Write,
+        R: Write,
+    {
+        if let Some(headers) = batch.headers() {
+            let names = batch.source_headers.as_deref().unwrap_or(headers);
+            if self.written_headers.as_deref() != Some(names) {
+                self.release_rejected();
+                self.names = Some(names.to_vec());
+                self.write_headers();
+            }
+            self.headers = Some(headers.to_vec());
+        }
+        for row in batch.processed_rows() {
+            self.write_valid(row);
+        }
+        // Erros da mesma linha chegam juntos e viram um único registro
+        let errors: Vec<&ValidationError> = batch.errors().iter().filter(|e| e.severity == Severity::Error).collect();
+        for group in errors.chunk_by(|a, b| a.line == b.line) {
+            self.write_rejected(group);
+        }
+        self.flush(valid, rejected)
+    }
+
+    // Escreve as rejeitadas retidas, sem a coluna `_extra`. Chamado no fim
+    // da entrada, depois do último lote.
+    pub fn finish<V, R>(&mut self, valid: &mut V, rejected: &mut R) -> io::Result<()>
+    where
+        V: Write,
+        R: Write,
+    {
+        self.release_rejected();
+        self.flush(valid, rejected)
+    }
+
+    fn flush<V: Write, R: Write>(&mut self, valid: &mut V, rejected: &mut R) -> io::Result<()> {
+        valid.write_all(&self.valid)?;
+        rejected.write_all(&self.rejected)?;
+        self.valid.clear();
+        self.rejected.clear();
+        Ok(())
+    }
+
+    fn write_headers(&mut self) {
+        let Some(names) = &self.names else {
+            return;
+        };
+        self.written_headers = Some(names.clone());
+        self.rejected_names = self.has_headers.then(|| names.clone());
+        if !self.has_headers {
+            return;
+        }
+        let valid = names
+            .iter()
+            .map(String::as_str)
+            .chain(self.include_extra.then_some(EXTRA_FIELDS))
+            .chain(self.include_line.then_some("line"))
+            .chain(self.include_hash.then_some("hash"));
+        self.records.write(valid, &mut self.valid);
+    }
+
+    // Cabeçalho das rejeitadas, com ou sem `_extra`, seguido das linhas
+    // retidas até agora
+    fn write_rejected_headers(&mut self, extra: bool) {
+        if let Some(names) = self.rejected_names.take() {
+            let fields = names
+                .iter()
+                .map(String::as_str)
+                .chain(extra.then_some(EXTRA_FIELDS))
+                .chain(self.include_line.then_some("line"))
+                .chain(["error_code", "error_message"]);
+            self.records.write(fields, &mut self.rejected);
+        }
+        for mut record in std::mem::take(&mut self.held) {
+            if extra {
+                let at = self.headers.as_ref().map_or(0, Vec::len).min(record.len());
+                record.insert(at, String::new());
+            }
+            self.records.write(record.iter().map(String::as_str), &mut self.rejected);
+        }
+    }
+
+    // Fim de um cabeçalho: o que ainda estava retido sai sem `_extra`
+    fn release_rejected(&mut self) {
+        if !self.rejected_extra {
+            self.write_rejected_headers(false);
+        }
+        self.rejected_extra = false;
+    }
+
+    fn write_valid(&mut self, row: &ProcessedRow) {
+        let Some(headers) = &self.headers else {
+            return;
+        };
+        let values: Vec<String> = headers.iter().map(|h| cell(row.data.get(h))).collect();
+        let extra = self.include_extra.then(|| cell(row.data.get(EXTRA_FIELDS)));
+        let line = row.line.to_string();
+        let fields = values
+            .iter()
+            .map(String::as_str)
+            .chain(extra.as_deref())
+            .chain(self.include_line.then_some(line.as_str()))
+            .chain(self.include_hash.then_some(row.hash.as_str()));
+        self.records.write(fields, &mut self.valid);
+    }
+
+    fn write_rejected(&mut self, errors: &[&ValidationError]) {
+        let first = errors[0];
+        // Os campos como foram lidos, completados ou cortados no tamanho do
+        // cabeçalho; os campos a mais vão para `_extra` como array JSON. Sem
+        // os campos lidos, os valores de `data`.
+        let (mut record, extra): (Vec<String>, String) = match (&first.fields, &self.headers) {
+            (Some(fields), Some(headers)) => {
+                let mut values = fields.clone();
+                let extra = match values.len() > headers.len() {
+                    true => serde_json::Value::from(values.split_off(headers.len())).to_string(),
+                    false => String::new(),
+                };
+                values.resize(headers.len(), String::new());
+                (values, extra)
+            }
+            (Some(fields), None) => (fields.clone(), String::new()),
+            (None, Some(headers)) => (
+                headers.iter().map(|h| cell(first.data.get(h))).collect(),
+                cell(first.data.get(EXTRA_FIELDS)),
+            ),
+            (None, None) => (Vec::new(), String::new()),
+        };
+        let at = record.len();
+        if self.include_line {
+            record.push(first.line.to_string());
+        }
+        let codes = errors
+            .iter()
+            .map(|e| serde_json::to_value(e.code).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(";");
+        record.push(codes);
+        record.push(errors.iter().map(|e| e.error.as_str()).collect::<Vec<_>>().join("; "));
+
+        if !self.rejected_extra && extra.is_empty() {
+            self.held.push(record);
+            return;
+        }
+        if !self.rejected_extra {
+            self.rejected_extra = true;
+            self.write_rejected_headers(true);
+        }
+        record.insert(at, extra);
+        self.records.write(record.iter().map(String::as_str), &mut self.rejected);
+    }
+}
+
+// Valor de uma célula como texto; valores tipados voltam à forma JSON
+fn cell(value: Option<&serde_json::Value>) -> String {
+    match value {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+// Pedaços de CSV produzidos por um lote
+#[derive(Serialize)]
+struct SplitBatch<'a> {
+    valid: String,
+    rejected: String,
+    // Os erros do arquivo não têm lugar nos CSVs e seguem à parte
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    file_errors: &'a [FileError],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<&'a Stats>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    totals: Option<ProcessingTotals>,
+}
+
+// Processador em streaming que devolve, em vez de JSON, o texto dos dois
+// CSVs de saída: linhas válidas e rejeitadas
+#[wasm_bindgen]
+pub struct CsvSplitter {
+    pipeline: CsvPipeline,
+    writer: SplitWriter,
+}
+
+#[wasm_bindgen]
+impl CsvSplitter {
+    pub fn with_options(options: &CsvOptions, include_line: bool, include_hash: bool) -> CsvSplitter {
+        let mut writer = SplitWriter::new(options);
+        writer.set_include_line(include_line);
+        writer.set_include_hash(include_hash);
+        CsvSplitter { pipeline: CsvPipeline::new(options), writer }
+    }
+
+    // Processa um pedaço do arquivo e devolve `{ valid, rejected }` com o
+    // texto CSV das linhas que ficaram completas
+    pub fn push_chunk(&mut self, chunk: &[u8]) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.push(chunk, &mut batch).map_err(fatal)?;
+        self.split(&batch, None)
+    }
+
+    // Encerra a entrada e devolve o último pedaço junto com os totais
+    pub fn finish(&mut self) -> Result<JsValue, JsError> {
+        let mut batch = ProcessingResult::default();
+        self.pipeline.finish(&mut batch).map_err(fatal)?;
+        let totals = self.pipeline.totals();
+        self.split(&batch, Some(totals))
+    }
+
+    // Com `totals`, é o último lote e as rejeitadas retidas saem junto
+    fn split(&mut self, batch: &ProcessingResult, totals: Option<ProcessingTotals>) -> Result<JsValue, JsError> {
+        let (mut valid, mut rejected) = (Vec::new(), Vec::new());
+        self.writer.write_batch(batch, &mut valid, &mut rejected)?;
+        if totals.is_some() {
+            self.writer.finish(&mut valid, &mut rejected)?;
+        }
+        let output = SplitBatch {
+            valid: String::from_utf8_lossy(&valid).into_owned(),
+            rejected: String::from_utf8_lossy(&rejected).into_owned(),
+            file_errors: batch.file_errors(),
+            stats: batch.stats(),
+            totals,
+        };
+        Ok(JsValue::from_str(&serde_json::to_string(&output)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process, CsvSchema};
+
+    // Passa a entrada pelo pipeline em pedaços de `chunk` bytes e devolve o
+    // texto das válidas e das rejeitadas
+    fn split(options: &CsvOptions, input: &str, chunk: usize) -> (String, String) {
+        let mut pipeline = CsvPipeline::new(options);
+        let mut writer = SplitWriter::new(options);
+        let (mut valid, mut rejected) = (Vec::new(), Vec::new());
+        for piece in input.as_bytes().chunks(chunk) {
+            let mut batch = ProcessingResult::default();
+            pipeline.push(piece, &mut batch).unwrap();
+            writer.write_batch(&batch, &mut valid, &mut rejected).unwrap();
+        }
+        let mut batch = ProcessingResult::default();
+        pipeline.finish(&mut batch).unwrap();
+        writer.write_batch(&batch, &mut valid, &mut rejected).unwrap();
+        writer.finish(&mut valid, &mut rejected).unwrap();
+        (String::from_utf8(valid).unwrap(), String::from_utf8(rejected).unwrap())
+    }
+
+    fn rows(result: &ProcessingResult) -> Vec<&serde_json::Value> {
+        result.processed_rows().iter().map(ProcessedRow::data).collect()
+    }
+
+    #[test]
+    fn dialect_round_trip() {
+        let mut options = CsvOptions::default();
+        options.set_delimiter(';').unwrap();
+        options.set_quote('\'').unwrap();
+        let input = "nome;obs\n'Silva; Ana';'diz ''oi'''\nBia;'duas\nlinhas'\n;x\n";
+        let (valid, rejected) = split(&options, input, 1024);
+
+        assert_eq!(valid, "nome;obs\n'Silva; Ana';'diz ''oi'''\nBia;'duas\nlinhas'\n");
+        assert_eq!(rejected, "nome;obs;error_code;error_message\n;x;empty_fields;Row contains empty fields: nome.\n");
+
+        // Lidas de volta com o mesmo dialeto, as válidas dão os mesmos valores
+        let original = process(input.as_bytes(), &options).unwrap();
+        let reread = process(valid.as_bytes(), &options).unwrap();
+        assert_eq!(rows(&reread), rows(&original));
+        assert!(!reread.has_errors());
+    }
+
+    #[test]
+    fn extra_column_only_when_a_row_needs_it() {
+        let options = CsvOptions::default();
+        let (_, rejected) = split(&options, "id,nome\n1,\n", 1024);
+        assert_eq!(rejected, "id,nome,error_code,error_message\n1,,empty_fields,Row contains empty fields: nome.\n");
+
+        // A linha longa chega num lote depois da curta, que estava retida e
+        // ganha a coluna vazia
+        let input = "id,nome\n1,\n2,Bia,x,y\n3,Caio\n";
+        for chunk in [1, 7, 1024] {
+            let (valid, rejected) = split(&options, input, chunk);
+            assert_eq!(valid, "id,nome\n3,Caio\n");
+            assert_eq!(
+                rejected,
+                "id,nome,_extra,error_code,error_message\n\
+                 1,,,empty_fields,Row contains empty fields: nome.\n\
+                 2,Bia,\"[\"\"x\"\",\"\"y\"\"]\",field_count_mismatch,\"Expected 2 fields, got 4.\"\n",
+                "pedaços de {}",
+                chunk
+            );
+        }
+
+        // Com field_count "extra", as válidas sempre têm a coluna
+        let mut options = CsvOptions::default();
+        options.set_field_count("extra").unwrap();
+        let (valid, rejected) = split(&options, "id,nome\n1,Ana,x\n2,Bia\n", 1024);
+        assert_eq!(valid, "id,nome,_extra\n1,Ana,\"[\"\"x\"\"]\"\n2,Bia,\n");
+        assert_eq!(rejected, "id,nome,error_code,error_message\n");
+    }
+
+    #[test]
+    fn headers_keep_the_input_names() {
+        let mut options = CsvOptions::default();
+        let schema = r#"{
+            "headers": { "aliases": { "Nome Completo": "nome" } },
+            "columns": { "nome": { "min_length": 2 } }
+        }"#;
+        options.set_schema(&CsvSchema::new(schema).unwrap());
+        let (valid, rejected) = split(&options, "id,Nome Completo,id\n1,Ana,a\n2,B,b\n", 1024);
+        assert_eq!(valid, "id,Nome Completo,id\n1,Ana,a\n");
+        assert!(rejected.starts_with("id,Nome Completo,id,error_code,error_message\n2,B,b,min_length,"));
+    }
+
+    #[test]
+    fn error_codes_are_grouped_by_row() {
+        let mut options = CsvOptions::default();
+        let schema = r#"{ "columns": { "id": { "type": "int" }, "uf": { "type": "enum", "values": ["SP", "RJ"] } } }"#;
+        options.set_schema(&CsvSchema::new(schema).unwrap());
+        options.set_dedup("flag").unwrap();
+        let (valid, rejected) = split(&options, "id,uf\nx,MG\n1,SP\n1,SP\n", 1024);
+        assert_eq!(valid, "id,uf\n1,SP\n");
+
+        // Uma linha por linha rejeitada, com os códigos e as mensagens juntos
+        let reread = process(rejected.as_bytes(), &CsvOptions::default()).unwrap();
+        let fields: Vec<(&str, &str)> = rows(&reread)
+            .iter()
+            .map(|row| (row["id"].as_str().unwrap(), row["error_code"].as_str().unwrap()))
+            .collect();
+        assert_eq!(fields, [("x", "invalid_value;not_allowed"), ("1", "duplicate")]);
+        let messages = reread.processed_rows()[0].data()["error_message"].as_str().unwrap();
+        assert_eq!(messages.split("; ").count(), 2);
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::BTreeMap;
+
+use crate::spreadsheet::{self, SheetRow};
+
+// Pasta de trabalho do Excel 97-2003 (.xls): registros BIFF8 dentro do
+// stream "Workbook" de um arquivo composto (Compound File Binary)
+pub(crate) struct Xls {
+    stream: Vec<u8>,
+    names: Vec<String>,
+    // Posição do BOF de cada planilha dentro do stream
+    offsets: Vec<usize>,
+    strings: Vec<String>,
+    // Se cada XF (estilo de célula) usa um formato de data
+    date_xfs: Vec<bool>,
+    date1904: bool,
+}
+
+const SIGNATURE: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+// Tipos dos registros BIFF usados
+const BOF: u16 = 0x0809;
+const EOF: u16 = 0x000A;
+const CONTINUE: u16 = 0x003C;
+const FILEPASS: u16 = 0x002F;
+const DATEMODE: u16 = 0x0022;
+const FORMAT: u16 = 0x041E;
+const XF: u16 = 0x00E0;
+const BOUNDSHEET: u16 = 0x0085;
+const SST: u16 = 0x00FC;
+const NUMBER: u16 = 0x0203;
+const RK: u16 = 0x027E;
+const MULRK: u16 = 0x00BD;
+const LABELSST: u16 = 0x00FD;
+const LABEL: u16 = 0x0204;
+const BOOLERR: u16 = 0x0205;
+const FORMULA: u16 = 0x0006;
+const STRING: u16 = 0x0207;
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn f64_at(data: &[u8], at: usize) -> Option<f64> {
+    Some(f64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+// Registros BIFF a partir de uma posição: (tipo, dados, posição do registro)
+fn records(stream: &[u8], mut at: usize) -> impl Iterator<Item = (u16, &[u8], usize)> {
+    std::iter::from_fn(move || {
+        let kind = u16_at(stream, at)?;
+        let len = u16_at(stream, at + 2)? as usize;
+        let data = stream.get(at + 4..at + 4 + len)?;
+        let start = at;
+        at += 4 + len;
+        Some((kind, data, start))
+    })
+}
+
+// Dados de um registro seguidos dos CONTINUE dele. Nos textos, cada CONTINUE
+// recomeça os caracteres com um novo byte de opções.
+struct Fragments<'a> {
+    parts: Vec<&'a [u8]>,
+    part: usize,
+    pos: usize,
+}
+
+impl<'a> Fragments<'a> {
+    fn new(stream: &'a [u8], start: usize) -> Self {
+        let mut parts = Vec::new();
+        for (kind, data, _) in records(stream, start) {
+            if !parts.is_empty() && kind != CONTINUE {
+                break;
+            }
+            parts.push(data);
+        }
+        Fragments { parts, part: 0, pos: 0 }
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        while self.pos >= self.parts.get(self.part)?.len() {
+            self.part += 1;
+            self.pos = 0;
+        }
+        let b = self.parts[self.part][self.pos];
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes([self.byte()?, self.byte()?]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes([self.byte()?, self.byte()?, self.byte()?, self.byte()?]))
+    }
+
+    fn skip(&mut self, n: usize) -> Option<()> {
+        for _ in 0..n {
+            self.byte()?;
+        }
+        Some(())
+    }
+
+    // Texto com `count` caracteres, de 1 byte (Latin-1) ou 2 (UTF-16)
+    fn chars(&mut self, count: usize, mut wide: bool) -> Option<String> {
+        let mut units: Vec<u16> = Vec::with_capacity(count);
+        while units.len() < count {
+            if self.pos >= self.parts.get(self.part)?.len() {
+                self.part += 1;
+                self.pos = 0;
+                wide = self.byte()? & 1 == 1;
+            }
+            units.push(if wide { self.u16()? } else { u16::from(self.byte()?) });
+        }
+        Some(String::from_utf16_lossy(&units))
+    }
+
+    // XLUnicodeRichExtendedString: o formato dos textos da SST
+    fn rich_string(&mut self) -> Option<String> {
+        let count = self.u16()? as usize;
+        let flags = self.byte()?;
+        let runs = if flags & 0x08 != 0 { self.u16()? as usize } else { 0 };
+        let extra = if flags & 0x04 != 0 { self.u32()? as usize } else { 0 };
+        let text = self.chars(count, flags & 1 == 1)?;
+        self.skip((runs * 4).checked_add(extra)?)?;
+        Some(text)
+    }
+
+    // XLUnicodeString: tamanho em 2 bytes, opções e caracteres
+    fn string(&mut self) -> Option<String> {
+        let count = self.u16()? as usize;
+        let flags = self.byte()?;
+        self.chars(count, flags & 1 == 1)
+    }
+}
+
+// Valor de um código de erro de célula
+fn error_text(code: u8) -> &'static str {
+    match code {
+        0x00 => "#NULL!",
+        0x07 => "#DIV/0!",
+        0x0F => "#VALUE!",
+        0x17 => "#REF!",
+        0x1D => "#NAME?",
+        0x24 => "#NUM!",
+        _ => "#N/A",
+    }
+}
+
+// Número compactado do RK: inteiro de 30 bits ou os 30 bits altos de um
+// f64, opcionalmente dividido por 100
+fn rk_value(rk: u32) -> f64 {
+    let number = if rk & 0x02 != 0 {
+        f64::from((rk as i32) >> 2)
+    } else {
+        f64::from_bits(u64::from(rk & 0xFFFF_FFFC) << 32)
+    };
+    if rk & 0x01 != 0 {
+        number / 100.0
+    } else {
+        number
+    }
+}
+
+impl Xls {
+    pub(crate) fn is_xls(data: &[u8]) -> bool {
+        data.starts_with(SIGNATURE)
+    }
+
+    pub(crate) fn new(data: &[u8]) -> Result<Self, String> {
+        let compound = Compound::new(data)?;
+        let stream = match compound.stream("Workbook")? {
+            Some(stream) => stream,
+            None if compound.stream("Book")?.is_some() => {
+                return Err("Excel 5.0/95 workbooks are not supported; save the file as .xls (97-2003) or .xlsx".to_string())
+            }
+            None => return Err("not a spreadsheet: the file has no Workbook stream".to_string()),
+        };
+        let corrupt = || "corrupt .xls workbook".to_string();
+
+        let mut xls = Xls {
+            stream: Vec::new(),
+            names: Vec::new(),
+            offsets: Vec::new(),
+            strings: Vec::new(),
+            date_xfs: Vec::new(),
+            date1904: false,
+        };
+        let mut formats = BTreeMap::new();
+        let mut xf_formats = Vec::new();
+        for (kind, record, start) in records(&stream, 0) {
+            match kind {
+                BOF if start == 0 && u16_at(record, 0) != Some(0x0600) => {
+                    return Err("only Excel 97-2003 (BIFF8) .xls workbooks are supported".to_string());
+                }
+                FILEPASS => return Err("encrypted workbooks are not supported".to_string()),
+                DATEMODE => xls.date1904 = u16_at(record, 0) == Some(1),
+                FORMAT => {
+                    let mut fragments = Fragments { parts: vec![record], part: 0, pos: 0 };
+                    let id = fragments.u16().ok_or_else(corrupt)?;
+                    let code = fragments.string().ok_or_else(corrupt)?;
+                    formats.insert(u32::from(id), spreadsheet::is_date_format(&code));
+                }
+                XF => xf_formats.push(u32::from(u16_at(record, 2).ok_or_else(corrupt)?)),
+                // Só planilhas de células; gráficos e macros ficam de fora
+                BOUNDSHEET if record.get(5) == Some(&0) => {
+                    let offset = u32_at(record, 0).ok_or_else(corrupt)? as usize;
+                    let count = *record.get(6).ok_or_else(corrupt)? as usize;
+                    let wide = record.get(7).ok_or_else(corrupt)? & 1 == 1;
+                    let mut fragments = Fragments { parts: vec![record.get(8..).ok_or_else(corrupt)?], part: 0, pos: 0 };
+                    xls.names.push(fragments.chars(count, wide).ok_or_else(corrupt)?);
+                    xls.offsets.push(offset);
+                }
+                SST => {
+                    let mut fragments = Fragments::new(&stream, start);
+                    fragments.skip(4).ok_or_else(corrupt)?;
+                    let unique = fragments.u32().ok_or_else(corrupt)? as usize;
+                    xls.strings.reserve(unique.min(1 << 20));
+                    for _ in 0..unique {
+                        xls.strings.push(fragments.rich_string().ok_or_else(corrupt)?);
+                    }
+                }
+                EOF => break,
+                _ => {}
+            }
+        }
+        xls.date_xfs = xf_formats
+            .into_iter()
+            .map(|id| formats.get(&id).copied().unwrap_or_else(|| spreadsheet::is_builtin_date_format(id)))
+            .collect();
+        xls.stream = stream;
+        Ok(xls)
+    }
+
+    pub(crate) fn sheet_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub(crate) fn rows(&mut self, index: usize) -> Result<Vec<SheetRow>, String> {
+        let corrupt = || "corrupt .xls sheet".to_string();
+        let mut cells: BTreeMap<u32, Vec<(u32, String)>> = BTreeMap::new();
+        let mut push = |row: u16, col: u16, value: String| {
+            cells.entry(u32::from(row)).or_default().push((u32::from(col), value));
+        };
+        // Célula de fórmula cujo texto vem no registro STRING seguinte
+        let mut pending: Option<(u16, u16)> = None;
+        // Gráficos embutidos trazem BOF e EOF próprios
+        let mut depth = 0;
+
+        for (kind, record, start) in records(&self.stream, self.offsets[index]) {
+            let cell = || Some((u16_at(record, 0)?, u16_at(record, 2)?, u16_at(record, 4)? as usize));
+            match kind {
+                BOF => depth += 1,
+                EOF => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ if depth > 1 => {}
+                NUMBER => {
+                    let (row, col, xf) = cell().ok_or_else(corrupt)?;
+                    push(row, col, self.number(f64_at(record, 6).ok_or_else(corrupt)?, xf));
+                }
+                RK => {
+                    let (row, col, xf) = cell().ok_or_else(corrupt)?;
+                    push(row, col, self.number(rk_value(u32_at(record, 6).ok_or_else(corrupt)?), xf));
+                }
+                MULRK => {
+                    let (row, first, _) = cell().ok_or_else(corrupt)?;
+                    let count = record.len().saturating_sub(6) / 6;
+                    for i in 0..count {
+                        let at = 4 + i * 6;
+                        let xf = u16_at(record, at).ok_or_else(corrupt)? as usize;
+                        let value = rk_value(u32_at(record, at + 2).ok_or_else(corrupt)?);
+                        push(row, first.saturating_add(i as u16), self.number(value, xf));
+                    }
+                }
+                LABELSST => {
+                    let (row, col, _) = cell().ok_or_else(corrupt)?;
+                    let index = u32_at(record, 6).ok_or_else(corrupt)? as usize;
+                    push(row, col, self.strings.get(index).cloned().unwrap_or_default());
+                }
+                LABEL => {
+                    let (row, col, _) = cell().ok_or_else(corrupt)?;
+                    let mut fragments = Fragments::new(&self.stream, start);
+                    fragments.skip(6).ok_or_else(corrupt)?;
+                    push(row, col, fragments.string().ok_or_else(corrupt)?);
+                }
+                BOOLERR => {
+                    let (row, col, _) = cell().ok_or_else(corrupt)?;
+                    let value = *record.get(6).ok_or_else(corrupt)?;
+                    let text = match record.get(7) {
+                        Some(0) => if value == 0 { "false" } else { "true" },
+                        _ => error_text(value),
+                    };
+                    push(row, col, text.to_string());
+                }
+                FORMULA => {
+                    let (row, col, xf) = cell().ok_or_else(corrupt)?;
+                    let result = record.get(6..14).ok_or_else(corrupt)?;
+                    if result[6..8] != [0xFF, 0xFF] {
+                        push(row, col, self.number(f64_at(record, 6).ok_or_else(corrupt)?, xf));
+                        continue;
+                    }
+                    match result[0] {
+                        0 => pending = Some((row, col)),
+                        1 => push(row, col, if result[2] == 0 { "false" } else { "true" }.to_string()),
+                        2 => push(row, col, error_text(result[2]).to_string()),
+                        _ => {}
+                    }
+                }
+                STRING => {
+                    if let Some((row, col)) = pending.take() {
+                        let mut fragments = Fragments::new(&self.stream, start);
+                        push(row, col, fragments.string().ok_or_else(corrupt)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(cells
+            .into_iter()
+            .map(|(index, mut cells)| {
+                cells.sort_by_key(|(col, _)| *col);
+                SheetRow { index, cells }
+            })
+            .collect())
+    }
+
+    fn number(&self, value: f64, xf: usize) -> String {
+        match self.date_xfs.get(xf) {
+            Some(true) => spreadsheet::serial_to_iso(value, self.date1904).unwrap_or_else(|| spreadsheet::number_text(value)),
+            _ => spreadsheet::number_text(value),
+        }
+    }
+}
+
+// Arquivo composto do OLE: um pequeno sistema de arquivos em setores, com a
+// tabela de alocação (FAT) encadeando os setores de cada stream
+struct Compound<'a> {
+    data: &'a [u8],
+    sector_size: usize,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    mini_stream: Vec<u8>,
+    mini_cutoff: u64,
+    entries: Vec<(String, u8, u32, u64)>,
+}
+
+const FREE: u32 = 0xFFFF_FFFF;
+const END_OF_CHAIN: u32 = 0xFFFF_FFFE;
+const MINI_SECTOR: usize = 64;
+
+impl<'a> Compound<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, String> {
+        let corrupt = || "corrupt .xls file".to_string();
+        let header = data.get(..512).ok_or_else(corrupt)?;
+        let sector_size = match u16_at(header, 30) {
+            Some(9) => 512,
+            Some(12) => 4096,
+            _ => return Err(corrupt()),
+        };
+        let field = |at| u32_at(header, at).ok_or_else(corrupt);
+        let mut compound = Compound {
+            data,
+            sector_size,
+            fat: Vec::new(),
+            mini_fat: Vec::new(),
+            mini_stream: Vec::new(),
+            mini_cutoff: u64::from(field(56)?),
+            entries: Vec::new(),
+        };
+
+        // Setores da FAT: os 109 primeiros no cabeçalho, os demais encadeados (DIFAT)
+        let mut fat_sectors: Vec<u32> = (0..109).filter_map(|i| u32_at(header, 76 + i * 4)).collect();
+        let mut difat = field(68)?;
+        let mut guard = 0;
+        while difat != END_OF_CHAIN && difat != FREE && guard < data.len() / sector_size {
+            let sector = compound.sector(difat).ok_or_else(corrupt)?;
+            let per_sector = sector_size / 4 - 1;
+            fat_sectors.extend((0..per_sector).filter_map(|i| u32_at(sector, i * 4)));
+            difat = u32_at(sector, per_sector * 4).ok_or_else(corrupt)?;
+            guard += 1;
+        }
+        for id in fat_sectors.into_iter().filter(|&id| id != FREE) {
+            let sector = compound.sector(id).ok_or_else(corrupt)?;
+            compound.fat.extend(sector.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])));
+        }
+
+        let directory = compound.chain(field(48)?)?;
+        for entry in directory.chunks_exact(128) {
+            let name_len = (u16_at(entry, 64).unwrap_or(0) as usize).min(64);
+            let units: Vec<u16> = entry[..name_len.saturating_sub(2)]
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            let size = u32_at(entry, 120).map_or(0, u64::from);
+            compound.entries.push((String::from_utf16_lossy(&units), entry[66], u32_at(entry, 116).unwrap_or(0), size));
+        }
+
+        // Streams pequenos ficam no mini stream, em setores de 64 bytes
+        if let Some(&(_, 5, start, size)) = compound.entries.first() {
+            let mut mini_stream = compound.chain(start)?;
+            mini_stream.truncate(size as usize);
+            compound.mini_stream = mini_stream;
+            let mini_fat = compound.chain(field(60)?)?;
+            compound.mini_fat = mini_fat.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+        }
+        Ok(compound)
+    }
+
+    fn sector(&self, id: u32) -> Option<&'a [u8]> {
+        let start = (id as usize).checked_add(1)?.checked_mul(self.sector_size)?;
+        // O último setor pode vir incompleto, mas não ausente
+        let end = start.saturating_add(self.sector_size).min(self.data.len());
+        self.data.get(start..end).filter(|sector| !sector.is_empty())
+    }
+
+    // Conteúdo de uma cadeia de setores, protegido contra cadeias em laço
+    fn chain(&self, start: u32) -> Result<Vec<u8>, String> {
+        let mut output = Vec::new();
+        let mut id = start;
+        let mut steps = 0;
+        while id != END_OF_CHAIN && id != FREE {
+            steps += 1;
+            if steps > self.fat.len() {
+                return Err("corrupt .xls file: sector chain loops".to_string());
+            }
+            output.extend_from_slice(self.sector(id).ok_or("corrupt .xls file: sector out of range")?);
+            id = *self.fat.get(id as usize).ok_or("corrupt .xls file: sector out of range")?;
+        }
+        Ok(output)
+    }
+
+    fn mini_chain(&self, start: u32, size: usize) -> Result<Vec<u8>, String> {
+        let mut output = Vec::with_capacity(size.min(self.mini_stream.len()));
+        let mut id = start;
+        let mut steps = 0;
+        while id != END_OF_CHAIN && id != FREE && output.len() < size {
+            steps += 1;
+            if steps > self.mini_fat.len() {
+                return Err("corrupt .xls file: sector chain loops".to_string());
+            }
+            let sector = (id as usize)
+                .checked_mul(MINI_SECTOR)
+                .and_then(|at| self.mini_stream.get(at..at.checked_add(MINI_SECTOR)?))
+                .ok_or("corrupt .xls file: sector out of range")?;
+            output.extend_from_slice(sector);
+            id = *self.mini_fat.get(id as usize).ok_or("corrupt .xls file: sector out of range")?;
+        }
+        Ok(output)
+    }
+
+    fn stream(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        let Some(&(_, _, start, size)) = self.entries.iter().find(|(n, kind, _, _)| *kind == 2 && n.eq_ignore_ascii_case(name)) else {
+            return Ok(None);
+        };
+        let mut stream = if size < self.mini_cutoff {
+            self.mini_chain(start, size as usize)?
+        } else {
+            self.chain(start)?
+        };
+        stream.truncate(size as usize);
+        Ok(Some(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(kind: u16, data: &[u8]) -> Vec<u8> {
+        let mut out = kind.to_le_bytes().to_vec();
+        out.extend((data.len() as u16).to_le_bytes());
+        out.extend(data);
+        out
+    }
+
+    // Texto de 1 byte por caractere, com o tamanho em 2 bytes e as opções
+    fn short_string(text: &str) -> Vec<u8> {
+        let mut out = (text.chars().count() as u16).to_le_bytes().to_vec();
+        out.push(0);
+        out.extend(text.chars().map(|c| c as u8));
+        out
+    }
+
+    fn cell(row: u16, col: u16, xf: u16, rest: &[u8]) -> Vec<u8> {
+        let mut out = [row.to_le_bytes(), col.to_le_bytes(), xf.to_le_bytes()].concat();
+        out.extend(rest);
+        out
+    }
+
+    fn rk_int(value: i32, percent: bool) -> u32 {
+        ((value << 2) as u32) | 2 | u32::from(percent)
+    }
+
+    // Arquivo composto com um único stream em setores de 512 bytes: setor 0
+    // com a FAT, 1 com o diretório e os demais com o stream
+    fn compound(name: &str, stream: &[u8]) -> Vec<u8> {
+        let sectors = stream.len().div_ceil(512).max(1);
+        let mut header = vec![0u8; 512];
+        header[..8].copy_from_slice(SIGNATURE);
+        header[30..32].copy_from_slice(&9u16.to_le_bytes());
+        header[48..52].copy_from_slice(&1u32.to_le_bytes());
+        header[60..64].copy_from_slice(&END_OF_CHAIN.to_le_bytes());
+        header[68..72].copy_from_slice(&END_OF_CHAIN.to_le_bytes());
+        header[76..].fill(0xFF);
+        header[76..80].copy_from_slice(&0u32.to_le_bytes());
+
+        let mut fat: Vec<u32> = vec![0xFFFF_FFFD, END_OF_CHAIN];
+        fat.extend((0..sectors as u32).map(|i| if i + 1 == sectors as u32 { END_OF_CHAIN } else { i + 3 }));
+        fat.resize(128, FREE);
+
+        let mut directory = vec![0u8; 512];
+        let entries = [("Root Entry", 5u8, END_OF_CHAIN, 0usize), (name, 2, 2, stream.len())];
+        for (i, (entry_name, kind, start, size)) in entries.into_iter().enumerate() {
+            let entry = &mut directory[i * 128..(i + 1) * 128];
+            let units: Vec<u8> = entry_name.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+            entry[..units.len()].copy_from_slice(&units);
+            entry[64..66].copy_from_slice(&(units.len() as u16).to_le_bytes());
+            entry[66] = kind;
+            entry[116..120].copy_from_slice(&start.to_le_bytes());
+            entry[120..124].copy_from_slice(&(size as u32).to_le_bytes());
+        }
+
+        let mut data = header;
+        data.extend(fat.iter().flat_map(|id| id.to_le_bytes()));
+        data.extend(directory);
+        data.extend(stream);
+        data.resize(512 * (3 + sectors), 0);
+        data
+    }
+
+    // Pasta com a planilha "Dados" e um gráfico; as células usam os XF 0
+    // (geral), 1 (data embutida), 2 (data personalizada) e 3 (0.00)
+    fn workbook(date1904: bool, sheet: &[Vec<u8>]) -> Vec<u8> {
+        let mut globals = record(BOF, &[0x00, 0x06, 0x05, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        globals.extend(record(DATEMODE, &[u8::from(date1904), 0]));
+        globals.extend(record(FORMAT, &[&164u16.to_le_bytes()[..], &short_string("dd/mm/yyyy")].concat()));
+        for format in [0u16, 14, 164, 2] {
+            let mut xf = vec![0u8; 20];
+            xf[2..4].copy_from_slice(&format.to_le_bytes());
+            globals.extend(record(XF, &xf));
+        }
+        let boundsheet = |kind: u8, name: &str| {
+            let mut data = vec![0, 0, 0, 0, 0, kind, name.chars().count() as u8, 0];
+            data.extend(name.chars().map(|c| c as u8));
+            data
+        };
+        let (dados, grafico) = (boundsheet(0, "Dados"), boundsheet(2, "Gráfico"));
+        let dados_at = globals.len();
+        globals.extend(record(BOUNDSHEET, &dados));
+        globals.extend(record(BOUNDSHEET, &grafico));
+
+        // SST com "Nome", um texto dividido por CONTINUE no meio dos
+        // caracteres (passando a 2 bytes por caractere), um com formatação e
+        // um que começa no CONTINUE seguinte
+        let mut sst = [4u32.to_le_bytes(), 4u32.to_le_bytes()].concat();
+        sst.extend(short_string("Nome"));
+        sst.extend([9, 0, 0]);
+        sst.extend(b"Ol\xE1 ");
+        globals.extend(record(SST, &sst));
+        let mut continued = vec![1];
+        continued.extend("mundo".encode_utf16().flat_map(u16::to_le_bytes));
+        continued.extend([3, 0, 0x08, 1, 0]);
+        continued.extend(b"Ana");
+        continued.extend([0, 0, 1, 0]);
+        globals.extend(record(CONTINUE, &continued));
+        globals.extend(record(CONTINUE, &short_string("último")));
+        globals.extend(record(EOF, &[]));
+
+        let offset = globals.len() as u32;
+        globals[dados_at + 4..dados_at + 8].copy_from_slice(&offset.to_le_bytes());
+        globals.extend(record(BOF, &[0x00, 0x06, 0x10, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]));
+        for part in sheet {
+            globals.extend(part);
+        }
+        globals.extend(record(EOF, &[]));
+        compound("Workbook", &globals)
+    }
+
+    fn rows(data: &[u8]) -> Vec<(u32, Vec<(u32, String)>)> {
+        let mut xls = Xls::new(data).unwrap();
+        xls.rows(0).unwrap().into_iter().map(|row| (row.index, row.cells)).collect()
+    }
+
+    fn cells(values: &[(u32, &str)]) -> Vec<(u32, String)> {
+        values.iter().map(|(col, value)| (*col, value.to_string())).collect()
+    }
+
+    fn sample_sheet() -> Vec<Vec<u8>> {
+        let mut mulrk = cell(2, 0, 0, &rk_int(1234, true).to_le_bytes());
+        mulrk.extend(2u16.to_le_bytes());
+        mulrk.extend(((45292.75f64.to_bits() >> 32) as u32).to_le_bytes());
+        mulrk.extend(3u16.to_le_bytes());
+        mulrk.extend(rk_int(5, false).to_le_bytes());
+        mulrk.extend(2u16.to_le_bytes());
+
+        let formula = |col: u16, result: [u8; 8]| record(FORMULA, &cell(3, col, 0, &[&result[..], &[0; 8]].concat()));
+        vec![
+            record(LABELSST, &cell(0, 0, 0, &0u32.to_le_bytes())),
+            record(LABELSST, &cell(0, 1, 0, &1u32.to_le_bytes())),
+            record(LABELSST, &cell(0, 2, 0, &2u32.to_le_bytes())),
+            record(LABELSST, &cell(0, 3, 0, &3u32.to_le_bytes())),
+            record(NUMBER, &cell(1, 0, 1, &45292f64.to_le_bytes())),
+            record(RK, &cell(1, 1, 0, &rk_int(30, false).to_le_bytes())),
+            record(MULRK, &mulrk),
+            formula(0, [0, 0, 0, 0, 0, 0, 0xFF, 0xFF]),
+            record(STRING, &short_string("calculado")),
+            formula(1, 2.5f64.to_le_bytes()),
+            formula(2, [1, 0, 1, 0, 0, 0, 0xFF, 0xFF]),
+            formula(3, [2, 0, 0x07, 0, 0, 0, 0xFF, 0xFF]),
+            record(BOOLERR, &cell(4, 0, 0, &[0, 0])),
+            record(BOOLERR, &cell(4, 1, 0, &[0x2A, 1])),
+            record(LABEL, &cell(4, 2, 0, &short_string("rótulo"))),
+            // Gráfico embutido: as células dele não são da planilha
+            record(BOF, &[0x00, 0x06, 0x20, 0x00]),
+            record(NUMBER, &cell(9, 0, 0, &1f64.to_le_bytes())),
+            record(EOF, &[]),
+        ]
+    }
+
+    #[test]
+    fn lists_worksheets_only() {
+        let xls = Xls::new(&workbook(false, &[])).unwrap();
+        assert_eq!(xls.sheet_names(), ["Dados"]);
+    }
+
+    #[test]
+    fn reads_cells() {
+        assert_eq!(
+            rows(&workbook(false, &sample_sheet())),
+            [
+                (0, cells(&[(0, "Nome"), (1, "Olá mundo"), (2, "Ana"), (3, "último")])),
+                (1, cells(&[(0, "2024-01-01"), (1, "30")])),
+                (2, cells(&[(0, "12.34"), (1, "2024-01-01T18:00:00"), (2, "5")])),
+                (3, cells(&[(0, "calculado"), (1, "2.5"), (2, "true"), (3, "#DIV/0!")])),
+                (4, cells(&[(0, "false"), (1, "#N/A"), (2, "rótulo")])),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_dates_in_the_1904_system() {
+        let dates = rows(&workbook(true, &sample_sheet()));
+        assert_eq!(dates[1].1, cells(&[(0, "2028-01-02"), (1, "30")]));
+        assert_eq!(dates[2].1, cells(&[(0, "12.34"), (1, "2028-01-02T18:00:00"), (2, "5")]));
+    }
+
+    #[test]
+    fn rejects_unsupported_and_corrupt_files() {
+        let data = workbook(false, &sample_sheet());
+        assert!(Xls::is_xls(&data));
+        assert_eq!(Xls::new(&data[..300]).err().unwrap(), "corrupt .xls file");
+        assert_eq!(Xls::new(&data[..1024]).err().unwrap(), "corrupt .xls file: sector out of range");
+        assert_eq!(Xls::new(&data[..1600]).err().unwrap(), "corrupt .xls file: sector out of range");
+
+        // FAT com o diretório apontando para si mesmo
+        let mut looped = data.clone();
+        looped[516..520].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(Xls::new(&looped).err().unwrap(), "corrupt .xls file: sector chain loops");
+
+        let book = compound("Book", &record(BOF, &[0x00, 0x05, 0x05, 0x00]));
+        assert!(Xls::new(&book).err().unwrap().starts_with("Excel 5.0/95 workbooks are not supported"));
+        let other = compound("Outro", &[]);
+        assert_eq!(Xls::new(&other).err().unwrap(), "not a spreadsheet: the file has no Workbook stream");
+        let biff5 = compound("Workbook", &record(BOF, &[0x00, 0x05, 0x05, 0x00]));
+        assert_eq!(Xls::new(&biff5).err().unwrap(), "only Excel 97-2003 (BIFF8) .xls workbooks are supported");
+        let biff8 = record(BOF, &[0x00, 0x06, 0x05, 0x00]);
+        let encrypted = compound("Workbook", &[biff8.clone(), record(FILEPASS, &[0; 6])].concat());
+        assert_eq!(Xls::new(&encrypted).err().unwrap(), "encrypted workbooks are not supported");
+
+        // SST que promete mais textos do que tem
+        let sst = [biff8, record(SST, &[&[0; 4][..], &2u32.to_le_bytes(), &short_string("a")].concat())].concat();
+        assert_eq!(Xls::new(&compound("Workbook", &sst)).err().unwrap(), "corrupt .xls workbook");
+    }
+
+    #[test]
+    fn sector_ids_out_of_range() {
+        let data = [0u8; 1024];
+        let compound = Compound {
+            data: &data,
+            sector_size: 512,
+            fat: vec![END_OF_CHAIN],
+            mini_fat: vec![1, END_OF_CHAIN],
+            mini_stream: vec![7; 64],
+            mini_cutoff: u64::MAX,
+            entries: vec![("Workbook".to_string(), 2, 0, u64::from(u32::MAX))],
+        };
+        assert_eq!(compound.sector(u32::MAX - 2), None);
+        assert_eq!(compound.sector(1), None);
+        // O tamanho declarado não é reservado de antemão, e o segundo setor
+        // da cadeia não existe no mini stream
+        assert_eq!(compound.stream("Workbook").err().unwrap(), "corrupt .xls file: sector out of range");
+        assert_eq!(compound.mini_chain(0, 64).unwrap(), [7; 64]);
+        assert_eq!(compound.mini_chain(u32::MAX - 2, 64).err().unwrap(), "corrupt .xls file: sector out of range");
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+
+use crate::spreadsheet::{self, SheetRow};
+use crate::xml::{Event, XmlReader};
+use crate::zip::ZipArchive;
+
+// Pasta de trabalho do Excel 2007 em diante (.xlsx, .xlsm): XML dentro de um ZIP
+pub(crate) struct Xlsx<'a> {
+    zip: ZipArchive<'a>,
+    names: Vec<String>,
+    // Caminho do XML de cada planilha, na ordem das abas
+    paths: Vec<String>,
+    shared_strings: Vec<String>,
+    // Se o estilo de cada índice `s` das células é um formato de data
+    date_styles: Vec<bool>,
+    date1904: bool,
+}
+
+// Caminho de um alvo de relacionamento, relativo à pasta da origem
+fn resolve(base: &str, target: &str) -> String {
+    if let Some(absolute) = target.strip_prefix('/') {
+        return absolute.to_string();
+    }
+    let mut parts: Vec<&str> = base.rsplit_once('/').map_or(vec![], |(dir, _)| dir.split('/').collect());
+    for part in target.split('/') {
+        match part {
+            ".." => {
+                parts.pop();
+            }
+            "." | "" => {}
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+// Arquivo de relacionamentos de uma parte: "xl/workbook.xml" -> "xl/_rels/workbook.xml.rels"
+fn rels_path(part: &str) -> String {
+    match part.rsplit_once('/') {
+        Some((dir, file)) => format!("{}/_rels/{}.rels", dir, file),
+        None => format!("_rels/{}.rels", part),
+    }
+}
+
+// Relacionamentos: Id -> (tipo, caminho do alvo)
+fn relationships(zip: &ZipArchive<'_>, part: &str) -> Result<HashMap<String, (String, String)>, String> {
+    let mut rels = HashMap::new();
+    let Some(xml) = zip.read_text(&rels_path(part))? else {
+        return Ok(rels);
+    };
+    let mut reader = XmlReader::new(&xml);
+    while let Some(event) = reader.next_event()? {
+        if let Event::Start(tag, _) = event {
+            if tag.name() == "Relationship" {
+                let (Some(id), Some(target)) = (tag.attr("Id"), tag.attr("Target")) else {
+                    continue;
+                };
+                let kind = tag.attr("Type").unwrap_or_default().rsplit('/').next().unwrap_or("").to_string();
+                rels.insert(id.into_owned(), (kind, resolve(part, &target)));
+            }
+        }
+    }
+    Ok(rels)
+}
+
+fn find_rel<'r>(rels: &'r HashMap<String, (String, String)>, kind: &str) -> Option<&'r str> {
+    rels.values().find(|(k, _)| k == kind).map(|(_, path)| path.as_str())
+}
+
+impl<'a> Xlsx<'a> {
+    pub(crate) fn new(zip: ZipArchive<'a>) -> Result<Self, String> {
+        let root = relationships(&zip, "")?;
+        let workbook_path = find_rel(&root, "officeDocument").unwrap_or("xl/workbook.xml").to_string();
+        let workbook = zip
+            .read_text(&workbook_path)?
+            .ok_or("not a spreadsheet: the ZIP archive has no workbook")?;
+        let rels = relationships(&zip, &workbook_path)?;
+
+        let mut names = Vec::new();
+        let mut paths = Vec::new();
+        let mut date1904 = false;
+        let mut reader = XmlReader::new(&workbook);
+        while let Some(event) = reader.next_event()? {
+            let Event::Start(tag, _) = event else { continue };
+            match tag.name() {
+                "workbookPr" => date1904 = matches!(tag.attr("date1904").as_deref(), Some("1" | "true")),
+                "sheet" => {
+                    let name = tag.attr("name").unwrap_or_default().into_owned();
+                    let path = tag.attr("id").and_then(|id| rels.get(id.as_ref())).map(|(_, path)| path.clone());
+                    // Planilhas de gráfico e macros não têm células
+                    if let Some(path) = path.filter(|p| !p.contains("chartsheets") && !p.contains("macrosheets")) {
+                        names.push(name);
+                        paths.push(path);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let shared_strings = match find_rel(&rels, "sharedStrings") {
+            Some(path) => read_shared_strings(&zip, path)?,
+            None => Vec::new(),
+        };
+        let date_styles = match find_rel(&rels, "styles") {
+            Some(path) => read_date_styles(&zip, path)?,
+            None => Vec::new(),
+        };
+        Ok(Xlsx { zip, names, paths, shared_strings, date_styles, date1904 })
+    }
+
+    pub(crate) fn sheet_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub(crate) fn rows(&mut self, index: usize) -> Result<Vec<SheetRow>, String> {
+        let path = &self.paths[index];
+        let xml = self.zip.read_text(path)?.ok_or_else(|| format!("'{}' is missing from the workbook", path))?;
+        let mut reader = XmlReader::new(&xml);
+        let mut rows: Vec<SheetRow> = Vec::new();
+        // Linhas e células sem o atributo `r` seguem a anterior
+        let mut next_row = 0;
+        let mut next_col = 0;
+        let mut cell: Option<Cell> = None;
+        let mut text: Option<String> = None;
+
+        while let Some(event) = reader.next_event()? {
+            match event {
+                Event::Start(tag, empty) => match tag.name() {
+                    "row" => {
+                        let index = tag.attr("r").and_then(|r| r.parse::<u32>().ok()).map_or(next_row, |r| r.saturating_sub(1));
+                        next_row = index + 1;
+                        next_col = 0;
+                        rows.push(SheetRow { index, cells: Vec::new() });
+                    }
+                    "c" => {
+                        let col = tag.attr("r").and_then(|r| column_of(&r)).unwrap_or(next_col);
+                        next_col = col + 1;
+                        let kind = tag.attr("t").unwrap_or_default().into_owned();
+                        let style = tag.attr("s").and_then(|s| s.parse::<usize>().ok()).unwrap_or(0);
+                        cell = (!empty).then_some(Cell { col, kind, style, value: String::new() });
+                    }
+                    "v" | "t" if cell.is_some() && !empty => text = Some(String::new()),
+                    // Fórmulas e textos fonéticos não são o valor da célula
+                    "f" | "rPh" if !empty => reader.skip()?,
+                    _ => {}
+                },
+                Event::Text(content) => {
+                    if let Some(text) = &mut text {
+                        text.push_str(&content);
+                    }
+                }
+                Event::End(name) => match name {
+                    "v" | "t" => {
+                        if let (Some(cell), Some(text)) = (&mut cell, text.take()) {
+                            cell.value.push_str(&text);
+                        }
+                    }
+                    "c" => {
+                        if let Some(cell) = cell.take() {
+                            let value = self.cell_value(&cell);
+                            if let Some(row) = rows.last_mut() {
+                                row.cells.push((cell.col, value));
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+        Ok(rows)
+    }
+
+    fn cell_value(&self, cell: &Cell) -> String {
+        match cell.kind.as_str() {
+            "s" => cell
+                .value
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| self.shared_strings.get(i))
+                .cloned()
+                .unwrap_or_default(),
+            "b" => if cell.value.trim() == "1" { "true" } else { "false" }.to_string(),
+            "str" | "inlineStr" | "e" | "d" => cell.value.clone(),
+            _ => match cell.value.trim().parse::<f64>() {
+                Ok(number) if self.date_styles.get(cell.style).copied().unwrap_or(false) => {
+                    spreadsheet::serial_to_iso(number, self.date1904).unwrap_or_else(|| spreadsheet::number_text(number))
+                }
+                Ok(number) => spreadsheet::number_text(number),
+                Err(_) => cell.value.clone(),
+            },
+        }
+    }
+}
+
+struct Cell {
+    col: u32,
+    // Atributo `t`: "s" (texto compartilhado), "b", "str", "inlineStr", "e", "d" ou número
+    kind: String,
+    style: usize,
+    value: String,
+}
+
+// Coluna de uma referência como "AB12"
+fn column_of(reference: &str) -> Option<u32> {
+    let letters = reference.bytes().take_while(u8::is_ascii_alphabetic);
+    let mut col = 0u32;
+    let mut any = false;
+    for b in letters {
+        col = col.checked_mul(26)?.checked_add(u32::from(b.to_ascii_uppercase() - b'A') + 1)?;
+        any = true;
+    }
+    any.then(|| col - 1)
+}
+
+// Textos compartilhados: cada <si> é um texto, às vezes dividido em trechos
+// com formatação (<r><t>)
+fn read_shared_strings(zip: &ZipArchive<'_>, path: &str) -> Result<Vec<String>, String> {
+    let Some(xml) = zip.read_text(path)? else {
+        return Ok(Vec::new());
+    };
+    let mut strings = Vec::new();
+    let mut reader = XmlReader::new(&xml);
+    let mut current: Option<String> = None;
+    let mut in_text = false;
+    while let Some(event) = reader.next_event()? {
+        match event {
+            Event::Start(tag, empty) => match tag.name() {
+                "si" if empty => strings.push(String::new()),
+                "si" => current = Some(String::new()),
+                "t" => in_text = !empty,
+                "rPh" if !empty => reader.skip()?,
+                _ => {}
+            },
+            Event::Text(text) if in_text => {
+                if let Some(current) = &mut current {
+                    current.push_str(&text);
+                }
+            }
+            Event::End("t") => in_text = false,
+            Event::End("si") => strings.extend(current.take()),
+            _ => {}
+        }
+    }
+    Ok(strings)
+}
+
+// Quais estilos de célula (<cellXfs>) usam um formato de data
+fn read_date_styles(zip: &ZipArchive<'_>, path: &str) -> Result<Vec<bool>, String> {
+    let Some(xml) = zip.read_text(path)? else {
+        return Ok(Vec::new());
+    };
+    let mut custom = HashMap::new();
+    let mut styles = Vec::new();
+    let mut in_cell_xfs = false;
+    let mut reader = XmlReader::new(&xml);
+    while let Some(event) = reader.next_event()? {
+        match event {
+            Event::Start(tag, empty) => match tag.name() {
+                "numFmt" => {
+                    if let (Some(id), Some(code)) = (tag.attr("numFmtId"), tag.attr("formatCode")) {
+                        if let Ok(id) = id.parse::<u32>() {
+                            custom.insert(id, spreadsheet::is_date_format(&code));
+                        }
+                    }
+                }
+                "cellXfs" => in_cell_xfs = !empty,
+                "xf" if in_cell_xfs => {
+                    let id = tag.attr("numFmtId").and_then(|id| id.parse::<u32>().ok()).unwrap_or(0);
+                    let date = custom.get(&id).copied().unwrap_or_else(|| spreadsheet::is_builtin_date_format(id));
+                    styles.push(date);
+                }
+                _ => {}
+            },
+            Event::End("cellXfs") => in_cell_xfs = false,
+            _ => {}
+        }
+    }
+    Ok(styles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zip::tests::archive;
+
+    const RELS: &str = r#"<Relationships>
+        <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument"
+            Target="xl/workbook.xml"/>
+    </Relationships>"#;
+
+    const WORKBOOK_RELS: &str = r#"<Relationships>
+        <Relationship Id="rId1" Type=".../worksheet" Target="worksheets/sheet1.xml"/>
+        <Relationship Id="rId2" Type=".../chartsheet" Target="chartsheets/sheet1.xml"/>
+        <Relationship Id="rId3" Type=".../sharedStrings" Target="sharedStrings.xml"/>
+        <Relationship Id="rId4" Type=".../styles" Target="/xl/styles.xml"/>
+    </Relationships>"#;
+
+    // Estilo 0 geral, 1 data embutida (14), 2 data personalizada, 3 número com casas
+    const STYLES: &str = r#"<styleSheet>
+        <numFmts><numFmt numFmtId="164" formatCode="dd/mm/yyyy\ hh:mm"/><numFmt numFmtId="165" formatCode="&quot;dia&quot;\ 0.00"/></numFmts>
+        <cellStyleXfs><xf numFmtId="14"/></cellStyleXfs>
+        <cellXfs><xf numFmtId="0"/><xf numFmtId="14"/><xf numFmtId="164"/><xf numFmtId="165"/></cellXfs>
+    </styleSheet>"#;
+
+    const SHARED: &str = r#"<sst>
+        <si><t>Nome</t></si><si/>
+        <si><r><t>Jo</t></r><r><rPr/><t xml:space="preserve">ão </t></r><rPh><t>ジョ</t></rPh></si>
+        <si><t>a &amp; b</t></si>
+    </sst>"#;
+
+    fn workbook(date1904: bool, sheet: &str) -> Vec<u8> {
+        let book = format!(
+            r#"<workbook><workbookPr date1904="{}"/>
+                <sheets><sheet name="Dados" r:id="rId1"/><sheet name="Gráfico" r:id="rId2"/></sheets>
+            </workbook>"#,
+            if date1904 { 1 } else { 0 }
+        );
+        archive(&[
+            ("_rels/.rels", RELS.as_bytes()),
+            ("xl/workbook.xml", book.as_bytes()),
+            ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.as_bytes()),
+            ("xl/sharedStrings.xml", SHARED.as_bytes()),
+            ("xl/styles.xml", STYLES.as_bytes()),
+            ("xl/worksheets/sheet1.xml", sheet.as_bytes()),
+        ])
+    }
+
+    fn rows(data: &[u8]) -> Vec<(u32, Vec<(u32, String)>)> {
+        let mut xlsx = Xlsx::new(ZipArchive::new(data).unwrap()).unwrap();
+        xlsx.rows(0).unwrap().into_iter().map(|row| (row.index, row.cells)).collect()
+    }
+
+    fn cells(values: &[(u32, &str)]) -> Vec<(u32, String)> {
+        values.iter().map(|(col, value)| (*col, value.to_string())).collect()
+    }
+
+    #[test]
+    fn lists_worksheets_only() {
+        let data = workbook(false, "<worksheet/>");
+        let xlsx = Xlsx::new(ZipArchive::new(&data).unwrap()).unwrap();
+        assert_eq!(xlsx.sheet_names(), ["Dados"]);
+    }
+
+    #[test]
+    fn reads_shared_and_inline_strings() {
+        let sheet = r#"<worksheet><sheetData>
+            <row r="1"><c r="A1" t="s"><v>0</v></c><c r="C1" t="s"><v>2</v></c><c t="s"><v>3</v></c></row>
+            <row><c t="inlineStr"><is><t>em linha</t></is></c><c r="B2" t="s"><v>1</v></c><c t="s"><v>99</v></c></row>
+            <row r="5">
+                <c r="B5" t="b"><v>1</v></c><c t="str"><f>A1&amp;"x"</f><v>Nomex</v></c><c t="e"><v>#DIV/0!</v></c><c r="AA5"/>
+            </row>
+        </sheetData></worksheet>"#;
+        assert_eq!(
+            rows(&workbook(false, sheet)),
+            [
+                (0, cells(&[(0, "Nome"), (2, "João "), (3, "a & b")])),
+                (1, cells(&[(0, "em linha"), (1, ""), (2, "")])),
+                (4, cells(&[(1, "true"), (2, "Nomex"), (3, "#DIV/0!")])),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_dates_in_both_date_systems() {
+        let sheet = r#"<worksheet><sheetData><row r="1">
+            <c r="A1" s="1"><v>45292</v></c><c r="B1" s="2"><v>45292.75</v></c><c r="C1" s="3"><v>45292</v></c>
+            <c r="D1"><v>0.30000000000000004</v></c><c r="E1" s="1"><v>59</v></c><c r="F1" s="1"><v>61</v></c>
+        </row></sheetData></worksheet>"#;
+        assert_eq!(
+            rows(&workbook(false, sheet))[0].1,
+            cells(&[(0, "2024-01-01"), (1, "2024-01-01T18:00:00"), (2, "45292"), (3, "0.3"), (4, "1900-02-28"), (5, "1900-03-01")])
+        );
+        assert_eq!(
+            rows(&workbook(true, sheet))[0].1,
+            cells(&[(0, "2028-01-02"), (1, "2028-01-02T18:00:00"), (2, "45292"), (3, "0.3"), (4, "1904-02-29"), (5, "1904-03-02")])
+        );
+    }
+
+    #[test]
+    fn rejects_archives_without_a_workbook() {
+        let data = archive(&[("_rels/.rels", RELS.as_bytes())]);
+        assert_eq!(Xlsx::new(ZipArchive::new(&data).unwrap()).err().unwrap(), "not a spreadsheet: the ZIP archive has no workbook");
+
+        let data = workbook(false, "<worksheet><sheetData><row><c");
+        let mut xlsx = Xlsx::new(ZipArchive::new(&data).unwrap()).unwrap();
+        assert_eq!(xlsx.rows(0).err().unwrap(), "malformed XML at byte 27");
+    }
+}
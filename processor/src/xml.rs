@@ -0,0 +1,237 @@
+use std::borrow::Cow;
+
+// Leitor de XML por eventos, só com o necessário para as planilhas: tags,
+// atributos e texto. Declarações, comentários e DOCTYPE são ignorados.
+pub(crate) struct XmlReader<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+pub(crate) enum Event<'a> {
+    // Tag de abertura; `empty` para <tag/>, que não tem fechamento
+    Start(Tag<'a>, bool),
+    End(&'a str),
+    Text(Cow<'a, str>),
+}
+
+pub(crate) struct Tag<'a> {
+    name: &'a str,
+    attrs: &'a str,
+}
+
+// Nome sem o prefixo do namespace: "table:table-cell" -> "table-cell"
+pub(crate) fn local(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+impl<'a> Tag<'a> {
+    pub(crate) fn name(&self) -> &'a str {
+        local(self.name)
+    }
+
+    // Valor de um atributo pelo nome local, com as entidades resolvidas
+    pub(crate) fn attr(&self, name: &str) -> Option<Cow<'a, str>> {
+        let mut rest = self.attrs;
+        loop {
+            rest = rest.trim_start();
+            let eq = rest.find('=')?;
+            let key = rest[..eq].trim();
+            let value = rest[eq + 1..].trim_start();
+            let quote = value.chars().next()?;
+            if quote != '"' && quote != '\'' {
+                return None;
+            }
+            let end = value[1..].find(quote)? + 1;
+            if local(key) == name {
+                return Some(unescape(&value[1..end]));
+            }
+            rest = &value[end + 1..];
+        }
+    }
+}
+
+// Troca as entidades XML pelo caractere correspondente
+pub(crate) fn unescape(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        output.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else { break };
+        let entity = &rest[1..semi];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                output.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    Cow::Owned(output)
+}
+
+impl<'a> XmlReader<'a> {
+    pub(crate) fn new(src: &'a str) -> Self {
+        XmlReader { src: src.trim_start_matches('\u{feff}'), pos: 0 }
+    }
+
+    pub(crate) fn next_event(&mut self) -> Result<Option<Event<'a>>, String> {
+        loop {
+            let rest = &self.src[self.pos..];
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.pos += end;
+                return Ok(Some(Event::Text(unescape(&rest[..end]))));
+            }
+
+            let at = self.pos;
+            let unclosed = || format!("malformed XML at byte {}", at);
+            let skip_to = |marker: &str| rest.find(marker).map(|i| i + marker.len()).ok_or_else(unclosed);
+            if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").ok_or_else(unclosed)?;
+                self.pos += 9 + end + 3;
+                return Ok(Some(Event::Text(Cow::Borrowed(&cdata[..end]))));
+            }
+            if rest.starts_with("<!--") {
+                self.pos += skip_to("-->")?;
+                continue;
+            }
+            if rest.starts_with("<?") {
+                self.pos += skip_to("?>")?;
+                continue;
+            }
+            if rest.starts_with("<!") {
+                self.pos += skip_to(">")?;
+                continue;
+            }
+
+            // Fim da tag, sem contar '>' dentro dos valores dos atributos
+            let mut quote = None;
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|&(_, c)| match quote {
+                    Some(q) => {
+                        if c == q {
+                            quote = None;
+                        }
+                        false
+                    }
+                    None if c == '"' || c == '\'' => {
+                        quote = Some(c);
+                        false
+                    }
+                    None => c == '>',
+                })
+                .map(|(i, _)| i)
+                .ok_or_else(unclosed)?;
+            self.pos += end + 1;
+
+            if let Some(name) = rest[..end].strip_prefix("</") {
+                return Ok(Some(Event::End(local(name.trim()))));
+            }
+            let (body, empty) = match rest[1..end].strip_suffix('/') {
+                Some(body) => (body, true),
+                None => (&rest[1..end], false),
+            };
+            let split = body.find(|c: char| c.is_ascii_whitespace()).unwrap_or(body.len());
+            let tag = Tag { name: &body[..split], attrs: &body[split..] };
+            return Ok(Some(Event::Start(tag, empty)));
+        }
+    }
+
+    // Pula até o fechamento da tag que acabou de abrir, incluindo as filhas
+    pub(crate) fn skip(&mut self) -> Result<(), String> {
+        let mut depth = 1;
+        while let Some(event) = self.next_event()? {
+            match event {
+                Event::Start(_, false) => depth += 1,
+                Event::End(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Os eventos como texto: "<nome/>" para tags vazias, "</nome>" e o texto
+    fn events(xml: &str) -> Result<Vec<String>, String> {
+        let mut reader = XmlReader::new(xml);
+        let mut out = Vec::new();
+        while let Some(event) = reader.next_event()? {
+            out.push(match event {
+                Event::Start(tag, true) => format!("<{}/>", tag.name()),
+                Event::Start(tag, false) => format!("<{}>", tag.name()),
+                Event::End(name) => format!("</{}>", name),
+                Event::Text(text) => text.into_owned(),
+            });
+        }
+        Ok(out)
+    }
+
+    #[test]
+    fn reads_tags_text_and_entities() {
+        let xml = "\u{feff}<?xml version=\"1.0\"?><!-- nota --><x:a><b/>1 &lt; 2 &amp; &#233;&#xE9;<![CDATA[<c>]]></x:a>";
+        assert_eq!(events(xml).unwrap(), ["<a>", "<b/>", "1 < 2 & éé", "<c>", "</a>"]);
+        assert_eq!(unescape("&desconhecida; &amp"), "&desconhecida; &amp");
+    }
+
+    #[test]
+    fn reads_attributes_by_local_name() {
+        let mut reader = XmlReader::new("<c r=\"A1\" t='s' x:val=\"a &gt; b\" f=\"1>0\">");
+        let Some(Event::Start(tag, false)) = reader.next_event().unwrap() else { panic!("expected a start tag") };
+        assert_eq!(tag.attr("r").as_deref(), Some("A1"));
+        assert_eq!(tag.attr("t").as_deref(), Some("s"));
+        assert_eq!(tag.attr("val").as_deref(), Some("a > b"));
+        assert_eq!(tag.attr("f").as_deref(), Some("1>0"));
+        assert_eq!(tag.attr("s"), None);
+    }
+
+    #[test]
+    fn skips_nested_elements() {
+        let mut reader = XmlReader::new("<a><f><g>x</g><h/></f><v>1</v></a>");
+        reader.next_event().unwrap();
+        reader.next_event().unwrap();
+        reader.skip().unwrap();
+        assert!(matches!(reader.next_event().unwrap(), Some(Event::Start(tag, false)) if tag.name() == "v"));
+    }
+
+    #[test]
+    fn rejects_unclosed_markup() {
+        assert_eq!(events("<a><b").unwrap_err(), "malformed XML at byte 3");
+        assert_eq!(events("<a><![CDATA[x").unwrap_err(), "malformed XML at byte 3");
+        assert_eq!(events("<a><!-- x").unwrap_err(), "malformed XML at byte 3");
+    }
+}
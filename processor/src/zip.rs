@@ -0,0 +1,233 @@
+use std::io::Read;
+
+use flate2::read::DeflateDecoder;
+
+// Leitor mínimo de arquivos ZIP, o contêiner do .xlsx e do .ods: lista as
+// entradas pelo diretório central e descompacta uma entrada por vez
+pub(crate) struct ZipArchive<'a> {
+    data: &'a [u8],
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    compressed_size: usize,
+    size: usize,
+    offset: usize,
+}
+
+// Uma entrada não descompacta para mais que isso; protege contra arquivos
+// feitos para explodir a memória
+const MAX_ENTRY_BYTES: usize = 1 << 30;
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+impl<'a> ZipArchive<'a> {
+    pub(crate) fn is_zip(data: &[u8]) -> bool {
+        data.starts_with(b"PK\x03\x04")
+    }
+
+    pub(crate) fn new(data: &'a [u8]) -> Result<Self, String> {
+        let corrupt = || "corrupt ZIP archive".to_string();
+        // O fim do diretório central fica nos últimos bytes, antes de um
+        // comentário de até 64 KiB
+        let search_from = data.len().saturating_sub(22 + 0xFFFF);
+        let end = (search_from..data.len().saturating_sub(21))
+            .rev()
+            .find(|&i| data[i..].starts_with(b"PK\x05\x06"))
+            .ok_or_else(corrupt)?;
+        let count = u16_at(data, end + 10).ok_or_else(corrupt)? as usize;
+        let directory = u32_at(data, end + 16).ok_or_else(corrupt)?;
+        if directory == u32::MAX {
+            return Err("ZIP64 archives are not supported".to_string());
+        }
+
+        let mut entries = Vec::with_capacity(count);
+        let mut at = directory as usize;
+        for _ in 0..count {
+            if !data.get(at..).is_some_and(|d| d.starts_with(b"PK\x01\x02")) {
+                return Err(corrupt());
+            }
+            let field = |offset| u32_at(data, at + offset).map(|v| v as usize).ok_or_else(corrupt);
+            let name_len = u16_at(data, at + 28).ok_or_else(corrupt)? as usize;
+            let extra_len = u16_at(data, at + 30).ok_or_else(corrupt)? as usize;
+            let comment_len = u16_at(data, at + 32).ok_or_else(corrupt)? as usize;
+            let name = data.get(at + 46..at + 46 + name_len).ok_or_else(corrupt)?;
+            entries.push(Entry {
+                name: String::from_utf8_lossy(name).replace('\\', "/"),
+                method: u16_at(data, at + 10).ok_or_else(corrupt)?,
+                compressed_size: field(20)?,
+                size: field(24)?,
+                offset: field(42)?,
+            });
+            at += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(ZipArchive { data, entries })
+    }
+
+    // Conteúdo de uma entrada; o nome não diferencia maiúsculas, como no Excel
+    pub(crate) fn read(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        let name = name.trim_start_matches('/');
+        let Some(entry) = self.entries.iter().find(|e| e.name.eq_ignore_ascii_case(name)) else {
+            return Ok(None);
+        };
+        let corrupt = || format!("corrupt ZIP entry '{}'", entry.name);
+        let header = entry.offset;
+        if !self.data.get(header..).is_some_and(|d| d.starts_with(b"PK\x03\x04")) {
+            return Err(corrupt());
+        }
+        let name_len = u16_at(self.data, header + 26).ok_or_else(corrupt)? as usize;
+        let extra_len = u16_at(self.data, header + 28).ok_or_else(corrupt)? as usize;
+        let start = header + 30 + name_len + extra_len;
+        let end = start.checked_add(entry.compressed_size).ok_or_else(corrupt)?;
+        let compressed = self.data.get(start..end).ok_or_else(corrupt)?;
+
+        match entry.method {
+            0 => Ok(Some(compressed.to_vec())),
+            8 => {
+                // O tamanho declarado vem do próprio arquivo: serve só de
+                // estimativa inicial, e o limite fica a cargo do `take`
+                let mut output = Vec::with_capacity(entry.size.min(1 << 20));
+                DeflateDecoder::new(compressed)
+                    .take(MAX_ENTRY_BYTES as u64 + 1)
+                    .read_to_end(&mut output)
+                    .map_err(|e| format!("{}: {}", corrupt(), e))?;
+                if output.len() > MAX_ENTRY_BYTES {
+                    return Err(format!("ZIP entry '{}' is too large", entry.name));
+                }
+                Ok(Some(output))
+            }
+            method => Err(format!("ZIP entry '{}' uses unsupported compression method {}", entry.name, method)),
+        }
+    }
+
+    // Conteúdo de uma entrada como texto UTF-8
+    pub(crate) fn read_text(&self, name: &str) -> Result<Option<String>, String> {
+        match self.read(name)? {
+            Some(bytes) => String::from_utf8(bytes)
+                .map(Some)
+                .map_err(|_| format!("'{}' is not valid UTF-8", name)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Write;
+
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    // Monta um ZIP com as entradas comprimidas (deflate), menos o "mimetype",
+    // que fica sem compressão como no .ods
+    pub(crate) fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut directory = Vec::new();
+        for (name, content) in entries {
+            let (method, stored) = if *name == "mimetype" {
+                (0u16, content.to_vec())
+            } else {
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(content).unwrap();
+                (8u16, encoder.finish().unwrap())
+            };
+            let offset = data.len() as u32;
+            // Versão, flags, método, hora, data e CRC, iguais nos dois cabeçalhos
+            let mut common = vec![20, 0, 0, 0];
+            common.extend(method.to_le_bytes());
+            common.extend([0; 8]);
+            common.extend((stored.len() as u32).to_le_bytes());
+            common.extend((content.len() as u32).to_le_bytes());
+            common.extend((name.len() as u16).to_le_bytes());
+            common.extend([0, 0]);
+
+            data.extend(b"PK\x03\x04");
+            data.extend(&common);
+            data.extend(name.as_bytes());
+            data.extend(&stored);
+
+            directory.extend(b"PK\x01\x02");
+            directory.extend([20, 0]);
+            directory.extend(&common);
+            directory.extend([0; 10]);
+            directory.extend(offset.to_le_bytes());
+            directory.extend(name.as_bytes());
+        }
+        let start = data.len() as u32;
+        data.extend(&directory);
+        data.extend(b"PK\x05\x06\0\0\0\0");
+        data.extend((entries.len() as u16).to_le_bytes());
+        data.extend((entries.len() as u16).to_le_bytes());
+        data.extend((directory.len() as u32).to_le_bytes());
+        data.extend(start.to_le_bytes());
+        data.extend([0, 0]);
+        data
+    }
+
+    #[test]
+    fn reads_stored_and_deflated_entries() {
+        let data = archive(&[("mimetype", b"text/plain"), ("Dir/Data.xml", b"<a>texto repetido repetido</a>")]);
+        assert!(ZipArchive::is_zip(&data));
+        let zip = ZipArchive::new(&data).unwrap();
+        assert_eq!(zip.read("mimetype").unwrap().unwrap(), b"text/plain");
+        assert_eq!(zip.read_text("/dir/data.XML").unwrap().unwrap(), "<a>texto repetido repetido</a>");
+        assert_eq!(zip.read("missing.xml").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_truncated_archives() {
+        let data = archive(&[("a.xml", b"<a/>"), ("b.xml", b"<b/>")]);
+        // Sem o fim do diretório central
+        assert_eq!(ZipArchive::new(&data[..data.len() - 10]).err().unwrap(), "corrupt ZIP archive");
+        assert!(ZipArchive::new(b"PK\x03\x04").is_err());
+
+        // Diretório apontando para fora do arquivo
+        let mut broken = data.clone();
+        let end = broken.len() - 22;
+        broken[end + 16..end + 20].copy_from_slice(&u32::MAX.wrapping_sub(1).to_le_bytes());
+        assert_eq!(ZipArchive::new(&broken).err().unwrap(), "corrupt ZIP archive");
+
+        // Dados comprimidos cortados no meio
+        let mut cut = data.clone();
+        let size_at = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap() + 20;
+        cut[size_at..size_at + 4].copy_from_slice(&1000u32.to_le_bytes());
+        let zip = ZipArchive::new(&cut).unwrap();
+        assert_eq!(zip.read("a.xml").err().unwrap(), "corrupt ZIP entry 'a.xml'");
+    }
+
+    #[test]
+    fn rejects_corrupt_deflate_data() {
+        let mut data = archive(&[("a.xml", &[b'x'; 64])]);
+        // Um bloco deflate de tipo reservado logo no início dos dados
+        let start = 30 + "a.xml".len();
+        data[start..start + 6].copy_from_slice(&[0xFF; 6]);
+        let zip = ZipArchive::new(&data).unwrap();
+        assert!(zip.read("a.xml").unwrap_err().starts_with("corrupt ZIP entry 'a.xml'"));
+    }
+
+    #[test]
+    fn declared_sizes_are_not_trusted() {
+        let mut data = archive(&[("a.xml", b"<a/>")]);
+        let directory = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        // Tamanho descompactado de 1 GiB: não é reservado de antemão
+        data[directory + 24..directory + 28].copy_from_slice(&(1u32 << 30).to_le_bytes());
+        let output = ZipArchive::new(&data).unwrap().read("a.xml").unwrap().unwrap();
+        assert_eq!(output, b"<a/>");
+        assert!(output.capacity() <= 1 << 20);
+
+        // Tamanho comprimido que passaria do fim do endereçamento
+        data[directory + 20..directory + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+        let zip = ZipArchive::new(&data).unwrap();
+        assert_eq!(zip.read("a.xml").err().unwrap(), "corrupt ZIP entry 'a.xml'");
+    }
+}